@@ -0,0 +1,27 @@
+//! The build script of `websurfx` which embeds the git commit the binary is built from, so that it
+//! can be reported by the `/version` route. The commit can be provided through the
+//! `WEBSURFX_GIT_COMMIT` environment variable (like when building from a source archive or in a
+//! container without the `.git` folder), otherwise it is read from git if it is available.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=WEBSURFX_GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let commit: Option<String> = std::env::var("WEBSURFX_GIT_COMMIT").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "--short", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+    });
+
+    if let Some(commit) = commit.map(|commit| commit.trim().to_owned()) {
+        if !commit.is_empty() {
+            println!("cargo:rustc-env=WEBSURFX_GIT_COMMIT={commit}");
+        }
+    }
+}
@@ -12,8 +12,11 @@ document.addEventListener(
   'DOMContentLoaded',
   () => {
     try {
-      // Decode the cookie value
-      let cookie = decodeURIComponent(document.cookie)
+      // Decode the cookie value (or get the preferences saved in the browser when the
+      // cookies are disabled on the instance)
+      let cookie = document.body.hasAttribute('data-disable-cookies')
+        ? localStorage.getItem('appSettings') || ''
+        : decodeURIComponent(document.cookie)
       // Set the value of the input field to the decoded cookie value if it is not empty
       // Otherwise, display a message indicating that no cookies have been saved on the user's system
       document.querySelector('.cookies input').value = cookie.length
@@ -28,3 +31,46 @@ document.addEventListener(
   },
   false,
 )
+
+/**
+ * This function imports the preferences from the settings file selected in the cookies tab by
+ * sending it to the settings import route, the page is reloaded once the preferences are saved
+ * so that the imported theme and engines are applied. The settings backup is hidden when the
+ * cookies are disabled on the instance as the preferences are then only saved in the browser.
+ *
+ * @function
+ * @listens DOMContentLoaded
+ * @returns {void}
+ */
+document.addEventListener(
+  'DOMContentLoaded',
+  () => {
+    const backup = document.querySelector('.settings_backup')
+    if (!backup) return
+
+    if (document.body.hasAttribute('data-disable-cookies')) {
+      backup.style.display = 'none'
+      return
+    }
+
+    backup
+      .querySelector('input[type="file"]')
+      .addEventListener('change', async (event) => {
+        const file = event.target.files[0]
+        if (!file) return
+
+        const response = await fetch('/settings/import', {
+          method: 'POST',
+          headers: { 'Content-Type': 'application/json' },
+          body: await file.text(),
+        })
+
+        if (response.ok) {
+          window.location.reload()
+        } else {
+          backup.querySelector('.import_error').textContent = await response.text()
+        }
+      })
+  },
+  false,
+)
@@ -4,6 +4,25 @@
  */
 const searchBox = document.querySelector('input')
 
+/**
+ * Returns the search parameters of the engines selected on the settings page when the
+ * cookies are disabled on the instance, as the server can not read them from the cookie.
+ * @returns {string} - The search parameters to be appended to the search url.
+ */
+function storedEnginesParam() {
+    if (!document.body.hasAttribute('data-disable-cookies')) {
+        return ''
+    }
+    try {
+        let settings = JSON.parse(localStorage.getItem('appSettings'))
+        return settings && settings.engines && settings.engines.length
+            ? `&engines=${encodeURIComponent(settings.engines.join(','))}`
+            : ''
+    } catch (error) {
+        return ''
+    }
+}
+
 /**
  * Redirects the user to the search results page with the query parameter
  */
@@ -14,11 +33,11 @@ function searchWeb() {
         if (query) {
             window.location.href = `search?q=${encodeURIComponent(
                 query,
-            )}&safesearch=${encodeURIComponent(safeSearchLevel)}`
+            )}&safesearch=${encodeURIComponent(safeSearchLevel)}${storedEnginesParam()}`
         }
     } catch (error) {
         if (query) {
-            window.location.href = `search?q=${encodeURIComponent(query)}`
+            window.location.href = `search?q=${encodeURIComponent(query)}${storedEnginesParam()}`
         }
     }
 }
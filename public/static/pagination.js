@@ -8,6 +8,9 @@ function navigate_forward() {
 
     let q = searchParams.get('q');
     let page = parseInt(searchParams.get('page'));
+    let type = searchParams.get('type');
+    let engines = searchParams.get('engines');
+    let sort = searchParams.get('sort');
 
     if (isNaN(page)) {
         page = 1;
@@ -15,7 +18,7 @@ function navigate_forward() {
         page++;
     }
 
-    window.location.href = `${url.origin}${url.pathname}?q=${encodeURIComponent(q)}&page=${page}`;
+    window.location.href = `${url.origin}${url.pathname}?q=${encodeURIComponent(q)}&page=${page}${type ? `&type=${encodeURIComponent(type)}` : ''}${engines ? `&engines=${encodeURIComponent(engines)}` : ''}${sort ? `&sort=${encodeURIComponent(sort)}` : ''}`;
 }
 
 /**
@@ -28,6 +31,9 @@ function navigate_backward() {
 
     let q = searchParams.get('q');
     let page = parseInt(searchParams.get('page'));
+    let type = searchParams.get('type');
+    let engines = searchParams.get('engines');
+    let sort = searchParams.get('sort');
 
     if (isNaN(page)) {
         page = 0;
@@ -35,5 +41,5 @@ function navigate_backward() {
         page--;
     }
 
-    window.location.href = `${url.origin}${url.pathname}?q=${encodeURIComponent(q)}&page=${page}`;
+    window.location.href = `${url.origin}${url.pathname}?q=${encodeURIComponent(q)}&page=${page}${type ? `&type=${encodeURIComponent(type)}` : ''}${engines ? `&engines=${encodeURIComponent(engines)}` : ''}${sort ? `&sort=${encodeURIComponent(sort)}` : ''}`;
 }
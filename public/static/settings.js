@@ -33,9 +33,32 @@ function setActiveTab(current_tab) {
     .classList.add('active')
 }
 
+/**
+ * This function checks whether the cookies are disabled on the instance, in which case
+ * the preferences are saved in the local storage of the browser instead of a cookie.
+ * @returns {boolean} - Whether the cookies are disabled.
+ */
+function cookiesDisabled() {
+  return document.body.hasAttribute('data-disable-cookies')
+}
+
+/**
+ * This function retrieves the preferences saved in the local storage of the browser
+ * when the cookies are disabled on the instance.
+ * @returns {Object|null} - The saved preferences or null if none have been saved.
+ */
+function getStoredSettings() {
+  try {
+    return JSON.parse(localStorage.getItem('appSettings'))
+  } catch (error) {
+    return null
+  }
+}
+
 /**
  * This function adds the functionality to save all the user selected preferences
- * to be saved in a cookie on the users machine.
+ * to be saved in a cookie on the users machine (or in the local storage of the
+ * browser when the cookies are disabled on the instance).
  */
 function setClientSettings() {
   // Create an object to store the user's preferences
@@ -67,14 +90,19 @@ function setClientSettings() {
 
   cookie_dictionary['engines'] = engines
 
-  // Set the expiration date for the cookie to 1 year from the current date
-  let expiration_date = new Date()
-  expiration_date.setFullYear(expiration_date.getFullYear() + 1)
+  if (cookiesDisabled()) {
+    // Save the preferences in the browser as the server never reads nor sets cookies
+    localStorage.setItem('appSettings', JSON.stringify(cookie_dictionary))
+  } else {
+    // Set the expiration date for the cookie to 1 year from the current date
+    let expiration_date = new Date()
+    expiration_date.setFullYear(expiration_date.getFullYear() + 1)
 
-  // Save the cookie to the user's machine
-  document.cookie = `appCookie=${JSON.stringify(
-    cookie_dictionary,
-  )}; expires=${expiration_date.toUTCString()}`
+    // Save the cookie to the user's machine
+    document.cookie = `appCookie=${JSON.stringify(
+      cookie_dictionary,
+    )}; expires=${expiration_date.toUTCString()}`
+  }
 
   // Display a success message to the user
   document.querySelector('.message').innerText =
@@ -93,16 +121,25 @@ function setClientSettings() {
  * website otherwise the function does nothing and the default server side settings are loaded.
  */
 function getClientSettings() {
-  // Get the appCookie from the user's machine
-  let cookie = decodeURIComponent(document.cookie)
+  let cookie_value = null
 
-  // If the cookie is not empty, parse it and use it to set the user's preferences
-  if (cookie.length) {
-    let cookie_value = cookie
-      .split(';')
-      .map((item) => item.split('='))
-      .reduce((acc, [_, v]) => (acc = JSON.parse(v)) && acc, {})
+  if (cookiesDisabled()) {
+    // Get the preferences saved in the browser when the cookies are disabled
+    cookie_value = getStoredSettings()
+  } else {
+    // Get the appCookie from the user's machine
+    let cookie = decodeURIComponent(document.cookie)
+
+    // If the cookie is not empty, parse it and use it to set the user's preferences
+    if (cookie.length) {
+      cookie_value = cookie
+        .split(';')
+        .map((item) => item.split('='))
+        .reduce((acc, [_, v]) => (acc = JSON.parse(v)) && acc, {})
+    }
+  }
 
+  if (cookie_value) {
     // Loop through all link tags and update their href values to match the user's preferences
     Array.from(document.querySelectorAll('link')).forEach((item) => {
       if (item.href.includes('static/themes')) {
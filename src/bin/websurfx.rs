@@ -5,7 +5,13 @@
 
 use mimalloc::MiMalloc;
 use std::net::TcpListener;
-use websurfx::{cache::cacher::Cache, config::parser::Config, run};
+use websurfx::{
+    cache::cacher::Cache,
+    config::parser::Config,
+    handler::paths::{file_path, FileType},
+    results::engine_probe::probe_engines_on_start,
+    run_with_listeners,
+};
 
 /// A dhat heap memory profiler
 #[cfg(feature = "dhat-heap")]
@@ -21,30 +27,74 @@ static GLOBAL: MiMalloc = MiMalloc;
 /// # Error
 ///
 /// Returns an error if the port is being used by something else on the system and is not
-/// available for being used for other applications.
+/// available for being used for other applications, or if an upstream search engine is unreachable
+/// when the `probe_engines_strict` option is enabled.
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // A dhat heap profiler initialization.
     #[cfg(feature = "dhat-heap")]
     let _profiler = dhat::Profiler::new_heap();
 
-    // Initialize the parsed config file.
-    let config = Config::parse(false).unwrap();
+    // Only validate the config file (the provided one or the one the server would use) when the
+    // `--check-config` flag is provided, without starting the server.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--check-config") {
+        let result = match args.next() {
+            Some(path) => Config::validate_only(&path),
+            None => file_path(FileType::Config)
+                .map_err(Into::into)
+                .and_then(Config::validate_only),
+        };
+        match result {
+            Ok(_) => println!("The config file is valid"),
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // Initialize the parsed config file and exit with all the problems found in it if it is
+    // invalid.
+    let config = match Config::parse(false) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+
+    // Probe the upstream search engines before accepting any traffic, so that the engines which
+    // are misconfigured or which block the server are noticed immediately.
+    probe_engines_on_start(&config).await?;
 
     let cache = Cache::build(&config).await;
 
-    log::info!(
-        "started server on port {} and IP {}",
-        config.port,
-        config.binding_ip
-    );
-    log::info!(
-        "Open http://{}:{}/ in your browser",
-        config.binding_ip,
-        config.port,
-    );
+    // Listen on the `ip:port` addresses from the `listen` option if any are provided otherwise on
+    // the `binding_ip` and `port` options.
+    let listeners: Vec<TcpListener> = match config.server.listen.is_empty() {
+        true => vec![TcpListener::bind((config.binding_ip.clone(), config.port))?],
+        false => config
+            .server
+            .listen
+            .iter()
+            .map(|address| TcpListener::bind(address.as_str()))
+            .collect::<std::io::Result<Vec<TcpListener>>>()?,
+    };
+
+    for listener in &listeners {
+        let address = listener.local_addr()?;
+        log::info!(
+            "started server on port {} and IP {}",
+            address.port(),
+            address.ip()
+        );
+        log::info!("Open http://{address}/ in your browser");
+    }
 
-    let listener = TcpListener::bind((config.binding_ip.clone(), config.port))?;
+    run_with_listeners(listeners, config, cache)?.await?;
 
-    run(listener, config, cache)?.await
+    log::info!("server stopped");
+    Ok(())
 }
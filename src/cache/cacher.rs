@@ -8,7 +8,9 @@ use mini_moka::sync::Cache as MokaCache;
 use std::time::Duration;
 use tokio::sync::Mutex;
 
-use crate::{config::parser::Config, models::aggregation_models::SearchResults};
+use crate::{
+    config::parser::Config, models::aggregation_models::SearchResults, results::favicon::Favicon,
+};
 
 use super::error::CacheError;
 #[cfg(feature = "redis-cache")]
@@ -45,24 +47,37 @@ impl Cache {
         {
             log::info!("Using a hybrid cache");
             Cache::new_hybrid(
-                RedisCache::new(&_config.redis_url, 5)
-                    .await
-                    .expect("Redis cache configured"),
+                RedisCache::new(
+                    &_config.redis_url,
+                    5,
+                    _config.cache_expiry,
+                    &_config.redis_prefix,
+                    _config.cache_compression,
+                )
+                .await
+                .expect("Redis cache configured"),
+                _config.cache_expiry,
             )
         }
         #[cfg(all(feature = "redis-cache", not(feature = "memory-cache")))]
         {
             log::info!("Listening redis server on {}", &_config.redis_url);
             Cache::new(
-                RedisCache::new(&_config.redis_url, 5)
-                    .await
-                    .expect("Redis cache configured"),
+                RedisCache::new(
+                    &_config.redis_url,
+                    5,
+                    _config.cache_expiry,
+                    &_config.redis_prefix,
+                    _config.cache_compression,
+                )
+                .await
+                .expect("Redis cache configured"),
             )
         }
         #[cfg(all(feature = "memory-cache", not(feature = "redis-cache")))]
         {
             log::info!("Using an in-memory cache");
-            Cache::new_in_memory()
+            Cache::new_in_memory(_config.cache_expiry)
         }
         #[cfg(not(any(feature = "memory-cache", feature = "redis-cache")))]
         {
@@ -89,14 +104,18 @@ impl Cache {
     /// memory with the search engine thus improving performance by making retrieval and caching of
     /// results faster.
     ///
+    /// # Arguments
+    ///
+    /// * `cache_expiry` - It takes the time (secs) for which the search results are cached.
+    ///
     /// # Returns
     ///
     /// It returns a `InMemory` variant with the newly initialized in memory cache type.
     #[cfg(all(feature = "memory-cache", not(feature = "redis-cache")))]
-    pub fn new_in_memory() -> Self {
+    pub fn new_in_memory(cache_expiry: u64) -> Self {
         let cache = MokaCache::builder()
             .max_capacity(1000)
-            .time_to_live(Duration::from_secs(60))
+            .time_to_live(Duration::from_secs(cache_expiry))
             .build();
         Cache::InMemory(cache)
     }
@@ -109,16 +128,17 @@ impl Cache {
     /// # Arguments
     ///
     /// * `redis_cache` - It takes `redis` client connection struct as an argument.
+    /// * `cache_expiry` - It takes the time (secs) for which the search results are cached.
     ///
     /// # Returns
     ///
     /// It returns a tuple variant `Hybrid` storing both the in-memory cache type and the `redis`
     /// client connection struct.
     #[cfg(all(feature = "redis-cache", feature = "memory-cache"))]
-    pub fn new_hybrid(redis_cache: RedisCache) -> Self {
+    pub fn new_hybrid(redis_cache: RedisCache, cache_expiry: u64) -> Self {
         let cache = MokaCache::builder()
             .max_capacity(1000)
-            .time_to_live(Duration::from_secs(60))
+            .time_to_live(Duration::from_secs(cache_expiry))
             .build();
         Cache::Hybrid(redis_cache, cache)
     }
@@ -204,6 +224,128 @@ impl Cache {
             }
         }
     }
+
+    /// A function which fetches the cached favicon of the provided domain. Favicons are only
+    /// cached in the `redis` cache as they are meant to be cached for a long time.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - It takes the domain whose favicon needs to be fetched.
+    ///
+    /// # Error
+    ///
+    /// Returns the `Favicon` from the cache if the program executes normally otherwise returns a
+    /// `CacheError` if the favicon cannot be retrieved from the cache.
+    pub async fn cached_favicon(&mut self, _domain: &str) -> Result<Favicon, Report<CacheError>> {
+        match self {
+            #[cfg(all(feature = "redis-cache", not(feature = "memory-cache")))]
+            Cache::Redis(redis_cache) => redis_cache.cached_favicon(_domain).await,
+            #[cfg(all(feature = "redis-cache", feature = "memory-cache"))]
+            Cache::Hybrid(redis_cache, _) => redis_cache.cached_favicon(_domain).await,
+            _ => Err(Report::new(CacheError::MissingValue)),
+        }
+    }
+
+    /// A function which caches the favicon of the provided domain by using the domain as the key.
+    /// Favicons are only cached in the `redis` cache and so this does nothing for the other caches.
+    ///
+    /// # Arguments
+    ///
+    /// * `favicon` - It takes the favicon which needs to be cached.
+    /// * `domain` - It takes the domain of the favicon.
+    ///
+    /// # Error
+    ///
+    /// Returns a unit type if the program caches the given favicon without a failure otherwise it
+    /// returns a `CacheError` if the favicon cannot be cached due to a failure.
+    pub async fn cache_favicon(
+        &mut self,
+        _favicon: &Favicon,
+        _domain: &str,
+    ) -> Result<(), Report<CacheError>> {
+        match self {
+            #[cfg(all(feature = "redis-cache", not(feature = "memory-cache")))]
+            Cache::Redis(redis_cache) => redis_cache.cache_favicon(_favicon, _domain).await,
+            #[cfg(all(feature = "redis-cache", feature = "memory-cache"))]
+            Cache::Hybrid(redis_cache, _) => redis_cache.cache_favicon(_favicon, _domain).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// A function which fetches the cached thumbnail of the provided url. Like the favicons, the
+    /// thumbnails are only cached in the `redis` cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the thumbnail which needs to be fetched.
+    ///
+    /// # Error
+    ///
+    /// Returns the thumbnail from the cache if the program executes normally otherwise returns a
+    /// `CacheError` if the thumbnail cannot be retrieved from the cache.
+    pub async fn cached_thumbnail(&mut self, _url: &str) -> Result<Favicon, Report<CacheError>> {
+        match self {
+            #[cfg(all(feature = "redis-cache", not(feature = "memory-cache")))]
+            Cache::Redis(redis_cache) => redis_cache.cached_thumbnail(_url).await,
+            #[cfg(all(feature = "redis-cache", feature = "memory-cache"))]
+            Cache::Hybrid(redis_cache, _) => redis_cache.cached_thumbnail(_url).await,
+            _ => Err(Report::new(CacheError::MissingValue)),
+        }
+    }
+
+    /// A function which caches the thumbnail of the provided url by using the url as the key.
+    /// Thumbnails are only cached in the `redis` cache and so this does nothing for the other
+    /// caches.
+    ///
+    /// # Arguments
+    ///
+    /// * `thumbnail` - It takes the thumbnail which needs to be cached.
+    /// * `url` - It takes the url of the thumbnail.
+    ///
+    /// # Error
+    ///
+    /// Returns a unit type if the program caches the given thumbnail without a failure otherwise
+    /// it returns a `CacheError` if the thumbnail cannot be cached due to a failure.
+    pub async fn cache_thumbnail(
+        &mut self,
+        _thumbnail: &Favicon,
+        _url: &str,
+    ) -> Result<(), Report<CacheError>> {
+        match self {
+            #[cfg(all(feature = "redis-cache", not(feature = "memory-cache")))]
+            Cache::Redis(redis_cache) => redis_cache.cache_thumbnail(_thumbnail, _url).await,
+            #[cfg(all(feature = "redis-cache", feature = "memory-cache"))]
+            Cache::Hybrid(redis_cache, _) => redis_cache.cache_thumbnail(_thumbnail, _url).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// A function which removes all the cached search results and favicons from the cache. Only
+    /// the keys stored by websurfx are removed from the `redis` cache.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of removed entries if the program executes normally otherwise returns
+    /// a `CacheError` if the cache cannot be flushed.
+    pub async fn flush(&mut self) -> Result<usize, Report<CacheError>> {
+        match self {
+            Cache::Disabled => Ok(0),
+            #[cfg(all(feature = "redis-cache", not(feature = "memory-cache")))]
+            Cache::Redis(redis_cache) => redis_cache.flush_prefix().await,
+            #[cfg(all(feature = "memory-cache", not(feature = "redis-cache")))]
+            Cache::InMemory(in_memory) => {
+                let removed: usize = in_memory.entry_count() as usize;
+                in_memory.invalidate_all();
+                Ok(removed)
+            }
+            #[cfg(all(feature = "redis-cache", feature = "memory-cache"))]
+            Cache::Hybrid(redis_cache, in_memory) => {
+                let removed: usize = in_memory.entry_count() as usize;
+                in_memory.invalidate_all();
+                Ok(removed + redis_cache.flush_prefix().await?)
+            }
+        }
+    }
 }
 
 /// A structure to efficiently share the cache between threads - as it is protected by a Mutex.
@@ -264,4 +406,85 @@ impl SharedCache {
         let mut mut_cache = self.cache.lock().await;
         mut_cache.cache_results(search_results, url).await
     }
+    /// A getter function which retrieves the cached favicon of the provided domain from the
+    /// internal cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - It takes the domain whose favicon needs to be fetched.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached `Favicon` if nothing goes wrong otherwise returns a `CacheError`.
+    pub async fn cached_favicon(&self, domain: &str) -> Result<Favicon, Report<CacheError>> {
+        let mut mut_cache = self.cache.lock().await;
+        mut_cache.cached_favicon(domain).await
+    }
+
+    /// A setter function which caches the favicon by using the domain as the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `favicon` - It takes the favicon which needs to be cached.
+    /// * `domain` - It takes the domain of the favicon.
+    ///
+    /// # Error
+    ///
+    /// Returns an unit type if the favicon is cached succesfully otherwise returns a `CacheError`
+    /// on a failure.
+    pub async fn cache_favicon(
+        &self,
+        favicon: &Favicon,
+        domain: &str,
+    ) -> Result<(), Report<CacheError>> {
+        let mut mut_cache = self.cache.lock().await;
+        mut_cache.cache_favicon(favicon, domain).await
+    }
+
+    /// A getter function which retrieves the cached thumbnail of the provided url from the
+    /// internal cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the thumbnail which needs to be fetched.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached thumbnail if nothing goes wrong otherwise returns a `CacheError`.
+    pub async fn cached_thumbnail(&self, url: &str) -> Result<Favicon, Report<CacheError>> {
+        let mut mut_cache = self.cache.lock().await;
+        mut_cache.cached_thumbnail(url).await
+    }
+
+    /// A setter function which caches the thumbnail by using its url as the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `thumbnail` - It takes the thumbnail which needs to be cached.
+    /// * `url` - It takes the url of the thumbnail.
+    ///
+    /// # Error
+    ///
+    /// Returns an unit type if the thumbnail is cached succesfully otherwise returns a
+    /// `CacheError` on a failure.
+    pub async fn cache_thumbnail(
+        &self,
+        thumbnail: &Favicon,
+        url: &str,
+    ) -> Result<(), Report<CacheError>> {
+        let mut mut_cache = self.cache.lock().await;
+        mut_cache.cache_thumbnail(thumbnail, url).await
+    }
+
+    /// A function which removes all the cached search results and favicons from the internal
+    /// cache.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of removed entries if the cache is flushed succesfully otherwise returns
+    /// a `CacheError` on a failure.
+    pub async fn flush(&self) -> Result<usize, Report<CacheError>> {
+        let mut mut_cache = self.cache.lock().await;
+        mut_cache.flush().await
+    }
 }
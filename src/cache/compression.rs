@@ -0,0 +1,122 @@
+//! This module provides the functionality to compress the json search results before they are
+//! stored in the cache and to decompress them when they are fetched, which reduces the memory
+//! used by the redis server for large result sets. The compressed entries are told apart from
+//! the uncompressed ones by the magic bytes the compression formats start with, so that the
+//! entries cached before the compression was enabled (or with another format) can still be read.
+
+use std::io::{Read, Write};
+
+use error_stack::{Report, ResultExt};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use super::error::CacheError;
+use crate::models::parser_models::CacheCompression;
+
+/// The magic bytes at the start of the gzip compressed data.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The magic bytes at the start of the zstd compressed data.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The compression level used for the zstd compressed data, which favours the speed as the
+/// results are compressed on every search.
+const ZSTD_LEVEL: i32 = 3;
+
+/// A function which compresses the json search results with the provided compression format
+/// before they are stored in the cache.
+///
+/// # Arguments
+///
+/// * `json` - It takes the json search results.
+/// * `compression` - It takes the compression format from the `cache_compression` option.
+///
+/// # Error
+///
+/// Returns a `SerializationError` if the results could not be compressed.
+pub fn compress(json: &str, compression: CacheCompression) -> Result<Vec<u8>, Report<CacheError>> {
+    match compression {
+        CacheCompression::None => Ok(json.as_bytes().to_vec()),
+        CacheCompression::Gzip => {
+            let mut encoder: GzEncoder<Vec<u8>> = GzEncoder::new(Vec::new(), Compression::fast());
+            encoder
+                .write_all(json.as_bytes())
+                .and_then(|_| encoder.finish())
+                .change_context(CacheError::SerializationError)
+        }
+        CacheCompression::Zstd => zstd::encode_all(json.as_bytes(), ZSTD_LEVEL)
+            .change_context(CacheError::SerializationError),
+    }
+}
+
+/// A function which decompresses the search results fetched from the cache into json. The
+/// compression format is detected from the data itself and the data which is not compressed is
+/// returned as it is.
+///
+/// # Arguments
+///
+/// * `data` - It takes the search results fetched from the cache.
+///
+/// # Error
+///
+/// Returns a `SerializationError` if the results could not be decompressed or are not valid
+/// text.
+pub fn decompress(data: Vec<u8>) -> Result<String, Report<CacheError>> {
+    let json: Vec<u8> = if data.starts_with(&GZIP_MAGIC) {
+        let mut json: Vec<u8> = Vec::new();
+        GzDecoder::new(data.as_slice())
+            .read_to_end(&mut json)
+            .change_context(CacheError::SerializationError)?;
+        json
+    } else if data.starts_with(&ZSTD_MAGIC) {
+        zstd::decode_all(data.as_slice()).change_context(CacheError::SerializationError)?
+    } else {
+        data
+    };
+
+    String::from_utf8(json).change_context(CacheError::SerializationError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::aggregation_models::{SearchResult, SearchResults};
+
+    /// A helper function which builds the json of a large result set.
+    fn large_results() -> String {
+        let results: Vec<SearchResult> = (0..100)
+            .map(|index| {
+                SearchResult::new(
+                    &format!("Rust Programming Language {index}"),
+                    &format!("https://www.rust-lang.org/learn/{index}"),
+                    "A language empowering everyone to build reliable and efficient software.",
+                    &["duckduckgo", "searx"],
+                )
+            })
+            .collect();
+        serde_json::to_string(&SearchResults::new(results, "rust", &[])).unwrap()
+    }
+
+    #[test]
+    fn test_compressed_results_are_smaller_and_round_trip() {
+        let json: String = large_results();
+
+        for compression in [CacheCompression::Gzip, CacheCompression::Zstd] {
+            let compressed: Vec<u8> = compress(&json, compression).unwrap();
+            assert!(
+                compressed.len() * 4 < json.len(),
+                "{compression:?} compressed {} bytes into {} bytes",
+                json.len(),
+                compressed.len()
+            );
+            assert_eq!(decompress(compressed).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_uncompressed_entries_are_still_read() {
+        let json: String = large_results();
+        let stored: Vec<u8> = compress(&json, CacheCompression::None).unwrap();
+        assert_eq!(stored, json.as_bytes());
+        assert_eq!(decompress(stored).unwrap(), json);
+    }
+}
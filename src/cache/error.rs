@@ -12,8 +12,9 @@ pub enum CacheError {
     #[cfg(feature = "redis-cache")]
     RedisError(RedisError),
     /// This variant handles the errors which occurs when all the connections
-    /// in the connection pool return a connection dropped redis error.
-    PoolExhaustionWithConnectionDropError,
+    /// in the connection pool return a connection dropped redis error and none of
+    /// them could be re-established.
+    PoolError,
     /// Whenever serialization or deserialization fails during communication with the cache.
     SerializationError,
     /// Returned when the value is missing.
@@ -31,10 +32,10 @@ impl fmt::Display for CacheError {
                     write!(f, "")
                 }
             }
-            CacheError::PoolExhaustionWithConnectionDropError => {
+            CacheError::PoolError => {
                 write!(
                     f,
-                    "Error all connections from the pool dropped with connection error"
+                    "Error all connections from the pool dropped with connection error and could not be re-established"
                 )
             }
             CacheError::MissingValue => {
@@ -2,6 +2,7 @@
 //! results fetched and aggregated from the upstream search engines in a json format.
 
 pub mod cacher;
+pub mod compression;
 pub mod error;
 #[cfg(feature = "redis-cache")]
 pub mod redis_cacher;
@@ -4,14 +4,43 @@
 use error_stack::Report;
 use futures::future::try_join_all;
 use md5::compute;
-use redis::{aio::ConnectionManager, AsyncCommands, Client, RedisError};
+use redis::{aio::ConnectionManager, cmd, Client, Cmd, FromRedisValue, RedisError};
+use std::{future::Future, time::Duration};
 
-use super::error::CacheError;
+use super::{
+    compression::{compress, decompress},
+    error::CacheError,
+};
+use crate::models::parser_models::CacheCompression;
+use crate::results::favicon::Favicon;
+
+/// The default prefix which namespaces all the keys stored by websurfx in the redis server, so
+/// that they can be told apart from the keys of the other applications sharing the same redis
+/// server.
+pub const DEFAULT_KEY_PREFIX: &str = "websurfx:";
+
+/// The number of keys which are asked for on every iteration while scanning the redis server.
+const SCAN_BATCH_SIZE: usize = 500;
+
+/// The time to live (in seconds) of the cached favicons, favicons rarely change and so they are
+/// cached for a week.
+const FAVICON_TTL: u64 = 7 * 24 * 60 * 60;
+
+/// The time to live (in seconds) of the cached thumbnails, they are cached for a day as the
+/// thumbnails of the results are requested far less often than the favicons.
+const THUMBNAIL_TTL: u64 = 24 * 60 * 60;
+
+/// The base delay (in milliseconds) which is doubled on every failed reconnection attempt.
+const BASE_BACKOFF_DELAY: u64 = 50;
+/// The maximum number of attempts made to re-establish a dropped connection.
+const MAX_RECONNECTION_ATTEMPTS: u32 = 5;
 
 /// A named struct which stores the redis Connection url address to which the client will
 /// connect to.
 #[derive(Clone)]
 pub struct RedisCache {
+    /// It stores the redis client which is used to re-establish dropped connections.
+    client: Client,
     /// It stores a pool of connections ready to be used.
     connection_pool: Vec<ConnectionManager>,
     /// It stores the size of the connection pool (in other words the number of
@@ -19,6 +48,12 @@ pub struct RedisCache {
     pool_size: u8,
     /// It stores the index of which connection is being used at the moment.
     current_connection: u8,
+    /// It stores the time (secs) for which the search results are cached.
+    cache_expiry: u64,
+    /// It stores the prefix which namespaces all the keys stored in the redis server.
+    key_prefix: String,
+    /// It stores the compression format of the cached search results.
+    compression: CacheCompression,
 }
 
 impl RedisCache {
@@ -29,6 +64,10 @@ impl RedisCache {
     /// * `redis_connection_url` - It takes the redis Connection url address.
     /// * `pool_size` - It takes the size of the connection pool (in other words the number of
     /// connections that should be stored in the pool).
+    /// * `cache_expiry` - It takes the time (secs) for which the search results are cached.
+    /// * `key_prefix` - It takes the prefix which namespaces all the keys stored in the redis
+    /// server.
+    /// * `compression` - It takes the compression format of the cached search results.
     ///
     /// # Error
     ///
@@ -37,6 +76,9 @@ impl RedisCache {
     pub async fn new(
         redis_connection_url: &str,
         pool_size: u8,
+        cache_expiry: u64,
+        key_prefix: &str,
+        compression: CacheCompression,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let client = Client::open(redis_connection_url)?;
         let mut tasks: Vec<_> = Vec::new();
@@ -47,8 +89,12 @@ impl RedisCache {
 
         let redis_cache = RedisCache {
             connection_pool: try_join_all(tasks).await?,
+            client,
             pool_size,
             current_connection: Default::default(),
+            cache_expiry,
+            key_prefix: key_prefix.to_owned(),
+            compression,
         };
         Ok(redis_cache)
     }
@@ -62,7 +108,8 @@ impl RedisCache {
         format!("{:?}", compute(url))
     }
 
-    /// A function which fetches the cached json results as json string from the redis server.
+    /// A function which fetches the cached json results as json string from the redis server,
+    /// the compressed results are decompressed whatever the configured compression format is.
     ///
     /// # Arguments
     ///
@@ -73,46 +120,16 @@ impl RedisCache {
     /// Returns the results as a String from the cache on success otherwise returns a `CacheError`
     /// on a failure.
     pub async fn cached_json(&mut self, url: &str) -> Result<String, Report<CacheError>> {
-        self.current_connection = Default::default();
-        let hashed_url_string: &str = &self.hash_url(url);
-
-        let mut result: Result<String, RedisError> = self.connection_pool
-            [self.current_connection as usize]
-            .get(hashed_url_string)
-            .await;
-
-        // Code to check whether the current connection being used is dropped with connection error
-        // or not. if it drops with the connection error then the current connection is replaced
-        // with a new connection from the pool which is then used to run the redis command then
-        // that connection is also checked whether it is dropped or not if it is not then the
-        // result is passed as a `Result` or else the same process repeats again and if all of the
-        // connections in the pool result in connection drop error then a custom pool error is
-        // returned.
-        loop {
-            match result {
-                Err(error) => match error.is_connection_dropped() {
-                    true => {
-                        self.current_connection += 1;
-                        if self.current_connection == self.pool_size {
-                            return Err(Report::new(
-                                CacheError::PoolExhaustionWithConnectionDropError,
-                            ));
-                        }
-                        result = self.connection_pool[self.current_connection as usize]
-                            .get(hashed_url_string)
-                            .await;
-                        continue;
-                    }
-                    false => return Err(Report::new(CacheError::RedisError(error))),
-                },
-                Ok(res) => return Ok(res),
-            }
-        }
+        let mut command = cmd("GET");
+        command.arg(namespaced_key(&self.key_prefix, &self.hash_url(url)));
+
+        let data: Option<Vec<u8>> = self.execute(&command).await?;
+        decompress(data.ok_or_else(|| Report::new(CacheError::MissingValue))?)
     }
 
     /// A function which caches the results by using the hashed `url` as the key and
-    /// `json results` as the value and stores it in redis server with ttl(time to live)
-    /// set to 60 seconds.
+    /// `json results` (compressed with the configured compression format) as the value and stores
+    /// it in redis server with ttl(time to live) set to the configured cache expiry.
     ///
     /// # Arguments
     ///
@@ -128,40 +145,479 @@ impl RedisCache {
         json_results: &str,
         url: &str,
     ) -> Result<(), Report<CacheError>> {
+        let mut command = cmd("SETEX");
+        command
+            .arg(namespaced_key(&self.key_prefix, &self.hash_url(url)))
+            .arg(self.cache_expiry)
+            .arg(compress(json_results, self.compression)?);
+
+        self.execute(&command).await
+    }
+
+    /// A function which fetches the cached favicon of the provided domain from the redis server.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - It takes the domain whose favicon needs to be fetched.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached `Favicon` on success otherwise returns a `CacheError` on a failure or
+    /// if the favicon is not cached.
+    pub async fn cached_favicon(&mut self, domain: &str) -> Result<Favicon, Report<CacheError>> {
+        let key: String = self.image_key("favicon", domain);
+        self.cached_image(&key).await
+    }
+
+    /// A function which caches the favicon of the provided domain in the redis server with ttl
+    /// (time to live) set to a week.
+    ///
+    /// # Arguments
+    ///
+    /// * `favicon` - It takes the favicon which needs to be cached.
+    /// * `domain` - It takes the domain of the favicon.
+    ///
+    /// # Error
+    ///
+    /// Returns an unit type if the favicon is cached succesfully otherwise returns a `CacheError`
+    /// on a failure.
+    pub async fn cache_favicon(
+        &mut self,
+        favicon: &Favicon,
+        domain: &str,
+    ) -> Result<(), Report<CacheError>> {
+        let key: String = self.image_key("favicon", domain);
+        self.cache_image(favicon, &key, FAVICON_TTL).await
+    }
+
+    /// A function which fetches the cached thumbnail of the provided url from the redis server.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the thumbnail which needs to be fetched.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached thumbnail on success otherwise returns a `CacheError` on a failure or
+    /// if the thumbnail is not cached.
+    pub async fn cached_thumbnail(&mut self, url: &str) -> Result<Favicon, Report<CacheError>> {
+        let key: String = self.image_key("thumbnail", url);
+        self.cached_image(&key).await
+    }
+
+    /// A function which caches the thumbnail of the provided url in the redis server with ttl
+    /// (time to live) set to a day.
+    ///
+    /// # Arguments
+    ///
+    /// * `thumbnail` - It takes the thumbnail which needs to be cached.
+    /// * `url` - It takes the url of the thumbnail.
+    ///
+    /// # Error
+    ///
+    /// Returns an unit type if the thumbnail is cached succesfully otherwise returns a
+    /// `CacheError` on a failure.
+    pub async fn cache_thumbnail(
+        &mut self,
+        thumbnail: &Favicon,
+        url: &str,
+    ) -> Result<(), Report<CacheError>> {
+        let key: String = self.image_key("thumbnail", url);
+        self.cache_image(thumbnail, &key, THUMBNAIL_TTL).await
+    }
+
+    /// A helper function which fetches the image cached under the provided key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the namespaced key of the image.
+    ///
+    /// # Error
+    ///
+    /// Returns a `CacheError` on a failure or if the image is not cached.
+    async fn cached_image(&mut self, key: &str) -> Result<Favicon, Report<CacheError>> {
+        let mut command = cmd("HMGET");
+        command.arg(key).arg("content_type").arg("body");
+
+        let (content_type, body): (Option<String>, Option<Vec<u8>>) =
+            self.execute(&command).await?;
+
+        match content_type.zip(body) {
+            Some((content_type, body)) => Ok(Favicon::new(&content_type, body)),
+            None => Err(Report::new(CacheError::MissingValue)),
+        }
+    }
+
+    /// A helper function which caches the provided image under the provided key.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - It takes the image which needs to be cached.
+    /// * `key` - It takes the namespaced key of the image.
+    /// * `ttl` - It takes the time to live (in seconds) of the cached image.
+    ///
+    /// # Error
+    ///
+    /// Returns a `CacheError` on a failure.
+    async fn cache_image(
+        &mut self,
+        image: &Favicon,
+        key: &str,
+        ttl: u64,
+    ) -> Result<(), Report<CacheError>> {
+        let mut command = cmd("HSET");
+        command
+            .arg(key)
+            .arg("content_type")
+            .arg(&image.content_type)
+            .arg("body")
+            .arg(&image.body);
+        self.execute::<()>(&command).await?;
+
+        let mut command = cmd("EXPIRE");
+        command.arg(key).arg(ttl);
+        self.execute(&command).await
+    }
+
+    /// A helper function which computes the key under which an image (like the favicon of a
+    /// domain or a thumbnail) is cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - It takes the kind of the image, which is used to namespace the key.
+    /// * `source` - It takes the domain or the url of the image.
+    fn image_key(&self, kind: &str, source: &str) -> String {
+        namespaced_key(
+            &self.key_prefix,
+            &format!("{kind}:{}", self.hash_url(source)),
+        )
+    }
+
+    /// A function which deletes all the keys stored by websurfx (the cached results and images)
+    /// from the redis server. Only the keys starting with the configured namespace prefix are
+    /// deleted, so the keys of the other applications sharing the redis server are never touched.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of deleted keys on success otherwise returns a `CacheError` on a
+    /// failure.
+    pub async fn flush_prefix(&mut self) -> Result<usize, Report<CacheError>> {
+        let mut cursor: u64 = 0;
+        let mut removed: usize = 0;
+
+        loop {
+            let mut command = cmd("SCAN");
+            command
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(scan_pattern(&self.key_prefix))
+                .arg("COUNT")
+                .arg(SCAN_BATCH_SIZE);
+            let (next_cursor, keys): (u64, Vec<String>) = self.execute(&command).await?;
+
+            if !keys.is_empty() {
+                let mut command = cmd("DEL");
+                command.arg(&keys);
+                removed += self.execute::<usize>(&command).await?;
+            }
+
+            match next_cursor {
+                0 => return Ok(removed),
+                _ => cursor = next_cursor,
+            }
+        }
+    }
+
+    /// A helper function which runs the provided redis command on the connections from the pool.
+    ///
+    /// Code to check whether the current connection being used is dropped with connection error
+    /// or not. If it drops with the connection error then the connection is re-established with
+    /// an exponential backoff and the command is retried on it once. If the connection could not
+    /// be re-established or it drops again then it is replaced with the next connection from the
+    /// pool and the same process repeats again and if all of the connections in the pool result
+    /// in connection drop error then a custom pool error is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - It takes the redis command which needs to be run.
+    ///
+    /// # Error
+    ///
+    /// Returns the value returned by the redis server on success otherwise returns a `CacheError`
+    /// on a failure.
+    async fn execute<T: FromRedisValue>(&mut self, command: &Cmd) -> Result<T, Report<CacheError>> {
         self.current_connection = Default::default();
-        let hashed_url_string: &str = &self.hash_url(url);
-
-        let mut result: Result<(), RedisError> = self.connection_pool
-            [self.current_connection as usize]
-            .set_ex(hashed_url_string, json_results, 60)
-            .await;
-
-        // Code to check whether the current connection being used is dropped with connection error
-        // or not. if it drops with the connection error then the current connection is replaced
-        // with a new connection from the pool which is then used to run the redis command then
-        // that connection is also checked whether it is dropped or not if it is not then the
-        // result is passed as a `Result` or else the same process repeats again and if all of the
-        // connections in the pool result in connection drop error then a custom pool error is
-        // returned.
+        let mut reconnected: bool = false;
+
         loop {
+            let result: Result<T, RedisError> = command
+                .query_async(&mut self.connection_pool[self.current_connection as usize])
+                .await;
+
             match result {
                 Err(error) => match error.is_connection_dropped() {
                     true => {
+                        if !reconnected && self.reconnect().await.is_ok() {
+                            reconnected = true;
+                            continue;
+                        }
+                        reconnected = false;
                         self.current_connection += 1;
                         if self.current_connection == self.pool_size {
-                            return Err(Report::new(
-                                CacheError::PoolExhaustionWithConnectionDropError,
-                            ));
+                            return Err(Report::new(CacheError::PoolError));
                         }
-                        result = self.connection_pool[self.current_connection as usize]
-                            .set_ex(hashed_url_string, json_results, 60)
-                            .await;
-                        continue;
                     }
                     false => return Err(Report::new(CacheError::RedisError(error))),
                 },
-                Ok(_) => return Ok(()),
+                Ok(res) => return Ok(res),
             }
         }
     }
+
+    /// A helper function which re-establishes the current connection of the pool by replacing it
+    /// with a newly created connection.
+    ///
+    /// # Error
+    ///
+    /// Returns an unit type if the connection was re-established successfully otherwise returns
+    /// a `CacheError` once all the reconnection attempts have failed.
+    async fn reconnect(&mut self) -> Result<(), Report<CacheError>> {
+        let client: &Client = &self.client;
+        let connection = with_backoff(|| client.get_tokio_connection_manager())
+            .await
+            .map_err(|error| {
+                log::error!("Redis Error: Unable to re-establish connection: {error}");
+                Report::new(CacheError::RedisError(error))
+            })?;
+
+        self.connection_pool[self.current_connection as usize] = connection;
+        Ok(())
+    }
+}
+
+/// A helper function which prefixes the provided key with the namespace prefix.
+///
+/// # Arguments
+///
+/// * `key_prefix` - It takes the prefix which namespaces the keys.
+/// * `key` - It takes the key which needs to be namespaced.
+fn namespaced_key(key_prefix: &str, key: &str) -> String {
+    format!("{key_prefix}{key}")
+}
+
+/// A helper function which builds the `SCAN` match pattern matching all the keys starting with the
+/// provided namespace prefix, the glob characters of the prefix are escaped so that they only
+/// match themselves.
+///
+/// # Arguments
+///
+/// * `key_prefix` - It takes the prefix which namespaces the keys.
+fn scan_pattern(key_prefix: &str) -> String {
+    let mut pattern: String = String::with_capacity(key_prefix.len() + 1);
+    for character in key_prefix.chars() {
+        if matches!(character, '*' | '?' | '[' | ']' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(character);
+    }
+    pattern.push('*');
+    pattern
+}
+
+/// A helper function which runs the provided operation and keeps on retrying it with an
+/// exponentially increasing delay for as long as it fails and until the maximum number of
+/// reconnection attempts is reached.
+///
+/// # Arguments
+///
+/// * `operation` - It takes a closure which produces the future of the operation to be run.
+///
+/// # Error
+///
+/// Returns the value produced by the operation on success otherwise returns the error produced
+/// by the last attempt.
+async fn with_backoff<T, F, Fut>(mut operation: F) -> Result<T, RedisError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, RedisError>>,
+{
+    let mut attempt: u32 = 0;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(error) => {
+                attempt += 1;
+                if attempt == MAX_RECONNECTION_ATTEMPTS {
+                    return Err(error);
+                }
+                tokio::time::sleep(backoff_delay(attempt)).await;
+            }
+        }
+    }
+}
+
+/// A helper function which computes the delay to wait for before the provided attempt.
+///
+/// # Arguments
+///
+/// * `attempt` - It takes the number of attempts which have failed so far.
+fn backoff_delay(attempt: u32) -> Duration {
+    Duration::from_millis(BASE_BACKOFF_DELAY * 2_u64.pow(attempt.saturating_sub(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Error, ErrorKind},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// A helper function which waits for a command to be sent to the fake redis server.
+    ///
+    /// # Returns
+    ///
+    /// It returns whether a command was received, which is not the case once the connection is
+    /// closed.
+    async fn receive_command(socket: &TcpStream) -> bool {
+        let mut buffer: [u8; 1024] = [0; 1024];
+        loop {
+            if socket.readable().await.is_err() {
+                return false;
+            }
+            match socket.try_read(&mut buffer) {
+                Ok(read) => return read > 0,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => continue,
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// A helper function which constructs a cache with a single connection to the provided
+    /// address.
+    async fn connect(address: std::net::SocketAddr) -> RedisCache {
+        RedisCache::new(
+            &format!("redis://{address}"),
+            1,
+            60,
+            DEFAULT_KEY_PREFIX,
+            CacheCompression::None,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn test_namespaced_key_matches_the_flush_pattern() {
+        assert_eq!(namespaced_key(DEFAULT_KEY_PREFIX, "abc"), "websurfx:abc");
+        assert_eq!(
+            namespaced_key("search:", "favicon:abc"),
+            "search:favicon:abc"
+        );
+        assert_eq!(scan_pattern(DEFAULT_KEY_PREFIX), "websurfx:*");
+        // the glob characters of the prefix only match themselves.
+        assert_eq!(scan_pattern("app[1]*:"), "app\\[1\\]\\*:*");
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles() {
+        assert_eq!(backoff_delay(1), Duration::from_millis(BASE_BACKOFF_DELAY));
+        assert_eq!(
+            backoff_delay(2),
+            Duration::from_millis(BASE_BACKOFF_DELAY * 2)
+        );
+        assert_eq!(
+            backoff_delay(3),
+            Duration::from_millis(BASE_BACKOFF_DELAY * 4)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_backoff_recovers_from_dropped_connection() {
+        let mut attempts: u32 = 0;
+        let result = with_backoff(|| {
+            attempts += 1;
+            let current_attempt = attempts;
+            async move {
+                match current_attempt {
+                    1 => Err(RedisError::from(Error::from(ErrorKind::ConnectionReset))),
+                    _ => Ok("reconnected"),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "reconnected");
+        assert_eq!(attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_with_backoff_gives_up_after_max_attempts() {
+        let mut attempts: u32 = 0;
+        let result: Result<(), RedisError> = with_backoff(|| {
+            attempts += 1;
+            async { Err(RedisError::from(Error::from(ErrorKind::BrokenPipe))) }
+        })
+        .await;
+
+        assert!(result.unwrap_err().is_connection_dropped());
+        assert_eq!(attempts, MAX_RECONNECTION_ATTEMPTS);
+    }
+
+    #[tokio::test]
+    async fn test_execute_reconnects_and_retries_on_dropped_connection() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let connections: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        // a fake redis server which drops the first connection as soon as a command is sent on
+        // it and answers every command sent on the other connections.
+        let accepted: Arc<AtomicUsize> = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let is_first: bool = accepted.fetch_add(1, Ordering::SeqCst) == 0;
+                tokio::spawn(async move {
+                    while receive_command(&socket).await {
+                        if is_first {
+                            return;
+                        }
+                        if socket.writable().await.is_err()
+                            || socket.try_write(b"$6\r\ncached\r\n").is_err()
+                        {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut cache: RedisCache = connect(address).await;
+        let value: String = cache.execute(cmd("GET").arg("key")).await.unwrap();
+
+        assert_eq!(value, "cached");
+        assert!(connections.load(Ordering::SeqCst) >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_returns_pool_error_once_the_server_is_gone() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let accepting =
+            tokio::spawn(async move { listener.accept().await.map(|(socket, _)| socket).unwrap() });
+
+        let mut cache: RedisCache = connect(address).await;
+        // the server goes away, which drops the connection of the pool and refuses the
+        // reconnection attempts as the listener is closed as well.
+        drop(accepting.await.unwrap());
+
+        let error: Report<CacheError> = cache
+            .execute::<String>(cmd("GET").arg("key"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error.current_context(), CacheError::PoolError));
+    }
 }
@@ -1,12 +1,50 @@
 //! This module provides the functionality to parse the lua config and convert the config options
 //! into rust readable form.
+//!
+//! Some of the config options can also be overridden with environment variables, which take
+//! precedence over the values provided in the config file. The supported environment variables
+//! and the options they override are:
+//!
+//! * `WEBSURFX_BINDING_IP` - overrides the `binding_ip` option.
+//! * `WEBSURFX_PORT` - overrides the `port` option.
+//! * `WEBSURFX_REDIS_URL` - overrides the `redis_url` option.
+//! * `WEBSURFX_THREADS` - overrides the `threads` option.
 
 use crate::handler::paths::{file_path, FileType};
 
-use crate::models::parser_models::{AggregatorConfig, RateLimiter, Style};
+#[cfg(feature = "redis-cache")]
+use crate::models::parser_models::CacheCompression;
+use crate::models::{
+    engine_models::{normalize_region, EngineHandler, DEFAULT_MAX_RESPONSE_BYTES},
+    parser_models::{
+        AggregationStrategy, AggregatorConfig, Bang, CircuitBreakerConfig, QueryLogMode,
+        QueryLogSinkKind, RateLimiter, RequestDelay, RobotsTxt, RouteRateLimit,
+        SafeSearchBlockMode, SecurityHeaders, ServerConfig, Style,
+    },
+};
+use crate::results::sanitizer::sanitize_notice_html;
+use crate::server::client_ip::TrustedProxy;
+use arc_swap::ArcSwap;
 use log::LevelFilter;
 use mlua::Lua;
-use std::{collections::HashMap, fs, thread::available_parallelism};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::Arc,
+    thread::available_parallelism,
+    time::Duration,
+};
+
+/// The environment variables which can be used to override the config file options mapped to the
+/// names of the options they override.
+const ENV_OVERRIDES: [(&str, &str); 4] = [
+    ("WEBSURFX_BINDING_IP", "binding_ip"),
+    ("WEBSURFX_PORT", "port"),
+    ("WEBSURFX_REDIS_URL", "redis_url"),
+    ("WEBSURFX_THREADS", "threads"),
+];
 
 /// A named struct which stores the parsed config file options.
 #[derive(Clone)]
@@ -15,12 +53,27 @@ pub struct Config {
     pub port: u16,
     /// It stores the parsed ip address option on which the server should launch
     pub binding_ip: String,
+    /// It stores the configuration options for the http server.
+    pub server: ServerConfig,
     /// It stores the theming options for the website.
     pub style: Style,
     #[cfg(feature = "redis-cache")]
     /// It stores the redis connection url address on which the redis
     /// client should connect.
     pub redis_url: String,
+    #[cfg(feature = "redis-cache")]
+    /// It stores the prefix which namespaces all the keys stored in the redis server.
+    pub redis_prefix: String,
+    #[cfg(feature = "redis-cache")]
+    /// It stores the compression format of the search results stored in the redis server.
+    pub cache_compression: CacheCompression,
+    /// It stores the time (secs) for which the search results are cached.
+    pub cache_expiry: u64,
+    /// It stores the minimum number of results the search results should have to be cached.
+    pub min_results_to_cache: usize,
+    /// It stores the option to whether cache the results of every upstream search engine on their
+    /// own, so that they are reused by the searches made with another selection of engines.
+    pub cache_per_engine: bool,
     /// It stores the option to whether enable or disable production use.
     pub aggregator: AggregatorConfig,
     /// It stores the option to whether enable or disable logs.
@@ -31,18 +84,32 @@ pub struct Config {
     pub upstream_search_engines: Vec<crate::models::engine_models::EngineHandler>,
     /// It stores the time (secs) which controls the server request timeout.
     pub request_timeout: u8,
-    /// It stores the number of threads which controls the app will use to run.
+    /// It stores the number of threads which controls the app will use to run, it is always at
+    /// least `1` as a value of `0` in the config file is resolved to the number of available cpus
+    /// (it is only `0` for the values out of range, which are reported by the validation).
     pub threads: u8,
     /// It stores configuration options for the ratelimiting middleware.
     pub rate_limiter: RateLimiter,
     /// It stores the level of safe search to be used for restricting content in the
     /// search results.
     pub safe_search: u8,
+    /// It stores the option to whether enforce the level of safe search from the config file by
+    /// ignoring the level provided in the search url or saved in the cookie.
+    pub safe_search_locked: bool,
+    /// It stores how the searches for the queries disallowed by the blocklist are answered when
+    /// the safe search level is `4`.
+    pub safe_search_block_mode: SafeSearchBlockMode,
+    /// It stores the default region (like `us-en`) the search results are localized to.
+    pub region: Option<String>,
+    /// It stores the token which is required to access the admin only routes. The admin only
+    /// routes are disabled if it is not provided.
+    pub admin_token: Option<String>,
 }
 
 impl Config {
     /// A function which parses the config.lua file and puts all the parsed options in the newly
-    /// constructed Config struct and returns it.
+    /// constructed Config struct and returns it. The options which have a corresponding environment
+    /// variable set are overridden with the value of the environment variable.
     ///
     /// # Arguments
     ///
@@ -52,16 +119,66 @@ impl Config {
     /// # Error
     ///
     /// Returns a lua parse error if parsing of the config.lua file fails or has a syntax error
-    /// or io error if the config.lua file doesn't exists otherwise it returns a newly constructed
-    /// Config struct with all the parsed config options from the parsed config file.
+    /// or io error if the config.lua file doesn't exists or a `ConfigError` if any of the options
+    /// is invalid otherwise it returns a newly constructed Config struct with all the parsed config
+    /// options from the parsed config file.
     pub fn parse(logging_initialized: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::parse_file(file_path(FileType::Config)?, logging_initialized, &env_var)
+    }
+
+    /// A function which loads and fully validates the provided config file without starting the
+    /// server, which allows checking a config file before deploying it (like with the
+    /// `--check-config` flag of the binary). The config file goes through the same parsing and
+    /// validation as on the startup of the server, but no socket is bound and the redis server is
+    /// never connected to.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - It takes the path of the config file to be validated.
+    ///
+    /// # Error
+    ///
+    /// Returns a lua parse error if the config file has a syntax error, an io error if it does not
+    /// exist or a `ConfigError` enumerating all the problems found in the config options at once.
+    pub fn validate_only(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::parse_file(path, true, &env_var)
+    }
+
+    /// A helper function which parses the provided config file into the Config struct and
+    /// validates it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - It takes the path of the config file.
+    /// * `logging_initialized` - It takes a boolean which ensures that the logging doesn't get
+    /// initialized twice.
+    /// * `env` - It takes the function which looks up the value of an environment variable.
+    ///
+    /// # Error
+    ///
+    /// Returns the same errors as the `parse` function.
+    fn parse_file(
+        path: &str,
+        logging_initialized: bool,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let lua = Lua::new();
         let globals = lua.globals();
 
-        lua.load(&fs::read_to_string(file_path(FileType::Config)?)?)
-            .exec()?;
+        lua.load(&fs::read_to_string(path)?).exec()?;
 
-        let parsed_threads: u8 = globals.get::<_, u8>("threads")?;
+        // Overlay the environment variables on top of the parsed config options so that they are
+        // validated in the same way as the options provided in the config file.
+        for (env_var, option) in ENV_OVERRIDES {
+            if let Some(value) = env(env_var) {
+                globals.set(option, value)?;
+            }
+        }
+
+        // the integer options which are validated are read as they are, so that the values out
+        // of the range of their types are reported by the validation instead of failing to be
+        // converted.
+        let parsed_threads: i64 = globals.get::<_, i64>("threads")?;
 
         let debug: bool = globals.get::<_, bool>("debug")?;
         let logging: bool = globals.get::<_, bool>("logging")?;
@@ -70,57 +187,1302 @@ impl Config {
             set_logging_level(debug, logging);
         }
 
-        let threads: u8 = if parsed_threads == 0 {
-            let total_num_of_threads: usize = available_parallelism()?.get() / 2;
-            log::error!(
-                "Config Error: The value of `threads` option should be a non zero positive integer"
-            );
-            log::error!("Falling back to using {} threads", total_num_of_threads);
-            total_num_of_threads as u8
-        } else {
-            parsed_threads
+        let threads: u8 = match u8::try_from(parsed_threads) {
+            Ok(0) => {
+                let threads: u8 = resolve_threads(0, available_parallelism()?.get());
+                log::info!("Using {threads} threads, one for every available cpu");
+                threads
+            }
+            Ok(threads) => threads,
+            // the value is left unresolved so that it is reported by the validation.
+            Err(_) => 0,
         };
 
-        let rate_limiter = globals.get::<_, HashMap<String, u8>>("rate_limiter")?;
+        let rate_limiter: mlua::Table<'_> = globals.get::<_, mlua::Table<'_>>("rate_limiter")?;
 
-        let parsed_safe_search: u8 = globals.get::<_, u8>("safe_search")?;
-        let safe_search: u8 = match parsed_safe_search {
-            0..=4 => parsed_safe_search,
-            _ => {
-                log::error!("Config Error: The value of `safe_search` option should be a non zero positive integer from 0 to 4.");
-                log::error!("Falling back to using the value `1` for the option");
-                1
-            }
-        };
+        let safe_search: u8 = out_of_range_to(globals.get::<_, i64>("safe_search")?, u8::MAX);
+
+        let safe_search_block_mode: SafeSearchBlockMode = parse_safe_search_block_mode(
+            globals
+                .get::<_, Option<String>>("safe_search_block_mode")?
+                .as_deref(),
+        );
+
+        let query_log: QueryLogMode =
+            parse_query_log_mode(globals.get::<_, Option<String>>("query_log")?.as_deref());
+
+        let query_log_sink: QueryLogSinkKind = parse_query_log_sink(
+            globals
+                .get::<_, Option<String>>("query_log_sink")?
+                .as_deref(),
+        );
+
+        let robots_txt: Option<RobotsTxt> = parse_robots_txt(
+            globals.get::<_, Option<String>>("robots_txt")?,
+            globals.get::<_, Option<String>>("robots_txt_file")?,
+        );
+
+        let request_timeout: u8 = globals.get::<_, u8>("request_timeout")?;
+
+        let max_page: u32 = non_zero_or("max_page", globals.get::<_, u32>("max_page")?, 10);
+
+        let max_concurrent_requests: usize = non_zero_or(
+            "max_concurrent_requests",
+            globals.get::<_, usize>("max_concurrent_requests")?,
+            16,
+        );
+
+        let max_query_length: usize = non_zero_or(
+            "max_query_length",
+            globals
+                .get::<_, Option<usize>>("max_query_length")?
+                .unwrap_or(512),
+            512,
+        );
+
+        let max_connections: usize = non_zero_or(
+            "max_connections",
+            globals
+                .get::<_, Option<usize>>("max_connections")?
+                .unwrap_or(25_000),
+            25_000,
+        );
+
+        let client_timeout: u64 = non_zero_or(
+            "client_timeout",
+            globals
+                .get::<_, Option<u64>>("client_timeout")?
+                .unwrap_or(5),
+            5,
+        );
+
+        let cache_expiry: u64 = non_zero_or(
+            "cache_expiry",
+            globals.get::<_, Option<u64>>("cache_expiry")?.unwrap_or(60),
+            60,
+        );
+
+        #[cfg(feature = "redis-cache")]
+        let cache_compression: CacheCompression = parse_cache_compression(
+            globals
+                .get::<_, Option<String>>("cache_compression")?
+                .as_deref(),
+        );
+
+        let mut style: Style = Style::new(
+            globals.get::<_, String>("theme")?,
+            globals.get::<_, String>("colorscheme")?,
+        );
+        style.set_open_in_new_tab(
+            globals
+                .get::<_, Option<bool>>("open_in_new_tab")?
+                .unwrap_or(false),
+        );
+        style.set_notice_html(&sanitize_notice_html(
+            &globals
+                .get::<_, Option<String>>("notice_html")?
+                .unwrap_or_default(),
+        ));
+        let disable_cookies: bool = globals
+            .get::<_, Option<bool>>("disable_cookies")?
+            .unwrap_or(false);
+        style.set_disable_cookies(disable_cookies);
+        style.set_snippet_max_length(
+            globals
+                .get::<_, Option<usize>>("snippet_max_length")?
+                .unwrap_or(0),
+        );
+        let redirect_clicks: bool = globals
+            .get::<_, Option<bool>>("redirect_clicks")?
+            .unwrap_or(false);
+        style.set_redirect_clicks(redirect_clicks);
+        style.set_show_result_stats(
+            globals
+                .get::<_, Option<bool>>("show_result_stats")?
+                .unwrap_or(false),
+        );
+        style.set_templates_path(globals.get::<_, Option<String>>("templates_path")?);
+
+        let region: Option<String> = parse_region(globals.get::<_, Option<String>>("region")?);
 
-        Ok(Config {
-            port: globals.get::<_, u16>("port")?,
-            binding_ip: globals.get::<_, String>("binding_ip")?,
-            style: Style::new(
-                globals.get::<_, String>("theme")?,
-                globals.get::<_, String>("colorscheme")?,
+        let circuit_breaker: CircuitBreakerConfig = parse_circuit_breaker(
+            globals.get::<_, Option<HashMap<String, u64>>>("circuit_breaker")?,
+        );
+
+        let delay: RequestDelay = match globals.get::<_, Option<HashMap<String, u64>>>("delay")? {
+            Some(delay) => parse_request_delay(
+                delay.get("min").copied().unwrap_or(0),
+                delay.get("max").copied().unwrap_or(0),
             ),
+            // the delay of the `production_use` option is kept when no delay is configured.
+            None => match globals.get::<_, bool>("production_use")? {
+                true => parse_request_delay(1000, 10000),
+                false => RequestDelay::disabled(),
+            },
+        };
+
+        let bangs: HashMap<String, Bang> = globals
+            .get::<_, Option<HashMap<String, String>>>("bangs")?
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(name, target)| parse_bang(&name, &target))
+            .collect();
+
+        let engine_entries: Vec<EngineEntry> = globals
+            .get::<_, HashMap<String, mlua::Value<'_>>>("upstream_search_engines")?
+            .into_iter()
+            .filter_map(|(engine, value)| parse_engine_entry(&engine, value))
+            .collect();
+        let upstream_search_engines: Vec<EngineHandler> = engine_entries
+            .iter()
+            .filter_map(|entry| EngineHandler::new(&entry.name))
+            .collect();
+        let engine_weights: HashMap<String, f64> = engine_entries
+            .iter()
+            .map(|entry| (entry.name.clone(), entry.weight))
+            .collect();
+        let engine_base_urls: HashMap<String, String> = engine_entries
+            .iter()
+            .filter_map(|entry| Some((entry.name.clone(), entry.base_url.clone()?)))
+            .collect();
+        let engine_fallbacks: HashMap<String, EngineHandler> = engine_entries
+            .into_iter()
+            .filter_map(|entry| Some((entry.name, entry.fallback?)))
+            .collect();
+
+        let ranking_weights: HashMap<String, f64> = globals
+            .get::<_, Option<HashMap<String, f64>>>("ranking_weights")?
+            .unwrap_or_default();
+
+        let security_headers: SecurityHeaders = parse_security_headers(
+            globals
+                .get::<_, Option<HashMap<String, String>>>("security_headers")?
+                .unwrap_or_default(),
+        );
+
+        let config: Config = Config {
+            port: out_of_range_to(globals.get::<_, i64>("port")?, 0),
+            binding_ip: normalize_binding_ip(&globals.get::<_, String>("binding_ip")?),
+            server: ServerConfig {
+                enable_compression: globals
+                    .get::<_, Option<bool>>("enable_compression")?
+                    .unwrap_or(true),
+                shutdown_timeout: globals
+                    .get::<_, Option<u64>>("shutdown_timeout")?
+                    .unwrap_or(30),
+                max_connections,
+                client_timeout,
+                max_pending_searches: globals
+                    .get::<_, Option<usize>>("max_pending_searches")?
+                    .unwrap_or(0),
+                listen: globals
+                    .get::<_, Option<Vec<String>>>("listen")?
+                    .unwrap_or_default(),
+                access_token: globals
+                    .get::<_, Option<String>>("access_token")?
+                    .filter(|access_token| !access_token.is_empty()),
+                maintenance_mode: globals
+                    .get::<_, Option<bool>>("maintenance_mode")?
+                    .unwrap_or(false),
+                disable_cookies,
+                trusted_proxies: globals
+                    .get::<_, Option<Vec<String>>>("trusted_proxies")?
+                    .unwrap_or_default(),
+                query_log,
+                query_log_sink,
+                query_log_file: globals
+                    .get::<_, Option<String>>("query_log_file")?
+                    .unwrap_or_else(|| "query_log.json".to_owned()),
+                query_log_salt: globals
+                    .get::<_, Option<String>>("query_log_salt")?
+                    .filter(|salt| !salt.is_empty()),
+                robots_txt,
+                security_headers,
+            },
+            style,
             #[cfg(feature = "redis-cache")]
             redis_url: globals.get::<_, String>("redis_url")?,
+            #[cfg(feature = "redis-cache")]
+            redis_prefix: globals
+                .get::<_, Option<String>>("redis_prefix")?
+                .unwrap_or_else(|| crate::cache::redis_cacher::DEFAULT_KEY_PREFIX.to_owned()),
+            #[cfg(feature = "redis-cache")]
+            cache_compression,
+            cache_expiry,
+            min_results_to_cache: globals
+                .get::<_, Option<usize>>("min_results_to_cache")?
+                .unwrap_or(0),
+            cache_per_engine: globals
+                .get::<_, Option<bool>>("cache_per_engine")?
+                .unwrap_or(false),
             aggregator: AggregatorConfig {
-                random_delay: globals.get::<_, bool>("production_use")?,
+                delay,
+                max_page,
+                max_query_length,
+                max_concurrent_requests,
+                max_engines_per_request: globals
+                    .get::<_, Option<usize>>("max_engines_per_request")?
+                    .unwrap_or(0),
+                engine_retries: globals.get::<_, Option<u8>>("engine_retries")?.unwrap_or(1),
+                user_agent: globals.get::<_, Option<String>>("user_agent")?,
+                rotate_user_agent: globals
+                    .get::<_, Option<bool>>("rotate_user_agent")?
+                    .unwrap_or(false),
+                forward_accept_language: globals
+                    .get::<_, Option<bool>>("forward_accept_language")?
+                    .unwrap_or(false),
+                prefetch_ahead: globals
+                    .get::<_, Option<u32>>("prefetch_ahead")?
+                    .unwrap_or(0),
+                bangs,
+                results_per_engine: globals
+                    .get::<_, Option<usize>>("results_per_engine")?
+                    .unwrap_or(0),
+                results_per_engine_overrides: globals
+                    .get::<_, Option<HashMap<String, usize>>>("results_per_engine_overrides")?
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(engine, limit)| (engine.to_lowercase(), limit))
+                    .collect(),
+                allow_private_addresses: globals
+                    .get::<_, Option<bool>>("allow_private_addresses")?
+                    .unwrap_or(false),
+                max_response_bytes: globals
+                    .get::<_, Option<usize>>("max_response_bytes")?
+                    .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES),
+                probe_engines_on_start: globals
+                    .get::<_, Option<bool>>("probe_engines_on_start")?
+                    .unwrap_or(false),
+                probe_engines_strict: globals
+                    .get::<_, Option<bool>>("probe_engines_strict")?
+                    .unwrap_or(false),
+                engine_weights,
+                engine_fallbacks,
+                engine_base_urls,
+                results_per_page: globals
+                    .get::<_, Option<usize>>("results_per_page")?
+                    .unwrap_or(0),
+                max_total_results: globals
+                    .get::<_, Option<usize>>("max_total_results")?
+                    .unwrap_or(0),
+                agreement_weight: ranking_weights.get("agreement").copied().unwrap_or(1.0),
+                position_weight: ranking_weights.get("position").copied().unwrap_or(1.0),
+                min_engines_responded: globals
+                    .get::<_, Option<usize>>("min_engines_responded")?
+                    .unwrap_or(0),
+                clean_urls: globals
+                    .get::<_, Option<bool>>("clean_urls")?
+                    .unwrap_or(false),
+                hide_sponsored: globals
+                    .get::<_, Option<bool>>("hide_sponsored")?
+                    .unwrap_or(false),
+                direct_navigation: globals
+                    .get::<_, Option<bool>>("direct_navigation")?
+                    .unwrap_or(false),
+                proxy_thumbnails: globals
+                    .get::<_, Option<bool>>("proxy_thumbnails")?
+                    .unwrap_or(false),
+                redirect_clicks,
+                log_clicks: globals
+                    .get::<_, Option<bool>>("log_clicks")?
+                    .unwrap_or(false),
+                connection_pool_size: globals
+                    .get::<_, Option<usize>>("connection_pool_size")?
+                    .unwrap_or(16),
+                connect_timeout: globals
+                    .get::<_, Option<u8>>("connect_timeout")?
+                    .unwrap_or(request_timeout),
+                read_timeout: globals.get::<_, Option<u8>>("read_timeout")?.unwrap_or(0),
+                strategy: parse_aggregation_strategy(
+                    globals.get::<_, Option<mlua::Value<'_>>>("strategy")?,
+                ),
+                circuit_breaker,
             },
             logging,
             debug,
-            upstream_search_engines: globals
-                .get::<_, HashMap<String, bool>>("upstream_search_engines")?
-                .into_iter()
-                .filter_map(|(key, value)| value.then_some(key))
-                .filter_map(|engine| crate::models::engine_models::EngineHandler::new(&engine))
-                .collect(),
-            request_timeout: globals.get::<_, u8>("request_timeout")?,
+            upstream_search_engines,
+            request_timeout,
             threads,
             rate_limiter: RateLimiter {
-                number_of_requests: rate_limiter["number_of_requests"],
-                time_limit: rate_limiter["time_limit"],
+                number_of_requests: rate_limiter.get::<_, u8>("number_of_requests")?,
+                time_limit: rate_limiter.get::<_, u8>("time_limit")?,
+                routes: parse_route_rate_limits(
+                    rate_limiter
+                        .get::<_, Option<HashMap<String, HashMap<String, u8>>>>("routes")?
+                        .unwrap_or_default(),
+                ),
             },
             safe_search,
+            safe_search_locked: globals
+                .get::<_, Option<bool>>("safe_search_locked")?
+                .unwrap_or(false),
+            safe_search_block_mode,
+            region,
+            admin_token: globals.get::<_, Option<String>>("admin_token")?,
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// A function which validates the ranges and the formats of the parsed config options, so
+    /// that a misconfigured option is reported clearly at startup instead of failing at runtime.
+    ///
+    /// # Error
+    ///
+    /// Returns a `ConfigError` enumerating all the problems found in the config options at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems: Vec<String> = Vec::new();
+
+        if self.port == 0 {
+            problems.push("The value of `port` option should be from 1 to 65535".to_owned());
+        }
+        if self.binding_ip.parse::<IpAddr>().is_err() {
+            problems.push(format!(
+                "The value of `binding_ip` option `{}` is not a valid ip address",
+                self.binding_ip
+            ));
+        }
+        for address in &self.server.listen {
+            if address.parse::<SocketAddr>().is_err() {
+                problems.push(format!(
+                    "The entry `{address}` of the `listen` option is not a valid `ip:port` address"
+                ));
+            }
+        }
+        for proxy in &self.server.trusted_proxies {
+            if TrustedProxy::parse(proxy).is_none() {
+                problems.push(format!(
+                    "The entry `{proxy}` of the `trusted_proxies` option is not a valid ip address or network (like `10.0.0.0/8`)"
+                ));
+            }
+        }
+        #[cfg(not(feature = "redis-cache"))]
+        if self.server.query_log != QueryLogMode::Off
+            && self.server.query_log_sink == QueryLogSinkKind::Redis
+        {
+            problems.push(
+                "The `redis` sink of the `query_log_sink` option requires the `redis-cache` feature"
+                    .to_owned(),
+            );
+        }
+        match &self.server.robots_txt {
+            Some(RobotsTxt::Inline(content)) if content.trim().is_empty() => {
+                problems.push("The value of `robots_txt` option should not be empty".to_owned())
+            }
+            Some(RobotsTxt::File(path)) if !Path::new(path).is_file() => problems.push(format!(
+                "The file `{path}` of the `robots_txt_file` option does not exist"
+            )),
+            _ => (),
+        }
+        for (name, value) in self.server.security_headers.headers() {
+            if actix_web::http::header::HeaderValue::from_str(value).is_err() {
+                problems.push(format!(
+                    "The value of the `{name}` header of the `security_headers` option is not a valid header value"
+                ));
+            }
+        }
+        for (name, weight) in [
+            ("agreement", self.aggregator.agreement_weight),
+            ("position", self.aggregator.position_weight),
+        ] {
+            if !weight.is_finite() || weight < 0.0 {
+                problems.push(format!(
+                    "The `{name}` weight of the `ranking_weights` option should be a positive number or zero"
+                ));
+            }
+        }
+        if self.aggregator.connect_timeout == 0 {
+            problems.push("The value of `connect_timeout` option should be at least 1".to_owned());
+        }
+        if self.threads == 0 {
+            problems.push("The value of `threads` option should be from 0 to 255".to_owned());
+        }
+        if self.safe_search > 4 {
+            problems.push("The value of `safe_search` option should be from 0 to 4".to_owned());
+        }
+        if self.upstream_search_engines.is_empty() {
+            problems.push(
+                "The `upstream_search_engines` option should enable at least one known upstream search engine"
+                    .to_owned(),
+            );
+        }
+        for (engine, base_url) in &self.aggregator.engine_base_urls {
+            if normalize_base_url(base_url).is_none() {
+                problems.push(format!(
+                    "The base url `{base_url}` of the upstream search engine `{engine}` should be an `http` or `https` url without a query"
+                ));
+            }
+        }
+        // the searxng engine has no built-in upstream instance to fall back to.
+        if self
+            .upstream_search_engines
+            .iter()
+            .any(|engine| engine.name() == "searxng")
+            && self.aggregator.engine_base_url("searxng").is_none()
+        {
+            problems.push(
+                "The `SearxNG` engine requires the `base_url` of the searxng instance (like `SearxNG = { base_url = \"https://searxng.example.org\" }`)"
+                    .to_owned(),
+            );
+        }
+        #[cfg(feature = "redis-cache")]
+        if !is_valid_redis_url(&self.redis_url) {
+            problems.push(format!(
+                "The value of `redis_url` option `{}` is not a valid redis url (like `redis://127.0.0.1:8082`)",
+                self.redis_url
+            ));
+        }
+        // an empty prefix would make flushing the cache delete the keys of the other applications.
+        #[cfg(feature = "redis-cache")]
+        if self.redis_prefix.is_empty() {
+            problems.push("The value of `redis_prefix` option should not be empty".to_owned());
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(ConfigError { problems }),
+        }
+    }
+}
+
+/// A custom error type which enumerates all the problems found while validating the config
+/// options.
+#[derive(Debug)]
+pub struct ConfigError {
+    /// It stores the descriptions of all the problems found in the config options.
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Config Error: The config file has the following problems:"
+        )?;
+        for problem in &self.problems {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// A helper function which checks whether the provided url looks like the url of a reachable
+/// redis server, that is a `redis` or `rediss` url with a host or a `redis+unix` or `unix` url
+/// with a socket path.
+///
+/// # Arguments
+///
+/// * `redis_url` - It takes the parsed `redis_url` option.
+#[cfg(feature = "redis-cache")]
+fn is_valid_redis_url(redis_url: &str) -> bool {
+    reqwest::Url::parse(redis_url).is_ok_and(|url| match url.scheme() {
+        "redis" | "rediss" => url.host_str().is_some_and(|host| !host.is_empty()),
+        "redis+unix" | "unix" => !url.path().is_empty(),
+        _ => false,
+    })
+}
+
+/// A structure to efficiently share the parsed config between threads while allowing it to be
+/// reloaded without restarting the server - as it is swapped atomically.
+pub struct SharedConfig {
+    /// The currently loaded config which is swapped atomically on reload.
+    config: ArcSwap<Config>,
+}
+
+impl SharedConfig {
+    /// A function that creates a new `SharedConfig` from the parsed config.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config as an argument.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config: ArcSwap::from_pointee(config),
+        }
+    }
+
+    /// A function which returns the currently loaded config. The returned config stays unchanged
+    /// for as long as it is held, even if the config is reloaded in the meantime.
+    pub fn load(&self) -> Arc<Config> {
+        self.config.load_full()
+    }
+
+    /// A function which parses the config file again and replaces the currently loaded config with
+    /// it. The options which can not be changed while the server is running (like `port` or
+    /// `threads`) keep their current values and a warning is logged if they were changed.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the config file could not be parsed, in which case the currently loaded
+    /// config is kept.
+    pub fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut config: Config = Config::parse(true)?;
+        let current: Arc<Config> = self.load();
+
+        for option in keep_immutable_options(&mut config, &current) {
+            log::warn!(
+                "The `{option}` option can not be changed while the server is running and the change is ignored until it is restarted"
+            );
+        }
+
+        self.config.store(Arc::new(config));
+        Ok(())
+    }
+}
+
+/// A helper function which restores the options of the reloaded config which can not be changed
+/// while the server is running, as they are only used while starting the server, to their current
+/// values.
+///
+/// # Arguments
+///
+/// * `config` - It takes the reloaded config.
+/// * `current` - It takes the currently loaded config.
+///
+/// # Returns
+///
+/// It returns the names of the options which were changed in the reloaded config.
+fn keep_immutable_options(config: &mut Config, current: &Config) -> Vec<&'static str> {
+    let mut changed: Vec<&'static str> = Vec::new();
+
+    keep_option("port", &mut config.port, &current.port, &mut changed);
+    keep_option(
+        "binding_ip",
+        &mut config.binding_ip,
+        &current.binding_ip,
+        &mut changed,
+    );
+    keep_option(
+        "threads",
+        &mut config.threads,
+        &current.threads,
+        &mut changed,
+    );
+    keep_option(
+        "logging",
+        &mut config.logging,
+        &current.logging,
+        &mut changed,
+    );
+    keep_option("debug", &mut config.debug, &current.debug, &mut changed);
+    #[cfg(feature = "redis-cache")]
+    keep_option(
+        "redis_url",
+        &mut config.redis_url,
+        &current.redis_url,
+        &mut changed,
+    );
+    #[cfg(feature = "redis-cache")]
+    keep_option(
+        "redis_prefix",
+        &mut config.redis_prefix,
+        &current.redis_prefix,
+        &mut changed,
+    );
+    #[cfg(feature = "redis-cache")]
+    keep_option(
+        "cache_compression",
+        &mut config.cache_compression,
+        &current.cache_compression,
+        &mut changed,
+    );
+    keep_option(
+        "cache_expiry",
+        &mut config.cache_expiry,
+        &current.cache_expiry,
+        &mut changed,
+    );
+    keep_option(
+        "enable_compression",
+        &mut config.server.enable_compression,
+        &current.server.enable_compression,
+        &mut changed,
+    );
+    keep_option(
+        "shutdown_timeout",
+        &mut config.server.shutdown_timeout,
+        &current.server.shutdown_timeout,
+        &mut changed,
+    );
+    keep_option(
+        "listen",
+        &mut config.server.listen,
+        &current.server.listen,
+        &mut changed,
+    );
+    // the allowed headers of the cors policy are only set up when the server starts.
+    keep_option(
+        "disable_cookies",
+        &mut config.server.disable_cookies,
+        &current.server.disable_cookies,
+        &mut changed,
+    );
+    config
+        .style
+        .set_disable_cookies(config.server.disable_cookies);
+    // the templates are only registered when the server starts.
+    keep_option(
+        "templates_path",
+        &mut config.style.templates_path,
+        &current.style.templates_path,
+        &mut changed,
+    );
+    // the rate limiters are only built when the server starts.
+    keep_option(
+        "trusted_proxies",
+        &mut config.server.trusted_proxies,
+        &current.server.trusted_proxies,
+        &mut changed,
+    );
+    // the query log is only set up when the server starts.
+    keep_option(
+        "query_log",
+        &mut config.server.query_log,
+        &current.server.query_log,
+        &mut changed,
+    );
+    keep_option(
+        "query_log_sink",
+        &mut config.server.query_log_sink,
+        &current.server.query_log_sink,
+        &mut changed,
+    );
+    keep_option(
+        "query_log_file",
+        &mut config.server.query_log_file,
+        &current.server.query_log_file,
+        &mut changed,
+    );
+    keep_option(
+        "query_log_salt",
+        &mut config.server.query_log_salt,
+        &current.server.query_log_salt,
+        &mut changed,
+    );
+    // the security headers are only set up when the server starts.
+    keep_option(
+        "security_headers",
+        &mut config.server.security_headers,
+        &current.server.security_headers,
+        &mut changed,
+    );
+    keep_option(
+        "access_token",
+        &mut config.server.access_token,
+        &current.server.access_token,
+        &mut changed,
+    );
+    keep_option(
+        "max_connections",
+        &mut config.server.max_connections,
+        &current.server.max_connections,
+        &mut changed,
+    );
+    keep_option(
+        "client_timeout",
+        &mut config.server.client_timeout,
+        &current.server.client_timeout,
+        &mut changed,
+    );
+    // the search queue is only set up when the server starts.
+    keep_option(
+        "max_pending_searches",
+        &mut config.server.max_pending_searches,
+        &current.server.max_pending_searches,
+        &mut changed,
+    );
+    keep_option(
+        "rate_limiter",
+        &mut config.rate_limiter.number_of_requests,
+        &current.rate_limiter.number_of_requests,
+        &mut changed,
+    );
+    keep_option(
+        "rate_limiter",
+        &mut config.rate_limiter.time_limit,
+        &current.rate_limiter.time_limit,
+        &mut changed,
+    );
+    keep_option(
+        "rate_limiter",
+        &mut config.rate_limiter.routes,
+        &current.rate_limiter.routes,
+        &mut changed,
+    );
+    keep_option(
+        "max_concurrent_requests",
+        &mut config.aggregator.max_concurrent_requests,
+        &current.aggregator.max_concurrent_requests,
+        &mut changed,
+    );
+    keep_option(
+        "connection_pool_size",
+        &mut config.aggregator.connection_pool_size,
+        &current.aggregator.connection_pool_size,
+        &mut changed,
+    );
+    keep_option(
+        "connect_timeout",
+        &mut config.aggregator.connect_timeout,
+        &current.aggregator.connect_timeout,
+        &mut changed,
+    );
+    keep_option(
+        "read_timeout",
+        &mut config.aggregator.read_timeout,
+        &current.aggregator.read_timeout,
+        &mut changed,
+    );
+    keep_option(
+        "allow_private_addresses",
+        &mut config.aggregator.allow_private_addresses,
+        &current.aggregator.allow_private_addresses,
+        &mut changed,
+    );
+    keep_option(
+        "max_response_bytes",
+        &mut config.aggregator.max_response_bytes,
+        &current.aggregator.max_response_bytes,
+        &mut changed,
+    );
+
+    changed.dedup();
+    changed
+}
+
+/// A helper function which restores the provided option of the reloaded config to its current
+/// value and records its name if it was changed.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name of the option in the config file.
+/// * `option` - It takes the value of the option in the reloaded config.
+/// * `current` - It takes the value of the option in the currently loaded config.
+/// * `changed` - It takes the names of the changed options recorded so far.
+fn keep_option<T: PartialEq + Clone>(
+    name: &'static str,
+    option: &mut T,
+    current: &T,
+    changed: &mut Vec<&'static str>,
+) {
+    if option != current {
+        changed.push(name);
+        *option = current.clone();
+    }
+}
+
+/// A helper function which parses a bang provided in the `bangs` option of the config file. The
+/// targets starting with `http://` or `https://` are treated as urls to redirect to and all the
+/// other targets are treated as names of the upstream search engines.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name of the bang (with or without the leading `!`).
+/// * `target` - It takes the upstream search engine name or the url the bang maps to.
+///
+/// # Returns
+///
+/// It returns the normalized name of the bang along with the parsed bang or `None` (after logging
+/// a config error) if the target is neither a url nor a known upstream search engine.
+fn parse_bang(name: &str, target: &str) -> Option<(String, Bang)> {
+    let name: String = name.trim_start_matches('!').to_lowercase();
+
+    if target.starts_with("http://") || target.starts_with("https://") {
+        if !target.contains("{query}") {
+            log::warn!(
+                "Config Error: The url of the bang `!{name}` does not contain the `{{query}}` placeholder"
+            );
+        }
+        return Some((name, Bang::Redirect(target.to_owned())));
+    }
+
+    match crate::models::engine_models::EngineHandler::new(target) {
+        Some(engine) => Some((name, Bang::Engine(engine))),
+        None => {
+            log::error!(
+                "Config Error: The bang `!{name}` maps to the unknown upstream search engine `{target}`"
+            );
+            log::error!("Ignoring the bang `!{name}`");
+            None
+        }
+    }
+}
+
+/// A named struct which stores the options of an enabled upstream search engine parsed from the
+/// `upstream_search_engines` option.
+struct EngineEntry {
+    /// It stores the lowercased name of the upstream search engine.
+    name: String,
+    /// It stores the ranking weight of the upstream search engine.
+    weight: f64,
+    /// It stores the engine used in place of the upstream search engine when it fails.
+    fallback: Option<EngineHandler>,
+    /// It stores the override of the base url of the upstream search engine.
+    base_url: Option<String>,
+}
+
+/// A helper function which parses the `strategy` option, which is either the `"wait_all"` string
+/// to wait for all the upstream search engines or a table (like
+/// `{ first_n = { count = 2, deadline = 1500 } }`) to return as soon as `count` engines have
+/// provided their results or `deadline` (millis) has passed.
+///
+/// # Arguments
+///
+/// * `value` - It takes the value of the `strategy` option.
+///
+/// # Returns
+///
+/// It returns the parsed strategy, an invalid strategy is reported as a config error and replaced
+/// with the default `wait_all` strategy.
+fn parse_aggregation_strategy(value: Option<mlua::Value<'_>>) -> AggregationStrategy {
+    let first_n: Option<(usize, u64)> = match value {
+        None => return AggregationStrategy::WaitAll,
+        Some(mlua::Value::String(name)) if name.to_str().ok() == Some("wait_all") => {
+            return AggregationStrategy::WaitAll
+        }
+        Some(mlua::Value::Table(options)) => options
+            .get::<_, Option<mlua::Table<'_>>>("first_n")
+            .ok()
+            .flatten()
+            .and_then(|first_n| {
+                Some((
+                    first_n.get::<_, usize>("count").ok()?,
+                    first_n.get::<_, u64>("deadline").ok()?,
+                ))
+            }),
+        Some(_) => None,
+    };
+
+    match first_n {
+        Some((count, deadline)) if count > 0 && deadline > 0 => AggregationStrategy::FirstN {
+            count,
+            deadline: Duration::from_millis(deadline),
+        },
+        _ => {
+            log::error!(
+                "Config Error: The value of `strategy` option should be `\"wait_all\"` or a table like `{{ first_n = {{ count = 2, deadline = 1500 }} }}` with a non zero count and deadline"
+            );
+            log::error!("Falling back to using the `wait_all` strategy");
+            AggregationStrategy::WaitAll
+        }
+    }
+}
+
+/// A helper function which parses the value of an upstream search engine in the
+/// `upstream_search_engines` option, which is either a boolean to enable or disable the engine, a
+/// positive number to enable the engine with the number as its ranking weight or a table (like
+/// `{ weight = 2, fallback = "Mojeek", base_url = "https://searx.example.org" }`) to enable the
+/// engine with an optional weight, an optional fallback engine which is used in its place when it
+/// fails and an optional base url which its requests are sent to instead of its built-in one.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine.
+/// * `value` - It takes the value of the upstream search engine in the option.
+///
+/// # Returns
+///
+/// It returns the options of the engine or `None` if the engine is disabled. An invalid weight is
+/// reported as a config error and replaced with the default weight of `1.0`, an invalid fallback
+/// engine is reported and ignored while an invalid base url is kept to be reported by the
+/// validation.
+fn parse_engine_entry(engine: &str, value: mlua::Value<'_>) -> Option<EngineEntry> {
+    let engine: String = engine.to_lowercase();
+
+    let (weight, fallback, base_url): (Option<f64>, Option<String>, Option<String>) = match value {
+        mlua::Value::Boolean(false) | mlua::Value::Nil => return None,
+        mlua::Value::Boolean(true) => (None, None, None),
+        mlua::Value::Integer(weight) => (Some(weight as f64), None, None),
+        mlua::Value::Number(weight) => (Some(weight), None, None),
+        mlua::Value::Table(options) => (
+            options.get::<_, Option<f64>>("weight").unwrap_or(Some(0.0)),
+            options.get::<_, Option<String>>("fallback").ok().flatten(),
+            options
+                .get::<_, Option<String>>("base_url")
+                .unwrap_or(Some(String::new())),
+        ),
+        _ => (Some(0.0), None, None),
+    };
+
+    let weight: f64 = match weight {
+        None => 1.0,
+        Some(weight) if weight.is_finite() && weight > 0.0 => weight,
+        Some(_) => {
+            log::error!(
+                "Config Error: The weight of the upstream search engine `{engine}` should be a positive number"
+            );
+            log::error!("Falling back to using the weight `1.0` for the engine");
+            1.0
+        }
+    };
+
+    let fallback: Option<EngineHandler> = match fallback {
+        Some(fallback) => match EngineHandler::new(&fallback) {
+            Some(handler) if handler.name() != engine => Some(handler),
+            _ => {
+                log::error!(
+                    "Config Error: The fallback `{fallback}` of the upstream search engine `{engine}` is not another known upstream search engine"
+                );
+                log::error!("Ignoring the fallback of the engine `{engine}`");
+                None
+            }
+        },
+        None => None,
+    };
+
+    // an invalid base url is kept as it is so that it is reported by the validation instead of
+    // silently falling back to the built-in base url of the engine.
+    let base_url: Option<String> =
+        base_url.map(|base_url| normalize_base_url(&base_url).unwrap_or(base_url));
+
+    Some(EngineEntry {
+        name: engine,
+        weight,
+        fallback,
+        base_url,
+    })
+}
+
+/// A helper function which checks whether the provided url is a well-formed base url of an
+/// upstream search engine, that is an `http` or `https` url with a host and without a query or a
+/// fragment.
+///
+/// # Arguments
+///
+/// * `base_url` - It takes the base url provided for an upstream search engine.
+///
+/// # Returns
+///
+/// It returns the base url without its trailing slashes (so that the paths of the engine can be
+/// appended to it) or `None` if it is not well-formed.
+fn normalize_base_url(base_url: &str) -> Option<String> {
+    let url: reqwest::Url = reqwest::Url::parse(base_url).ok()?;
+
+    match matches!(url.scheme(), "http" | "https")
+        && url.host_str().is_some_and(|host| !host.is_empty())
+        && url.query().is_none()
+        && url.fragment().is_none()
+    {
+        true => Some(base_url.trim_end_matches('/').to_owned()),
+        false => None,
+    }
+}
+
+/// A helper function which strips the brackets around an IPv6 address provided for the
+/// `binding_ip` option (like `[::1]`), so that it can be parsed as an ip address.
+///
+/// # Arguments
+///
+/// * `binding_ip` - It takes the value of the `binding_ip` option.
+fn normalize_binding_ip(binding_ip: &str) -> String {
+    let binding_ip: &str = binding_ip.trim();
+    binding_ip
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(binding_ip)
+        .to_owned()
+}
+
+/// A helper function which parses the budgets of the routes which are rate limited on their own.
+/// The routes which do not start with a `/` or whose budget is missing a value or has a zero value
+/// are skipped with a logged error.
+///
+/// # Arguments
+///
+/// * `routes` - It takes the `routes` table of the `rate_limiter` option mapping the prefixes of
+/// the routes to their budgets.
+///
+/// # Returns
+///
+/// Returns the budgets sorted from the most specific route (the longest prefix) to the least
+/// specific one, so that the first matching budget is the most specific one.
+fn parse_route_rate_limits(routes: HashMap<String, HashMap<String, u8>>) -> Vec<RouteRateLimit> {
+    let mut route_limits: Vec<RouteRateLimit> = routes
+        .into_iter()
+        .filter_map(|(route, limits)| {
+            match (
+                route.starts_with('/'),
+                limits.get("number_of_requests").copied(),
+                limits.get("time_limit").copied(),
+            ) {
+                (true, Some(number_of_requests), Some(time_limit))
+                    if number_of_requests > 0 && time_limit > 0 =>
+                {
+                    Some(RouteRateLimit {
+                        route,
+                        number_of_requests,
+                        time_limit,
+                    })
+                }
+                _ => {
+                    log::error!("Config Error: The rate limit of the route `{route}` should start with a `/` and have a non zero `number_of_requests` and `time_limit`, it is ignored");
+                    None
+                }
+            }
         })
+        .collect();
+
+    route_limits.sort_by(|route_limit, other| {
+        other
+            .route
+            .len()
+            .cmp(&route_limit.route.len())
+            .then_with(|| route_limit.route.cmp(&other.route))
+    });
+    route_limits
+}
+
+/// A helper function which parses the overrides of the security headers added to all the
+/// responses. An override with an empty value disables its header, the unknown headers are
+/// skipped with a logged error and the headers which are not overridden keep their default values.
+///
+/// # Arguments
+///
+/// * `overrides` - It takes the `security_headers` option mapping the names of the headers (in
+/// snake case, like `referrer_policy`) to their values.
+fn parse_security_headers(overrides: HashMap<String, String>) -> SecurityHeaders {
+    let mut security_headers: SecurityHeaders = SecurityHeaders::default();
+    for (name, value) in overrides {
+        let header: &mut Option<String> = match name.as_str() {
+            "content_security_policy" => &mut security_headers.content_security_policy,
+            "x_content_type_options" => &mut security_headers.x_content_type_options,
+            "referrer_policy" => &mut security_headers.referrer_policy,
+            "x_frame_options" => &mut security_headers.x_frame_options,
+            _ => {
+                log::error!("Config Error: The header `{name}` of the `security_headers` option is not a known security header, it is ignored");
+                continue;
+            }
+        };
+        *header = match value.trim().is_empty() {
+            true => None,
+            false => Some(value),
+        };
+    }
+    security_headers
+}
+
+/// A helper function which looks up the value of the provided environment variable.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name of the environment variable.
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// A helper function which converts the provided value of an integer option into the type of the
+/// option, so that a value out of the range of the type is turned into an invalid value of the
+/// option which is reported by the validation instead of failing to be converted.
+///
+/// # Arguments
+///
+/// * `value` - It takes the value of the option provided in the config file.
+/// * `invalid` - It takes the invalid value of the option used for the values out of range.
+fn out_of_range_to<T: TryFrom<i64>>(value: i64, invalid: T) -> T {
+    T::try_from(value).unwrap_or(invalid)
+}
+
+/// A helper function which resolves the number of worker threads the server is run with from the
+/// `threads` option, a value of `0` uses one thread for every available cpu.
+///
+/// # Arguments
+///
+/// * `threads` - It takes the value of the `threads` option.
+/// * `available_cpus` - It takes the number of the cpus available to the server.
+///
+/// # Returns
+///
+/// Returns the number of worker threads, which is always at least `1`.
+fn resolve_threads(threads: u8, available_cpus: usize) -> u8 {
+    match threads {
+        0 => u8::try_from(available_cpus).unwrap_or(u8::MAX).max(1),
+        _ => threads,
+    }
+}
+
+/// A helper function which falls back to the default value of an option which should be a non
+/// zero positive integer when it is set to zero.
+///
+/// # Arguments
+///
+/// * `option` - It takes the name of the option.
+/// * `value` - It takes the parsed value of the option.
+/// * `default` - It takes the value used in place of zero.
+fn non_zero_or<T: Copy + Default + PartialEq + fmt::Display>(
+    option: &str,
+    value: T,
+    default: T,
+) -> T {
+    match value == T::default() {
+        true => {
+            log::error!(
+                "Config Error: The value of `{option}` option should be a non zero positive integer"
+            );
+            log::error!("Falling back to using the value `{default}` for the option");
+            default
+        }
+        false => value,
+    }
+}
+
+/// A helper function which parses the value of the `safe_search_block_mode` option, an unknown
+/// mode falls back to the block page.
+///
+/// # Arguments
+///
+/// * `mode` - It takes the value of the option from the config file.
+fn parse_safe_search_block_mode(mode: Option<&str>) -> SafeSearchBlockMode {
+    match mode {
+        None | Some("block_page") => SafeSearchBlockMode::BlockPage,
+        Some("silent") => SafeSearchBlockMode::Silent,
+        Some(mode) => {
+            log::error!("Config Error: The value `{mode}` of the `safe_search_block_mode` option should be either `block_page` or `silent`");
+            log::error!("Falling back to using the value `block_page` for the option");
+            SafeSearchBlockMode::BlockPage
+        }
+    }
+}
+
+/// A helper function which parses the value of the `query_log` option, an unknown mode falls back
+/// to not logging the queries.
+///
+/// # Arguments
+///
+/// * `mode` - It takes the value of the option from the config file.
+fn parse_query_log_mode(mode: Option<&str>) -> QueryLogMode {
+    match mode {
+        None | Some("off") => QueryLogMode::Off,
+        Some("counts") => QueryLogMode::Counts,
+        Some("anonymized") => QueryLogMode::Anonymized,
+        Some(mode) => {
+            log::error!("Config Error: The value `{mode}` of the `query_log` option should be either `off`, `counts` or `anonymized`");
+            log::error!("Falling back to using the value `off` for the option");
+            QueryLogMode::Off
+        }
+    }
+}
+
+/// A helper function which parses the value of the `query_log_sink` option, an unknown sink falls
+/// back to the file sink.
+///
+/// # Arguments
+///
+/// * `sink` - It takes the value of the option from the config file.
+fn parse_query_log_sink(sink: Option<&str>) -> QueryLogSinkKind {
+    match sink {
+        None | Some("file") => QueryLogSinkKind::File,
+        Some("redis") => QueryLogSinkKind::Redis,
+        Some(sink) => {
+            log::error!("Config Error: The value `{sink}` of the `query_log_sink` option should be either `file` or `redis`");
+            log::error!("Falling back to using the value `file` for the option");
+            QueryLogSinkKind::File
+        }
+    }
+}
+
+/// A helper function which parses the value of the `cache_compression` option, an unknown
+/// compression falls back to not compressing the cached results.
+///
+/// # Arguments
+///
+/// * `compression` - It takes the value of the option from the config file.
+#[cfg(feature = "redis-cache")]
+fn parse_cache_compression(compression: Option<&str>) -> CacheCompression {
+    match compression {
+        None | Some("none") => CacheCompression::None,
+        Some("gzip") => CacheCompression::Gzip,
+        Some("zstd") => CacheCompression::Zstd,
+        Some(compression) => {
+            log::error!("Config Error: The value `{compression}` of the `cache_compression` option should be either `none`, `gzip` or `zstd`");
+            log::error!("Falling back to using the value `none` for the option");
+            CacheCompression::None
+        }
+    }
+}
+
+/// A helper function which parses the `robots_txt` and the `robots_txt_file` options, the inline
+/// content is preferred when both of them are provided.
+///
+/// # Arguments
+///
+/// * `content` - It takes the value of the `robots_txt` option.
+/// * `path` - It takes the value of the `robots_txt_file` option.
+fn parse_robots_txt(content: Option<String>, path: Option<String>) -> Option<RobotsTxt> {
+    match (content, path) {
+        (Some(content), Some(_)) => {
+            log::error!("Config Error: Only one of the `robots_txt` and `robots_txt_file` options should be provided");
+            log::error!("Falling back to using the value of the `robots_txt` option");
+            Some(RobotsTxt::Inline(content))
+        }
+        (Some(content), None) => Some(RobotsTxt::Inline(content)),
+        (None, Some(path)) => Some(RobotsTxt::File(path)),
+        (None, None) => None,
+    }
+}
+
+/// A helper function which normalizes the value of the `region` option, an invalid region falls
+/// back to not localizing the results.
+///
+/// # Arguments
+///
+/// * `region` - It takes the value of the option from the config file.
+fn parse_region(region: Option<String>) -> Option<String> {
+    let parsed_region: String = region?;
+    let region: Option<String> = normalize_region(&parsed_region);
+    if region.is_none() {
+        log::error!(
+            "Config Error: The value of `region` option should be a country code and a language code separated by a hyphen (like `us-en`)"
+        );
+        log::error!("Falling back to not localizing the results to any region");
+    }
+    region
+}
+
+/// A helper function which builds the circuit breaker config from the `circuit_breaker` option,
+/// the circuit breaker is disabled when the option is not provided.
+///
+/// # Arguments
+///
+/// * `circuit_breaker` - It takes the table of the option from the config file.
+fn parse_circuit_breaker(circuit_breaker: Option<HashMap<String, u64>>) -> CircuitBreakerConfig {
+    let disabled: CircuitBreakerConfig = CircuitBreakerConfig::disabled();
+    match circuit_breaker {
+        Some(circuit_breaker) => CircuitBreakerConfig {
+            failures: circuit_breaker
+                .get("failures")
+                .map_or(disabled.failures, |failures| {
+                    u32::try_from(*failures).unwrap_or(u32::MAX)
+                }),
+            window: circuit_breaker
+                .get("window")
+                .map_or(disabled.window, |window| Duration::from_secs(*window)),
+            cooldown: circuit_breaker
+                .get("cooldown")
+                .map_or(disabled.cooldown, |cooldown| Duration::from_secs(*cooldown)),
+        },
+        None => disabled,
+    }
+}
+
+/// A helper function which builds the range of the random delay applied before every request to
+/// the upstream search engines, a maximum lower than the minimum is raised to the minimum.
+///
+/// # Arguments
+///
+/// * `min` - It takes the minimum delay (millis) from the config file.
+/// * `max` - It takes the maximum delay (millis) from the config file.
+fn parse_request_delay(min: u64, max: u64) -> RequestDelay {
+    if max < min {
+        log::error!(
+            "Config Error: The maximum delay ({max}) is lower than the minimum delay ({min}), the minimum delay is used"
+        );
+    }
+    RequestDelay {
+        min: Duration::from_millis(min),
+        max: Duration::from_millis(max.max(min)),
     }
 }
 
@@ -150,3 +1512,192 @@ fn set_logging_level(debug: bool, logging: bool) {
 
     env_logger::Builder::new().filter(None, log_level).init();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_threads_resolve_to_the_available_cpus() {
+        assert_eq!(resolve_threads(0, 8), 8);
+        assert_eq!(resolve_threads(0, 0), 1);
+        assert_eq!(resolve_threads(0, 512), u8::MAX);
+        assert_eq!(resolve_threads(4, 8), 4);
+        assert_eq!(resolve_threads(1, 8), 1);
+    }
+
+    #[test]
+    fn test_route_rate_limits_are_validated_and_sorted_by_specificity() {
+        let limits = |number_of_requests: u8, time_limit: u8| -> HashMap<String, u8> {
+            HashMap::from([
+                ("number_of_requests".to_owned(), number_of_requests),
+                ("time_limit".to_owned(), time_limit),
+            ])
+        };
+        let route_limits: Vec<RouteRateLimit> = parse_route_rate_limits(HashMap::from([
+            ("/search".to_owned(), limits(10, 3)),
+            ("/search/stream".to_owned(), limits(2, 3)),
+            ("/static".to_owned(), limits(200, 1)),
+            ("images".to_owned(), limits(200, 1)),
+            ("/about".to_owned(), limits(0, 1)),
+            ("/settings".to_owned(), HashMap::new()),
+        ]));
+
+        let routes: Vec<&str> = route_limits
+            .iter()
+            .map(|route_limit| route_limit.route.as_str())
+            .collect();
+        assert_eq!(routes, vec!["/search/stream", "/search", "/static"]);
+
+        let search: &RouteRateLimit = &route_limits[1];
+        assert!(search.matches("/search"));
+        assert!(search.matches("/search/stream"));
+        assert!(!search.matches("/searches"));
+        assert!(!search.matches("/api/search"));
+    }
+
+    #[test]
+    fn test_security_headers_can_be_overridden_and_disabled() {
+        let security_headers: SecurityHeaders = parse_security_headers(HashMap::from([
+            ("referrer_policy".to_owned(), "same-origin".to_owned()),
+            ("x_frame_options".to_owned(), "".to_owned()),
+            (
+                "strict_transport_security".to_owned(),
+                "max-age=63072000".to_owned(),
+            ),
+        ]));
+
+        let headers: HashMap<&str, &str> = security_headers.headers().into_iter().collect();
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers["referrer-policy"], "same-origin");
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert!(headers["content-security-policy"].contains("default-src 'self'"));
+        assert!(!headers.contains_key("x-frame-options"));
+
+        assert_eq!(
+            parse_security_headers(HashMap::new()),
+            SecurityHeaders::default()
+        );
+    }
+
+    #[test]
+    fn test_bracketed_ipv6_binding_ips_are_accepted() {
+        for (binding_ip, expected) in [
+            ("[::1]", "::1"),
+            ("::1", "::1"),
+            ("[2001:db8::8a2e:370:7334]", "2001:db8::8a2e:370:7334"),
+            ("127.0.0.1", "127.0.0.1"),
+        ] {
+            let normalized: String = normalize_binding_ip(binding_ip);
+            assert_eq!(normalized, expected);
+            assert!(normalized.parse::<IpAddr>().is_ok());
+        }
+        assert!(normalize_binding_ip("[::1").parse::<IpAddr>().is_err());
+    }
+
+    #[test]
+    fn test_validate_only_reports_the_problems_of_the_config_file() {
+        let path: &str = "./websurfx/config.lua";
+        assert!(Config::validate_only(path).is_ok());
+
+        let invalid_path = std::env::temp_dir().join("websurfx_invalid_config.lua");
+        fs::write(
+            &invalid_path,
+            format!("{}\nport = 0\n", fs::read_to_string(path).unwrap()),
+        )
+        .unwrap();
+        let error: String = Config::validate_only(invalid_path.to_str().unwrap())
+            .err()
+            .unwrap()
+            .to_string();
+        fs::remove_file(&invalid_path).unwrap();
+        assert!(error.contains("`port`"));
+
+        assert!(Config::validate_only("./websurfx/missing.lua").is_err());
+    }
+
+    #[test]
+    fn test_invalid_engine_base_urls_are_reported() {
+        let mut config: Config = Config::validate_only("./websurfx/config.lua").unwrap();
+        config
+            .aggregator
+            .engine_base_urls
+            .insert("searx".to_owned(), "ftp://searx.example.org".to_owned());
+        let error: String = config.validate().err().unwrap().to_string();
+        assert!(error.contains("`ftp://searx.example.org`"));
+
+        config
+            .aggregator
+            .engine_base_urls
+            .insert("searx".to_owned(), "https://searx.example.org".to_owned());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_only_reports_the_integer_options_out_of_range() {
+        let path: &str = "./websurfx/config.lua";
+        let invalid_path = std::env::temp_dir().join("websurfx_out_of_range_config.lua");
+        fs::write(
+            &invalid_path,
+            format!(
+                "{}\nport = 70000\nthreads = -1\nsafe_search = 9\n",
+                fs::read_to_string(path).unwrap()
+            ),
+        )
+        .unwrap();
+        let error: String = Config::validate_only(invalid_path.to_str().unwrap())
+            .err()
+            .unwrap()
+            .to_string();
+        fs::remove_file(&invalid_path).unwrap();
+        assert!(error.contains("`port`"));
+        assert!(error.contains("`threads`"));
+        assert!(error.contains("`safe_search`"));
+    }
+
+    #[test]
+    fn test_env_overrides_take_precedence_over_the_config_file() {
+        let path: &str = "./websurfx/config.lua";
+        // the environment of the process is shared between the tests and so the overrides are
+        // looked up from a map instead.
+        let env = |port: &'static str| {
+            move |name: &str| match name {
+                "WEBSURFX_PORT" => Some(port.to_owned()),
+                _ => None,
+            }
+        };
+
+        let config: Config = Config::parse_file(path, true, &env("9090")).unwrap();
+        assert_eq!(config.port, 9090);
+        assert_ne!(
+            Config::parse_file(path, true, &|_| None).unwrap().port,
+            9090
+        );
+
+        let error: String = Config::parse_file(path, true, &env("0"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("`port`"));
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_turned_into_invalid_values() {
+        assert_eq!(out_of_range_to::<u16>(8080, 0), 8080);
+        assert_eq!(out_of_range_to::<u16>(70000, 0), 0);
+        assert_eq!(out_of_range_to::<u8>(-1, u8::MAX), u8::MAX);
+    }
+
+    #[test]
+    fn test_request_delay_maximum_is_never_lower_than_the_minimum() {
+        let delay: RequestDelay = parse_request_delay(200, 800);
+        assert_eq!(delay.min, Duration::from_millis(200));
+        assert_eq!(delay.max, Duration::from_millis(800));
+
+        assert_eq!(
+            parse_request_delay(500, 100).max,
+            Duration::from_millis(500)
+        );
+        assert!(!parse_request_delay(0, 0).is_enabled());
+    }
+}
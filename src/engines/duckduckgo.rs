@@ -4,18 +4,39 @@
 
 use std::collections::HashMap;
 
-use reqwest::header::HeaderMap;
+use reqwest::{header::HeaderMap, Client, Url};
 use scraper::{Html, Selector};
 
 use crate::models::aggregation_models::SearchResult;
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, EngineSafeParam, SearchEngine, UpstreamHeaders};
+use crate::results::bangs::percent_encode;
 
 use error_stack::{Report, Result, ResultExt};
 
+/// The base url of the html version of the upstream duckduckgo search engine.
+const BASE_URL: &str = "https://html.duckduckgo.com";
+
 /// A new DuckDuckGo engine type defined in-order to implement the `SearchEngine` trait which allows to
 /// reduce code duplication as well as allows to create vector of different search engines easily.
-pub struct DuckDuckGo;
+pub struct DuckDuckGo {
+    /// It stores the base url of the upstream duckduckgo search engine.
+    base_url: String,
+}
+
+impl DuckDuckGo {
+    /// Constructs a new `DuckDuckGo` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url from the config file, the html version
+    /// of duckduckgo is used if it is not provided.
+    pub fn new(base_url: Option<&str>) -> Self {
+        DuckDuckGo {
+            base_url: base_url.unwrap_or(BASE_URL).to_owned(),
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl SearchEngine for DuckDuckGo {
@@ -23,94 +44,270 @@ impl SearchEngine for DuckDuckGo {
         &self,
         query: &str,
         page: u32,
-        user_agent: &str,
+        headers: &UpstreamHeaders,
+        client: &Client,
         request_timeout: u8,
-        _safe_search: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
     ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        let safe_search: &str = safe_search.value();
+        // the results are not localized to any region unless a region is provided.
+        let region: &str = headers.region.as_deref().unwrap_or("wt-wt");
+        let query: String = percent_encode(query);
+
         // Page number can be missing or empty string and so appropriate handling is required
         // so that upstream server recieves valid page number.
         let url: String = match page {
             1 | 0 => {
-                format!("https://html.duckduckgo.com/html/?q={query}&s=&dc=&v=1&o=json&api=/d.js")
+                format!(
+                    "{}/html/?q={query}&s=&dc=&v=1&o=json&api=/d.js&kl={region}&kp={safe_search}",
+                    self.base_url
+                )
             }
             _ => {
                 format!(
-                    "https://duckduckgo.com/html/?q={}&s={}&dc={}&v=1&o=json&api=/d.js",
+                    "{}/html/?q={}&s={}&dc={}&v=1&o=json&api=/d.js&kl={}&kp={}",
+                    self.base_url,
                     query,
                     (page / 2 + (page % 2)) * 30,
-                    (page / 2 + (page % 2)) * 30 + 1
+                    (page / 2 + (page % 2)) * 30 + 1,
+                    region,
+                    safe_search
                 )
             }
         };
 
         // initializing HeaderMap and adding appropriate headers.
-        let header_map = HeaderMap::try_from(&HashMap::from([
-            ("USER_AGENT".to_string(), user_agent.to_string()),
+        let header_map: HeaderMap = headers.header_map(HashMap::from([
             ("REFERER".to_string(), "https://google.com/".to_string()),
             (
                 "CONTENT_TYPE".to_string(),
                 "application/x-www-form-urlencoded".to_string(),
             ),
-            ("COOKIE".to_string(), "kl=wt-wt".to_string()),
-        ]))
-        .change_context(EngineError::UnexpectedError)?;
+            ("COOKIE".to_string(), format!("kl={region}")),
+        ]))?;
 
-        let document: Html = Html::parse_document(
-            &DuckDuckGo::fetch_html_from_upstream(self, &url, header_map, request_timeout).await?,
-        );
+        let html: String =
+            DuckDuckGo::fetch_html_from_upstream(self, client, &url, header_map, request_timeout)
+                .await?;
 
-        let no_result: Selector = Selector::parse(".no-results")
-            .map_err(|_| Report::new(EngineError::UnexpectedError))
-            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".no-results"))?;
+        parse_results(&html, max_results)
+    }
 
-        if document.select(&no_result).next().is_some() {
-            return Err(Report::new(EngineError::EmptyResultSet));
+    /// The unified safe search levels are mapped to the `kp` query parameter of duckduckgo as
+    /// follows:
+    ///
+    /// | Level | `kp`           |
+    /// | ----- | -------------- |
+    /// | 0     | `-2` (off)     |
+    /// | 1 - 2 | `-1` (moderate) |
+    /// | 3 - 4 | `1` (strict)   |
+    fn map_safe_search(&self, level: u8) -> EngineSafeParam {
+        match level {
+            0 => EngineSafeParam::Value("-2"),
+            1..=2 => EngineSafeParam::Value("-1"),
+            _ => EngineSafeParam::Value("1"),
         }
+    }
+}
 
-        let results: Selector = Selector::parse(".result")
-            .map_err(|_| Report::new(EngineError::UnexpectedError))
-            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result"))?;
-        let result_title: Selector = Selector::parse(".result__a")
-            .map_err(|_| Report::new(EngineError::UnexpectedError))
-            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result__a"))?;
-        let result_url: Selector = Selector::parse(".result__url")
-            .map_err(|_| Report::new(EngineError::UnexpectedError))
-            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result__url"))?;
-        let result_desc: Selector = Selector::parse(".result__snippet")
-            .map_err(|_| Report::new(EngineError::UnexpectedError))
-            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result__snippet"))?;
-
-        // scrape all the results from the html
-        Ok(document
-            .select(&results)
-            .map(|result| {
-                SearchResult::new(
-                    result
-                        .select(&result_title)
-                        .next()
-                        .unwrap()
-                        .inner_html()
-                        .trim(),
-                    format!(
-                        "https://{}",
-                        result
-                            .select(&result_url)
-                            .next()
-                            .unwrap()
-                            .inner_html()
-                            .trim()
-                    )
-                    .as_str(),
-                    result
-                        .select(&result_desc)
-                        .next()
-                        .unwrap()
-                        .inner_html()
-                        .trim(),
-                    &["duckduckgo"],
-                )
-            })
-            .map(|search_result| (search_result.url.clone(), search_result))
-            .collect())
+/// A helper function which scrapes the search results from the result page of duckduckgo. The ads
+/// mixed into the results (the results with the `result--ad` class or linking through the ad
+/// redirect of duckduckgo) are marked as sponsored.
+///
+/// # Arguments
+///
+/// * `html` - It takes the result page of duckduckgo.
+/// * `max_results` - It takes the maximum number of results to be scraped.
+///
+/// # Error
+///
+/// It returns an `EmptyResultSet` if duckduckgo found no results and an `UnexpectedError` if a
+/// selector used to scrape the results is invalid.
+fn parse_results(
+    html: &str,
+    max_results: usize,
+) -> Result<HashMap<String, SearchResult>, EngineError> {
+    let document: Html = Html::parse_document(html);
+
+    let no_result: Selector = Selector::parse(".no-results")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".no-results"))?;
+
+    if document.select(&no_result).next().is_some() {
+        return Err(Report::new(EngineError::EmptyResultSet));
+    }
+
+    let results: Selector = Selector::parse(".result")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result"))?;
+    let result_title: Selector = Selector::parse(".result__a")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result__a"))?;
+    let result_url: Selector = Selector::parse(".result__url")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result__url"))?;
+    let result_desc: Selector = Selector::parse(".result__snippet")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result__snippet"))?;
+
+    // scrape all the results from the html
+    Ok(document
+        .select(&results)
+        .filter_map(|result| {
+            let title = result.select(&result_title).next()?;
+            let href: Option<&str> = title.value().attr("href");
+            // the destination is taken from the link of the result (unwrapping the redirect
+            // of duckduckgo) and the displayed url is only used as a fallback.
+            let url: String = href.and_then(unwrap_redirect_url).or_else(|| {
+                Some(format!(
+                    "https://{}",
+                    result.select(&result_url).next()?.inner_html().trim()
+                ))
+            })?;
+            let mut search_result: SearchResult = SearchResult::new(
+                title.inner_html().trim(),
+                &url,
+                result
+                    .select(&result_desc)
+                    .next()
+                    .map(|description| description.inner_html())
+                    .unwrap_or_default()
+                    .trim(),
+                &["duckduckgo"],
+            );
+            search_result.set_sponsored(
+                result.value().classes().any(|class| class == "result--ad")
+                    || href.is_some_and(is_ad_redirect),
+            );
+            Some(search_result)
+        })
+        .take(max_results)
+        .enumerate()
+        .map(|(index, mut search_result)| {
+            search_result.set_position(index + 1);
+            (search_result.url.clone(), search_result)
+        })
+        .collect())
+}
+
+/// A helper function which checks whether the link of a result goes through the ad redirect of
+/// duckduckgo (like `//duckduckgo.com/y.js?ad_domain=example.com&u3=...`).
+///
+/// # Arguments
+///
+/// * `href` - It takes the value of the `href` attribute of the link of the result.
+fn is_ad_redirect(href: &str) -> bool {
+    let href: String = match href.starts_with("//") {
+        true => format!("https:{href}"),
+        false => href.to_owned(),
+    };
+    Url::parse(&href).is_ok_and(|url| {
+        url.host_str()
+            .is_some_and(|host| host == "duckduckgo.com" || host.ends_with(".duckduckgo.com"))
+            && url.path() == "/y.js"
+    })
+}
+
+/// A helper function which extracts the destination of a result from its link. Duckduckgo wraps
+/// the destinations of the results in redirects through its own domain (like
+/// `//duckduckgo.com/l/?uddg=https%3A%2F%2Fexample.com%2F&rut=...`) which are unwrapped, so that
+/// the users are sent directly to the destination.
+///
+/// # Arguments
+///
+/// * `href` - It takes the value of the `href` attribute of the link of the result.
+///
+/// # Returns
+///
+/// It returns the destination url or `None` if it is not an absolute `http` or `https` url.
+fn unwrap_redirect_url(href: &str) -> Option<String> {
+    let href: String = match href.starts_with("//") {
+        true => format!("https:{href}"),
+        false => href.to_owned(),
+    };
+    let url: Url = Url::parse(&href).ok()?;
+
+    let destination: Url = match url.host_str() {
+        Some(host) if host == "duckduckgo.com" || host.ends_with(".duckduckgo.com") => {
+            let (_, destination) = url.query_pairs().find(|(name, _)| name == "uddg")?;
+            Url::parse(&destination).ok()?
+        }
+        _ => url,
+    };
+
+    match destination.scheme() {
+        "http" | "https" => Some(destination.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_results_marks_the_sponsored_results() {
+        let html: &str = r#"
+            <div class="results">
+                <div class="result results_links results_links_deep result--ad">
+                    <h2 class="result__title">
+                        <a class="result__a" href="//duckduckgo.com/y.js?ad_domain=shop.example.com&ad_provider=bing&u3=abc">Buy Rust Books</a>
+                    </h2>
+                    <a class="result__url" href="https://shop.example.com/">shop.example.com</a>
+                    <a class="result__snippet">The best deals on rust books.</a>
+                </div>
+                <div class="result results_links results_links_deep web-result">
+                    <h2 class="result__title">
+                        <a class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust%2Dlang.org%2F&rut=abc">Rust Programming Language</a>
+                    </h2>
+                    <a class="result__url" href="https://www.rust-lang.org/">www.rust-lang.org</a>
+                    <a class="result__snippet">A language empowering everyone.</a>
+                </div>
+                <div class="result results_links web-result">
+                    <h2 class="result__title">
+                        <a class="result__a" href="//duckduckgo.com/y.js?ad_domain=ads.example.com&u3=def">Sponsored Link</a>
+                    </h2>
+                    <a class="result__url" href="https://ads.example.com/">ads.example.com</a>
+                </div>
+            </div>
+        "#;
+
+        let results: HashMap<String, SearchResult> = parse_results(html, usize::MAX).unwrap();
+        assert_eq!(results.len(), 3);
+        let organic: &SearchResult = &results["https://www.rust-lang.org/"];
+        assert_eq!(organic.title, "Rust Programming Language");
+        assert!(!organic.sponsored);
+        assert_eq!(
+            results.values().filter(|result| result.sponsored).count(),
+            2
+        );
+
+        assert!(matches!(
+            parse_results(r#"<div class="no-results">No results.</div>"#, usize::MAX)
+                .err()
+                .unwrap()
+                .current_context(),
+            EngineError::EmptyResultSet
+        ));
+    }
+
+    #[test]
+    fn test_unwrap_redirect_url() {
+        assert_eq!(
+            unwrap_redirect_url(
+                "//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust%2Dlang.org%2Flearn%3Fa%3D1%26b%3D2&rut=abc"
+            ),
+            Some("https://www.rust-lang.org/learn?a=1&b=2".to_owned())
+        );
+        assert_eq!(
+            unwrap_redirect_url("https://example.com/page"),
+            Some("https://example.com/page".to_owned())
+        );
+        assert_eq!(unwrap_redirect_url("//duckduckgo.com/l/?rut=abc"), None);
+        assert_eq!(
+            unwrap_redirect_url("//duckduckgo.com/l/?uddg=javascript%3Aalert(1)"),
+            None
+        );
     }
 }
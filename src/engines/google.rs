@@ -0,0 +1,312 @@
+//! The `google` module handles the scraping of results from the google search engine
+//! by querying the upstream google search engine with user provided query and with a page
+//! number if provided.
+
+use std::collections::HashMap;
+
+use reqwest::{header::HeaderMap, Client, Url};
+use scraper::{Html, Selector};
+
+use crate::models::aggregation_models::SearchResult;
+use crate::models::engine_models::{
+    EngineError, EngineErrorKind, EngineSafeParam, SearchEngine, UpstreamHeaders,
+};
+use crate::results::bangs::percent_encode;
+
+use error_stack::{Report, Result, ResultExt};
+
+/// The base url of the upstream google search engine.
+const BASE_URL: &str = "https://www.google.com";
+
+/// The number of results provided by google on every page.
+const RESULTS_PER_PAGE: u32 = 10;
+
+/// The cookies which record the (rejected) consent of the user, so that google answers the
+/// requests from the EU with the results instead of its consent interstitial.
+const CONSENT_COOKIES: &str =
+    "CONSENT=YES+cb.20230531-04-p0.en+FX+908; SOCS=CAESHAgBEhJnd3NfMjAyMzA1MzEtMF9SQzIaAmVuIAEaBgiA_LyaBg";
+
+/// The selectors which only match the "unusual traffic" page google answers with instead of the
+/// results when it suspects the requests to be automated.
+const CAPTCHA_SELECTOR: &str = "form#captcha-form, form[action*='/sorry/'], #recaptcha";
+
+/// The selectors which only match the consent interstitial of google.
+const CONSENT_SELECTOR: &str = "form[action*='consent.google.com'], div#CXQnmb";
+
+/// A new Google engine type defined in-order to implement the `SearchEngine` trait which allows to
+/// reduce code duplication as well as allows to create vector of different search engines easily.
+pub struct Google {
+    /// It stores the base url of the upstream google search engine.
+    base_url: String,
+}
+
+impl Google {
+    /// Constructs a new `Google` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url from the config file, the official
+    /// google search engine is used if it is not provided.
+    pub fn new(base_url: Option<&str>) -> Self {
+        Google {
+            base_url: base_url.unwrap_or(BASE_URL).to_owned(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for Google {
+    async fn results(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        let url: String = format!(
+            "{}/search?q={}&start={}&safe={}&hl=en&filter=0",
+            self.base_url,
+            percent_encode(query),
+            page_offset(page),
+            safe_search.value()
+        );
+
+        // initializing HeaderMap and adding appropriate headers, the consent cookies are sent
+        // along so that the consent interstitial is skipped.
+        let header_map: HeaderMap = headers.header_map(HashMap::from([
+            ("REFERER".to_string(), format!("{}/", self.base_url)),
+            (
+                "ACCEPT".to_string(),
+                "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".to_string(),
+            ),
+            ("COOKIE".to_string(), CONSENT_COOKIES.to_string()),
+        ]))?;
+
+        let html: String =
+            Google::fetch_html_from_upstream(self, client, &url, header_map, request_timeout)
+                .await?;
+
+        let search_results: HashMap<String, SearchResult> = parse_results(&html, max_results)?;
+
+        if search_results.is_empty() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+
+        Ok(search_results)
+    }
+
+    /// The unified safe search levels are mapped to the `safe` query parameter of google (which
+    /// only supports enabling or disabling the safe search) as follows:
+    ///
+    /// | Level | `safe`   |
+    /// | ----- | -------- |
+    /// | 0 - 1 | `off`    |
+    /// | 2 - 4 | `active` |
+    fn map_safe_search(&self, level: u8) -> EngineSafeParam {
+        match level {
+            0..=1 => EngineSafeParam::Value("off"),
+            _ => EngineSafeParam::Value("active"),
+        }
+    }
+}
+
+/// A helper function which converts the page number into the `start` query parameter of google,
+/// which is the (zero based) position of the first result of the page.
+///
+/// # Arguments
+///
+/// * `page` - It takes the page number requested by the user, where `0` is treated as the first
+/// page.
+fn page_offset(page: u32) -> u32 {
+    page.saturating_sub(1) * RESULTS_PER_PAGE
+}
+
+/// A helper function which scrapes the search results from the result page of google, the results
+/// without a title or a url (like the widgets) are skipped.
+///
+/// # Arguments
+///
+/// * `html` - It takes the result page of google.
+/// * `max_results` - It takes the maximum number of results to be scraped.
+///
+/// # Error
+///
+/// It returns an `UnexpectedError` with the `Captcha` kind attached if google answered with its
+/// "unusual traffic" page instead of the results, so that it is reported as such instead of as
+/// an empty result set. It also returns an `UnexpectedError` if google answered with its consent
+/// interstitial despite the consent cookies or if a selector used to scrape the results is
+/// invalid.
+fn parse_results(
+    html: &str,
+    max_results: usize,
+) -> Result<HashMap<String, SearchResult>, EngineError> {
+    let document: Html = Html::parse_document(html);
+
+    let captcha: Selector = Selector::parse(CAPTCHA_SELECTOR)
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", CAPTCHA_SELECTOR))?;
+    if document.select(&captcha).next().is_some() {
+        return Err(Report::new(EngineError::UnexpectedError)
+            .attach(EngineErrorKind::Captcha)
+            .attach_printable("upstream search engine responded with the unusual traffic page"));
+    }
+
+    let consent: Selector = Selector::parse(CONSENT_SELECTOR)
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", CONSENT_SELECTOR))?;
+    if document.select(&consent).next().is_some() {
+        return Err(Report::new(EngineError::UnexpectedError)
+            .attach_printable("upstream search engine responded with the consent page"));
+    }
+
+    let results: Selector = Selector::parse("div.g")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", "div.g"))?;
+    let result_link: Selector = Selector::parse("a[href]")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", "a[href]"))?;
+    let result_title: Selector = Selector::parse("h3")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", "h3"))?;
+    let result_desc: Selector = Selector::parse(".VwiC3b, [data-sncf]")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".VwiC3b, [data-sncf]"))?;
+
+    Ok(document
+        .select(&results)
+        .filter_map(|result| {
+            // the title of a result is the heading inside of its link.
+            let link = result
+                .select(&result_link)
+                .find(|link| link.select(&result_title).next().is_some())?;
+            let title = link.select(&result_title).next()?;
+            let url: String = unwrap_redirect_url(link.value().attr("href")?)?;
+            Some(SearchResult::new(
+                title.text().collect::<String>().trim(),
+                &url,
+                result
+                    .select(&result_desc)
+                    .next()
+                    .map(|description| description.text().collect::<String>())
+                    .unwrap_or_default()
+                    .trim(),
+                &["google"],
+            ))
+        })
+        .take(max_results)
+        .enumerate()
+        .map(|(index, mut search_result)| {
+            search_result.set_position(index + 1);
+            (search_result.url.clone(), search_result)
+        })
+        .collect())
+}
+
+/// A helper function which extracts the destination of a result from its link. Google wraps the
+/// destinations of the results in redirects through its own domain (like
+/// `/url?q=https://example.com/&sa=U`) for the clients without javascript, which are unwrapped.
+///
+/// # Arguments
+///
+/// * `href` - It takes the value of the `href` attribute of the link of the result.
+///
+/// # Returns
+///
+/// It returns the destination url or `None` if it is not an absolute `http` or `https` url.
+fn unwrap_redirect_url(href: &str) -> Option<String> {
+    let url: Url = Url::parse(BASE_URL).ok()?.join(href).ok()?;
+
+    let destination: Url = match url.host_str() {
+        Some(host) if host == "www.google.com" && url.path() == "/url" => {
+            let (_, destination) = url
+                .query_pairs()
+                .find(|(name, _)| name == "q" || name == "url")?;
+            Url::parse(&destination).ok()?
+        }
+        Some(host) if host == "google.com" || host.ends_with(".google.com") => return None,
+        _ => url,
+    };
+
+    match destination.scheme() {
+        "http" | "https" => Some(destination.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_offset() {
+        assert_eq!(page_offset(0), 0);
+        assert_eq!(page_offset(1), 0);
+        assert_eq!(page_offset(3), 20);
+    }
+
+    #[test]
+    fn test_parse_results() {
+        let html: &str = r#"
+            <div id="search">
+                <div class="g">
+                    <div class="yuRUbf">
+                        <a href="https://www.rust-lang.org/"><h3 class="LC20lb">Rust Programming Language</h3></a>
+                    </div>
+                    <div class="VwiC3b"><span>A language empowering everyone.</span></div>
+                </div>
+                <div class="g">
+                    <a href="/url?q=https://doc.rust-lang.org/book/&amp;sa=U&amp;ved=abc"><h3>The Rust Book</h3></a>
+                </div>
+                <div class="g">
+                    <a href="/search?q=rust&amp;tbm=isch"><h3>Images for rust</h3></a>
+                </div>
+                <div class="g"><div class="VwiC3b">A widget without a title.</div></div>
+            </div>
+        "#;
+
+        let results: HashMap<String, SearchResult> = parse_results(html, usize::MAX).unwrap();
+        assert_eq!(results.len(), 2);
+        let result: &SearchResult = &results["https://www.rust-lang.org/"];
+        assert_eq!(result.title, "Rust Programming Language");
+        assert_eq!(result.description, "A language empowering everyone.");
+        assert_eq!(results["https://doc.rust-lang.org/book/"].description, "");
+
+        assert_eq!(parse_results(html, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_unusual_traffic_page_is_reported_as_a_captcha() {
+        let html: &str = r#"
+            <html>
+                <body>
+                    <div id="infoDiv">Our systems have detected unusual traffic from your computer network.</div>
+                    <form id="captcha-form" action="index" method="post">
+                        <div id="recaptcha" class="g-recaptcha"></div>
+                    </form>
+                </body>
+            </html>
+        "#;
+
+        let error: Report<EngineError> = parse_results(html, usize::MAX).err().unwrap();
+        assert_eq!(EngineErrorKind::of(&error), EngineErrorKind::Captcha);
+    }
+
+    #[test]
+    fn test_consent_page_is_reported_as_an_error() {
+        let html: &str = r#"
+            <form action="https://consent.google.com/save" method="POST">
+                <input type="submit" value="Reject all" />
+            </form>
+        "#;
+
+        let error: Report<EngineError> = parse_results(html, usize::MAX).err().unwrap();
+        assert!(matches!(
+            error.current_context(),
+            EngineError::UnexpectedError
+        ));
+        assert_ne!(EngineErrorKind::of(&error), EngineErrorKind::Captcha);
+    }
+}
@@ -0,0 +1,244 @@
+//! The `invidious` module handles the fetching of video results from an invidious instance
+//! by querying the upstream invidious instance api with user provided query and with a page
+//! number if provided.
+
+use std::collections::HashMap;
+
+use reqwest::{header::HeaderMap, Client};
+use serde::Deserialize;
+
+use crate::models::aggregation_models::{SearchResult, VideoResult};
+use crate::models::engine_models::{EngineError, EngineSafeParam, SearchEngine, UpstreamHeaders};
+use crate::results::bangs::percent_encode;
+
+use error_stack::{Report, Result, ResultExt};
+
+/// The url of the upstream invidious instance which is queried unless another one is configured.
+const INVIDIOUS_INSTANCE_URL: &str = "https://vid.puffyan.us";
+
+/// A new Invidious engine type defined in-order to implement the `SearchEngine` trait which allows to
+/// reduce code duplication as well as allows to create vector of different search engines easily.
+pub struct Invidious {
+    /// It stores the base url of the upstream invidious instance.
+    base_url: String,
+}
+
+impl Invidious {
+    /// Constructs a new `Invidious` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url from the config file, the default
+    /// public invidious instance is used if it is not provided.
+    pub fn new(base_url: Option<&str>) -> Self {
+        Invidious {
+            base_url: base_url.unwrap_or(INVIDIOUS_INSTANCE_URL).to_owned(),
+        }
+    }
+}
+
+/// A named struct which deserializes a single video entry returned by the invidious search api.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InvidiousVideo {
+    /// It stores the title of the video.
+    title: String,
+    /// It stores the id of the video which is used to build the video and thumbnail urls.
+    video_id: String,
+    /// It stores the name of the channel which uploaded the video.
+    author: String,
+    /// It stores the length of the video in seconds.
+    length_seconds: u64,
+    /// It stores the description of the video.
+    #[serde(default)]
+    description: String,
+}
+
+impl Invidious {
+    /// A helper function which fetches the raw video entries from the upstream invidious
+    /// instance.
+    ///
+    /// # Error
+    ///
+    /// Returns an `EngineError` if the upstream instance cannot be reached, its response cannot
+    /// be deserialized or if it provides no results for the requested search query.
+    async fn fetch_videos(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+    ) -> Result<Vec<InvidiousVideo>, EngineError> {
+        let url: String = format!(
+            "{}/api/v1/search?q={}&page={}&type=video",
+            self.base_url,
+            percent_encode(query),
+            page.max(1)
+        );
+
+        // initializing HeaderMap and adding appropriate headers.
+        let header_map: HeaderMap = headers.header_map(HashMap::from([(
+            "REFERER".to_string(),
+            "https://google.com/".to_string(),
+        )]))?;
+
+        let json: String =
+            Invidious::fetch_html_from_upstream(self, client, &url, header_map, request_timeout)
+                .await?;
+
+        parse_videos(&json).attach_printable_lazy(|| format!("upstream url: {}", url))
+    }
+}
+
+/// A helper function which deserializes the video entries from the response of the invidious
+/// search api.
+///
+/// # Arguments
+///
+/// * `json` - It takes the response of the invidious search api.
+///
+/// # Error
+///
+/// Returns an `UnexpectedError` if the response cannot be deserialized or an `EmptyResultSet`
+/// error if it provides no videos.
+fn parse_videos(json: &str) -> Result<Vec<InvidiousVideo>, EngineError> {
+    let videos: Vec<InvidiousVideo> = serde_json::from_str(json)
+        .change_context(EngineError::UnexpectedError)
+        .attach_printable("invalid json response")?;
+
+    if videos.is_empty() {
+        return Err(Report::new(EngineError::EmptyResultSet));
+    }
+
+    Ok(videos)
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for Invidious {
+    async fn results(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        _safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        let base_url: &str = &self.base_url;
+        Ok(self
+            .fetch_videos(query, page, headers, client, request_timeout)
+            .await?
+            .into_iter()
+            .map(|video| {
+                SearchResult::new(
+                    &video.title,
+                    &format!("{base_url}/watch?v={}", video.video_id),
+                    &video.description,
+                    &["invidious"],
+                )
+            })
+            .take(max_results)
+            .enumerate()
+            .map(|(index, mut search_result)| {
+                search_result.set_position(index + 1);
+                (search_result.url.clone(), search_result)
+            })
+            .collect())
+    }
+
+    async fn videos(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        _safe_search: EngineSafeParam,
+    ) -> Result<Vec<VideoResult>, EngineError> {
+        let base_url: &str = &self.base_url;
+        Ok(self
+            .fetch_videos(query, page, headers, client, request_timeout)
+            .await?
+            .into_iter()
+            .map(|video| {
+                VideoResult::new(
+                    &format!("{base_url}/vi/{}/mqdefault.jpg", video.video_id),
+                    &video.title,
+                    &format_duration(video.length_seconds),
+                    &video.author,
+                    &format!("{base_url}/watch?v={}", video.video_id),
+                )
+            })
+            .collect())
+    }
+}
+
+/// A helper function which formats the provided length of a video in seconds into a human
+/// readable `h:mm:ss` or `m:ss` form.
+///
+/// # Arguments
+///
+/// * `seconds` - It takes the length of the video in seconds.
+fn format_duration(seconds: u64) -> String {
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, (seconds % 3600) / 60, seconds % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_videos() {
+        let json: &str = r#"[
+            {
+                "type": "video",
+                "title": "Rust in 100 Seconds",
+                "videoId": "5C_HPTJg5ek",
+                "author": "Fireship",
+                "lengthSeconds": 149,
+                "description": "Learn the basics of the Rust programming language."
+            },
+            {
+                "type": "video",
+                "title": "Rust for the impatient",
+                "videoId": "br3GIIQeefY",
+                "author": "No Boilerplate",
+                "lengthSeconds": 3725
+            }
+        ]"#;
+
+        let videos: Vec<InvidiousVideo> = parse_videos(json).unwrap();
+        assert_eq!(videos.len(), 2);
+        assert_eq!(videos[0].title, "Rust in 100 Seconds");
+        assert_eq!(videos[0].video_id, "5C_HPTJg5ek");
+        assert_eq!(videos[0].author, "Fireship");
+        assert_eq!(format_duration(videos[0].length_seconds), "2:29");
+        assert_eq!(
+            videos[0].description,
+            "Learn the basics of the Rust programming language."
+        );
+        // the description is optional.
+        assert_eq!(videos[1].description, "");
+        assert_eq!(format_duration(videos[1].length_seconds), "1:02:05");
+    }
+
+    #[test]
+    fn test_parse_videos_reports_empty_and_invalid_responses() {
+        assert!(matches!(
+            parse_videos("[]").err().unwrap().current_context(),
+            EngineError::EmptyResultSet
+        ));
+        assert!(matches!(
+            parse_videos("<html></html>")
+                .err()
+                .unwrap()
+                .current_context(),
+            EngineError::UnexpectedError
+        ));
+    }
+}
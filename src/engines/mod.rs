@@ -4,4 +4,12 @@
 //! code. Moreover, it also provides a custom error for the upstream search engine handling code.
 
 pub mod duckduckgo;
+pub mod google;
+pub mod invidious;
+pub mod mojeek;
+pub mod registry;
 pub mod searx;
+pub mod searxng;
+pub mod startpage;
+pub mod wikipedia;
+pub mod yandex;
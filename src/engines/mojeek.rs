@@ -0,0 +1,200 @@
+//! The `mojeek` module handles the scraping of results from the mojeek search engine
+//! by querying the upstream mojeek search engine with user provided query and with a page
+//! number if provided.
+
+use std::collections::HashMap;
+
+use reqwest::{header::HeaderMap, Client};
+use scraper::{Html, Selector};
+
+use crate::models::aggregation_models::SearchResult;
+
+use crate::models::engine_models::{EngineError, EngineSafeParam, SearchEngine, UpstreamHeaders};
+use crate::results::bangs::percent_encode;
+
+use error_stack::{Report, Result, ResultExt};
+
+/// The number of results provided by mojeek on every page.
+const RESULTS_PER_PAGE: u32 = 10;
+
+/// The base url of the upstream mojeek search engine.
+const BASE_URL: &str = "https://www.mojeek.com";
+
+/// A new Mojeek engine type defined in-order to implement the `SearchEngine` trait which allows to
+/// reduce code duplication as well as allows to create vector of different search engines easily.
+pub struct Mojeek {
+    /// It stores the base url of the upstream mojeek search engine.
+    base_url: String,
+}
+
+impl Mojeek {
+    /// Constructs a new `Mojeek` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url from the config file, the official
+    /// mojeek search engine is used if it is not provided.
+    pub fn new(base_url: Option<&str>) -> Self {
+        Mojeek {
+            base_url: base_url.unwrap_or(BASE_URL).to_owned(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for Mojeek {
+    async fn results(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        let safe_search: &str = safe_search.value();
+
+        let url: String = format!(
+            "{}/search?q={}&s={}&safe={safe_search}",
+            self.base_url,
+            percent_encode(query),
+            page_offset(page)
+        );
+
+        // initializing HeaderMap and adding appropriate headers.
+        let header_map: HeaderMap = headers.header_map(HashMap::from([(
+            "REFERER".to_string(),
+            format!("{}/", self.base_url),
+        )]))?;
+
+        let html: String =
+            Mojeek::fetch_html_from_upstream(self, client, &url, header_map, request_timeout)
+                .await?;
+
+        let search_results: HashMap<String, SearchResult> = parse_results(&html, max_results)?;
+
+        if search_results.is_empty() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+
+        Ok(search_results)
+    }
+
+    /// The unified safe search levels are mapped to the `safe` query parameter of mojeek (which
+    /// only supports enabling or disabling the safe search) as follows:
+    ///
+    /// | Level | `safe`    |
+    /// | ----- | --------- |
+    /// | 0 - 1 | `0` (off) |
+    /// | 2 - 4 | `1` (on)  |
+    fn map_safe_search(&self, level: u8) -> EngineSafeParam {
+        match level {
+            0..=1 => EngineSafeParam::Value("0"),
+            _ => EngineSafeParam::Value("1"),
+        }
+    }
+}
+
+/// A helper function which converts the page number into the `s` query parameter of mojeek, which
+/// is the (one based) position of the first result of the page.
+///
+/// # Arguments
+///
+/// * `page` - It takes the page number requested by the user, where `0` is treated as the first
+/// page.
+fn page_offset(page: u32) -> u32 {
+    page.saturating_sub(1) * RESULTS_PER_PAGE + 1
+}
+
+/// A helper function which scrapes the search results from the result page of mojeek, the results
+/// without a title or a url are skipped.
+///
+/// # Arguments
+///
+/// * `html` - It takes the result page of mojeek.
+/// * `max_results` - It takes the maximum number of results to be scraped.
+///
+/// # Error
+///
+/// It returns an `UnexpectedError` if a selector used to scrape the results is invalid.
+fn parse_results(
+    html: &str,
+    max_results: usize,
+) -> Result<HashMap<String, SearchResult>, EngineError> {
+    let document: Html = Html::parse_document(html);
+
+    let results: Selector = Selector::parse("ul.results-standard > li")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| {
+            format!("invalid CSS selector: {}", "ul.results-standard > li")
+        })?;
+    let result_title: Selector = Selector::parse("h2 > a.title")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", "h2 > a.title"))?;
+    let result_desc: Selector = Selector::parse("p.s")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", "p.s"))?;
+
+    Ok(document
+        .select(&results)
+        .filter_map(|result| {
+            let title = result.select(&result_title).next()?;
+            Some(SearchResult::new(
+                title.inner_html().trim(),
+                title.value().attr("href")?,
+                result
+                    .select(&result_desc)
+                    .next()
+                    .map(|description| description.inner_html())
+                    .unwrap_or_default()
+                    .trim(),
+                &["mojeek"],
+            ))
+        })
+        .take(max_results)
+        .enumerate()
+        .map(|(index, mut search_result)| {
+            search_result.set_position(index + 1);
+            (search_result.url.clone(), search_result)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_offset() {
+        assert_eq!(page_offset(0), 1);
+        assert_eq!(page_offset(1), 1);
+        assert_eq!(page_offset(2), 11);
+        assert_eq!(page_offset(5), 41);
+    }
+
+    #[test]
+    fn test_parse_results() {
+        let html: &str = r#"
+            <ul class="results-standard">
+                <li>
+                    <h2><a class="title" href="https://www.rust-lang.org/">Rust Programming Language</a></h2>
+                    <p class="s">A language empowering everyone.</p>
+                </li>
+                <li>
+                    <h2><a class="title" href="https://doc.rust-lang.org/book/">The Rust Book</a></h2>
+                </li>
+                <li><p class="s">A result without a title.</p></li>
+            </ul>
+        "#;
+
+        let results: HashMap<String, SearchResult> = parse_results(html, usize::MAX).unwrap();
+        assert_eq!(results.len(), 2);
+        let result: &SearchResult = &results["https://www.rust-lang.org/"];
+        assert_eq!(result.title, "Rust Programming Language");
+        assert_eq!(result.description, "A language empowering everyone.");
+        assert_eq!(results["https://doc.rust-lang.org/book/"].description, "");
+
+        assert_eq!(parse_results(html, 1).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+//! This module provides the registry of the upstream search engines which maps the names of the
+//! engines to the functions constructing them. The engines compiled into `websurfx` are always
+//! registered and the other engines (like the ones provided by third party crates) can be added
+//! with the `register_engine` function, so that they can be enabled in the config file and
+//! selected from the settings page like the built-in engines.
+
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, PoisonError, RwLock},
+};
+
+use crate::models::engine_models::SearchEngine;
+
+use super::{
+    duckduckgo::DuckDuckGo, google::Google, invidious::Invidious, mojeek::Mojeek, searx::Searx,
+    searxng::SearxNG, startpage::Startpage, wikipedia::Wikipedia, yandex::Yandex,
+};
+
+/// A function which constructs an upstream search engine, it takes the override of the base url
+/// of the engine from the config file (the built-in base url of the engine should be used if it
+/// is not provided).
+pub type EngineConstructor = fn(Option<&str>) -> Box<dyn SearchEngine>;
+
+/// The upstream search engines compiled into `websurfx` along with their constructors.
+const BUILT_IN_ENGINES: [(&str, EngineConstructor); 9] = [
+    ("duckduckgo", |base_url| Box::new(DuckDuckGo::new(base_url))),
+    ("searx", |base_url| Box::new(Searx::new(base_url))),
+    ("invidious", |base_url| Box::new(Invidious::new(base_url))),
+    ("wikipedia", |base_url| Box::new(Wikipedia::new(base_url))),
+    ("startpage", |base_url| Box::new(Startpage::new(base_url))),
+    ("mojeek", |base_url| Box::new(Mojeek::new(base_url))),
+    ("yandex", |base_url| Box::new(Yandex::new(base_url))),
+    ("google", |base_url| Box::new(Google::new(base_url))),
+    ("searxng", |base_url| Box::new(SearxNG::new(base_url))),
+];
+
+/// Aliases for long type annotations
+type Registry = RwLock<HashMap<&'static str, EngineConstructor>>;
+
+/// A static variable which stores the registered upstream search engines mapped by their names.
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// A helper function which returns the registry, which is initialized with the built-in engines
+/// when it is first used.
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::from(BUILT_IN_ENGINES)))
+}
+
+/// A function which registers an upstream search engine under the provided name, so that it can
+/// be enabled in the `upstream_search_engines` option of the config file and selected from the
+/// settings page. The engines should be registered before the config file is parsed (that is at
+/// the start of the `main` function) as the unknown engines are rejected while parsing it.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name of the engine, which should be lowercase.
+/// * `constructor` - It takes the function which constructs the engine.
+///
+/// # Returns
+///
+/// It returns whether the engine was registered, which is not the case when the name is empty or
+/// not lowercase, or an engine (like a built-in engine) is already registered under it.
+pub fn register_engine(name: &'static str, constructor: EngineConstructor) -> bool {
+    register_in(registry(), name, constructor)
+}
+
+/// A helper function which registers an upstream search engine in the provided registry in the
+/// same way as the `register_engine` function.
+///
+/// # Arguments
+///
+/// * `registry` - It takes the registry in which the engine is registered.
+/// * `name` - It takes the name of the engine, which should be lowercase.
+/// * `constructor` - It takes the function which constructs the engine.
+fn register_in(registry: &Registry, name: &'static str, constructor: EngineConstructor) -> bool {
+    if name.is_empty() || name != name.to_lowercase() {
+        return false;
+    }
+
+    let mut engines = registry.write().unwrap_or_else(PoisonError::into_inner);
+    match engines.contains_key(name) {
+        true => false,
+        false => {
+            engines.insert(name, constructor);
+            true
+        }
+    }
+}
+
+/// A function which finds the registered upstream search engine with the provided name, the name
+/// is matched case insensitively.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name of the engine.
+///
+/// # Returns
+///
+/// It returns the registered name of the engine along with its constructor or `None` if no engine
+/// is registered under the name.
+pub fn registered_engine(name: &str) -> Option<(&'static str, EngineConstructor)> {
+    find_in(registry(), name)
+}
+
+/// A helper function which finds the upstream search engine with the provided name in the
+/// provided registry in the same way as the `registered_engine` function.
+///
+/// # Arguments
+///
+/// * `registry` - It takes the registry in which the engine is looked up.
+/// * `name` - It takes the name of the engine.
+fn find_in(registry: &Registry, name: &str) -> Option<(&'static str, EngineConstructor)> {
+    registry
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_key_value(name.to_lowercase().as_str())
+        .map(|(name, constructor)| (*name, *constructor))
+}
+
+/// A function which returns the names of all the registered upstream search engines in the
+/// alphabetical order.
+pub fn registered_engines() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = registry()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .keys()
+        .copied()
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        aggregation_models::SearchResult,
+        engine_models::{EngineError, EngineHandler, EngineSafeParam, UpstreamHeaders},
+    };
+    use error_stack::{Report, Result};
+
+    /// An upstream search engine which never provides any result.
+    struct Silent;
+
+    #[async_trait::async_trait]
+    impl SearchEngine for Silent {
+        async fn results(
+            &self,
+            _query: &str,
+            _page: u32,
+            _headers: &UpstreamHeaders,
+            _client: &reqwest::Client,
+            _request_timeout: u8,
+            _safe_search: EngineSafeParam,
+            _max_results: usize,
+        ) -> Result<HashMap<String, SearchResult>, EngineError> {
+            Err(Report::new(EngineError::EmptyResultSet))
+        }
+    }
+
+    #[test]
+    fn test_built_in_engines_are_registered() {
+        for (name, _) in BUILT_IN_ENGINES {
+            assert_eq!(
+                EngineHandler::new(&name.to_uppercase()).map(|handler| handler.name()),
+                Some(name)
+            );
+        }
+        assert!(EngineHandler::new("unknown").is_none());
+    }
+
+    #[test]
+    fn test_registered_engines_are_found_by_their_name() {
+        // a registry of its own is used so that the engine never leaks into the other tests.
+        let engines: Registry = RwLock::new(HashMap::from(BUILT_IN_ENGINES));
+        assert!(register_in(&engines, "silent", |_| Box::new(Silent)));
+        assert!(!register_in(&engines, "silent", |_| Box::new(Silent)));
+        assert!(!register_in(&engines, "searx", |_| Box::new(Silent)));
+        assert!(!register_in(&engines, "Loud", |_| Box::new(Silent)));
+
+        assert_eq!(
+            find_in(&engines, "Silent").map(|(name, _)| name),
+            Some("silent")
+        );
+        assert!(find_in(registry(), "silent").is_none());
+    }
+}
@@ -2,17 +2,37 @@
 //! by querying the upstream searx search engine instance with user provided query and with a page
 //! number if provided.
 
-use reqwest::header::HeaderMap;
+use reqwest::{header::HeaderMap, Client};
 use scraper::{Html, Selector};
 use std::collections::HashMap;
 
-use crate::models::aggregation_models::SearchResult;
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::aggregation_models::{NewsResult, SearchResult, Suggestion};
+use crate::models::engine_models::{EngineError, EngineSafeParam, SearchEngine, UpstreamHeaders};
 use error_stack::{Report, Result, ResultExt};
 
+/// The base url of the upstream searx instance queried unless another one is configured.
+const BASE_URL: &str = "https://searx.work";
+
 /// A new Searx engine type defined in-order to implement the `SearchEngine` trait which allows to
 /// reduce code duplication as well as allows to create vector of different search engines easily.
-pub struct Searx;
+pub struct Searx {
+    /// It stores the base url of the upstream searx instance.
+    base_url: String,
+}
+
+impl Searx {
+    /// Constructs a new `Searx` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url from the config file, the default
+    /// public searx instance is used if it is not provided.
+    pub fn new(base_url: Option<&str>) -> Self {
+        Searx {
+            base_url: base_url.unwrap_or(BASE_URL).to_owned(),
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl SearchEngine for Searx {
@@ -20,36 +40,63 @@ impl SearchEngine for Searx {
         &self,
         query: &str,
         page: u32,
-        user_agent: &str,
+        headers: &UpstreamHeaders,
+        client: &Client,
         request_timeout: u8,
-        mut safe_search: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
     ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        let (results, _) = self
+            .results_with_suggestions(
+                query,
+                page,
+                headers,
+                client,
+                request_timeout,
+                safe_search,
+                max_results,
+            )
+            .await?;
+        Ok(results)
+    }
+
+    async fn results_with_suggestions(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<(HashMap<String, SearchResult>, Vec<Suggestion>), EngineError> {
+        let safe_search: &str = safe_search.value();
+
         // Page number can be missing or empty string and so appropriate handling is required
         // so that upstream server recieves valid page number.
-        if safe_search == 3 {
-            safe_search = 2;
-        };
-
         let url: String = match page {
             0 | 1 => {
-                format!("https://searx.work/search?q={query}&pageno=1&safesearch={safe_search}")
+                format!(
+                    "{}/search?q={query}&pageno=1&safesearch={safe_search}",
+                    self.base_url
+                )
             }
             _ => format!(
-                "https://searx.work/search?q={query}&pageno={page}&safesearch={safe_search}"
+                "{}/search?q={query}&pageno={page}&safesearch={safe_search}",
+                self.base_url
             ),
         };
 
         // initializing headers and adding appropriate headers.
-        let header_map = HeaderMap::try_from(&HashMap::from([
-            ("USER_AGENT".to_string(), user_agent.to_string()),
+        let header_map: HeaderMap = headers.header_map(HashMap::from([
             ("REFERER".to_string(), "https://google.com/".to_string()),
             ("CONTENT_TYPE".to_string(), "application/x-www-form-urlencoded".to_string()),
             ("COOKIE".to_string(), "categories=general; language=auto; locale=en; autocomplete=duckduckgo; image_proxy=1; method=POST; safesearch=2; theme=simple; results_on_new_tab=1; doi_resolver=oadoi.org; simple_style=auto; center_alignment=1; query_in_title=1; infinite_scroll=0; disabled_engines=; enabled_engines=\"archive is__general\\054yep__general\\054curlie__general\\054currency__general\\054ddg definitions__general\\054wikidata__general\\054duckduckgo__general\\054tineye__general\\054lingva__general\\054startpage__general\\054yahoo__general\\054wiby__general\\054marginalia__general\\054alexandria__general\\054wikibooks__general\\054wikiquote__general\\054wikisource__general\\054wikiversity__general\\054wikivoyage__general\\054dictzone__general\\054seznam__general\\054mojeek__general\\054naver__general\\054wikimini__general\\054brave__general\\054petalsearch__general\\054goo__general\"; disabled_plugins=; enabled_plugins=\"searx.plugins.hostname_replace\\054searx.plugins.oa_doi_rewrite\\054searx.plugins.vim_hotkeys\"; tokens=; maintab=on; enginetab=on".to_string())
-        ]))
-        .change_context(EngineError::UnexpectedError)?;
+                ]))?;
 
         let document: Html = Html::parse_document(
-            &Searx::fetch_html_from_upstream(self, &url, header_map, request_timeout).await?,
+            &Searx::fetch_html_from_upstream(self, client, &url, header_map, request_timeout)
+                .await?,
         );
 
         let no_result: Selector = Selector::parse("#urls>.dialog-error>p")
@@ -79,12 +126,15 @@ impl SearchEngine for Searx {
         let result_desc: Selector = Selector::parse(".content")
             .map_err(|_| Report::new(EngineError::UnexpectedError))
             .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".content"))?;
+        let result_published: Selector = Selector::parse(".published_date")
+            .map_err(|_| Report::new(EngineError::UnexpectedError))
+            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".published_date"))?;
 
         // scrape all the results from the html
-        Ok(document
+        let search_results: HashMap<String, SearchResult> = document
             .select(&results)
             .map(|result| {
-                SearchResult::new(
+                let mut search_result: SearchResult = SearchResult::new(
                     result
                         .select(&result_title)
                         .next()
@@ -105,9 +155,280 @@ impl SearchEngine for Searx {
                         .inner_html()
                         .trim(),
                     &["searx"],
-                )
+                );
+                search_result.set_published(
+                    result
+                        .select(&result_published)
+                        .next()
+                        .and_then(|published| published.value().attr("datetime"))
+                        .and_then(parse_published_date),
+                );
+                search_result
+            })
+            .take(max_results)
+            .enumerate()
+            .map(|(index, mut search_result)| {
+                search_result.set_position(index + 1);
+                (search_result.url.clone(), search_result)
+            })
+            .collect();
+
+        // the suggestions are not essential and so failing to scrape them never fails the results.
+        let suggestions: Vec<Suggestion> = parse_suggestions(&document).unwrap_or_default();
+
+        Ok((search_results, suggestions))
+    }
+
+    async fn news(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+    ) -> Result<Vec<NewsResult>, EngineError> {
+        let safe_search: &str = safe_search.value();
+        let page: u32 = page.max(1);
+
+        let url: String = format!(
+            "{}/search?q={query}&pageno={page}&safesearch={safe_search}&categories=news",
+            self.base_url
+        );
+
+        // initializing headers and adding appropriate headers.
+        let header_map: HeaderMap = headers.header_map(HashMap::from([
+            ("REFERER".to_string(), "https://google.com/".to_string()),
+            (
+                "CONTENT_TYPE".to_string(),
+                "application/x-www-form-urlencoded".to_string(),
+            ),
+        ]))?;
+
+        let document: Html = Html::parse_document(
+            &Searx::fetch_html_from_upstream(self, client, &url, header_map, request_timeout)
+                .await?,
+        );
+
+        let results: Selector = Selector::parse(".result")
+            .map_err(|_| Report::new(EngineError::UnexpectedError))
+            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".result"))?;
+        let result_title: Selector = Selector::parse("h3>a")
+            .map_err(|_| Report::new(EngineError::UnexpectedError))
+            .attach_printable_lazy(|| format!("invalid CSS selector: {}", "h3>a"))?;
+        let result_desc: Selector = Selector::parse(".content")
+            .map_err(|_| Report::new(EngineError::UnexpectedError))
+            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".content"))?;
+        let result_published: Selector = Selector::parse(".published_date")
+            .map_err(|_| Report::new(EngineError::UnexpectedError))
+            .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".published_date"))?;
+
+        // scrape all the news from the html, news without a title or a url are skipped.
+        Ok(document
+            .select(&results)
+            .filter_map(|result| {
+                let title = result.select(&result_title).next()?;
+                let description: String = result
+                    .select(&result_desc)
+                    .next()
+                    .map(|description| description.inner_html())
+                    .unwrap_or_default();
+                let published: i64 = result
+                    .select(&result_published)
+                    .next()
+                    .and_then(|published| published.value().attr("datetime"))
+                    .and_then(parse_published_date)
+                    .unwrap_or_default();
+
+                Some(NewsResult::new(
+                    title.inner_html().trim(),
+                    title.value().attr("href")?,
+                    description.trim(),
+                    "searx",
+                    published,
+                ))
             })
-            .map(|search_result| (search_result.url.clone(), search_result))
             .collect())
     }
+
+    /// The unified safe search levels are mapped to the `safesearch` query parameter of searx
+    /// as follows:
+    ///
+    /// | Level | `safesearch` |
+    /// | ----- | ------------ |
+    /// | 0     | `0` (none)   |
+    /// | 1     | `1` (moderate) |
+    /// | 2 - 4 | `2` (strict) |
+    fn map_safe_search(&self, level: u8) -> EngineSafeParam {
+        match level {
+            0 => EngineSafeParam::Value("0"),
+            1 => EngineSafeParam::Value("1"),
+            _ => EngineSafeParam::Value("2"),
+        }
+    }
+}
+
+/// A helper function which parses the publishing date of a news result provided by searx (or
+/// searxng) in the `YYYY-MM-DD HH:MM:SS` format (in UTC) into a unix timestamp (in seconds).
+///
+/// # Arguments
+///
+/// * `date` - It takes the publishing date to be parsed.
+///
+/// # Returns
+///
+/// It returns the unix timestamp if the date could be parsed otherwise it returns `None`.
+pub fn parse_published_date(date: &str) -> Option<i64> {
+    let (date, time) = date
+        .trim()
+        .split_once(' ')
+        .unwrap_or((date.trim(), "00:00:00"));
+
+    let mut date_parts = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+    );
+
+    let mut time_parts = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hours, minutes, seconds) = (
+        time_parts.next().flatten().unwrap_or_default(),
+        time_parts.next().flatten().unwrap_or_default(),
+        time_parts.next().flatten().unwrap_or_default(),
+    );
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // the number of days since the unix epoch computed using the days from civil algorithm.
+    let year: i64 = if month <= 2 { year - 1 } else { year };
+    let era: i64 = year.div_euclid(400);
+    let year_of_era: i64 = year - era * 400;
+    let day_of_year: i64 =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era: i64 = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days: i64 = era * 146_097 + day_of_era - 719_468;
+
+    Some(days * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
+
+/// A helper function which scrapes the related searches and the spelling corrections from the
+/// result page of searx.
+///
+/// # Arguments
+///
+/// * `document` - It takes the parsed result page of searx.
+///
+/// # Error
+///
+/// It returns an `UnexpectedError` if a selector used to scrape the suggestions is invalid.
+fn parse_suggestions(document: &Html) -> Result<Vec<Suggestion>, EngineError> {
+    let suggestion: Selector = Selector::parse("#suggestions .suggestion")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| {
+            format!("invalid CSS selector: {}", "#suggestions .suggestion")
+        })?;
+    let correction: Selector = Selector::parse("#corrections input[name=\"q\"], #corrections a")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| {
+            format!(
+                "invalid CSS selector: {}",
+                "#corrections input[name=\"q\"], #corrections a"
+            )
+        })?;
+
+    // the suggestions and the corrections are rendered either as form inputs or as links
+    // depending on the theme of the searx instance and can be prefixed with a bullet.
+    let text = |element: scraper::ElementRef<'_>| -> Option<String> {
+        let text: String = match element.value().attr("value") {
+            Some(value) => value.to_owned(),
+            None => element.text().collect(),
+        };
+        let text: &str = text.trim_start_matches('•').trim();
+        (!text.is_empty()).then(|| text.to_owned())
+    };
+
+    Ok(document
+        .select(&correction)
+        .filter_map(text)
+        .map(Suggestion::Correction)
+        .chain(
+            document
+                .select(&suggestion)
+                .filter_map(text)
+                .map(Suggestion::Related),
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_published_date() {
+        assert_eq!(parse_published_date("1970-01-01 00:00:00"), Some(0));
+        assert_eq!(
+            parse_published_date("2023-11-14 22:13:20"),
+            Some(1_700_000_000)
+        );
+        assert_eq!(parse_published_date("2000-03-01"), Some(951_868_800));
+        assert_eq!(parse_published_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_parse_suggestions() {
+        let html: &str = r#"
+            <div id="corrections">
+                <h4>Try searching for:</h4>
+                <form method="GET" action="/search">
+                    <input type="hidden" name="q" value="rust programming">
+                    <input type="submit" class="link-like" value="rust programming">
+                </form>
+            </div>
+            <div id="suggestions">
+                <form method="GET" action="/search">
+                    <input type="submit" class="suggestion" value="• rust book">
+                </form>
+                <a class="suggestion" href="/search?q=rust+lang">rust lang</a>
+            </div>
+        "#;
+
+        assert_eq!(
+            parse_suggestions(&Html::parse_document(html)).unwrap(),
+            vec![
+                Suggestion::Correction("rust programming".to_owned()),
+                Suggestion::Related("rust book".to_owned()),
+                Suggestion::Related("rust lang".to_owned()),
+            ]
+        );
+        assert!(parse_suggestions(&Html::parse_document("<div></div>"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_corrections_from_the_results_page() {
+        let html: &str = r#"
+            <div id="urls">
+                <article class="result">
+                    <h3><a href="https://www.rust-lang.org/">Rust Programming Language</a></h3>
+                    <p class="content">A language empowering everyone.</p>
+                </article>
+            </div>
+            <div id="sidebar">
+                <div id="corrections">
+                    <h4>Try searching for:</h4>
+                    <a href="/search?q=rust+programming">rust programming</a>
+                </div>
+            </div>
+        "#;
+
+        assert_eq!(
+            parse_suggestions(&Html::parse_document(html)).unwrap(),
+            vec![Suggestion::Correction("rust programming".to_owned())]
+        );
+    }
 }
@@ -0,0 +1,339 @@
+//! The `searxng` module handles the fetching of results from a searxng instance through its json
+//! api by querying the upstream searxng instance configured with the `base_url` of the engine
+//! with user provided query and with a page number if provided, which allows federating the
+//! searxng instances (like a self-hosted one) without scraping their result pages.
+
+use std::collections::HashMap;
+
+use reqwest::{header::HeaderMap, Client};
+use serde::Deserialize;
+
+use crate::models::aggregation_models::{SearchResult, Suggestion};
+use crate::models::engine_models::{EngineError, EngineSafeParam, SearchEngine, UpstreamHeaders};
+use crate::results::bangs::percent_encode;
+
+use super::searx::parse_published_date;
+
+use error_stack::{Report, Result, ResultExt};
+
+/// A new SearxNG engine type defined in-order to implement the `SearchEngine` trait which allows to
+/// reduce code duplication as well as allows to create vector of different search engines easily.
+pub struct SearxNG {
+    /// It stores the base url of the upstream searxng instance, there is no public instance used
+    /// by default and so the engine fails if it is not provided.
+    base_url: Option<String>,
+}
+
+impl SearxNG {
+    /// Constructs a new `SearxNG` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the base url of the searxng instance from the config file.
+    pub fn new(base_url: Option<&str>) -> Self {
+        SearxNG {
+            base_url: base_url.map(str::to_owned),
+        }
+    }
+
+    /// A helper function which builds the url of the json api of the searxng instance for the
+    /// provided search.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query.
+    /// * `page` - It takes the page number of the search.
+    /// * `safe_search` - It takes the searxng specific safe search value.
+    ///
+    /// # Error
+    ///
+    /// It returns an `UnexpectedError` if the base url of the instance is not configured.
+    fn search_url(&self, query: &str, page: u32, safe_search: &str) -> Result<String, EngineError> {
+        let base_url: &str = self.base_url.as_deref().ok_or_else(|| {
+            Report::new(EngineError::UnexpectedError)
+                .attach_printable("the `base_url` of the searxng engine is not configured")
+        })?;
+
+        Ok(format!(
+            "{}/search?q={}&pageno={}&safesearch={safe_search}&format=json",
+            base_url.trim_end_matches('/'),
+            percent_encode(query),
+            page.max(1)
+        ))
+    }
+
+    /// A helper function which requests the json api of the searxng instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the json api built with `search_url`.
+    /// * `headers` - It takes the headers (like the user agent) to be sent to the instance.
+    /// * `client` - It takes the http client shared between all the upstream requests.
+    /// * `request_timeout` - It takes the request timeout value as seconds.
+    ///
+    /// # Error
+    ///
+    /// It returns an `EngineError` if the instance could not be requested or its response could
+    /// not be parsed.
+    async fn fetch_response(
+        &self,
+        url: &str,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+    ) -> Result<SearxNGResponse, EngineError> {
+        // initializing HeaderMap and adding appropriate headers.
+        let header_map: HeaderMap = headers.header_map(HashMap::from([(
+            "ACCEPT".to_string(),
+            "application/json".to_string(),
+        )]))?;
+
+        let json: String = self
+            .fetch_html_from_upstream(client, url, header_map, request_timeout)
+            .await?;
+
+        parse_response(&json, url)
+    }
+}
+
+/// A named struct which deserializes the response returned by the json api of searxng.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SearxNGResponse {
+    /// It stores the results which matched the search query.
+    results: Vec<SearxNGResult>,
+    /// It stores the related searches suggested for the search query.
+    suggestions: Vec<String>,
+    /// It stores the corrections of the misspelled search query.
+    corrections: Vec<String>,
+    /// It stores the names of the engines of the instance which failed along with the reasons.
+    unresponsive_engines: Vec<Vec<String>>,
+    /// It stores the error message provided by the instance if the search failed.
+    error: Option<String>,
+}
+
+/// A named struct which deserializes a single result of the json api of searxng.
+#[derive(Deserialize)]
+struct SearxNGResult {
+    /// It stores the url of the result.
+    url: String,
+    /// It stores the title of the result.
+    #[serde(default)]
+    title: String,
+    /// It stores the description of the result.
+    #[serde(default)]
+    content: Option<String>,
+    /// It stores the publishing date of the result (like `2023-10-16T12:00:00`).
+    #[serde(default, rename = "publishedDate")]
+    published_date: Option<String>,
+}
+
+/// A helper function which parses the response of the json api of searxng, the error payloads
+/// of the instance (like all of its engines being unresponsive) are turned into an `EngineError`.
+///
+/// # Arguments
+///
+/// * `json` - It takes the body of the response.
+/// * `url` - It takes the requested url, which is attached to the error if the response is
+/// invalid.
+///
+/// # Error
+///
+/// It returns an `UnexpectedError` if the response is not valid json (which is the case when the
+/// json format is not enabled on the instance) or carries an error, a `RequestError` if all the
+/// engines of the instance were unresponsive and an `EmptyResultSet` if there are no results.
+fn parse_response(json: &str, url: &str) -> Result<SearxNGResponse, EngineError> {
+    let response: SearxNGResponse = serde_json::from_str(json)
+        .change_context(EngineError::UnexpectedError)
+        .attach_printable_lazy(|| {
+            format!("invalid json response from: {url} (the `json` format should be enabled in the `search.formats` setting of the searxng instance)")
+        })?;
+
+    if let Some(error) = &response.error {
+        return Err(Report::new(EngineError::UnexpectedError)
+            .attach_printable(format!("searxng instance responded with: {error}")));
+    }
+
+    if response.results.is_empty() {
+        if !response.unresponsive_engines.is_empty() {
+            let engines: Vec<String> = response
+                .unresponsive_engines
+                .iter()
+                .map(|engine| engine.join(": "))
+                .collect();
+            return Err(
+                Report::new(EngineError::RequestError).attach_printable(format!(
+                    "the engines of the searxng instance were unresponsive ({})",
+                    engines.join(", ")
+                )),
+            );
+        }
+        return Err(Report::new(EngineError::EmptyResultSet));
+    }
+
+    Ok(response)
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for SearxNG {
+    async fn results(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        let (results, _) = self
+            .results_with_suggestions(
+                query,
+                page,
+                headers,
+                client,
+                request_timeout,
+                safe_search,
+                max_results,
+            )
+            .await?;
+        Ok(results)
+    }
+
+    async fn results_with_suggestions(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<(HashMap<String, SearchResult>, Vec<Suggestion>), EngineError> {
+        let url: String = self.search_url(query, page, safe_search.value())?;
+        let response: SearxNGResponse = self
+            .fetch_response(&url, headers, client, request_timeout)
+            .await?;
+
+        let suggestions: Vec<Suggestion> = response
+            .corrections
+            .into_iter()
+            .map(Suggestion::Correction)
+            .chain(response.suggestions.into_iter().map(Suggestion::Related))
+            .collect();
+
+        let results: HashMap<String, SearchResult> = response
+            .results
+            .into_iter()
+            .filter(|result| !result.url.is_empty())
+            .map(|result| {
+                let mut search_result: SearchResult = SearchResult::new(
+                    result.title.trim(),
+                    &result.url,
+                    result.content.as_deref().unwrap_or_default().trim(),
+                    &["searxng"],
+                );
+                search_result.set_published(
+                    result
+                        .published_date
+                        .as_deref()
+                        .and_then(|date| parse_published_date(&date.replacen('T', " ", 1))),
+                );
+                search_result
+            })
+            .take(max_results)
+            .enumerate()
+            .map(|(index, mut search_result)| {
+                search_result.set_position(index + 1);
+                (search_result.url.clone(), search_result)
+            })
+            .collect();
+
+        Ok((results, suggestions))
+    }
+
+    /// The unified safe search levels are mapped to the `safesearch` query parameter of searxng
+    /// as follows:
+    ///
+    /// | Level | `safesearch` |
+    /// | ----- | ------------ |
+    /// | 0     | `0` (none)   |
+    /// | 1     | `1` (moderate) |
+    /// | 2 - 4 | `2` (strict) |
+    fn map_safe_search(&self, level: u8) -> EngineSafeParam {
+        match level {
+            0 => EngineSafeParam::Value("0"),
+            1 => EngineSafeParam::Value("1"),
+            _ => EngineSafeParam::Value("2"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_url_requires_a_base_url() {
+        let searxng: SearxNG = SearxNG::new(Some("https://searxng.example.org/"));
+        assert_eq!(
+            searxng.search_url("rust & c", 0, "1").unwrap(),
+            "https://searxng.example.org/search?q=rust%20%26%20c&pageno=1&safesearch=1&format=json"
+        );
+        assert!(SearxNG::new(None).search_url("rust", 1, "1").is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let json: &str = r#"{
+            "query": "rust",
+            "results": [
+                {
+                    "url": "https://www.rust-lang.org/",
+                    "title": "Rust Programming Language",
+                    "content": "A language empowering everyone.",
+                    "publishedDate": "2023-10-16T00:00:00"
+                },
+                { "url": "https://doc.rust-lang.org/book/", "title": "The Rust Book", "content": null }
+            ],
+            "suggestions": ["rust lang"],
+            "corrections": [],
+            "unresponsive_engines": [["google", "timeout"]]
+        }"#;
+        let response: SearxNGResponse = parse_response(json, "").unwrap();
+        assert_eq!(response.results.len(), 2);
+        assert_eq!(
+            response.results[0]
+                .published_date
+                .as_deref()
+                .and_then(|date| parse_published_date(&date.replacen('T', " ", 1))),
+            Some(1_697_414_400)
+        );
+        assert_eq!(response.suggestions, vec!["rust lang"]);
+
+        let unresponsive: Report<EngineError> = parse_response(
+            r#"{ "results": [], "unresponsive_engines": [["google", "timeout"]] }"#,
+            "",
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(
+            unresponsive.current_context(),
+            EngineError::RequestError
+        ));
+        assert!(matches!(
+            parse_response(r#"{ "results": [] }"#, "")
+                .err()
+                .unwrap()
+                .current_context(),
+            EngineError::EmptyResultSet
+        ));
+        assert!(matches!(
+            parse_response("<html>Forbidden</html>", "")
+                .err()
+                .unwrap()
+                .current_context(),
+            EngineError::UnexpectedError
+        ));
+    }
+}
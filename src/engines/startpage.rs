@@ -0,0 +1,308 @@
+//! The `startpage` module handles the scraping of results from the startpage search engine
+//! by querying the upstream startpage search engine with user provided query and with a page
+//! number if provided.
+
+use std::{collections::HashMap, time::Duration};
+
+use reqwest::{header::HeaderMap, Client};
+use scraper::{Html, Selector};
+
+use crate::models::aggregation_models::SearchResult;
+use crate::results::{
+    engine_session::{add_session_cookies, invalidate_session},
+    outbound_guard::guard_outbound_request,
+};
+
+use crate::models::engine_models::{
+    read_response_body, EngineError, EngineErrorKind, EngineSafeParam, SearchEngine,
+    UpstreamHeaders,
+};
+
+use error_stack::{Report, Result, ResultExt};
+
+/// The base url of the upstream startpage search engine used unless another one is configured.
+const STARTPAGE_URL: &str = "https://www.startpage.com";
+
+/// A new Startpage engine type defined in-order to implement the `SearchEngine` trait which allows to
+/// reduce code duplication as well as allows to create vector of different search engines easily.
+pub struct Startpage {
+    /// It stores the base url of the upstream startpage search engine.
+    base_url: String,
+}
+
+impl Startpage {
+    /// Constructs a new `Startpage` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url from the config file, the official
+    /// startpage search engine is used if it is not provided.
+    pub fn new(base_url: Option<&str>) -> Self {
+        Startpage {
+            base_url: base_url.unwrap_or(STARTPAGE_URL).to_owned(),
+        }
+    }
+
+    /// This helper function fetches the `sc` token from the home page of startpage which is
+    /// sometimes required by startpage to accept the search form. Failing to find the token is not
+    /// considered an error as startpage accepts the search form without it most of the time.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - It takes the http client shared between all the upstream requests.
+    /// * `header_map` - It takes the http request headers to be sent to startpage.
+    /// * `request_timeout` - It takes the request timeout value as seconds.
+    ///
+    /// # Error
+    ///
+    /// It returns an `EngineError` if the home page of startpage could not be fetched.
+    async fn fetch_sc_token(
+        &self,
+        client: &Client,
+        header_map: HeaderMap,
+        request_timeout: u8,
+    ) -> Result<Option<String>, EngineError> {
+        let html: String = self
+            .fetch_html_from_upstream(client, &self.base_url, header_map, request_timeout)
+            .await?;
+
+        let sc_token: Selector = Selector::parse("input[name=sc]")
+            .map_err(|_| Report::new(EngineError::UnexpectedError))
+            .attach_printable_lazy(|| format!("invalid CSS selector: {}", "input[name=sc]"))?;
+
+        Ok(Html::parse_document(&html)
+            .select(&sc_token)
+            .next()
+            .and_then(|input| input.value().attr("value"))
+            .map(str::to_owned))
+    }
+
+    /// This helper function submits the search form to startpage with a POST request and returns
+    /// the page containing the search results as a string.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - It takes the http client shared between all the upstream requests.
+    /// * `form` - It takes the fields of the search form to be submitted.
+    /// * `header_map` - It takes the http request headers to be sent to startpage.
+    /// * `request_timeout` - It takes the request timeout value as seconds.
+    ///
+    /// # Error
+    ///
+    /// It returns a `RequestError` if the search form could not be submitted or startpage
+    /// responded with a server error. The cookies of the session with startpage are sent along
+    /// with the search form.
+    async fn submit_search_form(
+        &self,
+        client: &Client,
+        form: &[(&str, &str)],
+        mut header_map: HeaderMap,
+        request_timeout: u8,
+    ) -> Result<String, EngineError> {
+        let url: String = format!("{}/sp/search", self.base_url);
+        guard_outbound_request(&url).await?;
+
+        if let Some(cookies) = self
+            .session_cookies(client, header_map.clone(), request_timeout)
+            .await?
+        {
+            add_session_cookies(&mut header_map, &cookies);
+        }
+
+        let response = client
+            .post(url)
+            .timeout(Duration::from_secs(request_timeout as u64))
+            .headers(header_map)
+            .form(form)
+            .send()
+            .await
+            .change_context(EngineError::RequestError)?;
+
+        // server errors are reported as request errors so that they can be retried while client
+        // errors (like being rate limited) are not retried.
+        if response.status().is_server_error() || response.status().is_client_error() {
+            let error: EngineError = match response.status().is_server_error() {
+                true => EngineError::RequestError,
+                false => EngineError::UnexpectedError,
+            };
+            // startpage might have rejected the cookies of the session so a new session is
+            // established by the next search.
+            if let (true, Some(session_url)) =
+                (response.status().is_client_error(), self.session_url())
+            {
+                invalidate_session(&session_url);
+            }
+            return Err(Report::new(error)
+                .attach(EngineErrorKind::Http(response.status().as_u16()))
+                .attach_printable(format!(
+                    "upstream search engine responded with status {}",
+                    response.status()
+                )));
+        }
+
+        read_response_body(response).await
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for Startpage {
+    /// Startpage sets the preferences and the consent cookies on its home page, without which it
+    /// sometimes responds with an empty result page.
+    fn session_url(&self) -> Option<String> {
+        Some(format!("{}/", self.base_url))
+    }
+
+    async fn results(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        // initializing HeaderMap and adding appropriate headers.
+        let header_map: HeaderMap = headers.header_map(HashMap::from([
+            ("REFERER".to_string(), format!("{}/", self.base_url)),
+            ("ORIGIN".to_string(), self.base_url.clone()),
+        ]))?;
+
+        let sc_token: Option<String> = self
+            .fetch_sc_token(client, header_map.clone(), request_timeout)
+            .await?;
+
+        // Page number can be missing or zero and so appropriate handling is required so that
+        // upstream server recieves valid page number.
+        let page: String = page.max(1).to_string();
+
+        let mut form: Vec<(&str, &str)> = vec![
+            ("query", query),
+            ("cat", "web"),
+            ("page", page.as_str()),
+            ("qadf", safe_search.value()),
+        ];
+        if let Some(sc_token) = &sc_token {
+            form.push(("sc", sc_token.as_str()));
+        }
+
+        let html: String = self
+            .submit_search_form(client, &form, header_map, request_timeout)
+            .await?;
+
+        let search_results: HashMap<String, SearchResult> = parse_results(&html, max_results)?;
+
+        if search_results.is_empty() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+
+        Ok(search_results)
+    }
+
+    /// The unified safe search levels are mapped to the `qadf` form field of startpage (which
+    /// only supports enabling or disabling the family filter) as follows:
+    ///
+    /// | Level | `qadf`          |
+    /// | ----- | --------------- |
+    /// | 0 - 1 | `none` (off)    |
+    /// | 2 - 4 | `heavy` (on)    |
+    fn map_safe_search(&self, level: u8) -> EngineSafeParam {
+        match level {
+            0..=1 => EngineSafeParam::Value("none"),
+            _ => EngineSafeParam::Value("heavy"),
+        }
+    }
+}
+
+/// A helper function which scrapes the search results from the result page of startpage, the
+/// results without a title or a url are skipped.
+///
+/// # Arguments
+///
+/// * `html` - It takes the result page of startpage.
+/// * `max_results` - It takes the maximum number of results to be scraped.
+///
+/// # Error
+///
+/// It returns an `UnexpectedError` if a selector used to scrape the results is invalid.
+fn parse_results(
+    html: &str,
+    max_results: usize,
+) -> Result<HashMap<String, SearchResult>, EngineError> {
+    let document: Html = Html::parse_document(html);
+
+    let results: Selector = Selector::parse(".w-gl__result")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".w-gl__result"))?;
+    let result_title: Selector = Selector::parse(".w-gl__result-title h3")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".w-gl__result-title h3"))?;
+    let result_url: Selector = Selector::parse("a.w-gl__result-title")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", "a.w-gl__result-title"))?;
+    let result_desc: Selector = Selector::parse(".w-gl__description")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".w-gl__description"))?;
+
+    Ok(document
+        .select(&results)
+        .filter_map(|result| {
+            Some(SearchResult::new(
+                result.select(&result_title).next()?.inner_html().trim(),
+                result.select(&result_url).next()?.value().attr("href")?,
+                result
+                    .select(&result_desc)
+                    .next()
+                    .map(|description| description.inner_html())
+                    .unwrap_or_default()
+                    .trim(),
+                &["startpage"],
+            ))
+        })
+        .take(max_results)
+        .enumerate()
+        .map(|(index, mut search_result)| {
+            search_result.set_position(index + 1);
+            (search_result.url.clone(), search_result)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_results() {
+        let html: &str = r#"
+            <div class="w-gl">
+                <div class="w-gl__result">
+                    <a class="w-gl__result-title" href="https://www.rust-lang.org/">
+                        <h3>Rust Programming Language</h3>
+                    </a>
+                    <p class="w-gl__description">A language empowering everyone.</p>
+                </div>
+                <div class="w-gl__result">
+                    <a class="w-gl__result-title" href="https://doc.rust-lang.org/book/">
+                        <h3>The Rust Book</h3>
+                    </a>
+                </div>
+                <div class="w-gl__result">
+                    <p class="w-gl__description">A result without a title or a url.</p>
+                </div>
+            </div>
+        "#;
+
+        let results: HashMap<String, SearchResult> = parse_results(html, usize::MAX).unwrap();
+        assert_eq!(results.len(), 2);
+        let result: &SearchResult = &results["https://www.rust-lang.org/"];
+        assert_eq!(result.title, "Rust Programming Language");
+        assert_eq!(result.description, "A language empowering everyone.");
+        assert_eq!(results["https://doc.rust-lang.org/book/"].description, "");
+
+        // the results are truncated to the maximum number of results in the order of the page.
+        let results: HashMap<String, SearchResult> = parse_results(html, 1).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results.contains_key("https://www.rust-lang.org/"));
+    }
+}
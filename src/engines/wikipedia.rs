@@ -0,0 +1,207 @@
+//! The `wikipedia` module handles the fetching of encyclopedic results from the wikipedia search
+//! api by querying the upstream wikipedia api with user provided query and with a page number if
+//! provided.
+
+use std::collections::HashMap;
+
+use reqwest::{header::HeaderMap, Client};
+use serde::Deserialize;
+
+use crate::models::aggregation_models::SearchResult;
+use crate::models::engine_models::{EngineError, EngineSafeParam, SearchEngine, UpstreamHeaders};
+use crate::results::bangs::percent_encode;
+
+use error_stack::{Report, Result, ResultExt};
+
+/// The language code of the wikipedia which is queried when no region is requested.
+const DEFAULT_LANGUAGE: &str = "en";
+/// The number of articles requested from the wikipedia api per page.
+const RESULTS_PER_PAGE: u32 = 10;
+
+/// A new Wikipedia engine type defined in-order to implement the `SearchEngine` trait which allows to
+/// reduce code duplication as well as allows to create vector of different search engines easily.
+pub struct Wikipedia {
+    /// It stores the override of the base url of the upstream wikipedia, the wikipedia of the
+    /// queried language is used if it is not provided.
+    base_url: Option<String>,
+}
+
+impl Wikipedia {
+    /// Constructs a new `Wikipedia` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url from the config file.
+    pub fn new(base_url: Option<&str>) -> Self {
+        Wikipedia {
+            base_url: base_url.map(str::to_owned),
+        }
+    }
+}
+
+/// A named struct which deserializes the response returned by the wikipedia search api.
+#[derive(Deserialize)]
+struct WikipediaResponse {
+    /// It stores the search results if the request was successful.
+    query: Option<WikipediaQuery>,
+    /// It stores the error provided by the api if the request failed.
+    error: Option<WikipediaApiError>,
+}
+
+/// A named struct which deserializes the query section of the wikipedia search api response.
+#[derive(Deserialize)]
+struct WikipediaQuery {
+    /// It stores the articles which matched the search query.
+    search: Vec<WikipediaArticle>,
+}
+
+/// A named struct which deserializes a single article matching the search query.
+#[derive(Deserialize)]
+struct WikipediaArticle {
+    /// It stores the title of the article.
+    title: String,
+    /// It stores the summary of the article with the matching words highlighted.
+    snippet: String,
+}
+
+/// A named struct which deserializes the error returned by the wikipedia search api.
+#[derive(Deserialize)]
+struct WikipediaApiError {
+    /// It stores the error code provided by the api.
+    code: String,
+    /// It stores the human readable error message provided by the api.
+    info: String,
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for Wikipedia {
+    async fn results(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        _safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        let language: &str = region_language(headers.region.as_deref());
+        let base_url: String = self
+            .base_url
+            .clone()
+            .unwrap_or_else(|| format!("https://{language}.wikipedia.org"));
+
+        let url: String = format!(
+            "{base_url}/w/api.php?action=query&list=search&format=json&srsearch={}&srlimit={RESULTS_PER_PAGE}&sroffset={}",
+            percent_encode(query),
+            page.saturating_sub(1) * RESULTS_PER_PAGE
+        );
+
+        // initializing HeaderMap and adding appropriate headers.
+        let header_map: HeaderMap = headers.header_map(HashMap::from([(
+            "REFERER".to_string(),
+            "https://google.com/".to_string(),
+        )]))?;
+
+        let json: String =
+            Wikipedia::fetch_html_from_upstream(self, client, &url, header_map, request_timeout)
+                .await?;
+
+        let response: WikipediaResponse = serde_json::from_str(&json)
+            .change_context(EngineError::UnexpectedError)
+            .attach_printable_lazy(|| format!("invalid json response from: {}", url))?;
+
+        if let Some(error) = response.error {
+            return Err(Report::new(EngineError::UnexpectedError)
+                .attach_printable(format!("{}: {}", error.code, error.info)));
+        }
+
+        let articles: Vec<WikipediaArticle> = match response.query {
+            Some(query) if !query.search.is_empty() => query.search,
+            _ => return Err(Report::new(EngineError::EmptyResultSet)),
+        };
+
+        Ok(articles
+            .into_iter()
+            .map(|article| {
+                SearchResult::new(
+                    &article.title,
+                    &format!("{base_url}/wiki/{}", article.title.replace(' ', "_")),
+                    &article.snippet,
+                    &["wikipedia"],
+                )
+            })
+            .take(max_results)
+            .enumerate()
+            .map(|(index, mut search_result)| {
+                search_result.set_position(index + 1);
+                (search_result.url.clone(), search_result)
+            })
+            .collect())
+    }
+
+    /// The `site:` operators (like `site:github.com`) are dropped from the query as the wikipedia
+    /// search api does not understand them and matches them as words otherwise, which leaves
+    /// no results for such queries. The query is kept as it is if it consists of the operators
+    /// only.
+    fn transform_query(&self, query: &str) -> String {
+        let words: Vec<&str> = query
+            .split_whitespace()
+            .filter(|word| {
+                !word
+                    .trim_start_matches('-')
+                    .to_ascii_lowercase()
+                    .starts_with("site:")
+            })
+            .collect();
+
+        match words.is_empty() {
+            true => query.to_owned(),
+            false => words.join(" "),
+        }
+    }
+}
+
+/// A helper function which picks the language of the wikipedia to be queried from the region the
+/// results should be localized to.
+///
+/// # Arguments
+///
+/// * `region` - It takes the normalized region (like `us-en`) the results should be localized to
+/// if any.
+///
+/// # Returns
+///
+/// It returns the language part of the region or the default language if no region is provided.
+fn region_language(region: Option<&str>) -> &str {
+    region
+        .and_then(|region| region.split_once('-'))
+        .map(|(_, language)| language)
+        .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_language() {
+        assert_eq!(region_language(Some("us-en")), "en");
+        assert_eq!(region_language(Some("ch-fr")), "fr");
+        assert_eq!(region_language(None), DEFAULT_LANGUAGE);
+    }
+
+    #[test]
+    fn test_site_operators_are_dropped_from_the_query() {
+        let wikipedia: Wikipedia = Wikipedia::new(None);
+        assert_eq!(
+            wikipedia.transform_query("rust site:github.com  ownership -SITE:reddit.com"),
+            "rust ownership"
+        );
+        assert_eq!(wikipedia.transform_query("c++ & rust"), "c++ & rust");
+        assert_eq!(
+            wikipedia.transform_query("site:github.com"),
+            "site:github.com"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! The `yandex` module handles the scraping of results from the yandex search engine
+//! by querying the upstream yandex search engine with user provided query and with a page
+//! number if provided.
+
+use std::collections::HashMap;
+
+use reqwest::{header::HeaderMap, Client};
+use scraper::{Html, Selector};
+
+use crate::models::aggregation_models::SearchResult;
+
+use crate::models::engine_models::{
+    EngineError, EngineErrorKind, EngineSafeParam, SearchEngine, UpstreamHeaders,
+};
+use crate::results::bangs::percent_encode;
+
+use error_stack::{Report, Result, ResultExt};
+
+/// The base url of the upstream yandex search engine.
+const BASE_URL: &str = "https://yandex.com";
+
+/// The selectors which only match the captcha (anti-bot) page yandex answers with instead of the
+/// results when it suspects the requests to be automated.
+const CAPTCHA_SELECTOR: &str =
+    "form[action*='checkcaptcha'], .CheckboxCaptcha, .AdvancedCaptcha, #checkbox-captcha-form";
+
+/// A new Yandex engine type defined in-order to implement the `SearchEngine` trait which allows to
+/// reduce code duplication as well as allows to create vector of different search engines easily.
+pub struct Yandex {
+    /// It stores the base url of the upstream yandex search engine.
+    base_url: String,
+}
+
+impl Yandex {
+    /// Constructs a new `Yandex` engine with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url from the config file, the official
+    /// yandex search engine is used if it is not provided.
+    pub fn new(base_url: Option<&str>) -> Self {
+        Yandex {
+            base_url: base_url.unwrap_or(BASE_URL).to_owned(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for Yandex {
+    async fn results(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        let url: String = format!(
+            "{}/search/?text={}&p={}&family={}",
+            self.base_url,
+            percent_encode(query),
+            page_number(page),
+            safe_search.value()
+        );
+
+        // initializing HeaderMap and adding appropriate headers, yandex is quick to answer with
+        // a captcha page to the requests which do not look like the ones of a browser.
+        let header_map: HeaderMap = headers.header_map(HashMap::from([
+            ("REFERER".to_string(), format!("{}/", self.base_url)),
+            (
+                "ACCEPT".to_string(),
+                "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".to_string(),
+            ),
+        ]))?;
+
+        let html: String =
+            Yandex::fetch_html_from_upstream(self, client, &url, header_map, request_timeout)
+                .await?;
+
+        let search_results: HashMap<String, SearchResult> = parse_results(&html, max_results)?;
+
+        if search_results.is_empty() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+
+        Ok(search_results)
+    }
+
+    /// The unified safe search levels are mapped to the `family` query parameter of yandex as
+    /// follows:
+    ///
+    /// | Level | `family`         |
+    /// | ----- | ---------------- |
+    /// | 0     | `0` (off)        |
+    /// | 1 - 2 | `1` (moderate)   |
+    /// | 3 - 4 | `2` (family)     |
+    fn map_safe_search(&self, level: u8) -> EngineSafeParam {
+        match level {
+            0 => EngineSafeParam::Value("0"),
+            1..=2 => EngineSafeParam::Value("1"),
+            _ => EngineSafeParam::Value("2"),
+        }
+    }
+}
+
+/// A helper function which converts the page number into the `p` query parameter of yandex, which
+/// is the zero based index of the page.
+///
+/// # Arguments
+///
+/// * `page` - It takes the page number requested by the user, where `0` is treated as the first
+/// page.
+fn page_number(page: u32) -> u32 {
+    page.saturating_sub(1)
+}
+
+/// A helper function which scrapes the search results from the result page of yandex, the results
+/// without a title or a url (like the ads and the widgets) are skipped.
+///
+/// # Arguments
+///
+/// * `html` - It takes the result page of yandex.
+/// * `max_results` - It takes the maximum number of results to be scraped.
+///
+/// # Error
+///
+/// It returns an `UnexpectedError` with the `Captcha` kind attached if yandex answered with its
+/// captcha page instead of the results, so that it is reported as such instead of as an empty
+/// result set. It also returns an `UnexpectedError` if a selector used to scrape the results is
+/// invalid.
+fn parse_results(
+    html: &str,
+    max_results: usize,
+) -> Result<HashMap<String, SearchResult>, EngineError> {
+    let document: Html = Html::parse_document(html);
+
+    let captcha: Selector = Selector::parse(CAPTCHA_SELECTOR)
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", CAPTCHA_SELECTOR))?;
+    if document.select(&captcha).next().is_some() {
+        return Err(Report::new(EngineError::UnexpectedError)
+            .attach(EngineErrorKind::Captcha)
+            .attach_printable("upstream search engine responded with a captcha page"));
+    }
+
+    let results: Selector = Selector::parse("li.serp-item")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", "li.serp-item"))?;
+    let result_title: Selector = Selector::parse("a.OrganicTitle-Link")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", "a.OrganicTitle-Link"))?;
+    let result_desc: Selector = Selector::parse(".OrganicTextContentSpan")
+        .map_err(|_| Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("invalid CSS selector: {}", ".OrganicTextContentSpan"))?;
+
+    Ok(document
+        .select(&results)
+        .filter_map(|result| {
+            let title = result.select(&result_title).next()?;
+            Some(SearchResult::new(
+                title.text().collect::<String>().trim(),
+                title.value().attr("href")?,
+                result
+                    .select(&result_desc)
+                    .next()
+                    .map(|description| description.inner_html())
+                    .unwrap_or_default()
+                    .trim(),
+                &["yandex"],
+            ))
+        })
+        .take(max_results)
+        .enumerate()
+        .map(|(index, mut search_result)| {
+            search_result.set_position(index + 1);
+            (search_result.url.clone(), search_result)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_number() {
+        assert_eq!(page_number(0), 0);
+        assert_eq!(page_number(1), 0);
+        assert_eq!(page_number(3), 2);
+    }
+
+    #[test]
+    fn test_parse_results() {
+        let html: &str = r#"
+            <ul id="search-result">
+                <li class="serp-item">
+                    <a class="OrganicTitle-Link" href="https://www.rust-lang.org/">
+                        <h2><span>Rust Programming Language</span></h2>
+                    </a>
+                    <span class="OrganicTextContentSpan">A language empowering everyone.</span>
+                </li>
+                <li class="serp-item">
+                    <a class="OrganicTitle-Link" href="https://doc.rust-lang.org/book/">The Rust Book</a>
+                </li>
+                <li class="serp-item"><div class="Advert">An advert without a title.</div></li>
+            </ul>
+        "#;
+
+        let results: HashMap<String, SearchResult> = parse_results(html, usize::MAX).unwrap();
+        assert_eq!(results.len(), 2);
+        let result: &SearchResult = &results["https://www.rust-lang.org/"];
+        assert_eq!(result.title, "Rust Programming Language");
+        assert_eq!(result.description, "A language empowering everyone.");
+        assert_eq!(results["https://doc.rust-lang.org/book/"].description, "");
+
+        assert_eq!(parse_results(html, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_captcha_page_is_reported_as_such() {
+        let html: &str = r#"
+            <html>
+                <head><title>Are you not a robot?</title></head>
+                <body>
+                    <form id="checkbox-captcha-form" action="/checkcaptcha?key=abc" method="post">
+                        <input type="submit" />
+                    </form>
+                </body>
+            </html>
+        "#;
+
+        let error: Report<EngineError> = parse_results(html, usize::MAX).err().unwrap();
+        assert_eq!(EngineErrorKind::of(&error), EngineErrorKind::Captcha);
+    }
+}
@@ -3,3 +3,4 @@
 //! can be used.
 
 pub mod paths;
+pub mod styles;
@@ -0,0 +1,146 @@
+//! This module provides the functionality to discover the themes and colorschemes available in
+//! the public folder so that the user selected ones can be validated against them.
+
+use crate::models::parser_models::Style;
+use serde::Serialize;
+use std::{fs::read_dir, io::Error, path::Path};
+
+use super::paths::{file_path, FileType};
+
+/// A named struct which stores the names of the themes and colorschemes available in the public
+/// folder.
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct AvailableStyles {
+    /// It stores the sorted names of the available themes.
+    pub themes: Vec<String>,
+    /// It stores the sorted names of the available colorschemes.
+    pub colorschemes: Vec<String>,
+}
+
+impl AvailableStyles {
+    /// A function which discovers the themes and colorschemes from the stylesheets present in the
+    /// `static/themes` and `static/colorschemes` folders of the public folder.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the public folder could not be found or its style folders could not be
+    /// read.
+    pub fn discover() -> Result<Self, Error> {
+        let static_folder: String = format!("{}/static", file_path(FileType::Theme)?);
+
+        Ok(AvailableStyles {
+            themes: style_names(&Path::new(&static_folder).join("themes"))?,
+            colorschemes: style_names(&Path::new(&static_folder).join("colorschemes"))?,
+        })
+    }
+
+    /// A function which checks whether the provided names refer to an available theme and an
+    /// available colorscheme.
+    ///
+    /// # Arguments
+    ///
+    /// * `theme` - It takes the name of the theme to be checked.
+    /// * `colorscheme` - It takes the name of the colorscheme to be checked.
+    pub fn is_valid(&self, theme: &str, colorscheme: &str) -> bool {
+        self.themes.iter().any(|available| available == theme)
+            && self
+                .colorschemes
+                .iter()
+                .any(|available| available == colorscheme)
+    }
+
+    /// A function which builds the style from the provided theme and colorscheme names, where any
+    /// of them which is not available is replaced with the one from the provided default style. The
+    /// other options of the style are taken from the default style.
+    ///
+    /// # Arguments
+    ///
+    /// * `theme` - It takes the name of the theme selected by the user.
+    /// * `colorscheme` - It takes the name of the colorscheme selected by the user.
+    /// * `default` - It takes the style from the config file used as the fallback.
+    pub fn resolve(&self, theme: &str, colorscheme: &str, default: &Style) -> Style {
+        let mut style: Style = Style::new(
+            match self.themes.iter().any(|available| available == theme) {
+                true => theme.to_owned(),
+                false => default.theme.clone(),
+            },
+            match self
+                .colorschemes
+                .iter()
+                .any(|available| available == colorscheme)
+            {
+                true => colorscheme.to_owned(),
+                false => default.colorscheme.clone(),
+            },
+        );
+        style.set_open_in_new_tab(default.open_in_new_tab);
+        style.set_notice_html(&default.notice_html);
+        style.set_disable_cookies(default.disable_cookies);
+        style.set_snippet_max_length(default.snippet_max_length);
+        style.set_redirect_clicks(default.redirect_clicks);
+        style
+    }
+}
+
+/// A helper function which lists the names (without the `.css` extension) of the stylesheets
+/// present in the provided folder.
+///
+/// # Arguments
+///
+/// * `folder` - It takes the path of the folder containing the stylesheets.
+///
+/// # Error
+///
+/// Returns an error if the folder could not be read.
+fn style_names(folder: &Path) -> Result<Vec<String>, Error> {
+    let mut names: Vec<String> = read_dir(folder)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "css"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .collect();
+
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_style_names_lists_stylesheets_only() -> Result<(), Error> {
+        let folder = tempfile::tempdir()?;
+        File::create(folder.path().join("nord.css"))?;
+        File::create(folder.path().join("dracula.css"))?;
+        File::create(folder.path().join("README.md"))?;
+
+        assert_eq!(style_names(folder.path())?, vec!["dracula", "nord"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_the_default_style() {
+        let styles = AvailableStyles {
+            themes: vec!["simple".to_owned()],
+            colorschemes: vec!["dracula".to_owned(), "nord".to_owned()],
+        };
+        let default: Style = Style::new("simple".to_owned(), "nord".to_owned());
+
+        let style: Style = styles.resolve("simple", "dracula", &default);
+        assert_eq!(
+            (style.theme.as_str(), style.colorscheme.as_str()),
+            ("simple", "dracula")
+        );
+
+        let style: Style = styles.resolve("removed", "../../secret", &default);
+        assert_eq!(
+            (style.theme.as_str(), style.colorscheme.as_str()),
+            ("simple", "nord")
+        );
+
+        assert!(styles.is_valid("simple", "nord"));
+        assert!(!styles.is_valid("simple", "removed"));
+    }
+}
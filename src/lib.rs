@@ -13,18 +13,101 @@ pub mod models;
 pub mod results;
 pub mod server;
 
-use std::net::TcpListener;
+use std::{
+    net::TcpListener,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use crate::server::router;
+use crate::server::{
+    access_token::{redacted_request_line, AccessToken},
+    client_ip::{ClientIpKeyExtractor, TrustedProxy},
+    query_log::QueryLog,
+    request_id::RequestIdentifier,
+    router,
+    search_queue::SearchQueue,
+};
 
 use actix_cors::Cors;
 use actix_files as fs;
 use actix_governor::{Governor, GovernorConfigBuilder};
-use actix_web::{dev::Server, http::header, middleware::Logger, web, App, HttpServer};
+use actix_web::{
+    dev::{Server, ServerHandle},
+    guard,
+    http::header,
+    middleware::{Compress, Condition, DefaultHeaders, Logger},
+    web, App, HttpServer,
+};
 use cache::cacher::{Cache, SharedCache};
-use config::parser::Config;
-use handlebars::Handlebars;
-use handler::paths::{file_path, FileType};
+use config::parser::{Config, SharedConfig};
+use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderError};
+use handler::{
+    paths::{file_path, FileType},
+    styles::AvailableStyles,
+};
+use models::{aggregation_models::relative_time, engine_models};
+use results::{
+    bangs::percent_encode,
+    circuit_breaker::CircuitBreaker,
+    filter_lists::{FilterLists, SharedFilterLists},
+    http_client, outbound_guard,
+};
+use tokio::sync::Semaphore;
+
+/// The templates from which the pages of the website are rendered, which have to be provided by
+/// the templates folder.
+const PAGE_TEMPLATES: [&str; 7] = [
+    "index", "search", "videos", "news", "about", "settings", "404",
+];
+
+/// A handlebars helper which percent encodes the string provided as its first parameter so that it
+/// can be placed in the query string of a url (like the related searches links).
+///
+/// # Error
+///
+/// It returns a `RenderError` if the first parameter is missing or is not a string.
+fn encode_query_helper(
+    helper: &Helper<'_, '_>,
+    _: &Handlebars<'_>,
+    _: &Context,
+    _: &mut RenderContext<'_, '_>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value: &str = helper
+        .param(0)
+        .and_then(|param| param.value().as_str())
+        .ok_or_else(|| RenderError::new("encode_query expects a string as parameter"))?;
+
+    out.write(&percent_encode(value))?;
+    Ok(())
+}
+
+/// A handlebars helper which formats the unix timestamp provided as its first parameter as the
+/// time elapsed since then in a human readable form like `3 hours ago`.
+///
+/// # Error
+///
+/// It returns a `RenderError` if the first parameter is missing or is not an integer.
+fn relative_time_helper(
+    helper: &Helper<'_, '_>,
+    _: &Handlebars<'_>,
+    _: &Context,
+    _: &mut RenderContext<'_, '_>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let published: i64 = helper
+        .param(0)
+        .and_then(|param| param.value().as_i64())
+        .ok_or_else(|| RenderError::new("relative_time expects a unix timestamp as parameter"))?;
+
+    let now: i64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+
+    out.write(&relative_time(published, now))?;
+    Ok(())
+}
 
 /// Runs the web server on the provided TCP listener and returns a `Server` instance.
 ///
@@ -35,76 +118,396 @@ use handler::paths::{file_path, FileType};
 /// # Returns
 ///
 /// Returns a `Result` containing a `Server` instance on success, or an `std::io::Error` on failure.
+/// The returned server stops gracefully on receiving the SIGINT or the SIGTERM signal, waiting for
+/// the in-flight requests to complete for at most the configured shutdown timeout, and reloads the
+/// config on receiving the SIGHUP signal.
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,no_run
 /// use std::net::TcpListener;
 /// use websurfx::{config::parser::Config, run, cache::cacher::Cache};
 ///
 /// let config = Config::parse(true).unwrap();
 /// let listener = TcpListener::bind("127.0.0.1:8080").expect("Failed to bind address");
-/// let cache = Cache::new_in_memory();
+/// let cache = Cache::new_in_memory(config.cache_expiry);
 /// let server = run(listener,config,cache).expect("Failed to start server");
 /// ```
 pub fn run(listener: TcpListener, config: Config, cache: Cache) -> std::io::Result<Server> {
+    run_with_listeners(vec![listener], config, cache)
+}
+
+/// Registers all the routes of the `websurfx` meta search engine website.
+///
+/// # Arguments
+///
+/// * `cfg` - It takes the service config of the scope the routes are registered in.
+/// * `public_folder_path` - It takes the path of the public folder which provides the static files.
+fn routes(cfg: &mut web::ServiceConfig, public_folder_path: &str) {
+    // Serve images and static files (css and js files).
+    cfg.service(
+        fs::Files::new("/static", format!("{}/static", public_folder_path)).show_files_listing(),
+    )
+    .service(
+        fs::Files::new("/images", format!("{}/images", public_folder_path)).show_files_listing(),
+    )
+    .service(router::health_check) // health check
+    .service(router::version) // version and build info
+    .service(router::robots_data) // robots.txt
+    .service(router::index) // index page
+    .service(server::routes::search::search) // search page
+    .service(server::routes::search::api_search) // search api
+    .service(server::routes::search::search_stream) // streaming search api
+    .service(server::routes::favicon::favicon_proxy) // favicon proxy
+    .service(server::routes::favicon::thumbnail_proxy) // thumbnail proxy
+    .service(server::routes::redirect::click_redirect) // click redirect
+    .service(router::about) // about page
+    .service(router::settings) // settings page
+    .service(router::save_settings) // settings page form submission
+    .service(router::export_settings) // settings export
+    .service(router::import_settings) // settings import
+    .service(router::reload_filters) // admin only filter lists reload
+    .service(router::flush_cache); // admin only cache flush
+}
+
+/// Runs the web server on all the provided TCP listeners at once (like an internal and an external
+/// interface) and returns a `Server` instance. It behaves exactly like `run` otherwise.
+///
+/// # Arguments
+///
+/// * `listeners` - The `TcpListener` instances representing the addresses and ports to listen on.
+///
+/// # Returns
+///
+/// Returns a `Result` containing a `Server` instance on success, or an `std::io::Error` on failure
+/// or if no listener is provided.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::net::TcpListener;
+/// use websurfx::{config::parser::Config, run_with_listeners, cache::cacher::Cache};
+///
+/// let config = Config::parse(true).unwrap();
+/// let listeners = vec![
+///     TcpListener::bind("127.0.0.1:8080").expect("Failed to bind address"),
+///     TcpListener::bind("127.0.0.1:8081").expect("Failed to bind address"),
+/// ];
+/// let cache = Cache::new_in_memory(config.cache_expiry);
+/// let server = run_with_listeners(listeners,config,cache).expect("Failed to start server");
+/// ```
+pub fn run_with_listeners(
+    listeners: Vec<TcpListener>,
+    config: Config,
+    cache: Cache,
+) -> std::io::Result<Server> {
+    if listeners.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "At least one listener is required to run the server",
+        ));
+    }
+
     let mut handlebars: Handlebars<'_> = Handlebars::new();
 
     let public_folder_path: &str = file_path(FileType::Theme)?;
 
+    let templates_path: String = match &config.style.templates_path {
+        Some(templates_path) => templates_path.clone(),
+        None => format!("{}/templates", public_folder_path),
+    };
+    if !Path::new(&templates_path).is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("The templates folder `{templates_path}` does not exist or is not a folder"),
+        ));
+    }
+
     handlebars
-        .register_templates_directory(".html", format!("{}/templates", public_folder_path))
-        .unwrap();
+        .register_templates_directory(".html", &templates_path)
+        .map_err(|error| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unable to register the templates from `{templates_path}`: {error}"),
+            )
+        })?;
+
+    // the pages are rendered from these templates and so a missing one fails the startup instead
+    // of every request to its page.
+    if let Some(missing) = PAGE_TEMPLATES
+        .iter()
+        .find(|template| !handlebars.has_template(template))
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("The `{missing}.html` template is missing from the templates folder `{templates_path}`"),
+        ));
+    }
+
+    handlebars.register_helper("relative_time", Box::new(relative_time_helper));
+    handlebars.register_helper("encode_query", Box::new(encode_query_helper));
 
     let handlebars_ref: web::Data<Handlebars<'_>> = web::Data::new(handlebars);
 
     let cloned_config_threads_opt: u8 = config.threads;
+    let shutdown_timeout: u64 = config.server.shutdown_timeout;
+    let max_connections: usize = config.server.max_connections;
+    let client_timeout: u64 = config.server.client_timeout;
 
     let cache = web::Data::new(SharedCache::new(cache));
 
-    let server = HttpServer::new(move || {
+    // The config shared between all the requests which is swapped atomically when it is reloaded
+    // on receiving the SIGHUP signal.
+    let shared_config = web::Data::new(SharedConfig::new(config.clone()));
+
+    outbound_guard::allow_private_addresses(config.aggregator.allow_private_addresses);
+    engine_models::limit_response_bytes(config.aggregator.max_response_bytes);
+    engine_models::limit_read_timeout(config.aggregator.read_timeout);
+
+    // Compile the rules of the blocklist and allowlist files once so that they can be shared
+    // between all the searches and any invalid pattern is rejected at startup.
+    let filter_lists = web::Data::new(SharedFilterLists::new(FilterLists::load().map_err(
+        |error| std::io::Error::new(std::io::ErrorKind::InvalidData, error.to_string()),
+    )?));
+
+    // The themes and colorschemes available in the public folder against which the ones selected
+    // by the users are validated.
+    let available_styles = web::Data::new(AvailableStyles::discover()?);
+
+    // A semaphore shared between all the searches which bounds the number of concurrent requests
+    // made to the upstream search engines.
+    let semaphore = web::Data::new(Semaphore::new(config.aggregator.max_concurrent_requests));
+
+    // The queue shared between all the searches which bounds the number of searches fetching their
+    // results from the upstream search engines at once.
+    let search_queue = web::Data::new(SearchQueue::new(config.server.max_pending_searches));
+
+    // An http client shared between all the searches which pools the connections to the upstream
+    // search engines.
+    let client =
+        web::Data::new(http_client::upstream_client(&config).map_err(std::io::Error::other)?);
+
+    // A circuit breaker shared between all the searches which temporarily skips the upstream
+    // search engines which keep failing.
+    let circuit_breaker = web::Data::new(CircuitBreaker::new());
+
+    // The query log shared between all the searches which gathers the aggregate statistics of the
+    // searches when the `query_log` option is enabled.
+    let query_log = web::Data::new(QueryLog::from_config(&config)?);
+
+    // The rate limiters are built once so that their budgets are shared between all the workers,
+    // the routes configured with their own budget get their own rate limiter.
+    // the requests are limited by the ip address of the client behind the trusted proxies.
+    let trusted_proxies: Vec<TrustedProxy> = config
+        .server
+        .trusted_proxies
+        .iter()
+        .filter_map(|proxy| TrustedProxy::parse(proxy))
+        .collect();
+    let governor_config = |number_of_requests: u8, time_limit: u8| {
+        GovernorConfigBuilder::default()
+            .key_extractor(ClientIpKeyExtractor::new(&trusted_proxies))
+            .per_second(time_limit as u64)
+            .burst_size(number_of_requests as u32)
+            .finish()
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "The values of the `rate_limiter` option should be greater than 0",
+                )
+            })
+    };
+    let default_governor = governor_config(
+        config.rate_limiter.number_of_requests,
+        config.rate_limiter.time_limit,
+    )?;
+    let route_governors = config
+        .rate_limiter
+        .routes
+        .iter()
+        .map(|route_limit| {
+            Ok((
+                route_limit.clone(),
+                governor_config(route_limit.number_of_requests, route_limit.time_limit)?,
+            ))
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    #[cfg(unix)]
+    tokio::spawn(reload_on_signal(
+        shared_config.clone(),
+        filter_lists.clone(),
+    ));
+
+    let mut server = HttpServer::new(move || {
+        let mut allowed_headers: Vec<header::HeaderName> =
+            vec![header::ORIGIN, header::CONTENT_TYPE, header::REFERER];
+        // the cookies are never accepted when they are disabled.
+        if !config.server.disable_cookies {
+            allowed_headers.push(header::COOKIE);
+        }
+        // the security headers are added to every response which does not set them itself.
+        let security_headers: DefaultHeaders = config
+            .server
+            .security_headers
+            .headers()
+            .into_iter()
+            .fold(DefaultHeaders::new(), |security_headers, header| {
+                security_headers.add(header)
+            });
         let cors: Cors = Cors::default()
             .allow_any_origin()
             .allowed_methods(vec!["GET"])
-            .allowed_headers(vec![
-                header::ORIGIN,
-                header::CONTENT_TYPE,
-                header::REFERER,
-                header::COOKIE,
-            ]);
-
-        App::new()
-            .wrap(Logger::default()) // added logging middleware for logging.
+            .allowed_headers(allowed_headers);
+
+        let mut app = App::new()
+            // reject the requests without the access token if one is required.
+            .wrap(AccessToken::new(config.server.access_token.as_deref()))
+            // assign an id to every request which is sent back in the `X-Request-Id` header.
+            .wrap(RequestIdentifier)
+            // add the security headers to all the responses, including the static files.
+            .wrap(security_headers)
+            // added logging middleware for logging along with the id of the request.
+            // the access token provided in the url is redacted from the logged request line.
+            .wrap(
+                Logger::new(
+                    r#"%a "%{request_line}xi" %s %b "%{Referer}i" "%{User-Agent}i" %T %{X-Request-Id}o"#,
+                )
+                .custom_request_replace("request_line", redacted_request_line),
+            )
+            // compress the responses based on the `Accept-Encoding` header of the request.
+            .wrap(Condition::new(
+                config.server.enable_compression,
+                Compress::default(),
+            ))
             .app_data(handlebars_ref.clone())
-            .app_data(web::Data::new(config.clone()))
+            .app_data(shared_config.clone())
             .app_data(cache.clone())
-            .wrap(cors)
-            .wrap(Governor::new(
-                &GovernorConfigBuilder::default()
-                    .per_second(config.rate_limiter.time_limit as u64)
-                    .burst_size(config.rate_limiter.number_of_requests as u32)
-                    .finish()
-                    .unwrap(),
-            ))
-            // Serve images and static files (css and js files).
-            .service(
-                fs::Files::new("/static", format!("{}/static", public_folder_path))
-                    .show_files_listing(),
-            )
-            .service(
-                fs::Files::new("/images", format!("{}/images", public_folder_path))
-                    .show_files_listing(),
-            )
-            .service(router::robots_data) // robots.txt
-            .service(router::index) // index page
-            .service(server::routes::search::search) // search page
-            .service(router::about) // about page
-            .service(router::settings) // settings page
-            .default_service(web::route().to(router::not_found)) // error page
+            .app_data(semaphore.clone())
+            .app_data(search_queue.clone())
+            .app_data(client.clone())
+            .app_data(circuit_breaker.clone())
+            .app_data(query_log.clone())
+            .app_data(filter_lists.clone())
+            .app_data(available_styles.clone())
+            .wrap(cors);
+
+        // every route configured with its own budget is served through a scope rate limited by
+        // its own rate limiter, the most specific routes come first so that they take precedence.
+        for (route_limit, governor) in &route_governors {
+            let route_limit = route_limit.clone();
+            app = app.service(
+                web::scope("")
+                    .guard(guard::fn_guard(move |ctx| {
+                        route_limit.matches(ctx.head().uri.path())
+                    }))
+                    .wrap(Governor::new(governor))
+                    .configure(|cfg| routes(cfg, public_folder_path)),
+            );
+        }
+
+        app.service(
+            web::scope("")
+                .wrap(Governor::new(&default_governor))
+                .configure(|cfg| routes(cfg, public_folder_path)),
+        )
+        .default_service(web::route().to(router::not_found)) // error page
     })
     .workers(cloned_config_threads_opt as usize)
-    // Start server on 127.0.0.1 with the user provided port number. for example 127.0.0.1:8080.
-    .listen(listener)?
-    .run();
+    // Bound the number of open connections and close the connections of slow clients so that a
+    // burst of connections can not exhaust the file descriptors.
+    .max_connections(max_connections)
+    .client_request_timeout(Duration::from_secs(client_timeout))
+    // Wait for the in-flight requests to complete before stopping the workers on shutdown.
+    .shutdown_timeout(shutdown_timeout)
+    // The signals are handled by `stop_on_signal` so that both SIGINT and SIGTERM stop the
+    // server gracefully.
+    .disable_signals();
+
+    // Start server on all the provided addresses, for example 127.0.0.1:8080.
+    for listener in listeners {
+        server = server.listen(listener)?;
+    }
+    let server: Server = server.run();
+
+    tokio::spawn(stop_on_signal(server.handle(), shutdown_timeout));
+
     Ok(server)
 }
+
+/// Waits for the SIGHUP signal and then reloads the config file and the blocklist and allowlist
+/// files, so that the changes made to them (like the upstream search engines) take effect on the
+/// next request without restarting the server and dropping the warm caches and connections. The
+/// currently loaded config and filter lists are kept if they could not be reloaded.
+///
+/// # Arguments
+///
+/// * `config` - It takes the shared config to be reloaded.
+/// * `filter_lists` - It takes the shared filter lists to be reloaded.
+#[cfg(unix)]
+async fn reload_on_signal(
+    config: web::Data<SharedConfig>,
+    filter_lists: web::Data<SharedFilterLists>,
+) {
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => signal,
+        Err(error) => {
+            log::error!("Failed to listen for the SIGHUP signal: {error}");
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        log::info!("Reloading the config on receiving the SIGHUP signal");
+        match config.reload() {
+            Ok(()) => log::info!("The config has been reloaded successfully"),
+            Err(error) => log::error!("Unable to reload the config: {error}"),
+        }
+        if let Err(error) = filter_lists.reload() {
+            log::error!("Unable to reload the filter lists: {error}");
+        }
+    }
+}
+
+/// Waits for the SIGINT or the SIGTERM signal and then gracefully stops the server so that the
+/// in-flight requests are drained (for at most the shutdown timeout) and the shared state like
+/// the cache connections is dropped cleanly.
+///
+/// # Arguments
+///
+/// * `handle` - It takes the handle of the server to be stopped.
+/// * `shutdown_timeout` - It takes the time (secs) to wait for the in-flight requests to complete.
+async fn stop_on_signal(handle: ServerHandle, shutdown_timeout: u64) {
+    let interrupt = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for the SIGINT signal: {error}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(error) => {
+                log::error!("Failed to listen for the SIGTERM signal: {error}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => (),
+        _ = terminate => (),
+    }
+
+    log::info!(
+        "Shutting down gracefully, waiting up to {shutdown_timeout} seconds for the in-flight requests to complete"
+    );
+    handle.stop(true).await;
+}
@@ -3,8 +3,13 @@
 
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::{collections::HashMap, time::Duration};
 
-use super::{engine_models::EngineError, parser_models::Style};
+use super::{
+    engine_models::{error_message, EngineError, EngineErrorKind},
+    parser_models::Style,
+};
+use error_stack::Report;
 
 /// A named struct to store the raw scraped search results scraped search results from the
 /// upstream search engines before aggregating it.It derives the Clone trait which is needed
@@ -21,6 +26,23 @@ pub struct SearchResult {
     pub description: String,
     /// The names of the upstream engines from which this results were provided.
     pub engine: SmallVec<[String; 0]>,
+    /// The regions for which this result was provided, which are only stored when the results
+    /// of multiple regions are searched for at once.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<String>,
+    /// The time at which the search result was published as a unix timestamp (in seconds), which
+    /// is only stored when the upstream engine provided it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<i64>,
+    /// Whether the search result is a sponsored result (an ad) mixed into the organic results by
+    /// the upstream engine, the engines which can not tell them apart leave it unset.
+    #[serde(default)]
+    pub sponsored: bool,
+    /// The positions (starting from 1) of the search result in the results of the upstream
+    /// engines which provided it mapped by the names of the engines, which are only stored for
+    /// the engines which keep the order of their results.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub positions: HashMap<String, usize>,
 }
 
 impl SearchResult {
@@ -39,9 +61,61 @@ impl SearchResult {
             url: url.to_owned(),
             description: description.to_owned(),
             engine: engine.iter().map(|name| name.to_string()).collect(),
+            regions: Vec::new(),
+            published: None,
+            sponsored: false,
+            positions: HashMap::new(),
         }
     }
 
+    /// A setter function to set the position of the search result in the results of the
+    /// upstream engines which provided it.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - It takes the position of the result (starting from 1) in the order the
+    /// results were provided by the upstream engine.
+    pub fn set_position(&mut self, position: usize) {
+        for engine in &self.engine {
+            self.positions.insert(engine.clone(), position);
+        }
+    }
+
+    /// A function which adds the positions of the search result in the results of other
+    /// upstream engines (like when the result is merged with the same result provided by
+    /// another engine), the highest position is kept for an engine which provided the result
+    /// more than once.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - It takes the positions mapped by the names of the engines.
+    pub fn add_positions(&mut self, positions: &HashMap<String, usize>) {
+        for (engine, position) in positions {
+            self.positions
+                .entry(engine.clone())
+                .and_modify(|current| *current = (*current).min(*position))
+                .or_insert(*position);
+        }
+    }
+
+    /// A setter function to set whether the search result is a sponsored result.
+    ///
+    /// # Arguments
+    ///
+    /// * `sponsored` - It takes whether the upstream engine marked the result as sponsored.
+    pub fn set_sponsored(&mut self, sponsored: bool) {
+        self.sponsored = sponsored;
+    }
+
+    /// A setter function to set the time at which the search result was published.
+    ///
+    /// # Arguments
+    ///
+    /// * `published` - It takes the publishing time as a unix timestamp (in seconds).
+    pub fn set_published(&mut self, published: Option<i64>) {
+        self.published = published;
+    }
+
     /// A function which adds the engine name provided as a string into a vector of strings.
     ///
     /// # Arguments
@@ -51,6 +125,18 @@ impl SearchResult {
         self.engine.push(engine.to_owned())
     }
 
+    /// A function which adds the region for which the result was provided, unless it was already
+    /// added.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - Takes the normalized region (like `us-en`).
+    pub fn add_region(&mut self, region: &str) {
+        if !self.regions.iter().any(|added| added == region) {
+            self.regions.push(region.to_owned())
+        }
+    }
+
     /// A function which returns the engine name stored from the struct as a string.
     ///
     /// # Returns
@@ -61,6 +147,216 @@ impl SearchResult {
     }
 }
 
+/// A named struct to store the raw scraped video results scraped from the video capable
+/// upstream search engines before aggregating it.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoResult {
+    /// The url of the thumbnail image of the video.
+    pub thumbnail: String,
+    /// The title of the video.
+    pub title: String,
+    /// The human readable length of the video.
+    pub duration: String,
+    /// The name of the source (channel or uploader) which published the video.
+    pub source: String,
+    /// The url which is accessed when clicked on the video.
+    pub url: String,
+}
+
+impl VideoResult {
+    /// Constructs a new `VideoResult` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `thumbnail` - The url of the thumbnail image of the video.
+    /// * `title` - The title of the video.
+    /// * `duration` - The human readable length of the video.
+    /// * `source` - The name of the source which published the video.
+    /// * `url` - The url which is accessed when clicked on the video.
+    pub fn new(thumbnail: &str, title: &str, duration: &str, source: &str, url: &str) -> Self {
+        VideoResult {
+            thumbnail: thumbnail.to_owned(),
+            title: title.to_owned(),
+            duration: duration.to_owned(),
+            source: source.to_owned(),
+            url: url.to_owned(),
+        }
+    }
+}
+
+/// A named struct to store the raw scraped news results scraped from the news capable upstream
+/// search engines before aggregating it.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsResult {
+    /// The title of the news.
+    pub title: String,
+    /// The url which is accessed when clicked on the news.
+    pub url: String,
+    /// The description of the news.
+    pub description: String,
+    /// The name of the upstream engine from which the news was provided.
+    pub source: String,
+    /// The time at which the news was published as a unix timestamp (in seconds).
+    pub published: i64,
+}
+
+impl NewsResult {
+    /// Constructs a new `NewsResult` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - The title of the news.
+    /// * `url` - The url which is accessed when clicked on the news.
+    /// * `description` - The description of the news.
+    /// * `source` - The name of the upstream engine from which the news was provided.
+    /// * `published` - The time at which the news was published as a unix timestamp.
+    pub fn new(title: &str, url: &str, description: &str, source: &str, published: i64) -> Self {
+        NewsResult {
+            title: title.to_owned(),
+            url: url.to_owned(),
+            description: description.to_owned(),
+            source: source.to_owned(),
+            published,
+        }
+    }
+}
+
+/// A function which formats the time elapsed since the provided unix timestamp in a human readable
+/// relative form like `3 hours ago`.
+///
+/// # Arguments
+///
+/// * `published` - It takes the unix timestamp (in seconds) to be formatted.
+/// * `now` - It takes the current time as a unix timestamp (in seconds).
+pub fn relative_time(published: i64, now: i64) -> String {
+    let elapsed: i64 = (now - published).max(0);
+
+    let (amount, unit): (i64, &str) = match elapsed {
+        0..=59 => return "just now".to_owned(),
+        60..=3_599 => (elapsed / 60, "minute"),
+        3_600..=86_399 => (elapsed / 3_600, "hour"),
+        86_400..=2_591_999 => (elapsed / 86_400, "day"),
+        2_592_000..=31_535_999 => (elapsed / 2_592_000, "month"),
+        _ => (elapsed / 31_536_000, "year"),
+    };
+
+    match amount {
+        1 => format!("1 {unit} ago"),
+        _ => format!("{amount} {unit}s ago"),
+    }
+}
+
+/// A named struct to store the time taken by an upstream search engine to provide its results.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineTiming {
+    /// It stores the name of the upstream search engine.
+    pub engine: String,
+    /// It stores the time taken to fetch the results from the upstream search engine (in
+    /// milliseconds).
+    pub duration_ms: u64,
+}
+
+impl EngineTiming {
+    /// Constructs a new `EngineTiming` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the upstream search engine.
+    /// * `duration` - It takes the time taken to fetch the results from the upstream search engine.
+    pub fn new(engine: &str, duration: Duration) -> Self {
+        EngineTiming {
+            engine: engine.to_owned(),
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+}
+
+/// A named struct to store the time taken to aggregate the search results which is used for
+/// debugging which upstream search engines are slow.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregationTimings {
+    /// It stores the time taken by each of the upstream search engines.
+    pub engines: Vec<EngineTiming>,
+    /// It stores the total time taken to aggregate the search results (in milliseconds).
+    pub total_ms: u64,
+}
+
+impl AggregationTimings {
+    /// Constructs a new `AggregationTimings` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `engines` - It takes the time taken by each of the upstream search engines.
+    /// * `total` - It takes the total time taken to aggregate the search results.
+    pub fn new(engines: Vec<EngineTiming>, total: Duration) -> Self {
+        AggregationTimings {
+            engines,
+            total_ms: total.as_millis() as u64,
+        }
+    }
+}
+
+/// A named struct to store the number of the search results along with the time taken to fetch
+/// them, which is shown above the search results (like `About 42 results in 0.80s`).
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ResultStats {
+    /// It stores the total number of the search results.
+    pub count: usize,
+    /// It stores the time taken from the start of the search request to the completion of the
+    /// aggregation (in seconds, formatted with two decimal places).
+    pub seconds: String,
+}
+
+impl ResultStats {
+    /// Constructs a new `ResultStats` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - It takes the total number of the search results.
+    /// * `elapsed` - It takes the time taken to fetch the search results.
+    pub fn new(count: usize, elapsed: Duration) -> Self {
+        ResultStats {
+            count,
+            seconds: format!("{:.2}", elapsed.as_secs_f64()),
+        }
+    }
+}
+
+/// A named struct to store the pagination metadata of the search results, which tells the clients
+/// whether a next page of results is available so that they do not request the empty pages.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    /// It stores the page number of the search results.
+    pub page: u32,
+    /// It stores whether the next page has any search results.
+    pub has_next: bool,
+    /// It stores the url of the next page if it has any search results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_url: Option<String>,
+}
+
+impl Pagination {
+    /// Constructs a new `Pagination` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - It takes the page number of the search results.
+    /// * `next_url` - It takes the url of the next page, which should only be provided if the
+    /// next page has any search results.
+    pub fn new(page: u32, next_url: Option<String>) -> Self {
+        Pagination {
+            page,
+            has_next: next_url.is_some(),
+            next_url,
+        }
+    }
+}
+
 /// A named struct that stores the error info related to the upstream search engines.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct EngineErrorInfo {
@@ -72,6 +368,15 @@ pub struct EngineErrorInfo {
     /// It stores the name of the color to indicate whether how severe the particular error is (In
     /// other words it indicates the severity of the error/issue).
     pub severity_color: String,
+    /// It stores the name of the engine whose results were fetched in place of the failed engine.
+    #[serde(default)]
+    pub fallback: Option<String>,
+    /// It stores the kind of the failure (like a timeout or an http status) behind the error.
+    #[serde(default)]
+    pub kind: Option<EngineErrorKind>,
+    /// It stores the message of the underlying error, which is only provided in debug mode.
+    #[serde(default)]
+    pub message: Option<String>,
 }
 
 impl EngineErrorInfo {
@@ -95,6 +400,84 @@ impl EngineErrorInfo {
                 EngineError::EmptyResultSet => "blue".to_owned(),
                 EngineError::UnexpectedError => "red".to_owned(),
             },
+            fallback: None,
+            kind: None,
+            message: None,
+        }
+    }
+
+    /// Constructs a new `EngineErrorInfo` from the error report returned by an upstream search
+    /// engine, which also records the kind of the failure and, in debug mode, the message of the
+    /// underlying error. A captcha page served by the engine is reported as a `CaptchaError` so
+    /// that it is told apart from the other errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - It takes the error report returned by the upstream search engine.
+    /// * `engine` - It takes the name of the engine that failed to provide the requested search results.
+    /// * `debug` - It takes the option to whether include the message of the underlying error.
+    pub fn from_report(error: &Report<EngineError>, engine: &str, debug: bool) -> Self {
+        let mut error_info: EngineErrorInfo = Self::new(error.current_context(), engine);
+        error_info.kind = Some(EngineErrorKind::of(error));
+        if error_info.kind == Some(EngineErrorKind::Captcha) {
+            error_info.error = "CaptchaError".to_owned();
+            error_info.severity_color = "orange".to_owned();
+        }
+        if debug {
+            error_info.message = Some(error_message(error));
+        }
+        error_info
+    }
+
+    /// A function which notes the engine whose results were fetched in place of the failed engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `fallback` - It takes the name of the fallback engine.
+    pub fn set_fallback(&mut self, fallback: &str) {
+        self.fallback = Some(fallback.to_owned());
+    }
+
+    /// Constructs a new `EngineErrorInfo` which notes that none of the engines selected through
+    /// the settings cookie were valid and hence the engines from the config file were used instead.
+    pub fn invalid_engine_selection() -> Self {
+        Self {
+            error: "InvalidEngineSelection".to_owned(),
+            engine: "settings".to_owned(),
+            severity_color: "orange".to_owned(),
+            fallback: None,
+            kind: None,
+            message: None,
+        }
+    }
+
+    /// Constructs a new `EngineErrorInfo` which notes that more engines were selected than the
+    /// `max_engines_per_request` option allows and hence only the first of them were used.
+    pub fn engines_limit_exceeded() -> Self {
+        Self {
+            error: "EnginesLimitExceeded".to_owned(),
+            engine: "settings".to_owned(),
+            severity_color: "orange".to_owned(),
+            fallback: None,
+            kind: None,
+            message: None,
+        }
+    }
+
+    /// Constructs a new `EngineErrorInfo` which notes that the engine was skipped as it has been
+    /// temporarily disabled by the circuit breaker after failing too many times in a row.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the temporarily disabled engine.
+    pub fn temporarily_disabled(engine: &str) -> Self {
+        Self {
+            error: "TemporarilyDisabled".to_owned(),
+            engine: engine.to_owned(),
+            severity_color: "orange".to_owned(),
+            fallback: None,
+            kind: None,
+            message: None,
         }
     }
 }
@@ -107,6 +490,16 @@ impl EngineErrorInfo {
 pub struct SearchResults {
     /// Stores the individual serializable `SearchResult` struct into a vector of
     pub results: Vec<SearchResult>,
+    /// Stores the video results aggregated from the video capable upstream search engines.
+    pub videos: Vec<VideoResult>,
+    /// Stores the news results aggregated from the news capable upstream search engines.
+    pub news: Vec<NewsResult>,
+    /// Stores the related search terms suggested by the upstream search engines.
+    pub suggestions: Vec<String>,
+    /// Stores the correction of the misspelled search query suggested by the upstream search
+    /// engines (the "did you mean" query).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spelling_correction: Option<String>,
     /// Stores the current pages search query `q` provided in the search url.
     pub page_query: String,
     /// Stores the theming options for the website.
@@ -127,6 +520,26 @@ pub struct SearchResults {
     /// Stores the flag option which holds the check value that whether any search engines were
     /// selected or not.
     pub no_engines_selected: bool,
+    /// Stores the flag option which holds the check value that whether the instance is in the
+    /// maintenance mode, in which only the cached results are served.
+    #[serde(default)]
+    pub maintenance: bool,
+    /// Stores the flag option which holds the check value that whether fewer upstream search
+    /// engines than required returned results, in which case the results may be incomplete.
+    #[serde(default)]
+    pub degraded: bool,
+    /// Stores the time taken to aggregate the search results which is only available when the
+    /// debug mode is enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<AggregationTimings>,
+    /// Stores the number of the search results along with the time taken to fetch them which is
+    /// only available when the `show_result_stats` option is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<ResultStats>,
+    /// Stores the pagination metadata of the search results, which is never cached as it is
+    /// computed for every search.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
 }
 
 impl SearchResults {
@@ -147,6 +560,10 @@ impl SearchResults {
     ) -> Self {
         Self {
             results,
+            videos: Vec::new(),
+            news: Vec::new(),
+            suggestions: Vec::new(),
+            spelling_correction: None,
             page_query: page_query.to_owned(),
             style: Style::default(),
             engine_errors_info: engine_errors_info.to_owned(),
@@ -154,6 +571,11 @@ impl SearchResults {
             filtered: Default::default(),
             safe_search_level: Default::default(),
             no_engines_selected: Default::default(),
+            maintenance: Default::default(),
+            degraded: Default::default(),
+            timings: None,
+            stats: None,
+            pagination: None,
         }
     }
 
@@ -178,14 +600,108 @@ impl SearchResults {
     }
 
     /// A getter function that gets the value of `engine_errors_info`.
-    pub fn engine_errors_info(&mut self) -> Vec<EngineErrorInfo> {
-        std::mem::take(&mut self.engine_errors_info)
+    pub fn engine_errors_info(&self) -> &[EngineErrorInfo] {
+        &self.engine_errors_info
+    }
+
+    /// A setter function to add an error info to the `engine_errors_info`.
+    pub fn add_engine_error_info(&mut self, engine_error_info: EngineErrorInfo) {
+        self.engine_errors_info.push(engine_error_info);
     }
     /// A getter function that gets the value of `results`.
     pub fn results(&mut self) -> Vec<SearchResult> {
         self.results.clone()
     }
 
+    /// A setter function to set the aggregated search results.
+    pub fn set_results(&mut self, results: Vec<SearchResult>) {
+        self.results = results;
+    }
+
+    /// A getter function that gets the value of `videos`.
+    pub fn videos(&self) -> &[VideoResult] {
+        &self.videos
+    }
+
+    /// A setter function to set the aggregated video results.
+    pub fn set_videos(&mut self, videos: Vec<VideoResult>) {
+        self.videos = videos;
+    }
+
+    /// A function which checks whether no results of any kind (general, videos or news) are
+    /// available.
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty() && self.videos.is_empty() && self.news.is_empty()
+    }
+
+    /// A function which returns the number of the results of all kinds (general, videos and
+    /// news) available.
+    pub fn len(&self) -> usize {
+        self.results.len() + self.videos.len() + self.news.len()
+    }
+
+    /// A function which checks whether all the upstream search engines failed to provide the
+    /// search results, that is no results are available and every engine failed with an error
+    /// other than providing an empty result set.
+    pub fn upstream_failed(&self) -> bool {
+        let mut engine_errors = self
+            .engine_errors_info
+            .iter()
+            .filter(|error_info| {
+                !matches!(
+                    error_info.error.as_str(),
+                    "InvalidEngineSelection" | "EnginesLimitExceeded"
+                )
+            })
+            .peekable();
+
+        self.is_empty()
+            && engine_errors.peek().is_some()
+            && engine_errors.all(|error_info| error_info.error != "EmptyResultSet")
+    }
+
+    /// A setter function to set the time taken to aggregate the search results.
+    pub fn set_timings(&mut self, timings: AggregationTimings) {
+        self.timings = Some(timings);
+    }
+
+    /// A setter function to set the number of the search results along with the time taken to
+    /// fetch them.
+    pub fn set_stats(&mut self, stats: ResultStats) {
+        self.stats = Some(stats);
+    }
+
+    /// A setter function to set the pagination metadata of the search results.
+    pub fn set_pagination(&mut self, pagination: Pagination) {
+        self.pagination = Some(pagination);
+    }
+
+    /// A getter function that gets the value of `news`.
+    pub fn news(&self) -> &[NewsResult] {
+        &self.news
+    }
+
+    /// A setter function to set the aggregated news results.
+    pub fn set_news(&mut self, news: Vec<NewsResult>) {
+        self.news = news;
+    }
+
+    /// A getter function that gets the value of `suggestions`.
+    pub fn suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+
+    /// A setter function to set the related search terms suggested by the upstream search engines.
+    pub fn set_suggestions(&mut self, suggestions: Vec<String>) {
+        self.suggestions = suggestions;
+    }
+
+    /// A setter function to set the correction of the misspelled search query suggested by the
+    /// upstream search engines.
+    pub fn set_spelling_correction(&mut self, spelling_correction: Option<String>) {
+        self.spelling_correction = spelling_correction;
+    }
+
     /// A setter function to set the current page safe search level.
     pub fn set_safe_search_level(&mut self, safe_search_level: u8) {
         self.safe_search_level = safe_search_level;
@@ -200,4 +716,264 @@ impl SearchResults {
     pub fn set_no_engines_selected(&mut self) {
         self.no_engines_selected = true;
     }
+
+    /// A setter function to set the `maintenance` to true.
+    pub fn set_maintenance(&mut self) {
+        self.maintenance = true;
+    }
+
+    /// A setter function to set the `degraded` to true.
+    pub fn set_degraded(&mut self) {
+        self.degraded = true;
+    }
+
+    /// A function which formats the general search results as an RSS 2.0 feed, so that a search
+    /// query can be subscribed to in a feed reader. Every result becomes an `item` with its
+    /// title, url and description.
+    ///
+    /// # Arguments
+    ///
+    /// * `link` - It takes the absolute url of the search page the feed is built from.
+    pub fn to_rss(&self, link: &str) -> String {
+        let items: String = self
+            .results
+            .iter()
+            .map(|result| {
+                format!(
+                    "<item><title>{}</title><link>{}</link><guid isPermaLink=\"true\">{}</guid><description>{}</description></item>",
+                    escape_xml(&result.title),
+                    escape_xml(&result.url),
+                    escape_xml(&result.url),
+                    escape_xml(&result.description),
+                )
+            })
+            .collect();
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\"><channel><title>{} - Websurfx</title><link>{}</link><description>Search results for {}</description>{items}</channel></rss>",
+            escape_xml(&self.page_query),
+            escape_xml(link),
+            escape_xml(&self.page_query),
+        )
+    }
+}
+
+/// A helper function which escapes the provided text so that it can be placed inside an xml
+/// element or attribute. The control characters which are not allowed in xml are dropped.
+///
+/// # Arguments
+///
+/// * `text` - It takes the text which needs to be escaped.
+fn escape_xml(text: &str) -> String {
+    text.chars()
+        .filter(|character| !character.is_control() || matches!(character, '\t' | '\n' | '\r'))
+        .fold(
+            String::with_capacity(text.len()),
+            |mut escaped, character| {
+                match character {
+                    '&' => escaped.push_str("&amp;"),
+                    '<' => escaped.push_str("&lt;"),
+                    '>' => escaped.push_str("&gt;"),
+                    '"' => escaped.push_str("&quot;"),
+                    '\'' => escaped.push_str("&apos;"),
+                    _ => escaped.push(character),
+                }
+                escaped
+            },
+        )
+}
+
+/// An enum type which stores the suggestions made by the upstream search engines for a search
+/// query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Suggestion {
+    /// This variant stores a related search term.
+    Related(String),
+    /// This variant stores the correction of a misspelled search query (the "did you mean"
+    /// query).
+    Correction(String),
+}
+
+/// An enum type which stores the events sent while streaming the search results as they are
+/// provided by the upstream search engines.
+pub enum StreamEvent {
+    /// This variant stores the name of an upstream search engine along with its results which
+    /// were not already sent.
+    Results(String, Vec<SearchResult>),
+    /// This variant stores the final search results (without any results) which carry the errors
+    /// returned by the upstream search engines, it is sent once all the engines are done.
+    Done(Box<SearchResults>),
+}
+
+impl StreamEvent {
+    /// A function which formats the event as a server-sent event, the `results` events carry the
+    /// name of the engine along with its results and the `done` event carries the final search
+    /// results as json.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the data of the event could not be serialized.
+    pub fn to_sse(&self) -> Result<String, serde_json::Error> {
+        let (event, data): (&str, String) = match self {
+            StreamEvent::Results(engine, results) => (
+                "results",
+                serde_json::to_string(&serde_json::json!({
+                    "engine": engine,
+                    "results": results,
+                }))?,
+            ),
+            StreamEvent::Done(results) => ("done", serde_json::to_string(results)?),
+        };
+
+        Ok(format!("event: {event}\ndata: {data}\n\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_time() {
+        let now: i64 = 1_700_000_000;
+        assert_eq!(relative_time(now - 30, now), "just now");
+        assert_eq!(relative_time(now - 60, now), "1 minute ago");
+        assert_eq!(relative_time(now - 3 * 3_600, now), "3 hours ago");
+        assert_eq!(relative_time(now - 86_400, now), "1 day ago");
+        assert_eq!(relative_time(now - 2 * 31_536_000, now), "2 years ago");
+        assert_eq!(relative_time(now + 120, now), "just now");
+    }
+
+    #[test]
+    fn test_timings_are_only_serialized_when_set() {
+        let mut results: SearchResults = SearchResults::new(Vec::new(), "query", &[]);
+        assert!(!serde_json::to_string(&results).unwrap().contains("timings"));
+
+        results.set_timings(AggregationTimings::new(
+            vec![EngineTiming::new("searx", Duration::from_millis(120))],
+            Duration::from_millis(150),
+        ));
+        let json: String = serde_json::to_string(&results).unwrap();
+        assert!(json.contains(
+            r#""timings":{"engines":[{"engine":"searx","durationMs":120}],"totalMs":150}"#
+        ));
+    }
+
+    #[test]
+    fn test_result_stats_are_formatted_in_seconds() {
+        let stats: ResultStats = ResultStats::new(42, Duration::from_millis(804));
+        assert_eq!(stats.count, 42);
+        assert_eq!(stats.seconds, "0.80");
+
+        let mut results: SearchResults = SearchResults::new(Vec::new(), "query", &[]);
+        assert!(!serde_json::to_string(&results)
+            .unwrap()
+            .contains(r#""stats":"#));
+        results.set_stats(stats);
+        assert!(serde_json::to_string(&results)
+            .unwrap()
+            .contains(r#""stats":{"count":42,"seconds":"0.80"}"#));
+    }
+
+    #[test]
+    fn test_engine_error_info_reports_captcha_pages_distinctly() {
+        let error: Report<EngineError> =
+            Report::new(EngineError::UnexpectedError).attach(EngineErrorKind::Captcha);
+
+        let error_info: EngineErrorInfo = EngineErrorInfo::from_report(&error, "yandex", false);
+        assert_eq!(error_info.error, "CaptchaError");
+        assert_eq!(error_info.kind, Some(EngineErrorKind::Captcha));
+    }
+
+    #[test]
+    fn test_engine_error_info_records_the_kind_and_the_message_in_debug_mode() {
+        let error: Report<EngineError> = Report::new(EngineError::UnexpectedError)
+            .attach(EngineErrorKind::Http(429))
+            .attach_printable("upstream search engine responded with status 429");
+
+        let error_info: EngineErrorInfo = EngineErrorInfo::from_report(&error, "startpage", false);
+        assert_eq!(error_info.kind, Some(EngineErrorKind::Http(429)));
+        assert_eq!(error_info.message, None);
+
+        let error_info: EngineErrorInfo = EngineErrorInfo::from_report(&error, "startpage", true);
+        let json: String = serde_json::to_string(&error_info).unwrap();
+        assert!(json.contains(r#""kind":{"type":"http","status":429}"#));
+        assert!(error_info
+            .message
+            .is_some_and(|message| message.contains("responded with status 429")));
+    }
+
+    #[test]
+    fn test_stream_event_is_formatted_as_a_server_sent_event() {
+        let event: StreamEvent = StreamEvent::Results(
+            "searx".to_owned(),
+            vec![SearchResult::new(
+                "Rust",
+                "https://www.rust-lang.org/",
+                "A language\nempowering everyone.",
+                &["searx"],
+            )],
+        );
+        let sse: String = event.to_sse().unwrap();
+        assert!(sse.starts_with("event: results\ndata: {\"engine\":\"searx\",\"results\":[{"));
+        assert!(sse.ends_with("}]}\n\n"));
+        // the newlines of the data are escaped so that the event is never split.
+        assert_eq!(sse.matches('\n').count(), 3);
+
+        let sse: String = StreamEvent::Done(Box::new(SearchResults::new(
+            Vec::new(),
+            "rust",
+            &[EngineErrorInfo::new(
+                &EngineError::RequestError,
+                "duckduckgo",
+            )],
+        )))
+        .to_sse()
+        .unwrap();
+        assert!(sse.starts_with("event: done\ndata: {"));
+        assert!(sse.contains(r#""engine":"duckduckgo""#));
+    }
+
+    #[test]
+    fn test_results_are_formatted_as_an_rss_feed() {
+        let results: SearchResults = SearchResults::new(
+            vec![SearchResult::new(
+                "Rust & <Cargo>",
+                "https://doc.rust-lang.org/cargo/?a=1&b=2",
+                "The \"package\" manager\u{0}.",
+                &["searx"],
+            )],
+            "rust cargo",
+            &[],
+        );
+
+        let rss: String = results.to_rss("https://search.example/search?q=rust+cargo&page=1");
+        assert!(
+            rss.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">")
+        );
+        assert!(rss.contains("<title>rust cargo - Websurfx</title>"));
+        assert!(rss.contains("<link>https://search.example/search?q=rust+cargo&amp;page=1</link>"));
+        assert!(rss.contains(
+            "<item><title>Rust &amp; &lt;Cargo&gt;</title><link>https://doc.rust-lang.org/cargo/?a=1&amp;b=2</link>"
+        ));
+        assert!(rss.contains("<description>The &quot;package&quot; manager.</description></item>"));
+        assert!(rss.ends_with("</channel></rss>"));
+    }
+
+    #[test]
+    fn test_upstream_failed_only_when_every_engine_failed() {
+        let request_error: EngineErrorInfo =
+            EngineErrorInfo::new(&EngineError::RequestError, "duckduckgo");
+        let empty_result_set: EngineErrorInfo =
+            EngineErrorInfo::new(&EngineError::EmptyResultSet, "searx");
+
+        assert!(!SearchResults::new(Vec::new(), "query", &[]).upstream_failed());
+        assert!(
+            SearchResults::new(Vec::new(), "query", &[request_error.clone()]).upstream_failed()
+        );
+        assert!(
+            !SearchResults::new(Vec::new(), "query", &[request_error, empty_result_set])
+                .upstream_failed()
+        );
+    }
 }
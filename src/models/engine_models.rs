@@ -1,9 +1,18 @@
 //! This module provides the error enum to handle different errors associated while requesting data from
 //! the upstream search engines with the search query provided by the user.
 
-use super::aggregation_models::SearchResult;
-use error_stack::{Result, ResultExt};
-use std::{collections::HashMap, fmt, time::Duration};
+use super::aggregation_models::{NewsResult, SearchResult, Suggestion, VideoResult};
+use crate::engines::registry::registered_engine;
+use crate::results::{
+    engine_session::{
+        add_session_cookies, cached_session, cookies_from_headers, invalidate_session,
+        store_session,
+    },
+    outbound_guard::guard_outbound_request,
+};
+use error_stack::{AttachmentKind, FrameKind, Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, sync::OnceLock, time::Duration};
 
 /// A custom error type used for handle engine associated errors.
 #[derive(Debug)]
@@ -42,14 +51,418 @@ impl fmt::Display for EngineError {
 
 impl error_stack::Context for EngineError {}
 
+/// An enum type which stores the kind of the failure behind an `EngineError`, which tells apart
+/// the errors like a timeout, a blocked request or a page which could not be parsed. It is
+/// attached to the error report where the kind is known and otherwise derived from the report.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "status", rename_all = "camelCase")]
+pub enum EngineErrorKind {
+    /// This variant indicates that the upstream request did not complete in time.
+    Timeout,
+    /// This variant stores the status code of the unsuccessful response of the upstream search
+    /// engine (like `429` when the server is rate limited).
+    Http(u16),
+    /// This variant indicates that the response of the upstream search engine could not be
+    /// parsed into results.
+    Parse,
+    /// This variant indicates that the upstream search engine could not be reached.
+    Network,
+    /// This variant indicates that the upstream search engine answered with a captcha (anti-bot)
+    /// page instead of the results.
+    Captcha,
+    /// This variant indicates that the response of the upstream search engine exceeded the
+    /// `max_response_bytes` limit and was not read any further.
+    TooLarge,
+}
+
+impl EngineErrorKind {
+    /// A function which finds the kind of the failure behind the provided error report, the kind
+    /// attached to the report takes precedence over the kind derived from the `reqwest` error or
+    /// the context of the report.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - It takes the error report returned by the upstream search engine.
+    pub fn of(error: &Report<EngineError>) -> Self {
+        if let Some(kind) = error.downcast_ref::<EngineErrorKind>() {
+            return *kind;
+        }
+
+        if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            return match (error.is_timeout(), error.status()) {
+                (true, _) => EngineErrorKind::Timeout,
+                (false, Some(status)) => EngineErrorKind::Http(status.as_u16()),
+                (false, None) => EngineErrorKind::Network,
+            };
+        }
+
+        match error.current_context() {
+            EngineError::RequestError => EngineErrorKind::Network,
+            EngineError::EmptyResultSet | EngineError::UnexpectedError => EngineErrorKind::Parse,
+        }
+    }
+}
+
+/// The default maximum size (in bytes) of the body of a response of an upstream search engine
+/// which is read, which is 5 MiB.
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 5 * 1024 * 1024;
+
+/// A static variable which stores the maximum size (in bytes) of the body of a response of an
+/// upstream search engine which is read. It is set once from the config at startup and defaults
+/// to `DEFAULT_MAX_RESPONSE_BYTES`.
+static MAX_RESPONSE_BYTES: OnceLock<usize> = OnceLock::new();
+
+/// A function which sets the maximum size of the body of a response of an upstream search engine
+/// which is read. Only the first call has an effect as the option is meant to be set once at
+/// startup.
+///
+/// # Arguments
+///
+/// * `max_bytes` - It takes the parsed `max_response_bytes` option from the config, where `0`
+/// disables the limit.
+pub fn limit_response_bytes(max_bytes: usize) {
+    if MAX_RESPONSE_BYTES.set(max_bytes).is_err() {
+        log::debug!("The maximum response size was already configured");
+    }
+}
+
+/// A static variable which stores the time (secs) to wait for every read of the body of a response
+/// of an upstream search engine. It is set once from the config at startup and defaults to `0`,
+/// which only bounds the reads by the request timeout.
+static READ_TIMEOUT: OnceLock<u8> = OnceLock::new();
+
+/// A function which sets the time to wait for every read of the body of a response of an upstream
+/// search engine. Only the first call has an effect as the option is meant to be set once at
+/// startup.
+///
+/// # Arguments
+///
+/// * `read_timeout` - It takes the parsed `read_timeout` option from the config, where `0` only
+/// bounds the reads by the request timeout.
+pub fn limit_read_timeout(read_timeout: u8) {
+    if READ_TIMEOUT.set(read_timeout).is_err() {
+        log::debug!("The read timeout was already configured");
+    }
+}
+
+/// A helper function which checks whether a body of the provided size exceeds the maximum size.
+///
+/// # Arguments
+///
+/// * `size` - It takes the size (in bytes) of the body read or announced so far.
+/// * `max_bytes` - It takes the maximum size (in bytes) of the body, where `0` disables the limit.
+fn exceeds_limit(size: u64, max_bytes: usize) -> bool {
+    max_bytes != 0 && size > max_bytes as u64
+}
+
+/// A function which reads the body of the response of an upstream search engine as text. The body
+/// is read in chunks, every read is bounded by the `read_timeout` and the request is aborted as
+/// soon as it exceeds the `max_response_bytes` limit, so that a huge page can not exhaust the
+/// memory of the server.
+///
+/// # Arguments
+///
+/// * `response` - It takes the successful response of the upstream search engine.
+///
+/// # Error
+///
+/// It returns an `UnexpectedError` with the `TooLarge` kind attached if the body exceeds the
+/// limit, a `RequestError` with the `Timeout` kind attached if a read of the body took longer than
+/// the `read_timeout` and a `RequestError` if the body could not be read.
+pub async fn read_response_body(mut response: reqwest::Response) -> Result<String, EngineError> {
+    let max_bytes: usize = MAX_RESPONSE_BYTES
+        .get()
+        .copied()
+        .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+    let too_large = || {
+        Report::new(EngineError::UnexpectedError)
+            .attach(EngineErrorKind::TooLarge)
+            .attach_printable(format!(
+                "upstream search engine responded with more than {max_bytes} bytes"
+            ))
+    };
+
+    if response
+        .content_length()
+        .is_some_and(|length| exceeds_limit(length, max_bytes))
+    {
+        return Err(too_large());
+    }
+
+    let read_timeout: u8 = READ_TIMEOUT.get().copied().unwrap_or(0);
+
+    // the body is read in chunks as the content length header can be missing or wrong.
+    let mut body: Vec<u8> = Vec::new();
+    loop {
+        let chunk = match read_timeout {
+            0 => response.chunk().await,
+            _ => tokio::time::timeout(Duration::from_secs(read_timeout as u64), response.chunk())
+                .await
+                .map_err(|_| {
+                    Report::new(EngineError::RequestError)
+                        .attach(EngineErrorKind::Timeout)
+                        .attach_printable(format!(
+                            "upstream search engine sent nothing for {read_timeout} seconds"
+                        ))
+                })?,
+        };
+        let Some(chunk) = chunk.change_context(EngineError::RequestError)? else {
+            break;
+        };
+
+        if exceeds_limit((body.len() + chunk.len()) as u64, max_bytes) {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// A function which builds a single line message out of the contexts and the printable
+/// attachments of the provided error report (the outermost first), which is used to expose the
+/// underlying error of an upstream search engine in debug mode.
+///
+/// # Arguments
+///
+/// * `error` - It takes the error report returned by the upstream search engine.
+pub fn error_message(error: &Report<EngineError>) -> String {
+    error
+        .frames()
+        .filter_map(|frame| match frame.kind() {
+            FrameKind::Context(context) => Some(context.to_string()),
+            FrameKind::Attachment(AttachmentKind::Printable(attachment)) => {
+                Some(attachment.to_string())
+            }
+            FrameKind::Attachment(_) => None,
+        })
+        .collect::<Vec<String>>()
+        .join(": ")
+}
+
+/// An enum type which stores the engine specific value of the safe search level which should be
+/// sent to the upstream search engine. It is produced by translating the unified safe search level
+/// (0 to 4) with the `map_safe_search` function of the engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineSafeParam {
+    /// This variant indicates that the upstream search engine does not support safe search.
+    Unsupported,
+    /// This variant stores the value of the safe search query parameter of the upstream search
+    /// engine.
+    Value(&'static str),
+}
+
+impl EngineSafeParam {
+    /// A function which returns the value of the safe search query parameter or an empty string
+    /// if the upstream search engine does not support safe search.
+    pub fn value(&self) -> &'static str {
+        match self {
+            EngineSafeParam::Unsupported => "",
+            EngineSafeParam::Value(value) => value,
+        }
+    }
+}
+
+/// A named struct which stores the headers sent to the upstream search engines which are either
+/// chosen by the server (like the user agent) or forwarded from the request of the user (like the
+/// `Accept-Language` header), along with the region the results should be localized to.
+#[derive(Clone, Debug)]
+pub struct UpstreamHeaders {
+    /// It stores the user agent sent to the upstream search engines.
+    pub user_agent: String,
+    /// It stores the `Accept-Language` header of the user which is forwarded to the upstream
+    /// search engines if forwarding it is enabled.
+    pub accept_language: Option<String>,
+    /// It stores the normalized region (like `us-en`) the results should be localized to, which
+    /// is sent to the upstream search engines supporting it in their own format.
+    pub region: Option<String>,
+    /// It stores the id of the search request which the upstream requests are made for, it is
+    /// never sent to the upstream search engines and is only used to correlate the log lines.
+    pub request_id: String,
+}
+
+impl UpstreamHeaders {
+    /// Constructs a new `UpstreamHeaders` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - It takes the user agent to be sent to the upstream search engines.
+    /// * `accept_language` - It takes the `Accept-Language` header to be forwarded to the upstream
+    /// search engines if any.
+    /// * `region` - It takes the normalized region the results should be localized to if any.
+    pub fn new(user_agent: &str, accept_language: Option<&str>, region: Option<&str>) -> Self {
+        UpstreamHeaders {
+            user_agent: user_agent.to_owned(),
+            accept_language: accept_language.map(str::to_owned),
+            region: region.map(str::to_owned),
+            request_id: "-".to_owned(),
+        }
+    }
+
+    /// A setter function which sets the id of the search request which the upstream requests are
+    /// made for.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_id` - It takes the id assigned to the search request.
+    pub fn set_request_id(&mut self, request_id: &str) {
+        self.request_id = request_id.to_owned();
+    }
+
+    /// A function which returns a copy of the headers with the results localized to the provided
+    /// region instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - It takes the normalized region the results should be localized to.
+    pub fn with_region(&self, region: &str) -> Self {
+        UpstreamHeaders {
+            region: Some(region.to_owned()),
+            ..self.clone()
+        }
+    }
+
+    /// A function which builds the `HeaderMap` sent to an upstream search engine from the engine
+    /// specific headers along with the user agent and the forwarded headers.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine_headers` - It takes the headers specific to the upstream search engine.
+    ///
+    /// # Error
+    ///
+    /// Returns an `UnexpectedError` if any of the header names or values is invalid.
+    pub fn header_map(
+        &self,
+        mut engine_headers: HashMap<String, String>,
+    ) -> Result<reqwest::header::HeaderMap, EngineError> {
+        engine_headers.insert("USER-AGENT".to_owned(), self.user_agent.clone());
+        if let Some(accept_language) = &self.accept_language {
+            engine_headers.insert("ACCEPT-LANGUAGE".to_owned(), accept_language.clone());
+        }
+
+        reqwest::header::HeaderMap::try_from(&engine_headers)
+            .change_context(EngineError::UnexpectedError)
+    }
+}
+
+/// The maximum number of regions the results of a single search can be fetched for, which bounds
+/// the load of a search as the upstream search engines are requested once for every region.
+pub const MAX_REGIONS: usize = 3;
+
+/// A function which normalizes the provided region, which must be made up of a two letter country
+/// code and a two letter language code separated by a hyphen (like `us-en` or `de-de`).
+///
+/// # Arguments
+///
+/// * `region` - It takes the region provided in the config file or the search url.
+///
+/// # Returns
+///
+/// It returns the lowercased region or `None` if it is not a valid region.
+pub fn normalize_region(region: &str) -> Option<String> {
+    let region: String = region.trim().to_ascii_lowercase();
+    match region.split_once('-') {
+        Some((country, language))
+            if country.len() == 2
+                && language.len() == 2
+                && country
+                    .bytes()
+                    .chain(language.bytes())
+                    .all(|byte| byte.is_ascii_lowercase()) =>
+        {
+            Some(region)
+        }
+        _ => None,
+    }
+}
+
+/// A function which normalizes the provided comma separated list of regions (like `us-en,de-de`),
+/// the invalid and the duplicate regions are skipped and only the first `MAX_REGIONS` regions are
+/// kept.
+///
+/// # Arguments
+///
+/// * `regions` - It takes the comma separated regions provided in the search url.
+pub fn normalize_regions(regions: &str) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for region in regions.split(',').filter_map(normalize_region) {
+        if normalized.len() == MAX_REGIONS {
+            break;
+        }
+        if !normalized.contains(&region) {
+            normalized.push(region);
+        }
+    }
+    normalized
+}
+
 /// A trait to define common behavior for all search engines.
 #[async_trait::async_trait]
 pub trait SearchEngine: Sync + Send {
+    /// This function returns the url of the page which sets the cookies (like a consent cookie)
+    /// required by the upstream search engine to provide the results. The engines which do not
+    /// require any cookie do not establish a session and so they need not implement it.
+    fn session_url(&self) -> Option<String> {
+        None
+    }
+
+    /// This helper function returns the cookies of the session established with the upstream
+    /// search engine. The session is established by requesting the `session_url` if none has been
+    /// established yet or it has expired, otherwise the cookies of the established session are
+    /// reused.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - It takes the http client shared between all the upstream requests.
+    /// * `header_map` - It takes the http request headers to be sent to the upstream engine.
+    /// * `request_timeout` - It takes the request timeout value as seconds.
+    ///
+    /// # Error
+    ///
+    /// It returns a `RequestError` if the session could not be established, otherwise it returns
+    /// the cookies of the session or `None` if the upstream engine does not require a session.
+    async fn session_cookies(
+        &self,
+        client: &reqwest::Client,
+        header_map: reqwest::header::HeaderMap,
+        request_timeout: u8,
+    ) -> Result<Option<String>, EngineError> {
+        let session_url: String = match self.session_url() {
+            Some(session_url) => session_url,
+            None => return Ok(None),
+        };
+
+        if let Some(cookies) = cached_session(&session_url) {
+            return Ok(Some(cookies));
+        }
+
+        guard_outbound_request(&session_url).await?;
+
+        let response = client
+            .get(&session_url)
+            .timeout(Duration::from_secs(request_timeout as u64))
+            .headers(header_map)
+            .send()
+            .await
+            .change_context(EngineError::RequestError)?;
+
+        let cookies: Option<String> = cookies_from_headers(response.headers());
+        if let Some(cookies) = &cookies {
+            store_session(&session_url, cookies);
+        }
+
+        Ok(cookies)
+    }
+
     /// This helper function fetches/requests the search results from the upstream search engine in
-    /// an html form.
+    /// an html form. The cookies of the session with the upstream engine are sent along if it
+    /// requires a session and the session is forgotten if the upstream engine rejects the request.
     ///
     /// # Arguments
     ///
+    /// * `client` - It takes the http client shared between all the upstream requests, which
+    /// reuses the pooled connections to the upstream search engines.
     /// * `url` - It takes the url of the upstream search engine with the user requested search
     /// query appended in the search parameters.
     /// * `header_map` - It takes the http request headers to be sent to the upstream engine in
@@ -61,24 +474,56 @@ pub trait SearchEngine: Sync + Send {
     /// # Error
     ///
     /// It returns the html data as a string if the upstream engine provides the data as expected
-    /// otherwise it returns a custom `EngineError`.
+    /// otherwise it returns a custom `EngineError`. The request is rejected with an
+    /// `UnexpectedError` if the url resolves to a non public address (unless allowed in the config).
     async fn fetch_html_from_upstream(
         &self,
+        client: &reqwest::Client,
         url: &str,
-        header_map: reqwest::header::HeaderMap,
+        mut header_map: reqwest::header::HeaderMap,
         request_timeout: u8,
     ) -> Result<String, EngineError> {
+        guard_outbound_request(url).await?;
+
+        if let Some(cookies) = self
+            .session_cookies(client, header_map.clone(), request_timeout)
+            .await?
+        {
+            add_session_cookies(&mut header_map, &cookies);
+        }
+
         // fetch the html from upstream search engine
-        Ok(reqwest::Client::new()
+        let response = client
             .get(url)
             .timeout(Duration::from_secs(request_timeout as u64)) // Add timeout to request to avoid DDOSing the server
             .headers(header_map) // add spoofed headers to emulate human behavior
             .send()
             .await
-            .change_context(EngineError::RequestError)?
-            .text()
-            .await
-            .change_context(EngineError::RequestError)?)
+            .change_context(EngineError::RequestError)?;
+
+        // server errors are reported as request errors so that they can be retried while client
+        // errors (like being rate limited) are not retried.
+        if response.status().is_server_error() || response.status().is_client_error() {
+            let error: EngineError = match response.status().is_server_error() {
+                true => EngineError::RequestError,
+                false => EngineError::UnexpectedError,
+            };
+            // the upstream engine might have rejected the cookies of the session so a new
+            // session is established by the next request.
+            if let (true, Some(session_url)) =
+                (response.status().is_client_error(), self.session_url())
+            {
+                invalidate_session(&session_url);
+            }
+            return Err(Report::new(error)
+                .attach(EngineErrorKind::Http(response.status().as_u16()))
+                .attach_printable(format!(
+                    "upstream search engine responded with status {}",
+                    response.status()
+                )));
+        }
+
+        read_response_body(response).await
     }
 
     /// This function scrapes results from the upstream engine and puts all the scraped results like
@@ -90,8 +535,12 @@ pub trait SearchEngine: Sync + Send {
     ///
     /// * `query` - Takes the user provided query to query to the upstream search engine with.
     /// * `page` - Takes an u32 as an argument.
-    /// * `user_agent` - Takes a random user agent string as an argument.
+    /// * `headers` - Takes the headers (like the user agent) to be sent to the upstream search engine.
+    /// * `client` - Takes the http client shared between all the upstream requests.
     /// * `request_timeout` - Takes a time (secs) as a value which controls the server request timeout.
+    /// * `safe_search` - Takes the engine specific safe search value produced by `map_safe_search`.
+    /// * `max_results` - Takes the maximum number of results to be returned, the results are
+    /// truncated to it in the order the upstream search engine ranked them.
     ///
     /// # Errors
     ///
@@ -99,14 +548,148 @@ pub trait SearchEngine: Sync + Send {
     /// reach the above `upstream search engine` page or if the `upstream search engine` is unable to
     /// provide results for the requested search query and also returns error if the scraping selector
     /// or HeaderMap fails to initialize.
+    #[allow(clippy::too_many_arguments)]
     async fn results(
         &self,
         query: &str,
         page: u32,
-        user_agent: &str,
+        headers: &UpstreamHeaders,
+        client: &reqwest::Client,
         request_timeout: u8,
-        safe_search: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
     ) -> Result<HashMap<String, SearchResult>, EngineError>;
+
+    /// This function fetches the video results from the upstream engine and puts all the fetched
+    /// results like thumbnail, title, duration, source and url in a `VideoResult` struct and
+    /// then returns them as a vector within a Result enum. Engines which are not video capable
+    /// do not need to implement it and provide no video results.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Takes the user provided query to query to the upstream search engine with.
+    /// * `page` - Takes an u32 as an argument.
+    /// * `headers` - Takes the headers (like the user agent) to be sent to the upstream search engine.
+    /// * `client` - Takes the http client shared between all the upstream requests.
+    /// * `request_timeout` - Takes a time (secs) as a value which controls the server request timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `EngineErrorKind` if the user is not connected to the internet or if their is failure to
+    /// reach the above `upstream search engine` page or if the `upstream search engine` is unable to
+    /// provide results for the requested search query.
+    async fn videos(
+        &self,
+        _query: &str,
+        _page: u32,
+        _headers: &UpstreamHeaders,
+        _client: &reqwest::Client,
+        _request_timeout: u8,
+        _safe_search: EngineSafeParam,
+    ) -> Result<Vec<VideoResult>, EngineError> {
+        Ok(Vec::new())
+    }
+
+    /// This function fetches the news results from the upstream engine and puts all the fetched
+    /// results like title, url, description and the time of publishing in a `NewsResult` struct
+    /// and then returns them as a vector within a Result enum. Engines which are not news capable
+    /// do not need to implement it and provide no news results.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Takes the user provided query to query to the upstream search engine with.
+    /// * `page` - Takes an u32 as an argument.
+    /// * `headers` - Takes the headers (like the user agent) to be sent to the upstream search engine.
+    /// * `client` - Takes the http client shared between all the upstream requests.
+    /// * `request_timeout` - Takes a time (secs) as a value which controls the server request timeout.
+    /// * `safe_search` - Takes the engine specific safe search value produced by `map_safe_search`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `EngineErrorKind` if the user is not connected to the internet or if their is failure to
+    /// reach the above `upstream search engine` page or if the `upstream search engine` is unable to
+    /// provide results for the requested search query.
+    async fn news(
+        &self,
+        _query: &str,
+        _page: u32,
+        _headers: &UpstreamHeaders,
+        _client: &reqwest::Client,
+        _request_timeout: u8,
+        _safe_search: EngineSafeParam,
+    ) -> Result<Vec<NewsResult>, EngineError> {
+        Ok(Vec::new())
+    }
+
+    /// This function fetches the results of the upstream engine along with the related search
+    /// terms and the spelling corrections which it suggests for the search query, both of which
+    /// are scraped from the same response so that no additional request is made for the
+    /// suggestions. Engines which do not provide suggestions do not need to implement it and
+    /// provide the results of the `results` function without any suggestions.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Takes the user provided query to query to the upstream search engine with.
+    /// * `page` - Takes an u32 as an argument.
+    /// * `headers` - Takes the headers (like the user agent) to be sent to the upstream search engine.
+    /// * `client` - Takes the http client shared between all the upstream requests.
+    /// * `request_timeout` - Takes a time (secs) as a value which controls the server request timeout.
+    /// * `safe_search` - Takes the engine specific safe search value produced by `map_safe_search`.
+    /// * `max_results` - Takes the maximum number of results to be returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as the `results` function, failing to scrape the suggestions never
+    /// fails the results as the suggestions are not essential.
+    #[allow(clippy::too_many_arguments)]
+    async fn results_with_suggestions(
+        &self,
+        query: &str,
+        page: u32,
+        headers: &UpstreamHeaders,
+        client: &reqwest::Client,
+        request_timeout: u8,
+        safe_search: EngineSafeParam,
+        max_results: usize,
+    ) -> Result<(HashMap<String, SearchResult>, Vec<Suggestion>), EngineError> {
+        Ok((
+            self.results(
+                query,
+                page,
+                headers,
+                client,
+                request_timeout,
+                safe_search,
+                max_results,
+            )
+            .await?,
+            Vec::new(),
+        ))
+    }
+
+    /// This function reformats the search query before it is sent to the upstream search engine
+    /// (like dropping the search operators which the engine does not understand), so that the
+    /// quirks of the engine stay encapsulated in it. The aggregator applies it before fetching
+    /// any kind of results from the engine. Engines which need no reformatting do not need to
+    /// implement it and the query is sent as it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Takes the user provided query as an argument.
+    fn transform_query(&self, query: &str) -> String {
+        query.to_owned()
+    }
+
+    /// This function translates the unified safe search level (0 to 4) into the value which the
+    /// upstream search engine expects in its safe search query parameter. Engines which do not
+    /// support safe search do not need to implement it and the level is ignored for them.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - Takes the unified safe search level as an argument.
+    fn map_safe_search(&self, _level: u8) -> EngineSafeParam {
+        EngineSafeParam::Unsupported
+    }
 }
 
 /// A named struct which stores the engine struct with the name of the associated engine.
@@ -116,16 +699,22 @@ pub struct EngineHandler {
     engine: Box<dyn SearchEngine>,
     /// It stores the name of the engine to which the struct is associated to.
     name: &'static str,
+    /// It stores the override of the base url of the engine, the built-in base url of the engine
+    /// is used if it is not provided.
+    base_url: Option<String>,
 }
 
 impl Clone for EngineHandler {
     fn clone(&self) -> Self {
-        Self::new(self.name).unwrap()
+        Self::new(self.name)
+            .unwrap()
+            .with_base_url(self.base_url.as_deref())
     }
 }
 
 impl EngineHandler {
-    /// Parses an engine name into an engine handler.
+    /// Parses an engine name into an engine handler, the engine is looked up in the registry of
+    /// the upstream search engines.
     ///
     /// # Arguments
     ///
@@ -135,25 +724,137 @@ impl EngineHandler {
     ///
     /// It returns an option either containing the value or a none if the engine is unknown
     pub fn new(engine_name: &str) -> Option<Self> {
-        let engine: (&'static str, Box<dyn SearchEngine>) =
-            match engine_name.to_lowercase().as_str() {
-                "duckduckgo" => (
-                    "duckduckgo",
-                    Box::new(crate::engines::duckduckgo::DuckDuckGo),
-                ),
-                "searx" => ("searx", Box::new(crate::engines::searx::Searx)),
-                _ => return None,
-            };
+        Self::with_engine_base_url(engine_name, None)
+    }
+
+    /// This function returns the engine handler with the provided override of the base url of the
+    /// engine, the engine handler is returned as is if no override is provided.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - It takes the override of the base url of the engine from the config file.
+    pub fn with_base_url(self, base_url: Option<&str>) -> Self {
+        match base_url {
+            Some(base_url) => Self::with_engine_base_url(self.name, Some(base_url)).unwrap(),
+            None => self,
+        }
+    }
+
+    /// A helper function which parses an engine name into an engine handler whose engine sends
+    /// its requests to the provided base url.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine_name` - It takes the name of the engine to which the struct was associated to.
+    /// * `base_url` - It takes the optional override of the base url of the engine.
+    fn with_engine_base_url(engine_name: &str, base_url: Option<&str>) -> Option<Self> {
+        let (name, constructor) = registered_engine(engine_name)?;
 
         Some(Self {
-            engine: engine.1,
-            name: engine.0,
+            engine: constructor(base_url),
+            name,
+            base_url: base_url.map(str::to_owned),
         })
     }
 
+    /// This function returns the name of the engine to which the struct is associated to.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
     /// This function converts the EngineHandler type into a tuple containing the engine name and
     /// the associated engine struct.
     pub fn into_name_engine(self) -> (&'static str, Box<dyn SearchEngine>) {
         (self.name, self.engine)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_error_kind_prefers_the_attached_kind() {
+        let error: Report<EngineError> =
+            Report::new(EngineError::RequestError).attach(EngineErrorKind::Timeout);
+        assert_eq!(EngineErrorKind::of(&error), EngineErrorKind::Timeout);
+
+        let error: Report<EngineError> = Report::new(EngineError::RequestError);
+        assert_eq!(EngineErrorKind::of(&error), EngineErrorKind::Network);
+
+        let error: Report<EngineError> = Report::new(EngineError::EmptyResultSet);
+        assert_eq!(EngineErrorKind::of(&error), EngineErrorKind::Parse);
+    }
+
+    #[test]
+    fn test_response_size_limit() {
+        assert!(!exceeds_limit(1024, 1024));
+        assert!(exceeds_limit(1025, 1024));
+        assert!(!exceeds_limit(u64::MAX, 0));
+    }
+
+    #[test]
+    fn test_header_map_includes_user_agent_and_forwarded_headers() {
+        let headers: UpstreamHeaders =
+            UpstreamHeaders::new("websurfx-test", Some("de-DE,de;q=0.9"), None);
+        let header_map = headers
+            .header_map(HashMap::from([(
+                "REFERER".to_string(),
+                "https://google.com/".to_string(),
+            )]))
+            .unwrap();
+
+        assert_eq!(header_map["user-agent"], "websurfx-test");
+        assert_eq!(header_map["accept-language"], "de-DE,de;q=0.9");
+        assert_eq!(header_map["referer"], "https://google.com/");
+
+        let header_map = UpstreamHeaders::new("websurfx-test", None, None)
+            .header_map(HashMap::new())
+            .unwrap();
+        assert!(header_map.get("accept-language").is_none());
+    }
+
+    #[test]
+    fn test_registered_engines_are_known_engines() {
+        for engine in crate::engines::registry::registered_engines() {
+            assert_eq!(
+                EngineHandler::new(engine).map(|handler| handler.name()),
+                Some(engine)
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_region() {
+        assert_eq!(normalize_region("us-en"), Some("us-en".to_owned()));
+        assert_eq!(normalize_region(" DE-de "), Some("de-de".to_owned()));
+        for region in [
+            "",
+            "us",
+            "usa-en",
+            "u1-en",
+            "us_en",
+            "us-en-gb",
+            "wt-wt;kp=1",
+        ] {
+            assert_eq!(
+                normalize_region(region),
+                None,
+                "{region} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_regions_skips_invalid_and_duplicate_regions() {
+        assert_eq!(
+            normalize_regions("us-en, DE-de,invalid,us-en"),
+            vec!["us-en".to_owned(), "de-de".to_owned()]
+        );
+        assert_eq!(
+            normalize_regions("us-en,de-de,fr-fr,es-es").len(),
+            MAX_REGIONS
+        );
+        assert!(normalize_regions("").is_empty());
+    }
+}
@@ -2,6 +2,9 @@
 //! options from config.lua by grouping them together.
 
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+use super::engine_models::EngineHandler;
 
 /// A named struct which stores,deserializes, serializes and groups the parsed config file options
 /// of theme and colorscheme names into the Style struct which derives the `Clone`, `Serialize`
@@ -19,6 +22,34 @@ pub struct Style {
     /// It stores the parsed colorscheme option used to set a colorscheme for the
     /// theme being used.
     pub colorscheme: String,
+    /// It stores the parsed option to whether open the links of the search results in a new tab.
+    #[serde(default)]
+    pub open_in_new_tab: bool,
+    /// It stores the sanitized markup of the notice banner shown on all the pages, no banner is
+    /// shown when it is empty.
+    #[serde(default)]
+    pub notice_html: String,
+    /// It stores the option to whether the preferences of the user are saved in the browser
+    /// storage instead of a cookie.
+    #[serde(default)]
+    pub disable_cookies: bool,
+    /// It stores the maximum length (in characters) of the descriptions of the search results, a
+    /// value of zero disables the truncation.
+    #[serde(default)]
+    pub snippet_max_length: usize,
+    /// It stores the option to whether the links of the search results point to the click
+    /// redirect route of the server.
+    #[serde(default)]
+    pub redirect_clicks: bool,
+    /// It stores the option to whether show the number of the search results along with the time
+    /// taken to fetch them above the search results.
+    #[serde(default)]
+    pub show_result_stats: bool,
+    /// It stores the path of the folder containing the handlebars templates, the `templates`
+    /// folder of the public folder is used if it is not provided. It is only used by the server
+    /// and so it is never passed to the templates.
+    #[serde(skip)]
+    pub templates_path: Option<String>,
 }
 
 impl Style {
@@ -30,16 +61,481 @@ impl Style {
     /// * `colorscheme` - It takes the parsed colorscheme option used to set a colorscheme
     /// for the theme being used.
     pub fn new(theme: String, colorscheme: String) -> Self {
-        Style { theme, colorscheme }
+        Style {
+            theme,
+            colorscheme,
+            open_in_new_tab: false,
+            notice_html: String::new(),
+            disable_cookies: false,
+            snippet_max_length: 0,
+            redirect_clicks: false,
+            show_result_stats: false,
+            templates_path: None,
+        }
+    }
+
+    /// A setter function to set whether the links of the search results are opened in a new tab.
+    ///
+    /// # Arguments
+    ///
+    /// * `open_in_new_tab` - It takes the option to whether open the links in a new tab.
+    pub fn set_open_in_new_tab(&mut self, open_in_new_tab: bool) {
+        self.open_in_new_tab = open_in_new_tab;
+    }
+
+    /// A setter function to set the markup of the notice banner shown on all the pages.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice_html` - It takes the markup of the notice banner, which should be sanitized.
+    pub fn set_notice_html(&mut self, notice_html: &str) {
+        self.notice_html = notice_html.to_owned();
+    }
+
+    /// A setter function to set whether the preferences of the user are saved in the browser
+    /// storage instead of a cookie.
+    ///
+    /// # Arguments
+    ///
+    /// * `disable_cookies` - It takes the `disable_cookies` option from the config file.
+    pub fn set_disable_cookies(&mut self, disable_cookies: bool) {
+        self.disable_cookies = disable_cookies;
+    }
+
+    /// A setter function to set the maximum length of the descriptions of the search results.
+    ///
+    /// # Arguments
+    ///
+    /// * `snippet_max_length` - It takes the maximum length (in characters), where zero disables
+    /// the truncation.
+    pub fn set_snippet_max_length(&mut self, snippet_max_length: usize) {
+        self.snippet_max_length = snippet_max_length;
+    }
+
+    /// A setter function to set whether the links of the search results point to the click
+    /// redirect route of the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `redirect_clicks` - It takes the `redirect_clicks` option from the config file.
+    pub fn set_redirect_clicks(&mut self, redirect_clicks: bool) {
+        self.redirect_clicks = redirect_clicks;
+    }
+
+    /// A setter function to set whether the number of the search results along with the time taken
+    /// to fetch them is shown above the search results.
+    ///
+    /// # Arguments
+    ///
+    /// * `show_result_stats` - It takes the `show_result_stats` option from the config file.
+    pub fn set_show_result_stats(&mut self, show_result_stats: bool) {
+        self.show_result_stats = show_result_stats;
+    }
+
+    /// A setter function to set the path of the folder containing the handlebars templates.
+    ///
+    /// # Arguments
+    ///
+    /// * `templates_path` - It takes the `templates_path` option from the config file.
+    pub fn set_templates_path(&mut self, templates_path: Option<String>) {
+        self.templates_path = templates_path;
     }
 }
 
 /// Configuration options for the aggregator.
 #[derive(Clone)]
 pub struct AggregatorConfig {
-    /// It stores the option to whether enable or disable random delays between
-    /// requests.
-    pub random_delay: bool,
+    /// It stores the range of the random delay applied before every request to the upstream
+    /// search engines.
+    pub delay: RequestDelay,
+    /// It stores the maximum page number which can be requested from the search route.
+    pub max_page: u32,
+    /// It stores the maximum length (in characters) of the search query accepted by the search
+    /// route.
+    pub max_query_length: usize,
+    /// It stores the maximum number of requests which can be made to the upstream search engines
+    /// concurrently.
+    pub max_concurrent_requests: usize,
+    /// It stores the maximum number of the upstream search engines requested by a single search,
+    /// the engines selected beyond it are dropped. A value of zero disables the limit.
+    pub max_engines_per_request: usize,
+    /// It stores the number of times a request to an upstream search engine is retried when it
+    /// fails with a transient error.
+    pub engine_retries: u8,
+    /// It stores the user agent sent to the upstream search engines. A randomly generated user
+    /// agent is used if it is not provided.
+    pub user_agent: Option<String>,
+    /// It stores the option to whether pick the user agent from a built-in pool of common user
+    /// agents for every search.
+    pub rotate_user_agent: bool,
+    /// It stores the option to whether forward the `Accept-Language` header of the user to the
+    /// upstream search engines.
+    pub forward_accept_language: bool,
+    /// It stores the number of pages after the next page whose results are fetched and cached in
+    /// the background after a search page is served.
+    pub prefetch_ahead: u32,
+    /// It stores the bang shortcuts (like `!w`) mapped by their names (without the `!`).
+    pub bangs: HashMap<String, Bang>,
+    /// It stores the maximum number of results taken from every upstream search engine. A value
+    /// of zero disables the limit.
+    pub results_per_engine: usize,
+    /// It stores the per engine overrides of the `results_per_engine` option mapped by the names
+    /// of the upstream search engines.
+    pub results_per_engine_overrides: HashMap<String, usize>,
+    /// It stores the option to whether allow the requests to the upstream search engines to reach
+    /// private, loopback and link-local addresses.
+    pub allow_private_addresses: bool,
+    /// It stores the maximum size (in bytes) of the body of a response of an upstream search
+    /// engine which is read. A value of zero disables the limit.
+    pub max_response_bytes: usize,
+    /// It stores the option to whether probe the enabled upstream search engines with a test
+    /// query when the server starts.
+    pub probe_engines_on_start: bool,
+    /// It stores the option to whether refuse to start the server if any upstream search engine
+    /// is unreachable when they are probed.
+    pub probe_engines_strict: bool,
+    /// It stores the ranking weights of the enabled upstream search engines mapped by their
+    /// lowercased names.
+    pub engine_weights: HashMap<String, f64>,
+    /// It stores the fallback engines which are used in place of the upstream search engines when
+    /// they fail, mapped by the lowercased names of the upstream search engines.
+    pub engine_fallbacks: HashMap<String, EngineHandler>,
+    /// It stores the overrides of the base urls (like the url of a self-hosted searx instance)
+    /// which the upstream search engines send their requests to, mapped by the lowercased names of
+    /// the upstream search engines.
+    pub engine_base_urls: HashMap<String, String>,
+    /// It stores the number of results shown on every search page, the results of all the
+    /// upstream search engines are merged and paginated server-side. A value of zero disables it
+    /// in which case every search page shows a page of results of every upstream search engine.
+    pub results_per_page: usize,
+    /// It stores the maximum number of results kept after merging and ranking the results of all
+    /// the upstream search engines. A value of zero disables the limit.
+    pub max_total_results: usize,
+    /// It stores the weight of the agreement of the upstream search engines in the scores of the
+    /// search results.
+    pub agreement_weight: f64,
+    /// It stores the weight of the positions of the search results in the results of the
+    /// upstream search engines in the scores of the search results.
+    pub position_weight: f64,
+    /// It stores the minimum number of upstream search engines which should return results for
+    /// the search results to be considered complete, the results returned by fewer engines are
+    /// flagged as degraded and are not cached. A value of zero disables it.
+    pub min_engines_responded: usize,
+    /// It stores the option to whether remove the tracking query parameters (like `utm_source` or
+    /// `fbclid`) from the urls of the search results.
+    pub clean_urls: bool,
+    /// It stores the option to whether remove the sponsored results (the ads) marked by the
+    /// upstream search engines from the search results instead of only flagging them.
+    pub hide_sponsored: bool,
+    /// It stores the option to whether redirect the search queries which are exactly a domain or
+    /// an url (like `github.com`) straight to the website instead of searching for them.
+    pub direct_navigation: bool,
+    /// It stores the option to whether load the thumbnails of the results through the thumbnail
+    /// proxy route of the server instead of directly from the upstream domains.
+    pub proxy_thumbnails: bool,
+    /// It stores the option to whether route the clicks on the search results through the click
+    /// redirect route of the server.
+    pub redirect_clicks: bool,
+    /// It stores the option to whether log an anonymized counter of the clicks routed through the
+    /// click redirect route.
+    pub log_clicks: bool,
+    /// It stores the maximum number of idle connections kept open to every upstream search engine
+    /// by the http client shared between all the upstream requests.
+    pub connection_pool_size: usize,
+    /// It stores the time (secs) within which the connection to an upstream search engine has to
+    /// be established.
+    pub connect_timeout: u8,
+    /// It stores the time (secs) to wait for every read of the body of a response of an upstream
+    /// search engine, where `0` only bounds the reads by the request timeout.
+    pub read_timeout: u8,
+    /// It stores how long the aggregator waits for the upstream search engines to respond.
+    pub strategy: AggregationStrategy,
+    /// It stores the thresholds of the circuit breaker which temporarily skips the upstream search
+    /// engines which keep failing.
+    pub circuit_breaker: CircuitBreakerConfig,
+}
+
+impl AggregatorConfig {
+    /// A function which returns the maximum number of results to be taken from the given upstream
+    /// search engine, the per engine override takes precedence over the `results_per_engine`
+    /// option.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the upstream search engine.
+    pub fn results_limit(&self, engine: &str) -> usize {
+        match self
+            .results_per_engine_overrides
+            .get(engine)
+            .copied()
+            .unwrap_or(self.results_per_engine)
+        {
+            0 => usize::MAX,
+            limit => limit,
+        }
+    }
+
+    /// A function which returns the ranking weight of the given upstream search engine, which
+    /// defaults to `1.0` if no weight is configured for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the upstream search engine.
+    pub fn engine_weight(&self, engine: &str) -> f64 {
+        self.engine_weights.get(engine).copied().unwrap_or(1.0)
+    }
+
+    /// A function which returns the override of the base url of the given upstream search engine
+    /// or `None` if the built-in base url of the engine should be used.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the upstream search engine.
+    pub fn engine_base_url(&self, engine: &str) -> Option<&str> {
+        self.engine_base_urls.get(engine).map(String::as_str)
+    }
+}
+
+/// An enum type which stores how the searches for the queries disallowed by the blocklist are
+/// answered when the safe search level is `4`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SafeSearchBlockMode {
+    /// This variant shows a page telling the user that the search query has been disallowed.
+    #[default]
+    BlockPage,
+    /// This variant silently answers the search with no results, as if the results had been
+    /// filtered, so that the user is never told that the query is disallowed.
+    Silent,
+}
+
+/// An enum type which stores how long the aggregator waits for the upstream search engines to
+/// respond before the search results are returned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregationStrategy {
+    /// This variant waits for all the upstream search engines to respond.
+    WaitAll,
+    /// This variant returns as soon as the stored number of upstream search engines have
+    /// provided their results or the stored deadline has passed, whichever comes first. The
+    /// requests to the remaining engines are cancelled and their results are dropped.
+    FirstN {
+        /// It stores the number of upstream search engines to wait for.
+        count: usize,
+        /// It stores the maximum time to wait for the upstream search engines.
+        deadline: Duration,
+    },
+}
+
+/// A named struct which stores the thresholds of the circuit breaker which temporarily skips the
+/// upstream search engines which keep failing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// It stores the number of consecutive failures after which an engine is skipped, a value of
+    /// zero disables the circuit breaker.
+    pub failures: u32,
+    /// It stores the window within which the consecutive failures are counted.
+    pub window: Duration,
+    /// It stores the period for which an engine is skipped.
+    pub cooldown: Duration,
+}
+
+impl CircuitBreakerConfig {
+    /// Constructs a new `CircuitBreakerConfig` with the circuit breaker disabled.
+    pub fn disabled() -> Self {
+        Self {
+            failures: 0,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(300),
+        }
+    }
+
+    /// A function which checks whether the circuit breaker is enabled, that is whether the number
+    /// of failures after which an engine is skipped is not zero.
+    pub fn is_enabled(&self) -> bool {
+        self.failures > 0
+    }
+}
+
+/// A named struct which stores the range of the uniformly distributed random delay applied before
+/// every request to the upstream search engines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RequestDelay {
+    /// It stores the minimum delay.
+    pub min: Duration,
+    /// It stores the maximum delay.
+    pub max: Duration,
+}
+
+impl RequestDelay {
+    /// Constructs a new `RequestDelay` with the delay disabled.
+    pub fn disabled() -> Self {
+        Self {
+            min: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+
+    /// A function which checks whether the delay is enabled, that is whether the maximum delay is
+    /// not zero.
+    pub fn is_enabled(&self) -> bool {
+        !self.max.is_zero()
+    }
+}
+
+/// An enum type which stores what a bang shortcut (like `!w query`) provided at the start of the
+/// search query does.
+#[derive(Clone)]
+pub enum Bang {
+    /// This variant restricts the search to the stored upstream search engine.
+    Engine(EngineHandler),
+    /// This variant redirects the search to the stored url in which the `{query}` placeholder is
+    /// replaced with the search query.
+    Redirect(String),
+}
+
+/// Configuration options for the http server.
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// It stores the option to whether enable or disable the compression of the responses.
+    pub enable_compression: bool,
+    /// It stores the time (secs) to wait for the in-flight requests to complete when the server
+    /// is shutting down.
+    pub shutdown_timeout: u64,
+    /// It stores the maximum number of concurrent connections accepted by each worker of the
+    /// server.
+    pub max_connections: usize,
+    /// It stores the time (secs) within which a client has to send the headers of its request
+    /// before the connection is closed.
+    pub client_timeout: u64,
+    /// It stores the maximum number of searches which can fetch their results from the upstream
+    /// search engines at once, the searches beyond it are turned away. A value of zero disables
+    /// the limit.
+    pub max_pending_searches: usize,
+    /// It stores the `ip:port` addresses on which the server should listen. When it is not empty the server listens on these addresses instead of the `binding_ip` and `port`
+    /// options.
+    pub listen: Vec<String>,
+    /// It stores the token required by all the requests (except the health check) to access the
+    /// instance. The instance is open to everyone if it is not provided.
+    pub access_token: Option<String>,
+    /// It stores the option to whether enable or disable the maintenance mode, in which only the
+    /// cached results are served and the upstream search engines are never requested.
+    pub maintenance_mode: bool,
+    /// It stores the option to whether the server never reads nor sets any cookie.
+    pub disable_cookies: bool,
+    /// It stores the ip addresses or networks (like `10.0.0.0/8`) of the reverse proxies whose
+    /// `X-Forwarded-For` and `X-Real-IP` headers are trusted to provide the ip address of the
+    /// client.
+    pub trusted_proxies: Vec<String>,
+    /// It stores what is logged about the searches for the aggregate query statistics.
+    pub query_log: QueryLogMode,
+    /// It stores where the aggregate query statistics are stored.
+    pub query_log_sink: QueryLogSinkKind,
+    /// It stores the path of the file in which the aggregate query statistics are stored when
+    /// the `file` sink is used.
+    pub query_log_file: String,
+    /// It stores the secret salt with which the search queries are hashed. A random salt is
+    /// generated every time the server is started if it is not provided.
+    pub query_log_salt: Option<String>,
+    /// It stores the content of the `robots.txt` served by the server instead of the
+    /// `robots.txt` file of the theme, if it is provided.
+    pub robots_txt: Option<RobotsTxt>,
+    /// It stores the security headers added to all the responses of the server.
+    pub security_headers: SecurityHeaders,
+}
+
+/// A named struct which stores the values of the security headers added to all the responses of
+/// the server (the rendered pages as well as the static files), a header is not added when its
+/// value is `None`. The headers already set by a route are never overridden.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecurityHeaders {
+    /// It stores the value of the `Content-Security-Policy` header.
+    pub content_security_policy: Option<String>,
+    /// It stores the value of the `X-Content-Type-Options` header.
+    pub x_content_type_options: Option<String>,
+    /// It stores the value of the `Referrer-Policy` header.
+    pub referrer_policy: Option<String>,
+    /// It stores the value of the `X-Frame-Options` header.
+    pub x_frame_options: Option<String>,
+}
+
+impl Default for SecurityHeaders {
+    /// The default content security policy only allows the resources of the instance itself,
+    /// except for the images (like the thumbnails of the videos) which are loaded from the
+    /// upstream domains. The inline scripts and styles are allowed as the event handlers and the
+    /// colors of the templates are provided inline.
+    fn default() -> Self {
+        Self {
+            content_security_policy: Some(
+                "default-src 'self'; script-src 'self' 'unsafe-inline'; style-src 'self' 'unsafe-inline'; img-src 'self' https: data:; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'"
+                    .to_owned(),
+            ),
+            x_content_type_options: Some("nosniff".to_owned()),
+            referrer_policy: Some("no-referrer".to_owned()),
+            x_frame_options: Some("DENY".to_owned()),
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// A function which returns the names of the security headers to be added to the responses
+    /// along with their values.
+    pub fn headers(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("content-security-policy", &self.content_security_policy),
+            ("x-content-type-options", &self.x_content_type_options),
+            ("referrer-policy", &self.referrer_policy),
+            ("x-frame-options", &self.x_frame_options),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.as_deref()?)))
+        .collect()
+    }
+}
+
+/// An enum type which stores where the content of the `robots.txt` served by the server is
+/// provided.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RobotsTxt {
+    /// This variant stores the content provided inline with the `robots_txt` option.
+    Inline(String),
+    /// This variant stores the path of the file provided with the `robots_txt_file` option.
+    File(String),
+}
+
+/// An enum type which stores the compression format of the search results stored in the redis
+/// cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CacheCompression {
+    /// This variant stores the search results as plain json.
+    #[default]
+    None,
+    /// This variant compresses the json search results with gzip.
+    Gzip,
+    /// This variant compresses the json search results with zstd.
+    Zstd,
+}
+
+/// An enum type which stores what is logged about the searches for the aggregate query
+/// statistics. The raw search queries are never logged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueryLogMode {
+    /// This variant logs nothing.
+    #[default]
+    Off,
+    /// This variant only counts the searches made every day.
+    Counts,
+    /// This variant counts the searches made every day along with the searches made for every
+    /// (salted) hash of the search queries.
+    Anonymized,
+}
+
+/// An enum type which stores where the aggregate query statistics are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueryLogSinkKind {
+    /// This variant stores the statistics as json in the file from the `query_log_file` option.
+    #[default]
+    File,
+    /// This variant stores the statistics in the redis server from the `redis_url` option.
+    Redis,
 }
 
 /// Configuration options for the rate limiter middleware.
@@ -49,4 +545,36 @@ pub struct RateLimiter {
     pub number_of_requests: u8,
     /// The time limit in which the quantity of requests that should be accepted.
     pub time_limit: u8,
+    /// The budgets of the routes which are rate limited on their own instead of sharing the
+    /// budget above, sorted from the most specific route to the least specific one.
+    pub routes: Vec<RouteRateLimit>,
+}
+
+/// A named struct which stores the rate limiting budget of the routes whose path starts with a
+/// given prefix (like `/search`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteRateLimit {
+    /// The prefix of the paths of the routes sharing this budget.
+    pub route: String,
+    /// The number of request that are allowed within a provided time limit.
+    pub number_of_requests: u8,
+    /// The time limit in which the quantity of requests that should be accepted.
+    pub time_limit: u8,
+}
+
+impl RouteRateLimit {
+    /// A function which checks whether the provided request path is covered by this budget,
+    /// which is the case when it is the route itself or a sub path of the route (like
+    /// `/search/stream` for the `/search` route).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - It takes the path of the request.
+    pub fn matches(&self, path: &str) -> bool {
+        let route: &str = self.route.trim_end_matches('/');
+        match path.strip_prefix(route) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
 }
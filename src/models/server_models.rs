@@ -1,6 +1,8 @@
 //! This module provides the models to parse cookies and search parameters from the search
 //! engine website.
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use super::parser_models::Style;
 
 /// A named struct which deserializes all the user provided search parameters and stores them.
 #[derive(Deserialize)]
@@ -14,11 +16,119 @@ pub struct SearchParams {
     /// It stores the search parameter `safesearch` (or safe search level in simple words) of the
     /// search url.
     pub safesearch: Option<u8>,
+    /// It stores the search parameter `type` (or the type of results requested in simple words)
+    /// of the search url.
+    #[serde(default, rename = "type")]
+    pub search_type: SearchType,
+    /// It stores the search parameter `region` (or the comma separated regions the results should
+    /// be localized to in simple words) of the search url.
+    pub region: Option<String>,
+    /// It stores the search parameter `engines` (or the comma separated names of the upstream
+    /// search engines to search with in simple words) of the search url.
+    pub engines: Option<String>,
+    /// It stores the search parameter `format` (or the format the search results are returned in
+    /// simple words) of the search url.
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// It stores the search parameter `sort` (or the order in which the search results are
+    /// sorted in simple words) of the search url.
+    #[serde(default)]
+    pub sort: SortOrder,
 }
 
-/// A named struct which is used to deserialize the cookies fetched from the client side.
-#[allow(dead_code)]
+/// An enum type which provides the different orders in which the general search results can be
+/// sorted.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// This variant sorts the results by their relevance, that is the agreement of the upstream
+    /// search engines weighted by their configured weights.
+    #[default]
+    Relevance,
+    /// This variant sorts the results by their publishing date (newest first), the results
+    /// without a publishing date follow in the order of their relevance.
+    Date,
+    /// This variant sorts the results alphabetically by their titles.
+    Alphabetical,
+}
+
+impl SortOrder {
+    /// A function which returns the name of the sort order as used in the search url.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Relevance => "relevance",
+            SortOrder::Date => "date",
+            SortOrder::Alphabetical => "alphabetical",
+        }
+    }
+}
+
+/// An enum type which provides the different formats in which the search results can be returned
+/// from the search route.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// This variant returns the search page (or the json of the search results when the client
+    /// prefers json).
+    #[default]
+    Html,
+    /// This variant returns the general search results as an RSS 2.0 feed.
+    Rss,
+}
+
+/// A named struct which deserializes the parameters of the favicon proxy route and stores them.
 #[derive(Deserialize)]
+pub struct FaviconParams {
+    /// It stores the parameter `domain` (or the domain whose favicon is requested in simple
+    /// words) of the favicon proxy url.
+    pub domain: Option<String>,
+}
+
+/// A named struct which deserializes the parameters of the thumbnail proxy route.
+#[derive(Deserialize)]
+pub struct ThumbnailParams {
+    /// It stores the parameter `url` (or the url of the requested thumbnail in simple words) of
+    /// the thumbnail proxy url.
+    pub url: Option<String>,
+}
+
+/// A named struct which deserializes the parameters of the click redirect route.
+#[derive(Deserialize)]
+pub struct RedirectParams {
+    /// It stores the parameter `url` (or the url of the clicked search result in simple words) of
+    /// the click redirect url.
+    pub url: Option<String>,
+}
+
+/// An enum type which provides the different types of results that can be requested from the
+/// search route.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchType {
+    /// This variant requests the general web search results.
+    #[default]
+    General,
+    /// This variant requests the video search results.
+    Videos,
+    /// This variant requests the news search results.
+    News,
+}
+
+impl SearchType {
+    /// A function which returns the name of the search type as used in the search url.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchType::General => "general",
+            SearchType::Videos => "videos",
+            SearchType::News => "news",
+        }
+    }
+}
+
+/// A named struct which is used to deserialize the cookies fetched from the client side and to
+/// serialize the cookies set from the server side.
+#[allow(dead_code)]
+#[derive(Deserialize, Serialize)]
 pub struct Cookie<'a> {
     /// It stores the theme name used in the website.
     pub theme: &'a str,
@@ -29,3 +139,33 @@ pub struct Cookie<'a> {
     /// It stores the user selected safe search level from the UI.
     pub safe_search_level: u8,
 }
+
+/// A named struct which deserializes the user preferences submitted from the settings page.
+#[derive(Deserialize)]
+pub struct SettingsForm {
+    /// It stores the theme name selected by the user.
+    pub theme: String,
+    /// It stores the colorscheme name selected by the user.
+    pub colorscheme: String,
+    /// It stores the comma separated names of the upstream search engines selected by the user.
+    #[serde(default)]
+    pub engines: String,
+    /// It stores the safe search level selected by the user.
+    pub safe_search_level: Option<u8>,
+}
+
+/// A named struct which stores the data needed to render the settings page, which are the
+/// current style of the website along with the themes, colorschemes and upstream search engines
+/// which can be selected.
+#[derive(Serialize)]
+pub struct SettingsPage<'a> {
+    /// It stores the style of the website from the config file.
+    #[serde(flatten)]
+    pub style: &'a Style,
+    /// It stores the names of the available themes.
+    pub themes: &'a [String],
+    /// It stores the names of the available colorschemes.
+    pub colorschemes: &'a [String],
+    /// It stores the names of the registered upstream search engines.
+    pub engines: &'a [&'static str],
+}
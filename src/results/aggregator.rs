@@ -1,25 +1,60 @@
 //! This module provides the functionality to scrape and gathers all the results from the upstream
 //! search engines and then removes duplicate results.
 
-use super::user_agent::random_user_agent;
-use crate::handler::paths::{file_path, FileType};
+use crate::cache::cacher::SharedCache;
+use crate::config::parser::Config;
 use crate::models::{
-    aggregation_models::{EngineErrorInfo, SearchResult, SearchResults},
-    engine_models::{EngineError, EngineHandler},
+    aggregation_models::{
+        AggregationTimings, EngineErrorInfo, EngineTiming, NewsResult, SearchResult, SearchResults,
+        StreamEvent, Suggestion, VideoResult,
+    },
+    engine_models::{
+        EngineError, EngineErrorKind, EngineHandler, EngineSafeParam, SearchEngine, UpstreamHeaders,
+    },
+    parser_models::{AggregationStrategy, AggregatorConfig, RequestDelay},
+    server_models::{SearchType, SortOrder},
+};
+use crate::results::{
+    circuit_breaker::CircuitBreaker,
+    favicon::thumbnail_proxy_url,
+    filter_lists::SharedFilterLists,
+    sanitizer::{html_to_text, is_safe_url, strip_tracking_parameters},
 };
 use error_stack::Report;
+use futures::{
+    future::join_all,
+    stream::{FuturesUnordered, StreamExt},
+};
 use rand::Rng;
-use regex::Regex;
+use reqwest::Client;
 use std::{
-    collections::HashMap,
-    io::{BufReader, Read},
-    time::Duration,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{mpsc::Sender, Semaphore},
+    task::{AbortHandle, JoinHandle},
 };
-use std::{fs::File, io::BufRead};
-use tokio::task::JoinHandle;
 
 /// Aliases for long type annotations
-type FutureVec = Vec<JoinHandle<Result<HashMap<String, SearchResult>, Report<EngineError>>>>;
+type FutureVec = Vec<
+    JoinHandle<(
+        Result<(HashMap<String, SearchResult>, Vec<Suggestion>), Report<EngineError>>,
+        Duration,
+    )>,
+>;
+/// Aliases for long type annotations
+type EngineResults = Vec<(&'static str, HashMap<String, SearchResult>)>;
+/// Aliases for long type annotations
+type ListFutureVec<T> = Vec<JoinHandle<(Result<Vec<T>, Report<EngineError>>, Duration)>>;
+/// Aliases for long type annotations
+type ResponseFutureVec<T> = Vec<JoinHandle<(Result<T, Report<EngineError>>, Duration)>>;
+
+/// The maximum number of related searches provided along with the search results.
+const MAX_SUGGESTIONS: usize = 8;
 
 /// The function aggregates the scraped results from the user-selected upstream search engines.
 /// These engines can be chosen either from the user interface (UI) or from the configuration file.
@@ -48,312 +83,2067 @@ type FutureVec = Vec<JoinHandle<Result<HashMap<String, SearchResult>, Report<Eng
 ///
 /// * `query` - Accepts a string to query with the above upstream search engines.
 /// * `page` - Accepts an u32 page number.
-/// * `random_delay` - Accepts a boolean value to add a random delay before making the request.
-/// * `debug` - Accepts a boolean value to enable or disable debug mode option.
+/// * `config` - Accepts the parsed config struct which provides the random delay, debug and
+/// request timeout options.
 /// * `upstream_search_engines` - Accepts a vector of search engine names which was selected by the
-/// * `request_timeout` - Accepts a time (secs) as a value which controls the server request timeout.
 /// user through the UI or the config file.
+/// * `safe_search` - Accepts the safe search level to be used for filtering the results.
+/// * `search_type` - Accepts the type of results (general, videos or news) which should be aggregated.
+/// * `headers` - Accepts the headers (like the user agent) to be sent to the upstream search engines.
+/// * `regions` - Accepts the regions the general search results are fetched for, the upstream
+/// search engines are requested once for every region and the results are tagged with their
+/// regions when more than one region is provided, otherwise the region of the headers is used.
+/// * `client` - Accepts the http client shared between all the upstream requests.
+/// * `semaphore` - Accepts the semaphore shared between all the searches which bounds the number
+/// of concurrent requests made to the upstream search engines.
+/// * `breaker` - Accepts the circuit breaker shared between all the searches which skips the
+/// upstream search engines which keep failing.
+/// * `cache` - Accepts the cache in which the results of every upstream search engine are cached
+/// when the `cache_per_engine` option is enabled.
+/// * `filter_lists` - Accepts the filter lists shared between all the searches which the results
+/// are filtered with when the safe search level is `3` or above.
 ///
 /// # Error
 ///
 /// Returns an error a reqwest and scraping selector errors if any error occurs in the results
 /// function in either `searx` or `duckduckgo` or both otherwise returns a `SearchResults struct`
 /// containing appropriate values.
+#[allow(clippy::too_many_arguments)]
 pub async fn aggregate(
     query: &str,
     page: u32,
-    random_delay: bool,
-    debug: bool,
+    config: &Config,
     upstream_search_engines: &[EngineHandler],
-    request_timeout: u8,
     safe_search: u8,
+    search_type: SearchType,
+    headers: &UpstreamHeaders,
+    regions: &[String],
+    client: &Client,
+    semaphore: &Arc<Semaphore>,
+    breaker: &CircuitBreaker,
+    cache: &SharedCache,
+    filter_lists: &SharedFilterLists,
 ) -> Result<SearchResults, Box<dyn std::error::Error>> {
-    let user_agent: &str = random_user_agent();
-
-    // Add a random delay before making the request.
-    if random_delay || !debug {
-        let mut rng = rand::thread_rng();
-        let delay_secs = rng.gen_range(1..10);
-        tokio::time::sleep(Duration::from_secs(delay_secs)).await;
+    match search_type {
+        SearchType::General => (),
+        SearchType::Videos => {
+            return Ok(aggregate_videos(
+                query,
+                page,
+                headers,
+                client,
+                upstream_search_engines,
+                config,
+                safe_search,
+                semaphore,
+                breaker,
+            )
+            .await)
+        }
+        SearchType::News => {
+            return Ok(aggregate_news(
+                query,
+                page,
+                headers,
+                client,
+                upstream_search_engines,
+                config,
+                safe_search,
+                semaphore,
+                breaker,
+            )
+            .await)
+        }
     }
 
-    let mut names: Vec<&str> = Vec::with_capacity(0);
+    let started: Instant = Instant::now();
 
-    // create tasks for upstream result fetching
-    let mut tasks: FutureVec = FutureVec::new();
+    // the engines which keep failing are skipped and noted as temporarily disabled.
+    let (available_engines, disabled_engines): (Vec<EngineHandler>, Vec<EngineErrorInfo>) =
+        available_engines(upstream_search_engines, breaker, &config.aggregator);
 
-    for engine_handler in upstream_search_engines {
-        let (name, search_engine) = engine_handler.to_owned().into_name_engine();
-        names.push(name);
-        let query: String = query.to_owned();
-        tasks.push(tokio::spawn(async move {
-            search_engine
-                .results(
-                    &query,
-                    page,
-                    user_agent.clone(),
-                    request_timeout,
-                    safe_search,
-                )
-                .await
-        }));
-    }
+    // the engines are requested once for every region when the results of multiple regions are
+    // searched for at once.
+    let region_headers: Vec<UpstreamHeaders> = match regions.len() {
+        0 | 1 => vec![headers.clone()],
+        _ => regions
+            .iter()
+            .map(|region| headers.with_region(region))
+            .collect(),
+    };
+
+    let region_results = join_all(region_headers.iter().map(|headers| {
+        fetch_region_results(
+            query,
+            page,
+            config,
+            upstream_search_engines,
+            &available_engines,
+            disabled_engines.clone(),
+            safe_search,
+            headers,
+            client,
+            semaphore,
+            breaker,
+            cache,
+        )
+    }));
 
     // get upstream responses
-    let mut responses = Vec::with_capacity(tasks.len());
+    let region_results = region_results.await;
 
-    for task in tasks {
-        if let Ok(result) = task.await {
-            responses.push(result)
+    // aggregate search results, removing duplicates and handling errors the upstream engines
+    // returned, the results are tagged with their region when multiple regions are searched for.
+    let mut engine_results: EngineResults = Vec::new();
+    let mut engine_errors_info: Vec<EngineErrorInfo> = Vec::new();
+    let mut engine_timings: Vec<EngineTiming> = Vec::new();
+    let mut engine_suggestions: Vec<Suggestion> = Vec::new();
+
+    for (headers, (results, errors_info, timings, suggestions)) in
+        region_headers.iter().zip(region_results)
+    {
+        for (engine, mut results) in results {
+            if let Some(region) = headers.region.as_deref().filter(|_| regions.len() > 1) {
+                results
+                    .values_mut()
+                    .for_each(|result| result.add_region(region));
+            }
+            engine_results.push((engine, results));
         }
+        for error_info in errors_info {
+            if !engine_errors_info
+                .iter()
+                .any(|added| added.engine == error_info.engine)
+            {
+                engine_errors_info.push(error_info);
+            }
+        }
+        engine_timings.extend(timings);
+        engine_suggestions.extend(suggestions);
     }
 
-    // aggregate search results, removing duplicates and handling errors the upstream engines returned
-    let mut result_map: HashMap<String, SearchResult> = HashMap::new();
-    let mut engine_errors_info: Vec<EngineErrorInfo> = Vec::new();
+    let degraded: bool = is_degraded(&engine_results, config.aggregator.min_engines_responded);
+
+    let mut result_map: HashMap<String, SearchResult> =
+        merge_results(engine_results, &config.aggregator);
+
+    if safe_search >= 3 {
+        filter_lists.filter_results(&mut result_map);
+    }
+
+    let mut results: Vec<SearchResult> = rank_results(result_map, &config.aggregator);
+    if config.style.snippet_max_length > 0 {
+        results.iter_mut().for_each(|result| {
+            result.description =
+                truncate_snippet(&result.description, config.style.snippet_max_length)
+        });
+    }
 
-    let mut handle_error = |error: &Report<EngineError>, engine_name: &'static str| {
-        log::error!("Engine Error: {:?}", error);
-        engine_errors_info.push(EngineErrorInfo::new(
-            error.downcast_ref::<EngineError>().unwrap(),
-            engine_name,
-        ));
+    let mut results = SearchResults::new(results, query, &engine_errors_info);
+    // the related searches and the spelling corrections are only provided for the first page.
+    if page == 1 {
+        let (suggestions, spelling_correction) = split_suggestions(engine_suggestions, query);
+        results.set_suggestions(suggestions);
+        results.set_spelling_correction(spelling_correction);
+    }
+    if degraded {
+        results.set_degraded();
+    }
+    if config.debug {
+        results.set_timings(AggregationTimings::new(engine_timings, started.elapsed()));
+    }
+    Ok(results)
+}
+
+/// A helper function which checks whether fewer upstream search engines than required returned
+/// results, in which case the search results may be incomplete.
+///
+/// # Arguments
+///
+/// * `engine_results` - It takes the results of every upstream search engine which provided
+/// them, an engine requested for multiple regions is only counted once.
+/// * `min_engines_responded` - It takes the `min_engines_responded` option from the config.
+fn is_degraded(engine_results: &EngineResults, min_engines_responded: usize) -> bool {
+    let mut responded: Vec<&str> = engine_results
+        .iter()
+        .filter(|(_, results)| !results.is_empty())
+        .map(|(engine, _)| *engine)
+        .collect();
+    responded.sort_unstable();
+    responded.dedup();
+    responded.len() < min_engines_responded
+}
+
+/// A helper function which fetches the general search results of the upstream search engines for
+/// the region of the provided headers. The results of the engines cached by the previous searches
+/// are reused when the `cache_per_engine` option is enabled and the results of the fallback
+/// engines are fetched in place of the engines which failed.
+///
+/// # Arguments
+///
+/// * `query` - Accepts a string to query with the upstream search engines.
+/// * `page` - Accepts an u32 page number.
+/// * `config` - Accepts the parsed config struct.
+/// * `upstream_search_engines` - Accepts the upstream search engines selected for the search.
+/// * `available_engines` - Accepts the selected engines which are not temporarily disabled.
+/// * `disabled_engines` - Accepts the errors of the selected engines which are temporarily
+/// disabled by the circuit breaker.
+/// * `safe_search` - Accepts the safe search level to be used for the upstream requests.
+/// * `headers` - Accepts the headers (like the user agent and the region) to be sent to the
+/// upstream search engines.
+/// * `client` - Accepts the http client shared between all the upstream requests.
+/// * `semaphore` - Accepts the semaphore which bounds the number of concurrent upstream requests.
+/// * `breaker` - Accepts the circuit breaker which skips the engines which keep failing.
+/// * `cache` - Accepts the cache in which the results of every upstream search engine are cached.
+///
+/// # Returns
+///
+/// Returns the results of every engine which provided them along with the errors of the engines
+/// which failed, the time taken by the engines and the suggestions scraped from their responses.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_region_results(
+    query: &str,
+    page: u32,
+    config: &Config,
+    upstream_search_engines: &[EngineHandler],
+    available_engines: &[EngineHandler],
+    disabled_engines: Vec<EngineErrorInfo>,
+    safe_search: u8,
+    headers: &UpstreamHeaders,
+    client: &Client,
+    semaphore: &Arc<Semaphore>,
+    breaker: &CircuitBreaker,
+    cache: &SharedCache,
+) -> (
+    EngineResults,
+    Vec<EngineErrorInfo>,
+    Vec<EngineTiming>,
+    Vec<Suggestion>,
+) {
+    // the results of the engines cached by the previous searches (which could have been made with
+    // another selection of engines) are reused and only the remaining engines are requested.
+    let region: Option<&str> = headers.region.as_deref();
+    let (cached_results, uncached_engines): (EngineResults, Vec<EngineHandler>) = match config
+        .cache_per_engine
+    {
+        true => {
+            cached_engine_results(cache, available_engines, query, page, safe_search, region).await
+        }
+        false => (Vec::new(), available_engines.to_vec()),
     };
 
-    for _ in 0..responses.len() {
-        let response = responses.pop().unwrap();
-        let engine = names.pop().unwrap();
+    // create tasks for upstream result fetching
+    let (names, tasks): (Vec<&'static str>, FutureVec) = spawn_results_tasks(
+        query,
+        page,
+        config,
+        &uncached_engines,
+        safe_search,
+        headers,
+        client,
+        semaphore,
+    );
+
+    let responses = collect_responses(
+        names,
+        tasks,
+        config.aggregator.strategy,
+        &headers.request_id,
+    )
+    .await;
+
+    let mut engine_results: EngineResults = cached_results;
+    let mut engine_errors_info: Vec<EngineErrorInfo> = disabled_engines;
+    let mut engine_timings: Vec<EngineTiming> = Vec::new();
+    // the suggestions are not cached along with the results of the engines.
+    let mut engine_suggestions: Vec<Suggestion> = Vec::new();
 
-        if result_map.is_empty() {
+    for (engine, response, elapsed) in responses {
+        engine_timings.push(EngineTiming::new(engine, elapsed));
+        record_response(breaker, engine, &response, config, &headers.request_id);
+
+        match response {
+            Ok((results, suggestions)) => {
+                if config.cache_per_engine {
+                    let key: String = engine_cache_key(engine, query, page, safe_search, region);
+                    cache_engine_results(cache, &key, &results, &headers.request_id).await;
+                }
+                engine_results.push((engine, results));
+                engine_suggestions.extend(suggestions);
+            }
+            Err(error) => {
+                log::error!("[{}] Engine Error: {:?}", headers.request_id, error);
+                engine_errors_info.push(EngineErrorInfo::from_report(&error, engine, config.debug));
+            }
+        }
+    }
+
+    // fetch the results of the fallback engines in place of the engines which failed.
+    let fallback_engines: Vec<EngineHandler> = select_fallbacks(
+        &mut engine_errors_info,
+        upstream_search_engines,
+        &config.aggregator,
+        breaker,
+    );
+    let (fallback_names, fallback_tasks): (Vec<&'static str>, FutureVec) = spawn_results_tasks(
+        query,
+        page,
+        config,
+        &fallback_engines,
+        safe_search,
+        headers,
+        client,
+        semaphore,
+    );
+    for (task, engine) in fallback_tasks.into_iter().zip(fallback_names) {
+        if let Ok((response, elapsed)) = task.await {
+            engine_timings.push(EngineTiming::new(engine, elapsed));
+            record_response(breaker, engine, &response, config, &headers.request_id);
             match response {
-                Ok(results) => {
-                    result_map = results.clone();
+                Ok((results, suggestions)) => {
+                    engine_results.push((engine, results));
+                    engine_suggestions.extend(suggestions);
                 }
                 Err(error) => {
-                    handle_error(&error, engine);
+                    log::error!("[{}] Engine Error: {:?}", headers.request_id, error);
+                    engine_errors_info.push(EngineErrorInfo::from_report(
+                        &error,
+                        engine,
+                        config.debug,
+                    ));
                 }
             }
-            continue;
         }
+    }
 
-        match response {
-            Ok(result) => {
-                result.into_iter().for_each(|(key, value)| {
-                    result_map
-                        .entry(key)
-                        .and_modify(|result| {
-                            result.add_engines(engine);
-                        })
-                        .or_insert_with(|| -> SearchResult { value });
-                });
+    (
+        engine_results,
+        engine_errors_info,
+        engine_timings,
+        engine_suggestions,
+    )
+}
+
+/// A helper function which builds the key under which the results of an upstream search engine
+/// are cached when the `cache_per_engine` option is enabled.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine.
+/// * `query` - It takes the search query sent to the upstream search engine.
+/// * `page` - It takes the page number of the results.
+/// * `safe_search` - It takes the safe search level the results were fetched with.
+/// * `region` - It takes the region the results were localized to.
+fn engine_cache_key(
+    engine: &str,
+    query: &str,
+    page: u32,
+    safe_search: u8,
+    region: Option<&str>,
+) -> String {
+    format!(
+        "engine:{engine}:{page}:{safe_search}:{}:{query}",
+        region.unwrap_or_default()
+    )
+}
+
+/// A helper function which fetches the cached results of the provided upstream search engines.
+///
+/// # Arguments
+///
+/// * `cache` - It takes the cache in which the results of the upstream search engines are cached.
+/// * `upstream_search_engines` - It takes the upstream search engines to fetch the results of.
+/// * `query` - It takes the search query sent to the upstream search engines.
+/// * `page` - It takes the page number of the results.
+/// * `safe_search` - It takes the safe search level of the search.
+/// * `region` - It takes the region the results are localized to.
+///
+/// # Returns
+///
+/// Returns the cached results mapped by the names of the engines along with the engines whose
+/// results are not cached.
+async fn cached_engine_results(
+    cache: &SharedCache,
+    upstream_search_engines: &[EngineHandler],
+    query: &str,
+    page: u32,
+    safe_search: u8,
+    region: Option<&str>,
+) -> (EngineResults, Vec<EngineHandler>) {
+    let mut cached_results: EngineResults = Vec::new();
+    let mut uncached_engines: Vec<EngineHandler> = Vec::new();
+
+    for engine_handler in upstream_search_engines {
+        let engine: &'static str = engine_handler.name();
+        let key: String = engine_cache_key(engine, query, page, safe_search, region);
+        match cache.cached_json(&key).await {
+            Ok(results) if !results.results.is_empty() => cached_results.push((
+                engine,
+                results
+                    .results
+                    .into_iter()
+                    .map(|result| (result.url.clone(), result))
+                    .collect(),
+            )),
+            _ => uncached_engines.push(engine_handler.clone()),
+        }
+    }
+
+    (cached_results, uncached_engines)
+}
+
+/// A helper function which caches the results of an upstream search engine, the failures are only
+/// logged as the results are still served.
+///
+/// # Arguments
+///
+/// * `cache` - It takes the cache in which the results of the upstream search engines are cached.
+/// * `key` - It takes the key built by `engine_cache_key`.
+/// * `results` - It takes the results of the upstream search engine mapped by their urls.
+/// * `request_id` - It takes the id of the search request which is included in the log lines.
+async fn cache_engine_results(
+    cache: &SharedCache,
+    key: &str,
+    results: &HashMap<String, SearchResult>,
+    request_id: &str,
+) {
+    let results: SearchResults = SearchResults::new(results.values().cloned().collect(), "", &[]);
+    if let Err(error) = cache.cache_results(&results, key).await {
+        log::error!("[{request_id}] Cache Error: Unable to cache the results of {key}: {error:?}");
+    }
+}
+
+/// The function streams the general search results from the user-selected upstream search engines
+/// as each of them provides its results, instead of waiting for all of them like `aggregate` does.
+/// The results of every engine are sanitized and filtered in the same way as the aggregated
+/// results and only the results which were not already sent (by another engine) are sent. Once
+/// all the engines are done, a final event containing the errors returned by the engines is sent.
+///
+/// # Arguments
+///
+/// * `query` - Accepts a string to query with the upstream search engines.
+/// * `page` - Accepts an u32 page number.
+/// * `config` - Accepts the parsed config struct.
+/// * `upstream_search_engines` - Accepts a vector of search engine names which was selected by the
+/// user through the UI or the config file.
+/// * `safe_search` - Accepts the safe search level to be used for filtering the results.
+/// * `headers` - Accepts the headers (like the user agent) to be sent to the upstream search engines.
+/// * `client` - Accepts the http client shared between all the upstream requests.
+/// * `semaphore` - Accepts the semaphore which bounds the number of concurrent upstream requests.
+/// * `breaker` - Accepts the circuit breaker which skips the upstream search engines which keep
+/// failing.
+/// * `filter_lists` - Accepts the filter lists which the results are filtered with when the safe
+/// search level is `3` or above.
+/// * `sender` - Accepts the sender of the channel on which the events are sent, the streaming
+/// stops as soon as the receiver is dropped.
+#[allow(clippy::too_many_arguments)]
+pub async fn stream_results(
+    query: &str,
+    page: u32,
+    config: &Config,
+    upstream_search_engines: &[EngineHandler],
+    safe_search: u8,
+    headers: &UpstreamHeaders,
+    client: &Client,
+    semaphore: &Arc<Semaphore>,
+    breaker: &CircuitBreaker,
+    filter_lists: &SharedFilterLists,
+    sender: Sender<StreamEvent>,
+) {
+    let (available_engines, mut engine_errors_info): (Vec<EngineHandler>, Vec<EngineErrorInfo>) =
+        available_engines(upstream_search_engines, breaker, &config.aggregator);
+
+    let (names, tasks): (Vec<&'static str>, FutureVec) = spawn_results_tasks(
+        query,
+        page,
+        config,
+        &available_engines,
+        safe_search,
+        headers,
+        client,
+        semaphore,
+    );
+
+    // the responses are handled in the order in which the engines complete.
+    let mut pending: FuturesUnordered<_> = tasks
+        .into_iter()
+        .zip(names)
+        .map(|(task, engine)| async move { (engine, task.await) })
+        .collect();
+
+    let mut sent_urls: HashSet<String> = HashSet::new();
+
+    while let Some((engine, task_result)) = pending.next().await {
+        let response = match task_result {
+            Ok((response, _)) => response,
+            Err(_) => continue,
+        };
+        record_response(breaker, engine, &response, config, &headers.request_id);
+
+        let mut result_map: HashMap<String, SearchResult> = match response {
+            Ok((results, _)) => merge_results(vec![(engine, results)], &config.aggregator),
+            Err(error) => {
+                log::error!("[{}] Engine Error: {:?}", headers.request_id, error);
+                engine_errors_info.push(EngineErrorInfo::from_report(&error, engine, config.debug));
+                continue;
+            }
+        };
+
+        if safe_search >= 3 {
+            filter_lists.filter_results(&mut result_map);
+        }
+        result_map.retain(|url, _| sent_urls.insert(url.clone()));
+
+        if !result_map.is_empty() {
+            let results: Vec<SearchResult> = rank_results(result_map, &config.aggregator);
+            if sender
+                .send(StreamEvent::Results(engine.to_owned(), results))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    let _ = sender
+        .send(StreamEvent::Done(Box::new(SearchResults::new(
+            Vec::new(),
+            query,
+            &engine_errors_info,
+        ))))
+        .await;
+}
+
+/// A helper function which picks a uniformly distributed random delay from the configured range
+/// to be applied before a request is made to an upstream search engine.
+///
+/// # Arguments
+///
+/// * `delay` - Accepts the range of the delay from the config.
+fn random_delay(delay: RequestDelay) -> Duration {
+    match delay.is_enabled() {
+        true => rand::thread_rng().gen_range(delay.min..=delay.max),
+        false => Duration::ZERO,
+    }
+}
+
+/// The function aggregates the video results from the user-selected upstream search engines which
+/// are video capable. Duplicate videos provided by multiple engines are removed and the errors
+/// returned by the engines are placed in the returned `SearchResults` struct.
+///
+/// # Arguments
+///
+/// * `query` - Accepts a string to query with the above upstream search engines.
+/// * `page` - Accepts an u32 page number.
+/// * `headers` - Accepts the headers (like the user agent) to be sent to the upstream search engines.
+/// * `client` - Accepts the http client shared between all the upstream requests.
+/// * `upstream_search_engines` - Accepts a vector of search engine names which was selected by the
+/// user through the UI or the config file.
+/// * `config` - Accepts the parsed config struct which provides the request timeout and the number
+/// of retries for the upstream requests.
+/// * `safe_search` - Accepts the safe search level to be used for the upstream requests.
+/// * `semaphore` - Accepts the semaphore which bounds the number of concurrent upstream requests.
+/// * `breaker` - Accepts the circuit breaker which skips the upstream search engines which keep
+/// failing.
+#[allow(clippy::too_many_arguments)]
+async fn aggregate_videos(
+    query: &str,
+    page: u32,
+    headers: &UpstreamHeaders,
+    client: &Client,
+    upstream_search_engines: &[EngineHandler],
+    config: &Config,
+    safe_search: u8,
+    semaphore: &Arc<Semaphore>,
+    breaker: &CircuitBreaker,
+) -> SearchResults {
+    let request_timeout: u8 = config.request_timeout;
+    let started: Instant = Instant::now();
+    let request_id: &str = &headers.request_id;
+    let headers: UpstreamHeaders = headers.clone();
+    let client: Client = client.clone();
+
+    let (videos, engine_errors_info, engine_timings) = fetch_from_engines(
+        query,
+        upstream_search_engines,
+        safe_search,
+        config,
+        semaphore,
+        breaker,
+        request_id,
+        move |search_engine, query, safe_search| {
+            let headers: UpstreamHeaders = headers.clone();
+            let client: Client = client.clone();
+            async move {
+                search_engine
+                    .videos(
+                        &query,
+                        page,
+                        &headers,
+                        &client,
+                        request_timeout,
+                        safe_search,
+                    )
+                    .await
+            }
+        },
+    )
+    .await;
+
+    let mut video_map: HashMap<String, VideoResult> = HashMap::new();
+    videos
+        .into_iter()
+        .filter(|video| is_safe_url(&video.url))
+        .for_each(|mut video| {
+            video.title = html_to_text(&video.title);
+            video.duration = html_to_text(&video.duration);
+            video.source = html_to_text(&video.source);
+            if config.aggregator.proxy_thumbnails && !video.thumbnail.is_empty() {
+                video.thumbnail = thumbnail_proxy_url(&video.thumbnail);
             }
+            video_map.entry(video.url.clone()).or_insert(video);
+        });
+
+    let mut results = SearchResults::new(Vec::new(), query, &engine_errors_info);
+    results.set_videos(video_map.into_values().collect());
+    if config.debug {
+        results.set_timings(AggregationTimings::new(engine_timings, started.elapsed()));
+    }
+    results
+}
+
+/// The function aggregates the news results from the user-selected upstream search engines which
+/// are news capable. Duplicate news provided by multiple engines are removed and the remaining
+/// news are sorted by their freshness (most recently published first). The errors returned by the
+/// engines are placed in the returned `SearchResults` struct.
+///
+/// # Arguments
+///
+/// * `query` - Accepts a string to query with the above upstream search engines.
+/// * `page` - Accepts an u32 page number.
+/// * `headers` - Accepts the headers (like the user agent) to be sent to the upstream search engines.
+/// * `client` - Accepts the http client shared between all the upstream requests.
+/// * `upstream_search_engines` - Accepts a vector of search engine names which was selected by the
+/// user through the UI or the config file.
+/// * `config` - Accepts the parsed config struct which provides the request timeout and the number
+/// of retries for the upstream requests.
+/// * `safe_search` - Accepts the safe search level to be used for the upstream requests.
+/// * `semaphore` - Accepts the semaphore which bounds the number of concurrent upstream requests.
+/// * `breaker` - Accepts the circuit breaker which skips the upstream search engines which keep
+/// failing.
+#[allow(clippy::too_many_arguments)]
+async fn aggregate_news(
+    query: &str,
+    page: u32,
+    headers: &UpstreamHeaders,
+    client: &Client,
+    upstream_search_engines: &[EngineHandler],
+    config: &Config,
+    safe_search: u8,
+    semaphore: &Arc<Semaphore>,
+    breaker: &CircuitBreaker,
+) -> SearchResults {
+    let request_timeout: u8 = config.request_timeout;
+    let started: Instant = Instant::now();
+    let request_id: &str = &headers.request_id;
+    let headers: UpstreamHeaders = headers.clone();
+    let client: Client = client.clone();
+
+    let (news, engine_errors_info, engine_timings) = fetch_from_engines(
+        query,
+        upstream_search_engines,
+        safe_search,
+        config,
+        semaphore,
+        breaker,
+        request_id,
+        move |search_engine, query, safe_search| {
+            let headers: UpstreamHeaders = headers.clone();
+            let client: Client = client.clone();
+            async move {
+                search_engine
+                    .news(
+                        &query,
+                        page,
+                        &headers,
+                        &client,
+                        request_timeout,
+                        safe_search,
+                    )
+                    .await
+            }
+        },
+    )
+    .await;
+
+    let mut news_map: HashMap<String, NewsResult> = HashMap::new();
+    news.into_iter()
+        .filter(|news_result| is_safe_url(&news_result.url))
+        .for_each(|mut news_result| {
+            news_result.title = html_to_text(&news_result.title);
+            news_result.description = html_to_text(&news_result.description);
+            news_result.source = html_to_text(&news_result.source);
+            news_map
+                .entry(news_result.url.clone())
+                .or_insert(news_result);
+        });
+
+    let mut news: Vec<NewsResult> = news_map.into_values().collect();
+    sort_by_freshness(&mut news);
+
+    let mut results = SearchResults::new(Vec::new(), query, &engine_errors_info);
+    results.set_news(news);
+    if config.debug {
+        results.set_timings(AggregationTimings::new(engine_timings, started.elapsed()));
+    }
+    results
+}
+
+/// A helper function which splits the suggestions scraped from the responses of the upstream
+/// search engines into the merged related searches and the spelling correction of the query.
+///
+/// # Arguments
+///
+/// * `suggestions` - It takes the suggestions of all the upstream search engines.
+/// * `query` - It takes the search query the suggestions were made for.
+///
+/// # Returns
+///
+/// Returns the merged related searches along with the spelling correction of the query if any.
+fn split_suggestions(suggestions: Vec<Suggestion>, query: &str) -> (Vec<String>, Option<String>) {
+    let (related, corrections): (Vec<String>, Vec<String>) = suggestions.into_iter().fold(
+        (Vec::new(), Vec::new()),
+        |(mut related, mut corrections), suggestion| {
+            match suggestion {
+                Suggestion::Related(suggestion) => related.push(suggestion),
+                Suggestion::Correction(correction) => corrections.push(correction),
+            }
+            (related, corrections)
+        },
+    );
+
+    (
+        merge_suggestions(related, query),
+        spelling_correction(corrections, query),
+    )
+}
+
+/// A helper function which picks the spelling correction of the search query among the
+/// corrections suggested by the upstream search engines, which is the first correction that
+/// differs from the query (ignoring the case).
+///
+/// # Arguments
+///
+/// * `corrections` - It takes the corrections of all the upstream search engines.
+/// * `query` - It takes the search query the corrections were made for.
+fn spelling_correction(corrections: Vec<String>, query: &str) -> Option<String> {
+    let query: String = query.trim().to_lowercase();
+
+    corrections
+        .into_iter()
+        .map(|correction| correction.trim().to_owned())
+        .find(|correction| !correction.is_empty() && correction.to_lowercase() != query)
+}
+
+/// A helper function which merges the related searches suggested by all the upstream search
+/// engines by removing the duplicates (ignoring the case) and the search query itself while
+/// keeping the order in which they were suggested.
+///
+/// # Arguments
+///
+/// * `suggestions` - It takes the suggestions of all the upstream search engines.
+/// * `query` - It takes the search query the suggestions were made for.
+fn merge_suggestions(suggestions: Vec<String>, query: &str) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::from([query.trim().to_lowercase()]);
+
+    suggestions
+        .into_iter()
+        .filter(|suggestion| seen.insert(suggestion.trim().to_lowercase()))
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// A helper function which concurrently fetches a list of results of any kind from the provided
+/// upstream search engines while respecting the shared semaphore and collects the results along
+/// with the errors returned by the engines and the time taken by each of the engines.
+///
+/// # Arguments
+///
+/// * `query` - Accepts a string to query with the above upstream search engines.
+/// * `upstream_search_engines` - Accepts a vector of search engine names which was selected by the
+/// user through the UI or the config file.
+/// * `safe_search` - Accepts the safe search level to be translated for each upstream engine.
+/// * `config` - Accepts the parsed config struct which provides the request timeout and the number
+/// of retries for the upstream requests.
+/// * `semaphore` - Accepts the semaphore which bounds the number of concurrent upstream requests.
+/// * `breaker` - Accepts the circuit breaker which skips the upstream search engines which keep
+/// failing, the skipped engines are noted as temporarily disabled in the returned errors.
+/// * `request_id` - Accepts the id of the search request which is included in the log lines.
+/// * `fetch` - Accepts a closure which produces the future fetching the results from an engine.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_from_engines<T, F, Fut>(
+    query: &str,
+    upstream_search_engines: &[EngineHandler],
+    safe_search: u8,
+    config: &Config,
+    semaphore: &Arc<Semaphore>,
+    breaker: &CircuitBreaker,
+    request_id: &str,
+    fetch: F,
+) -> (Vec<T>, Vec<EngineErrorInfo>, Vec<EngineTiming>)
+where
+    T: Send + 'static,
+    F: Fn(Arc<dyn SearchEngine>, String, EngineSafeParam) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<Vec<T>, Report<EngineError>>> + Send + 'static,
+{
+    let request_timeout: u8 = config.request_timeout;
+    let engine_retries: u8 = config.aggregator.engine_retries;
+    let delay: RequestDelay = config.aggregator.delay;
+
+    let (available_engines, mut engine_errors_info): (Vec<EngineHandler>, Vec<EngineErrorInfo>) =
+        available_engines(upstream_search_engines, breaker, &config.aggregator);
+
+    let mut names: Vec<&'static str> = Vec::with_capacity(available_engines.len());
+
+    // create tasks for upstream result fetching
+    let mut tasks: ListFutureVec<T> = ListFutureVec::new();
+
+    for engine_handler in &available_engines {
+        let (name, search_engine) = engine_handler
+            .to_owned()
+            .with_base_url(config.aggregator.engine_base_url(engine_handler.name()))
+            .into_name_engine();
+        names.push(name);
+        let semaphore: Arc<Semaphore> = semaphore.clone();
+        let safe_search: EngineSafeParam = search_engine.map_safe_search(safe_search);
+        let query: String = search_engine.transform_query(query);
+        let search_engine: Arc<dyn SearchEngine> = Arc::from(search_engine);
+        let request_id: String = request_id.to_owned();
+        let fetch: F = fetch.clone();
+        tasks.push(tokio::spawn(async move {
+            timed_fetch(
+                delay,
+                semaphore,
+                engine_retries,
+                request_timeout,
+                &request_id,
+                name,
+                || fetch(search_engine.clone(), query.clone(), safe_search),
+            )
+            .await
+        }));
+    }
+
+    let mut results: Vec<T> = Vec::new();
+    let mut engine_timings: Vec<EngineTiming> = Vec::new();
+
+    for (engine, response, elapsed) in
+        collect_responses(names, tasks, config.aggregator.strategy, request_id).await
+    {
+        engine_timings.push(EngineTiming::new(engine, elapsed));
+        record_response(breaker, engine, &response, config, request_id);
+        match response {
+            Ok(engine_results) => results.extend(engine_results),
             Err(error) => {
-                handle_error(&error, engine);
+                log::error!("[{request_id}] Engine Error: {:?}", error);
+                engine_errors_info.push(EngineErrorInfo::from_report(&error, engine, config.debug));
             }
         }
     }
 
-    if safe_search >= 3 {
-        let mut blacklist_map: HashMap<String, SearchResult> = HashMap::new();
-        filter_with_lists(
-            &mut result_map,
-            &mut blacklist_map,
-            file_path(FileType::BlockList)?,
-        )?;
+    (results, engine_errors_info, engine_timings)
+}
+
+/// A helper function which collects the responses of the tasks fetching the results from the
+/// upstream search engines according to the aggregation strategy. With the `first_n` strategy
+/// the collection stops as soon as the given number of engines have provided their results or
+/// the deadline has passed, the remaining tasks are cancelled and their results are dropped.
+///
+/// # Arguments
+///
+/// * `names` - Accepts the names of the upstream search engines in the same order as the tasks.
+/// * `tasks` - Accepts the spawned tasks fetching the results from the upstream search engines.
+/// * `strategy` - Accepts the aggregation strategy from the config.
+/// * `request_id` - Accepts the id of the search request which is included in the log lines.
+///
+/// # Returns
+///
+/// Returns the name of the engine, its response and the time taken by it for every collected
+/// response.
+async fn collect_responses<T: Send + 'static>(
+    names: Vec<&'static str>,
+    tasks: ResponseFutureVec<T>,
+    strategy: AggregationStrategy,
+    request_id: &str,
+) -> Vec<(&'static str, Result<T, Report<EngineError>>, Duration)> {
+    let mut responses = Vec::with_capacity(tasks.len());
+
+    let (count, deadline): (usize, Duration) = match strategy {
+        AggregationStrategy::WaitAll => {
+            for (task, engine) in tasks.into_iter().zip(names) {
+                if let Ok((response, elapsed)) = task.await {
+                    responses.push((engine, response, elapsed));
+                }
+            }
+            return responses;
+        }
+        AggregationStrategy::FirstN { count, deadline } => (count, deadline),
+    };
 
-        filter_with_lists(
-            &mut blacklist_map,
-            &mut result_map,
-            file_path(FileType::AllowList)?,
-        )?;
+    let abort_handles: Vec<AbortHandle> = tasks.iter().map(JoinHandle::abort_handle).collect();
+    let deadline: tokio::time::Instant = tokio::time::Instant::now() + deadline;
 
-        drop(blacklist_map);
+    // the responses are collected in the order in which the engines complete.
+    let mut pending: FuturesUnordered<_> = tasks
+        .into_iter()
+        .zip(names)
+        .map(|(task, engine)| async move { (engine, task.await) })
+        .collect();
+
+    let mut succeeded: usize = 0;
+    while succeeded < count {
+        match tokio::time::timeout_at(deadline, pending.next()).await {
+            Ok(Some((engine, Ok((response, elapsed))))) => {
+                if response.is_ok() {
+                    succeeded += 1;
+                }
+                responses.push((engine, response, elapsed));
+            }
+            Ok(Some((_, Err(_)))) => continue,
+            Ok(None) | Err(_) => break,
+        }
     }
 
-    let results: Vec<SearchResult> = result_map.into_values().collect();
+    if !pending.is_empty() {
+        log::debug!(
+            "[{request_id}] Dropping the results of {} late engines",
+            pending.len()
+        );
+    }
+    abort_handles.iter().for_each(AbortHandle::abort);
 
-    Ok(SearchResults::new(results, query, &engine_errors_info))
+    responses
 }
 
-/// Filters a map of search results using a list of regex patterns.
+/// A helper function which waits for the random delay and a permit from the shared semaphore and
+/// then runs the provided upstream request (retrying it on transient errors) while measuring the
+/// time taken by the request.
 ///
 /// # Arguments
 ///
-/// * `map_to_be_filtered` - A mutable reference to a `HashMap` of search results to filter, where the filtered results will be removed from.
-/// * `resultant_map` - A mutable reference to a `HashMap` to hold the filtered results.
-/// * `file_path` - A `&str` representing the path to a file containing regex patterns to use for filtering.
+/// * `delay` - Accepts the range of the random delay applied before the request.
+/// * `semaphore` - Accepts the semaphore which bounds the number of concurrent upstream requests.
+/// * `retries` - Accepts the maximum number of times the request should be retried.
+/// * `request_timeout` - Accepts a time (secs) which bounds all the attempts together.
+/// * `request_id` - Accepts the id of the search request which is included in the log lines.
+/// * `engine` - Accepts the name of the upstream search engine which is included in the log lines.
+/// * `request` - Accepts a closure which produces the future making the upstream request.
 ///
-/// # Errors
+/// # Returns
 ///
-/// Returns an error if the file at `file_path` cannot be opened or read, or if a regex pattern is invalid.
-pub fn filter_with_lists(
-    map_to_be_filtered: &mut HashMap<String, SearchResult>,
-    resultant_map: &mut HashMap<String, SearchResult>,
-    file_path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut reader = BufReader::new(File::open(file_path)?);
+/// Returns the result of the request along with the time taken by it (excluding the time spent
+/// waiting for the delay and the permit).
+async fn timed_fetch<T, F, Fut>(
+    delay: RequestDelay,
+    semaphore: Arc<Semaphore>,
+    retries: u8,
+    request_timeout: u8,
+    request_id: &str,
+    engine: &str,
+    request: F,
+) -> (Result<T, Report<EngineError>>, Duration)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, Report<EngineError>>>,
+{
+    // the delay is waited for before acquiring the permit so that no permit is held meanwhile.
+    tokio::time::sleep(random_delay(delay)).await;
 
-    for line in reader.by_ref().lines() {
-        let re = Regex::new(line?.trim())?;
+    match semaphore.acquire_owned().await {
+        Ok(_permit) => {
+            let started: Instant = Instant::now();
+            log::debug!("[{request_id}] Fetching the results from the `{engine}` engine");
+            let response = with_retries(retries, request_timeout, request_id, request).await;
+            log::debug!(
+                "[{request_id}] The `{engine}` engine responded in {:?}",
+                started.elapsed()
+            );
+            (response, started.elapsed())
+        }
+        Err(error) => (
+            Err(Report::new(error).change_context(EngineError::UnexpectedError)),
+            Duration::ZERO,
+        ),
+    }
+}
 
-        // Iterate over each search result in the map and check if it matches the regex pattern
-        for (url, search_result) in map_to_be_filtered.clone().into_iter() {
-            if re.is_match(&url.to_lowercase())
-                || re.is_match(&search_result.title.to_lowercase())
-                || re.is_match(&search_result.description.to_lowercase())
-            {
-                // If the search result matches the regex pattern, move it from the original map to the resultant map
-                resultant_map.insert(
-                    url.to_owned(),
-                    map_to_be_filtered.remove(&url.to_owned()).unwrap(),
-                );
+/// A helper function which runs the provided upstream request and retries it with a short
+/// exponential backoff when it fails with a transient error (a network error or a server error
+/// response). Other errors, like the errors which occur while parsing the fetched page, are
+/// returned right away. All the attempts together are bounded by the request timeout.
+///
+/// # Arguments
+///
+/// * `retries` - Accepts the maximum number of times the request should be retried.
+/// * `request_timeout` - Accepts a time (secs) which bounds all the attempts together.
+/// * `request_id` - Accepts the id of the search request which is included in the log lines.
+/// * `request` - Accepts a closure which produces the future making the upstream request.
+///
+/// # Error
+///
+/// Returns the error of the last attempt if all the attempts fail or a `RequestError` if the
+/// attempts did not complete within the request timeout.
+async fn with_retries<T, F, Fut>(
+    retries: u8,
+    request_timeout: u8,
+    request_id: &str,
+    request: F,
+) -> Result<T, Report<EngineError>>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, Report<EngineError>>>,
+{
+    let attempts = async {
+        let mut attempt: u8 = 0;
+        loop {
+            match request().await {
+                Err(error)
+                    if attempt < retries
+                        && matches!(error.current_context(), EngineError::RequestError) =>
+                {
+                    attempt += 1;
+                    log::debug!(
+                        "[{request_id}] Retrying the upstream request (attempt {attempt}): {error:?}"
+                    );
+                    tokio::time::sleep(retry_delay(attempt)).await;
+                }
+                response => return response,
             }
         }
+    };
+
+    tokio::time::timeout(Duration::from_secs(request_timeout as u64), attempts)
+        .await
+        .map_err(|_| {
+            Report::new(EngineError::RequestError)
+                .attach(EngineErrorKind::Timeout)
+                .attach_printable(
+                    "the upstream request did not complete within the request timeout",
+                )
+        })?
+}
+
+/// A helper function which computes the delay before retrying an upstream request, doubling it
+/// for every retry starting from 100 milliseconds.
+///
+/// # Arguments
+///
+/// * `attempt` - Accepts the number of the retry (starting from 1) for which the delay is computed.
+fn retry_delay(attempt: u8) -> Duration {
+    Duration::from_millis(100 * 2u64.pow(attempt.saturating_sub(1).min(10) as u32))
+}
+
+/// A helper function which sorts the news results by their freshness so that the most recently
+/// published news come first.
+///
+/// # Arguments
+///
+/// * `news` - Accepts a mutable reference to the news results to be sorted.
+pub fn sort_by_freshness(news: &mut [NewsResult]) {
+    news.sort_by_key(|news| Reverse(news.published));
+}
+
+/// A helper function which sorts the general search results, ranked by their relevance, in the
+/// provided order. The results which compare equal (like the results without a publishing date
+/// when sorting by the date) keep the order of their relevance.
+///
+/// # Arguments
+///
+/// * `results` - Accepts a mutable reference to the search results to be sorted.
+/// * `order` - Accepts the order requested in the `sort` parameter of the search url.
+pub fn sort_results(results: &mut [SearchResult], order: SortOrder) {
+    match order {
+        SortOrder::Relevance => (),
+        // the results without a publishing date (`None`) come last.
+        SortOrder::Date => results.sort_by_key(|result| Reverse(result.published)),
+        SortOrder::Alphabetical => results.sort_by_cached_key(|result| result.title.to_lowercase()),
+    }
+}
+
+/// A helper function which scores a merged search result by blending the agreement of the upstream
+/// search engines which returned it with its positions in their results. Every engine which
+/// returned the result contributes its configured weight multiplied by the sum of the
+/// `agreement_weight` and the `position_weight` scaled by the reciprocal of the position of the
+/// result in the results of the engine (`1` for the top result, `0.5` for the second one and so
+/// on). So a result returned by more engines ranks higher, the top hits of an engine bubble up
+/// and a result returned by a heavily weighted engine can outrank one returned by several lightly
+/// weighted engines. An engine which does not keep the order of its results only contributes the
+/// agreement part.
+///
+/// # Arguments
+///
+/// * `result` - It takes the merged search result to be scored.
+/// * `config` - It takes the aggregator config which provides the weights of the engines and the
+/// ranking weights.
+fn score_result(result: &SearchResult, config: &AggregatorConfig) -> f64 {
+    result
+        .engine
+        .iter()
+        .map(|engine| {
+            let reciprocal_rank: f64 = result
+                .positions
+                .get(engine.as_str())
+                .map_or(0.0, |position| 1.0 / (*position).max(1) as f64);
+            config.engine_weight(engine)
+                * (config.agreement_weight + config.position_weight * reciprocal_rank)
+        })
+        .sum()
+}
+
+/// Merges the results of the upstream search engines into a single map, removing the duplicate
+/// results and keeping track of all the engines which returned them. The titles and descriptions
+/// are sanitized into plain text and the results which do not link to an `http` or `https` url are
+/// dropped, so that the markup scraped from an engine can never be executed in the browser.
+///
+/// # Arguments
+///
+/// * `engine_results` - It takes the results of the upstream search engines along with their names.
+/// * `config` - It takes the aggregator config which provides the maximum number of results to be
+/// taken from every upstream search engine.
+///
+/// # Returns
+///
+/// Returns the merged search results mapped by their urls. The results of every engine are
+/// truncated to its limit before merging so that an engine which does not respect the limit can not
+/// outweigh the others, the results ranked highest by the engine are kept.
+fn merge_results(
+    engine_results: EngineResults,
+    config: &AggregatorConfig,
+) -> HashMap<String, SearchResult> {
+    let mut result_map: HashMap<String, SearchResult> = HashMap::new();
+
+    for (engine, results) in engine_results {
+        let mut results: Vec<(String, SearchResult)> = results.into_iter().collect();
+        // the results without a position come last.
+        results
+            .sort_by_key(|(_, result)| result.positions.get(engine).copied().unwrap_or(usize::MAX));
+
+        results
+            .into_iter()
+            .filter(|(_, result)| is_safe_url(&result.url))
+            .filter(|(_, result)| !(config.hide_sponsored && result.sponsored))
+            .take(config.results_limit(engine))
+            .for_each(|(mut key, mut value)| {
+                value.title = html_to_text(&value.title);
+                value.description = html_to_text(&value.description);
+                // the cleaned url is also used as the key so that the results which only differ
+                // in their tracking parameters are merged.
+                if config.clean_urls {
+                    value.url = strip_tracking_parameters(&value.url);
+                    key = value.url.clone();
+                }
+                // an engine requested for multiple regions is only counted once.
+                result_map
+                    .entry(key)
+                    .and_modify(|result| {
+                        if !result.engine.iter().any(|name| name == engine) {
+                            result.add_engines(engine);
+                        }
+                        value
+                            .regions
+                            .iter()
+                            .for_each(|region| result.add_region(region));
+                        if result.published.is_none() {
+                            result.set_published(value.published);
+                        }
+                        result.add_positions(&value.positions);
+                        // a result is only sponsored if none of the engines provided it as an
+                        // organic result.
+                        result.sponsored &= value.sponsored;
+                    })
+                    .or_insert_with(|| -> SearchResult { value });
+            });
+    }
+
+    result_map
+}
+
+/// A helper function which spawns the tasks fetching the search results from the provided upstream
+/// search engines.
+///
+/// # Arguments
+///
+/// * `query` - Accepts a string to query with the upstream search engines.
+/// * `page` - Accepts an u32 page number.
+/// * `config` - Accepts the parsed config struct which provides the request timeout, the number
+/// of retries and the results limits.
+/// * `upstream_search_engines` - Accepts the upstream search engines to fetch the results from.
+/// * `safe_search` - Accepts the safe search level to be used for the upstream requests.
+/// * `headers` - Accepts the headers (like the user agent) to be sent to the upstream search engines.
+/// * `client` - Accepts the http client shared between all the upstream requests.
+/// * `semaphore` - Accepts the semaphore which bounds the number of concurrent upstream requests.
+///
+/// # Returns
+///
+/// Returns the names of the upstream search engines along with the spawned tasks in the same
+/// order.
+#[allow(clippy::too_many_arguments)]
+fn spawn_results_tasks(
+    query: &str,
+    page: u32,
+    config: &Config,
+    upstream_search_engines: &[EngineHandler],
+    safe_search: u8,
+    headers: &UpstreamHeaders,
+    client: &Client,
+    semaphore: &Arc<Semaphore>,
+) -> (Vec<&'static str>, FutureVec) {
+    let request_timeout: u8 = config.request_timeout;
+    let engine_retries: u8 = config.aggregator.engine_retries;
+    let delay: RequestDelay = config.aggregator.delay;
+
+    let mut names: Vec<&'static str> = Vec::with_capacity(upstream_search_engines.len());
+    let mut tasks: FutureVec = FutureVec::with_capacity(upstream_search_engines.len());
+
+    for engine_handler in upstream_search_engines {
+        let (name, search_engine) = engine_handler
+            .to_owned()
+            .with_base_url(config.aggregator.engine_base_url(engine_handler.name()))
+            .into_name_engine();
+        names.push(name);
+        let query: String = search_engine.transform_query(query);
+        let semaphore: Arc<Semaphore> = semaphore.clone();
+        // translate the unified safe search level into the engine specific value.
+        let safe_search: EngineSafeParam = search_engine.map_safe_search(safe_search);
+        let headers: UpstreamHeaders = headers.clone();
+        let client: Client = client.clone();
+        let max_results: usize = config.aggregator.results_limit(name);
+        tasks.push(tokio::spawn(async move {
+            timed_fetch(
+                delay,
+                semaphore,
+                engine_retries,
+                request_timeout,
+                &headers.request_id,
+                name,
+                || {
+                    search_engine.results_with_suggestions(
+                        &query,
+                        page,
+                        &headers,
+                        &client,
+                        request_timeout,
+                        safe_search,
+                        max_results,
+                    )
+                },
+            )
+            .await
+        }));
+    }
+
+    (names, tasks)
+}
+
+/// A helper function which splits the provided upstream search engines into the engines which
+/// are available and the engines which are temporarily disabled by the circuit breaker.
+///
+/// # Arguments
+///
+/// * `upstream_search_engines` - It takes the upstream search engines selected for the search.
+/// * `breaker` - It takes the circuit breaker which skips the engines which keep failing.
+/// * `config` - It takes the aggregator config which provides the thresholds of the breaker.
+///
+/// # Returns
+///
+/// Returns the available engines along with the errors noting the disabled engines.
+fn available_engines(
+    upstream_search_engines: &[EngineHandler],
+    breaker: &CircuitBreaker,
+    config: &AggregatorConfig,
+) -> (Vec<EngineHandler>, Vec<EngineErrorInfo>) {
+    let (available, disabled): (Vec<&EngineHandler>, Vec<&EngineHandler>) = upstream_search_engines
+        .iter()
+        .partition(|engine| !breaker.is_open(engine.name(), &config.circuit_breaker));
+
+    (
+        available.into_iter().cloned().collect(),
+        disabled
+            .into_iter()
+            .map(|engine| EngineErrorInfo::temporarily_disabled(engine.name()))
+            .collect(),
+    )
+}
+
+/// A helper function which records the response of an upstream search engine in the circuit
+/// breaker and logs when the engine gets temporarily disabled.
+///
+/// # Arguments
+///
+/// * `breaker` - It takes the circuit breaker which skips the engines which keep failing.
+/// * `engine` - It takes the name of the upstream search engine.
+/// * `response` - It takes the response of the upstream search engine.
+/// * `config` - It takes the parsed config struct.
+/// * `request_id` - It takes the id of the search request which is included in the log lines.
+fn record_response<T>(
+    breaker: &CircuitBreaker,
+    engine: &str,
+    response: &Result<T, Report<EngineError>>,
+    config: &Config,
+    request_id: &str,
+) {
+    if breaker.record(engine, response, &config.aggregator.circuit_breaker) {
+        log::warn!(
+            "[{request_id}] Temporarily disabling the engine `{engine}` for {} secs after repeated failures",
+            config.aggregator.circuit_breaker.cooldown.as_secs()
+        );
+    }
+}
+
+/// A helper function which selects the fallback engines to be used in place of the upstream search
+/// engines which failed and notes the fallback engine in the error info of the failed engine. A
+/// fallback engine is only used once and only if it was not selected itself, so that its results
+/// are never counted twice. A fallback engine which is temporarily disabled by the circuit breaker
+/// is not used.
+///
+/// # Arguments
+///
+/// * `engine_errors_info` - It takes the errors of the upstream search engines which failed.
+/// * `upstream_search_engines` - It takes the upstream search engines selected for the search.
+/// * `config` - It takes the aggregator config which provides the fallback engines.
+/// * `breaker` - It takes the circuit breaker which skips the engines which keep failing.
+///
+/// # Returns
+///
+/// Returns the fallback engines whose results should be fetched.
+fn select_fallbacks(
+    engine_errors_info: &mut [EngineErrorInfo],
+    upstream_search_engines: &[EngineHandler],
+    config: &AggregatorConfig,
+    breaker: &CircuitBreaker,
+) -> Vec<EngineHandler> {
+    let mut fallback_engines: Vec<EngineHandler> = Vec::new();
+
+    for error_info in engine_errors_info.iter_mut() {
+        let fallback: &EngineHandler = match config.engine_fallbacks.get(&error_info.engine) {
+            Some(fallback) => fallback,
+            None => continue,
+        };
+
+        let already_used: bool = upstream_search_engines
+            .iter()
+            .chain(fallback_engines.iter())
+            .any(|engine| engine.name() == fallback.name());
+
+        if !already_used && !breaker.is_open(fallback.name(), &config.circuit_breaker) {
+            error_info.set_fallback(fallback.name());
+            fallback_engines.push(fallback.clone());
+        }
     }
 
-    Ok(())
+    fallback_engines
+}
+
+/// A helper function which truncates the description of a search result to the given number of
+/// characters (not bytes, so that a multibyte character is never split) at the last word
+/// boundary and appends an ellipsis. A description which is short enough is left untouched and a
+/// single word longer than the limit is cut at the limit.
+///
+/// # Arguments
+///
+/// * `description` - It takes the description of the search result.
+/// * `max_length` - It takes the maximum number of characters kept from the description.
+fn truncate_snippet(description: &str, max_length: usize) -> String {
+    let cut: usize = match description.char_indices().nth(max_length) {
+        Some((cut, _)) => cut,
+        None => return description.to_owned(),
+    };
+
+    // the description is cut at the limit when the character after it does not start a new word.
+    let truncated: &str = match description[cut..].starts_with(char::is_whitespace) {
+        true => &description[..cut],
+        false => description[..cut]
+            .rfind(char::is_whitespace)
+            .map_or(&description[..cut], |boundary| &description[..boundary]),
+    };
+
+    format!("{}…", truncated.trim_end())
+}
+
+/// A helper function which ranks the merged search results by their scores (see `score_result`).
+/// Only the `max_total_results` highest scored results are kept when the option is set.
+///
+/// # Arguments
+///
+/// * `result_map` - It takes the merged search results mapped by their urls.
+/// * `config` - It takes the aggregator config which provides the weights of the engines, the
+/// ranking weights and the maximum number of results.
+///
+/// # Returns
+///
+/// Returns the search results sorted by their scores (highest first).
+fn rank_results(
+    result_map: HashMap<String, SearchResult>,
+    config: &AggregatorConfig,
+) -> Vec<SearchResult> {
+    let mut scored_results: Vec<(f64, SearchResult)> = result_map
+        .into_values()
+        .map(|result| (score_result(&result, config), result))
+        .collect();
+
+    scored_results.sort_by(|(score, _), (other_score, _)| other_score.total_cmp(score));
+    if config.max_total_results > 0 {
+        scored_results.truncate(config.max_total_results);
+    }
+    scored_results
+        .into_iter()
+        .map(|(_, result)| result)
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use smallvec::smallvec;
+    use crate::models::parser_models::CircuitBreakerConfig;
     use std::collections::HashMap;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
 
     #[test]
-    fn test_filter_with_lists() -> Result<(), Box<dyn std::error::Error>> {
-        // Create a map of search results to filter
-        let mut map_to_be_filtered = HashMap::new();
-        map_to_be_filtered.insert(
-            "https://www.example.com".to_owned(),
-            SearchResult {
-                title: "Example Domain".to_owned(),
-                url: "https://www.example.com".to_owned(),
-                description: "This domain is for use in illustrative examples in documents."
-                    .to_owned(),
-                engine: smallvec!["Google".to_owned(), "Bing".to_owned()],
+    fn test_sort_by_freshness() {
+        let mut news: Vec<NewsResult> = vec![
+            NewsResult::new("Old", "https://www.example.com/old", "", "searx", 100),
+            NewsResult::new("Newest", "https://www.example.com/newest", "", "searx", 300),
+            NewsResult::new("Recent", "https://www.example.com/recent", "", "searx", 200),
+        ];
+
+        sort_by_freshness(&mut news);
+
+        let titles: Vec<&str> = news.iter().map(|news| news.title.as_str()).collect();
+        assert_eq!(titles, vec!["Newest", "Recent", "Old"]);
+    }
+
+    #[test]
+    fn test_sort_results() {
+        let result = |title: &str, published: Option<i64>| -> SearchResult {
+            let mut result: SearchResult = SearchResult::new(
+                title,
+                &format!("https://www.example.com/{title}"),
+                "",
+                &["searx"],
+            );
+            result.set_published(published);
+            result
+        };
+        let titles = |results: &[SearchResult]| -> Vec<String> {
+            results.iter().map(|result| result.title.clone()).collect()
+        };
+        let ranked: Vec<SearchResult> = vec![
+            result("beta", None),
+            result("Gamma", Some(100)),
+            result("alpha", None),
+            result("delta", Some(300)),
+        ];
+
+        let mut results: Vec<SearchResult> = ranked.clone();
+        sort_results(&mut results, SortOrder::Relevance);
+        assert_eq!(titles(&results), titles(&ranked));
+
+        sort_results(&mut results, SortOrder::Date);
+        assert_eq!(titles(&results), vec!["delta", "Gamma", "beta", "alpha"]);
+
+        let mut results: Vec<SearchResult> = ranked;
+        sort_results(&mut results, SortOrder::Alphabetical);
+        assert_eq!(titles(&results), vec!["alpha", "beta", "delta", "Gamma"]);
+    }
+
+    #[test]
+    fn test_random_delay_stays_within_the_configured_range() {
+        assert_eq!(random_delay(RequestDelay::disabled()), Duration::ZERO);
+
+        let delay: RequestDelay = RequestDelay {
+            min: Duration::from_millis(100),
+            max: Duration::from_millis(300),
+        };
+        for _ in 0..100 {
+            let picked: Duration = random_delay(delay);
+            assert!(delay.min <= picked && picked <= delay.max);
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_for_every_retry() {
+        assert_eq!(retry_delay(1), Duration::from_millis(100));
+        assert_eq!(retry_delay(2), Duration::from_millis(200));
+        assert_eq!(retry_delay(3), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_with_retries_retries_only_transient_errors() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let counter: AtomicUsize = AtomicUsize::new(0);
+        let attempts: &AtomicUsize = &counter;
+        let result: Result<(), Report<EngineError>> = with_retries(1, 5, "test", || async move {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(Report::new(EngineError::RequestError)),
+                _ => Ok(()),
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        let counter: AtomicUsize = AtomicUsize::new(0);
+        let attempts: &AtomicUsize = &counter;
+        let result: Result<(), Report<EngineError>> = with_retries(3, 5, "test", || async move {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(Report::new(EngineError::UnexpectedError))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    /// A helper function which spawns a task mimicking an upstream search engine which responds
+    /// after the given latency (millis) with either a result or an error.
+    fn mock_engine(
+        latency: u64,
+        succeeds: bool,
+    ) -> JoinHandle<(Result<Vec<u64>, Report<EngineError>>, Duration)> {
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(latency)).await;
+            let response = match succeeds {
+                true => Ok(vec![latency]),
+                false => Err(Report::new(EngineError::RequestError)),
+            };
+            (response, Duration::from_millis(latency))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_wait_all_strategy_collects_every_engine() {
+        let responses = collect_responses(
+            vec!["fast", "failing", "slow"],
+            vec![
+                mock_engine(10, true),
+                mock_engine(20, false),
+                mock_engine(80, true),
+            ],
+            AggregationStrategy::WaitAll,
+            "test",
+        )
+        .await;
+
+        let engines: Vec<&str> = responses.iter().map(|(engine, _, _)| *engine).collect();
+        assert_eq!(engines, ["fast", "failing", "slow"]);
+    }
+
+    #[tokio::test]
+    async fn test_first_n_strategy_returns_once_enough_engines_succeed() {
+        let started: Instant = Instant::now();
+        let responses = collect_responses(
+            vec!["slow", "failing", "fast", "medium"],
+            vec![
+                mock_engine(2000, true),
+                mock_engine(10, false),
+                mock_engine(20, true),
+                mock_engine(60, true),
+            ],
+            AggregationStrategy::FirstN {
+                count: 2,
+                deadline: Duration::from_secs(5),
             },
-        );
-        map_to_be_filtered.insert(
-            "https://www.rust-lang.org/".to_owned(),
-            SearchResult {
-                title: "Rust Programming Language".to_owned(),
-                url: "https://www.rust-lang.org/".to_owned(),
-                description: "A systems programming language that runs blazingly fast, prevents segfaults, and guarantees thread safety.".to_owned(),
-                engine: smallvec!["Google".to_owned(), "DuckDuckGo".to_owned()],
+            "test",
+        )
+        .await;
+
+        // the errors returned before enough engines succeeded are kept while the slow engine
+        // is dropped.
+        let engines: Vec<&str> = responses.iter().map(|(engine, _, _)| *engine).collect();
+        assert_eq!(engines, ["failing", "fast", "medium"]);
+        assert!(started.elapsed() < Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn test_first_n_strategy_returns_at_the_deadline() {
+        let started: Instant = Instant::now();
+        let responses = collect_responses(
+            vec!["fast", "slow", "slower"],
+            vec![
+                mock_engine(10, true),
+                mock_engine(2000, true),
+                mock_engine(3000, true),
+            ],
+            AggregationStrategy::FirstN {
+                count: 2,
+                deadline: Duration::from_millis(100),
             },
+            "test",
+        )
+        .await;
+
+        let engines: Vec<&str> = responses.iter().map(|(engine, _, _)| *engine).collect();
+        assert_eq!(engines, ["fast"]);
+        assert!(started.elapsed() < Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn test_results_of_too_few_engines_are_degraded() {
+        let tasks: FutureVec = [("duckduckgo", true), ("searx", false), ("brave", false)]
+            .into_iter()
+            .map(|(engine, succeeds)| {
+                tokio::spawn(async move {
+                    let response = match succeeds {
+                        true => Ok((engine_results(engine, 3), Vec::new())),
+                        false => Err(Report::new(EngineError::RequestError)),
+                    };
+                    (response, Duration::from_millis(10))
+                })
+            })
+            .collect();
+        let responses = collect_responses(
+            vec!["duckduckgo", "searx", "brave"],
+            tasks,
+            AggregationStrategy::WaitAll,
+            "test",
+        )
+        .await;
+
+        let results: EngineResults = responses
+            .into_iter()
+            .filter_map(|(engine, response, _)| response.ok().map(|(results, _)| (engine, results)))
+            .collect();
+        assert!(is_degraded(&results, 2));
+        assert!(!is_degraded(&results, 1));
+        assert!(!is_degraded(&results, 0));
+    }
+
+    /// A helper function which builds the results of an engine with the given number of results
+    /// having distinct urls.
+    fn engine_results(engine: &str, count: usize) -> HashMap<String, SearchResult> {
+        (0..count)
+            .map(|index| {
+                let url: String = format!("https://{engine}.example.com/{index}");
+                let result: SearchResult =
+                    SearchResult::new("title", &url, "description", &[engine]);
+                (url, result)
+            })
+            .collect()
+    }
+
+    /// A helper function which builds an aggregator config with the given results limits.
+    fn results_limit_config(
+        results_per_engine: usize,
+        results_per_engine_overrides: HashMap<String, usize>,
+    ) -> AggregatorConfig {
+        AggregatorConfig {
+            delay: RequestDelay::disabled(),
+            max_page: 10,
+            max_query_length: 512,
+            max_concurrent_requests: 16,
+            max_engines_per_request: 0,
+            engine_retries: 1,
+            user_agent: None,
+            rotate_user_agent: false,
+            forward_accept_language: false,
+            prefetch_ahead: 0,
+            bangs: HashMap::new(),
+            results_per_engine,
+            results_per_engine_overrides,
+            allow_private_addresses: false,
+            max_response_bytes: 0,
+            probe_engines_on_start: false,
+            probe_engines_strict: false,
+            engine_weights: HashMap::new(),
+            engine_fallbacks: HashMap::new(),
+            engine_base_urls: HashMap::new(),
+            results_per_page: 0,
+            max_total_results: 0,
+            agreement_weight: 1.0,
+            position_weight: 1.0,
+            min_engines_responded: 0,
+            clean_urls: false,
+            hide_sponsored: false,
+            direct_navigation: false,
+            proxy_thumbnails: false,
+            redirect_clicks: false,
+            log_clicks: false,
+            connection_pool_size: 16,
+            connect_timeout: 30,
+            read_timeout: 0,
+            strategy: AggregationStrategy::WaitAll,
+            circuit_breaker: CircuitBreakerConfig::disabled(),
+        }
+    }
+
+    #[test]
+    fn test_merge_results_never_exceeds_results_per_engine() {
+        let engines: [&'static str; 3] = ["duckduckgo", "searx", "startpage"];
+        let config: AggregatorConfig = results_limit_config(10, HashMap::new());
+
+        let merged: HashMap<String, SearchResult> = merge_results(
+            engines
+                .iter()
+                .map(|engine| (*engine, engine_results(engine, 25)))
+                .collect(),
+            &config,
         );
 
-        // Create a temporary file with regex patterns
-        let mut file = NamedTempFile::new()?;
-        writeln!(file, "example")?;
-        writeln!(file, "rust")?;
-        file.flush()?;
+        assert_eq!(merged.len(), 10 * engines.len());
+    }
 
-        let mut resultant_map = HashMap::new();
-        filter_with_lists(
-            &mut map_to_be_filtered,
-            &mut resultant_map,
-            file.path().to_str().unwrap(),
-        )?;
+    #[test]
+    fn test_sponsored_results_are_hidden_or_flagged() {
+        let sponsored = |url: &str, engine: &str| -> (String, SearchResult) {
+            let mut result: SearchResult = SearchResult::new("Ad", url, "", &[engine]);
+            result.set_sponsored(true);
+            (url.to_owned(), result)
+        };
+        let mut duckduckgo: HashMap<String, SearchResult> = engine_results("duckduckgo", 2);
+        duckduckgo.extend([
+            sponsored("https://ads.example.com/", "duckduckgo"),
+            sponsored("https://searx.example.com/0", "duckduckgo"),
+        ]);
+        let engine_results = || -> EngineResults {
+            vec![
+                ("duckduckgo", duckduckgo.clone()),
+                ("searx", engine_results("searx", 1)),
+            ]
+        };
 
-        assert_eq!(resultant_map.len(), 2);
-        assert!(resultant_map.contains_key("https://www.example.com"));
-        assert!(resultant_map.contains_key("https://www.rust-lang.org/"));
-        assert_eq!(map_to_be_filtered.len(), 0);
+        let mut config: AggregatorConfig = results_limit_config(10, HashMap::new());
+        let flagged: HashMap<String, SearchResult> = merge_results(engine_results(), &config);
+        assert_eq!(flagged.len(), 4);
+        assert!(flagged["https://ads.example.com/"].sponsored);
+        // the result provided as an organic result by another engine is not sponsored.
+        assert!(!flagged["https://searx.example.com/0"].sponsored);
 
-        Ok(())
+        config.hide_sponsored = true;
+        let hidden: HashMap<String, SearchResult> = merge_results(engine_results(), &config);
+        assert_eq!(hidden.len(), 3);
+        assert!(hidden.values().all(|result| !result.sponsored));
     }
 
     #[test]
-    fn test_filter_with_lists_wildcard() -> Result<(), Box<dyn std::error::Error>> {
-        let mut map_to_be_filtered = HashMap::new();
-        map_to_be_filtered.insert(
-            "https://www.example.com".to_owned(),
-            SearchResult {
-                title: "Example Domain".to_owned(),
-                url: "https://www.example.com".to_owned(),
-                description: "This domain is for use in illustrative examples in documents."
-                    .to_owned(),
-                engine: smallvec!["Google".to_owned(), "Bing".to_owned()],
-            },
+    fn test_merge_results_respects_engine_overrides() {
+        let config: AggregatorConfig =
+            results_limit_config(10, HashMap::from([("searx".to_owned(), 3)]));
+
+        let merged: HashMap<String, SearchResult> = merge_results(
+            vec![
+                ("duckduckgo", engine_results("duckduckgo", 25)),
+                ("searx", engine_results("searx", 25)),
+            ],
+            &config,
         );
-        map_to_be_filtered.insert(
-            "https://www.rust-lang.org/".to_owned(),
-            SearchResult {
-                title: "Rust Programming Language".to_owned(),
-                url: "https://www.rust-lang.org/".to_owned(),
-                description: "A systems programming language that runs blazingly fast, prevents segfaults, and guarantees thread safety.".to_owned(),
-                engine: smallvec!["Google".to_owned(), "DuckDuckGo".to_owned()],
-            },
+
+        assert!(merged.len() <= 10 + 3);
+        assert_eq!(
+            merged
+                .values()
+                .filter(|result| result.engine.contains(&"searx".to_owned()))
+                .count(),
+            3
         );
+    }
 
-        // Create a temporary file with a regex pattern containing a wildcard
-        let mut file = NamedTempFile::new()?;
-        writeln!(file, "ex.*le")?;
-        file.flush()?;
+    #[test]
+    fn test_merge_results_combines_the_regions_of_an_engine() {
+        let config: AggregatorConfig = results_limit_config(0, HashMap::new());
+        let tagged = |region: &str| -> HashMap<String, SearchResult> {
+            let mut results: HashMap<String, SearchResult> = engine_results("searx", 2);
+            results
+                .values_mut()
+                .for_each(|result| result.add_region(region));
+            results
+        };
 
-        let mut resultant_map = HashMap::new();
+        let merged: HashMap<String, SearchResult> = merge_results(
+            vec![("searx", tagged("us-en")), ("searx", tagged("de-de"))],
+            &config,
+        );
 
-        filter_with_lists(
-            &mut map_to_be_filtered,
-            &mut resultant_map,
-            file.path().to_str().unwrap(),
-        )?;
+        assert_eq!(merged.len(), 2);
+        for result in merged.values() {
+            assert_eq!(result.engine.len(), 1);
+            assert_eq!(result.regions, vec!["us-en".to_owned(), "de-de".to_owned()]);
+        }
+    }
 
-        assert_eq!(resultant_map.len(), 1);
-        assert!(resultant_map.contains_key("https://www.example.com"));
-        assert_eq!(map_to_be_filtered.len(), 1);
-        assert!(map_to_be_filtered.contains_key("https://www.rust-lang.org/"));
+    #[test]
+    fn test_merge_results_neutralizes_injected_markup() {
+        let malicious: SearchResult = SearchResult::new(
+            "Rust<script>alert(document.cookie)</script>",
+            "https://www.rust-lang.org/",
+            "A <b onmouseover=\"alert(1)\">systems</b> programming language &amp; more",
+            &["searx"],
+        );
+        let scripted: SearchResult =
+            SearchResult::new("Click me", "javascript:alert(1)", "", &["searx"]);
 
-        Ok(())
+        let merged: HashMap<String, SearchResult> = merge_results(
+            vec![(
+                "searx",
+                HashMap::from([
+                    (malicious.url.clone(), malicious),
+                    (scripted.url.clone(), scripted),
+                ]),
+            )],
+            &results_limit_config(0, HashMap::new()),
+        );
+
+        assert_eq!(merged.len(), 1);
+        let result: &SearchResult = &merged["https://www.rust-lang.org/"];
+        assert_eq!(result.title, "Rust");
+        assert_eq!(result.description, "A systems programming language & more");
     }
 
     #[test]
-    fn test_filter_with_lists_file_not_found() {
-        let mut map_to_be_filtered = HashMap::new();
+    fn test_results_limit_of_zero_disables_the_limit() {
+        let config: AggregatorConfig =
+            results_limit_config(0, HashMap::from([("searx".to_owned(), 5)]));
 
-        let mut resultant_map = HashMap::new();
+        assert_eq!(config.results_limit("duckduckgo"), usize::MAX);
+        assert_eq!(config.results_limit("searx"), 5);
+    }
 
-        // Call the `filter_with_lists` function with a non-existent file path
-        let result = filter_with_lists(
-            &mut map_to_be_filtered,
-            &mut resultant_map,
-            "non-existent-file.txt",
+    #[test]
+    fn test_truncate_snippet_cuts_at_a_word_boundary() {
+        assert_eq!(
+            truncate_snippet("short description", 17),
+            "short description"
         );
+        assert_eq!(truncate_snippet("short description", 16), "short…");
+        assert_eq!(truncate_snippet("short description", 6), "short…");
+        assert_eq!(truncate_snippet("indivisible", 5), "indiv…");
+        // the characters are counted instead of the bytes so multibyte characters are never split.
+        assert_eq!(truncate_snippet("日本語の説明 です", 4), "日本語の…");
+        assert_eq!(truncate_snippet("café crème brûlée", 12), "café crème…");
+    }
 
-        assert!(result.is_err());
+    #[test]
+    fn test_rank_results_weighs_the_agreement_of_engines() {
+        let mut config: AggregatorConfig = results_limit_config(0, HashMap::new());
+        let ranked_urls = |config: &AggregatorConfig| -> Vec<String> {
+            let result_map: HashMap<String, SearchResult> = [
+                ("https://single.example.com/", vec!["wikipedia"]),
+                (
+                    "https://consensus.example.com/",
+                    vec!["duckduckgo", "searx"],
+                ),
+                ("https://lonely.example.com/", vec!["searx"]),
+            ]
+            .into_iter()
+            .map(|(url, engines)| {
+                let mut result: SearchResult =
+                    SearchResult::new("title", url, "description", &[engines[0]]);
+                engines[1..]
+                    .iter()
+                    .for_each(|engine| result.add_engines(engine));
+                (url.to_owned(), result)
+            })
+            .collect();
+
+            rank_results(result_map, config)
+                .into_iter()
+                .map(|result| result.url)
+                .collect()
+        };
+
+        assert_eq!(ranked_urls(&config)[0], "https://consensus.example.com/");
+
+        config.engine_weights = HashMap::from([("wikipedia".to_owned(), 3.0)]);
+        assert_eq!(
+            ranked_urls(&config),
+            vec![
+                "https://single.example.com/".to_owned(),
+                "https://consensus.example.com/".to_owned(),
+                "https://lonely.example.com/".to_owned(),
+            ]
+        );
+
+        config.max_total_results = 2;
+        assert_eq!(
+            ranked_urls(&config),
+            vec![
+                "https://single.example.com/".to_owned(),
+                "https://consensus.example.com/".to_owned(),
+            ]
+        );
     }
 
     #[test]
-    fn test_filter_with_lists_invalid_regex() {
-        let mut map_to_be_filtered = HashMap::new();
-        map_to_be_filtered.insert(
-            "https://www.example.com".to_owned(),
-            SearchResult {
-                title: "Example Domain".to_owned(),
-                url: "https://www.example.com".to_owned(),
-                description: "This domain is for use in illustrative examples in documents."
-                    .to_owned(),
-                engine: smallvec!["Google".to_owned(), "Bing".to_owned()],
-            },
+    fn test_score_result_blends_the_agreement_and_the_positions() {
+        let mut config: AggregatorConfig = results_limit_config(0, HashMap::new());
+        let result = |positions: &[(&str, usize)]| -> SearchResult {
+            let mut result: SearchResult = SearchResult::new(
+                "title",
+                "https://example.com/",
+                "description",
+                &[positions[0].0],
+            );
+            positions[1..]
+                .iter()
+                .for_each(|(engine, _)| result.add_engines(engine));
+            result.add_positions(
+                &positions
+                    .iter()
+                    .map(|(engine, position)| (engine.to_string(), *position))
+                    .collect(),
+            );
+            result
+        };
+
+        let top_hit: SearchResult = result(&[("duckduckgo", 1)]);
+        let low_hit: SearchResult = result(&[("duckduckgo", 4)]);
+        let consensus: SearchResult = result(&[("duckduckgo", 4), ("searx", 4)]);
+        assert_eq!(score_result(&top_hit, &config), 2.0);
+        assert_eq!(score_result(&low_hit, &config), 1.25);
+        assert_eq!(score_result(&consensus, &config), 2.5);
+        // the results of the engines which do not keep the order of their results only score
+        // their agreement.
+        let unordered: SearchResult =
+            SearchResult::new("title", "https://example.com/", "", &["searx"]);
+        assert_eq!(score_result(&unordered, &config), 1.0);
+
+        config.engine_weights = HashMap::from([("duckduckgo".to_owned(), 2.0)]);
+        assert_eq!(score_result(&top_hit, &config), 4.0);
+        assert_eq!(score_result(&consensus, &config), 3.75);
+
+        // the positions are ignored when their weight is zero.
+        config.position_weight = 0.0;
+        assert_eq!(score_result(&top_hit, &config), 2.0);
+        assert_eq!(score_result(&consensus, &config), 3.0);
+
+        // the agreement is ignored when its weight is zero.
+        config.engine_weights = HashMap::new();
+        config.agreement_weight = 0.0;
+        config.position_weight = 1.0;
+        assert_eq!(score_result(&top_hit, &config), 1.0);
+        assert_eq!(score_result(&consensus, &config), 0.5);
+    }
+
+    #[test]
+    fn test_merge_results_keeps_the_top_results_of_every_engine() {
+        let mut results: HashMap<String, SearchResult> = engine_results("duckduckgo", 5);
+        let mut urls: Vec<String> = results.keys().cloned().collect();
+        urls.sort();
+        for (index, url) in urls.iter().rev().enumerate() {
+            results.get_mut(url).unwrap().set_position(index + 1);
+        }
+
+        let merged: HashMap<String, SearchResult> = merge_results(
+            vec![("duckduckgo", results)],
+            &results_limit_config(2, HashMap::new()),
+        );
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged["https://duckduckgo.example.com/4"].positions["duckduckgo"],
+            1
         );
+        assert_eq!(
+            merged["https://duckduckgo.example.com/3"].positions["duckduckgo"],
+            2
+        );
+    }
+
+    #[test]
+    fn test_select_fallbacks_uses_every_fallback_engine_once() {
+        let mut config: AggregatorConfig = results_limit_config(0, HashMap::new());
+        config.engine_fallbacks = HashMap::from([
+            (
+                "duckduckgo".to_owned(),
+                EngineHandler::new("mojeek").unwrap(),
+            ),
+            (
+                "startpage".to_owned(),
+                EngineHandler::new("mojeek").unwrap(),
+            ),
+            ("wikipedia".to_owned(), EngineHandler::new("searx").unwrap()),
+        ]);
+        let selected: Vec<EngineHandler> = ["duckduckgo", "startpage", "wikipedia", "searx"]
+            .into_iter()
+            .filter_map(EngineHandler::new)
+            .collect();
+        let mut errors: Vec<EngineErrorInfo> = ["duckduckgo", "startpage", "wikipedia"]
+            .into_iter()
+            .map(|engine| EngineErrorInfo::new(&EngineError::RequestError, engine))
+            .collect();
 
-        let mut resultant_map = HashMap::new();
+        let fallbacks: Vec<EngineHandler> =
+            select_fallbacks(&mut errors, &selected, &config, &CircuitBreaker::new());
 
-        // Create a temporary file with an invalid regex pattern
-        let mut file = NamedTempFile::new().unwrap();
-        writeln!(file, "example(").unwrap();
-        file.flush().unwrap();
+        assert_eq!(
+            fallbacks
+                .iter()
+                .map(|engine| engine.name())
+                .collect::<Vec<&str>>(),
+            vec!["mojeek"]
+        );
+        assert_eq!(errors[0].fallback.as_deref(), Some("mojeek"));
+        // the fallback is used only once and the searx engine was selected itself.
+        assert_eq!(errors[1].fallback, None);
+        assert_eq!(errors[2].fallback, None);
+    }
 
-        let result = filter_with_lists(
-            &mut map_to_be_filtered,
-            &mut resultant_map,
-            file.path().to_str().unwrap(),
+    #[test]
+    fn test_merge_suggestions_removes_duplicates_and_the_query() {
+        let suggestions: Vec<String> = ["rust book", "Rust", "rust lang", "Rust Book", "rust lang"]
+            .map(str::to_owned)
+            .to_vec();
+
+        assert_eq!(
+            merge_suggestions(suggestions, "rust"),
+            vec!["rust book".to_owned(), "rust lang".to_owned()]
         );
+        assert!(merge_suggestions(Vec::new(), "rust").is_empty());
+    }
 
-        assert!(result.is_err());
+    #[test]
+    fn test_spelling_correction_skips_the_query_itself() {
+        let corrections: Vec<String> = [" Rust ", "rust lang", "rust book"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            spelling_correction(corrections, "rust"),
+            Some("rust lang".to_owned())
+        );
+        assert_eq!(spelling_correction(vec!["Rust".to_owned()], "rust"), None);
+        assert_eq!(spelling_correction(Vec::new(), "rust"), None);
+    }
+
+    #[test]
+    fn test_split_suggestions_separates_the_corrections() {
+        let suggestions: Vec<Suggestion> = vec![
+            Suggestion::Related("rust book".to_owned()),
+            Suggestion::Correction("rust lang".to_owned()),
+            Suggestion::Related("rust".to_owned()),
+        ];
+        assert_eq!(
+            split_suggestions(suggestions, "rust"),
+            (vec!["rust book".to_owned()], Some("rust lang".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_engine_cache_key_tells_the_searches_apart() {
+        let key: String = engine_cache_key("searx", "rust", 1, 2, None);
+        assert_eq!(key, "engine:searx:1:2::rust");
+        assert_ne!(key, engine_cache_key("duckduckgo", "rust", 1, 2, None));
+        assert_ne!(key, engine_cache_key("searx", "rust", 2, 2, None));
+        assert_ne!(key, engine_cache_key("searx", "rust", 1, 0, None));
+        assert_ne!(key, engine_cache_key("searx", "rust", 1, 2, Some("de-de")));
+        assert_ne!(key, engine_cache_key("searx", "rust lang", 1, 2, None));
+    }
+
+    #[test]
+    fn test_merge_suggestions_is_capped() {
+        let suggestions: Vec<String> = (0..20).map(|index| format!("rust {index}")).collect();
+
+        assert_eq!(
+            merge_suggestions(suggestions, "rust").len(),
+            MAX_SUGGESTIONS
+        );
     }
 }
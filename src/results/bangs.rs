@@ -0,0 +1,105 @@
+//! This module provides the functionality to handle the bang shortcuts (like `!w query`) which
+//! either restrict a search to a single upstream search engine or redirect it to an external
+//! website.
+
+use std::collections::HashMap;
+
+use crate::models::parser_models::Bang;
+
+/// A function which detects a leading bang token (like `!w`) in the search query and looks it up
+/// in the configured bangs.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user.
+/// * `bangs` - It takes the bangs configured in the config file mapped by their names.
+///
+/// # Returns
+///
+/// It returns the matched bang along with the search query without the bang token or `None` if
+/// the query does not start with a known bang.
+pub fn parse_bang<'a>(query: &str, bangs: &'a HashMap<String, Bang>) -> Option<(&'a Bang, String)> {
+    let query: &str = query.trim_start();
+    let (token, rest) = query.split_once(char::is_whitespace).unwrap_or((query, ""));
+
+    let bang: &Bang = bangs.get(&token.strip_prefix('!')?.to_lowercase())?;
+    Some((bang, rest.trim().to_owned()))
+}
+
+/// A function which builds the url to which a search with a redirect bang is redirected by
+/// replacing the `{query}` placeholder of the configured url with the percent encoded query.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url configured for the bang.
+/// * `query` - It takes the search query without the bang token.
+pub fn redirect_url(url: &str, query: &str) -> String {
+    url.replace("{query}", &percent_encode(query))
+}
+
+/// A helper function which percent encodes all the characters of the provided value except the
+/// unreserved characters so that it can be safely placed in a url.
+///
+/// # Arguments
+///
+/// * `value` - It takes the value to be encoded.
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::engine_models::EngineHandler;
+
+    fn bangs() -> HashMap<String, Bang> {
+        HashMap::from([
+            (
+                "w".to_owned(),
+                Bang::Engine(EngineHandler::new("wikipedia").unwrap()),
+            ),
+            (
+                "gh".to_owned(),
+                Bang::Redirect("https://github.com/search?q={query}".to_owned()),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_parse_bang_strips_the_bang_token() {
+        let bangs: HashMap<String, Bang> = bangs();
+
+        let (bang, query) = parse_bang("!W rust  lang ", &bangs).unwrap();
+        assert!(matches!(bang, Bang::Engine(_)));
+        assert_eq!(query, "rust  lang");
+
+        let (bang, query) = parse_bang("!gh websurfx", &bangs).unwrap();
+        assert!(matches!(bang, Bang::Redirect(_)));
+        assert_eq!(query, "websurfx");
+    }
+
+    #[test]
+    fn test_parse_bang_ignores_unknown_and_missing_bangs() {
+        let bangs: HashMap<String, Bang> = bangs();
+
+        assert!(parse_bang("!unknown rust", &bangs).is_none());
+        assert!(parse_bang("rust !w", &bangs).is_none());
+        assert!(parse_bang("w rust", &bangs).is_none());
+    }
+
+    #[test]
+    fn test_redirect_url_encodes_the_query() {
+        assert_eq!(
+            redirect_url("https://github.com/search?q={query}", "rust & c++"),
+            "https://github.com/search?q=rust%20%26%20c%2B%2B"
+        );
+    }
+}
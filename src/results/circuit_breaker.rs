@@ -0,0 +1,178 @@
+//! This module provides the circuit breaker which temporarily stops requesting the upstream search
+//! engines which keep failing (like when they block the server with `429` or `403` responses), so
+//! that a blocked engine is not hammered with every search and the chance of the whole server
+//! being blocked is reduced.
+
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+use error_stack::Report;
+
+use crate::models::{engine_models::EngineError, parser_models::CircuitBreakerConfig};
+
+/// A named struct which stores the state of the circuit of an upstream search engine.
+#[derive(Default)]
+struct Circuit {
+    /// It stores the number of consecutive failures of the engine within the current window.
+    failures: u32,
+    /// It stores the time of the first failure of the current window.
+    window_started: Option<Instant>,
+    /// It stores the time until which the engine is skipped.
+    open_until: Option<Instant>,
+}
+
+/// A struct which tracks the consecutive failures of the upstream search engines and skips the
+/// engines which failed too many times in a row within a window for a cooldown period. It is
+/// shared between all the searches.
+#[derive(Default)]
+pub struct CircuitBreaker {
+    /// It stores the circuits of the upstream search engines mapped by their names.
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+impl CircuitBreaker {
+    /// Constructs a new `CircuitBreaker` in which no engine is skipped.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A function which checks whether the given upstream search engine is temporarily disabled
+    /// because it failed too many times in a row.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the upstream search engine.
+    /// * `config` - It takes the thresholds of the circuit breaker from the config.
+    pub fn is_open(&self, engine: &str, config: &CircuitBreakerConfig) -> bool {
+        if !config.is_enabled() {
+            return false;
+        }
+
+        self.circuits
+            .lock()
+            .ok()
+            .and_then(|circuits| circuits.get(engine)?.open_until)
+            .is_some_and(|open_until| Instant::now() < open_until)
+    }
+
+    /// A function which records the outcome of a request to the given upstream search engine. A
+    /// successful request (or an empty result set, which is not considered a failure) closes the
+    /// circuit of the engine while a failed request opens it for the cooldown period once the
+    /// engine has failed the configured number of times in a row within the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the upstream search engine.
+    /// * `response` - It takes the response of the upstream search engine.
+    /// * `config` - It takes the thresholds of the circuit breaker from the config.
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the circuit of the engine has been opened by the recorded failure.
+    pub fn record<T>(
+        &self,
+        engine: &str,
+        response: &Result<T, Report<EngineError>>,
+        config: &CircuitBreakerConfig,
+    ) -> bool {
+        if !config.is_enabled() {
+            return false;
+        }
+
+        let mut circuits = match self.circuits.lock() {
+            Ok(circuits) => circuits,
+            Err(_) => return false,
+        };
+
+        let failed: bool = match response {
+            Ok(_) => false,
+            Err(error) => !matches!(error.current_context(), EngineError::EmptyResultSet),
+        };
+        if !failed {
+            circuits.remove(engine);
+            return false;
+        }
+
+        let now: Instant = Instant::now();
+        let circuit: &mut Circuit = circuits.entry(engine.to_owned()).or_default();
+        match circuit.window_started {
+            Some(window_started) if now.duration_since(window_started) <= config.window => {
+                circuit.failures += 1
+            }
+            _ => {
+                circuit.failures = 1;
+                circuit.window_started = Some(now);
+            }
+        }
+
+        match circuit.failures >= config.failures {
+            true => {
+                *circuit = Circuit {
+                    open_until: Some(now + config.cooldown),
+                    ..Circuit::default()
+                };
+                true
+            }
+            false => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failures: 3,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(300),
+        }
+    }
+
+    fn failure() -> Result<(), Report<EngineError>> {
+        Err(Report::new(EngineError::UnexpectedError))
+    }
+
+    #[test]
+    fn test_circuit_opens_after_consecutive_failures() {
+        let breaker: CircuitBreaker = CircuitBreaker::new();
+        let config: CircuitBreakerConfig = config();
+
+        assert!(!breaker.record("searx", &failure(), &config));
+        assert!(!breaker.record("searx", &failure(), &config));
+        assert!(!breaker.is_open("searx", &config));
+        assert!(breaker.record("searx", &failure(), &config));
+        assert!(breaker.is_open("searx", &config));
+        assert!(!breaker.is_open("duckduckgo", &config));
+    }
+
+    #[test]
+    fn test_successes_and_empty_results_reset_the_failures() {
+        let breaker: CircuitBreaker = CircuitBreaker::new();
+        let config: CircuitBreakerConfig = config();
+
+        breaker.record("searx", &failure(), &config);
+        breaker.record("searx", &failure(), &config);
+        breaker.record("searx", &Ok(()), &config);
+        breaker.record("searx", &failure(), &config);
+        breaker.record(
+            "searx",
+            &Err::<(), _>(Report::new(EngineError::EmptyResultSet)),
+            &config,
+        );
+        breaker.record("searx", &failure(), &config);
+        assert!(!breaker.is_open("searx", &config));
+    }
+
+    #[test]
+    fn test_disabled_circuit_breaker_never_opens() {
+        let breaker: CircuitBreaker = CircuitBreaker::new();
+        let config: CircuitBreakerConfig = CircuitBreakerConfig::disabled();
+
+        for _ in 0..10 {
+            assert!(!breaker.record("searx", &failure(), &config));
+        }
+        assert!(!breaker.is_open("searx", &config));
+    }
+}
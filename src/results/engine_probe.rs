@@ -0,0 +1,197 @@
+//! This module provides the functionality to probe the upstream search engines enabled in the
+//! config when the server starts, so that the engines which are misconfigured or which block the
+//! server are noticed immediately instead of on the first searches.
+
+use std::time::{Duration, Instant};
+
+use error_stack::Report;
+use futures::future::join_all;
+use reqwest::Client;
+
+use crate::{
+    config::parser::Config,
+    models::engine_models::{
+        self, error_message, EngineError, EngineErrorKind, EngineHandler, UpstreamHeaders,
+    },
+    results::{http_client, outbound_guard, user_agent::select_user_agent},
+};
+
+/// The search query sent to the upstream search engines to probe them.
+const PROBE_QUERY: &str = "test";
+
+/// A named struct which stores the outcome of the probe of an upstream search engine.
+pub struct ProbeOutcome {
+    /// It stores the name of the probed upstream search engine.
+    pub engine: &'static str,
+    /// It stores the number of results provided by the engine or the error it failed with.
+    pub response: Result<usize, Report<EngineError>>,
+    /// It stores the time taken by the engine to respond.
+    pub elapsed: Duration,
+}
+
+impl ProbeOutcome {
+    /// A function which checks whether the engine was reachable, an engine which provided no
+    /// results for the probe query is still considered reachable.
+    pub fn is_reachable(&self) -> bool {
+        match &self.response {
+            Ok(_) => true,
+            Err(error) => matches!(error.current_context(), EngineError::EmptyResultSet),
+        }
+    }
+}
+
+/// A function which probes the upstream search engines enabled in the config with a test query
+/// when the `probe_engines_on_start` option is enabled, and logs which of them are reachable.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct.
+///
+/// # Error
+///
+/// Returns an error naming the unreachable engines if the `probe_engines_strict` option is
+/// enabled, so that the server is not started. It also returns an error if the http client could
+/// not be built.
+pub async fn probe_engines_on_start(config: &Config) -> std::io::Result<()> {
+    if !config.aggregator.probe_engines_on_start {
+        return Ok(());
+    }
+
+    // the outbound requests are configured here as the engines are probed before the server (which
+    // otherwise configures them) is started.
+    outbound_guard::allow_private_addresses(config.aggregator.allow_private_addresses);
+    engine_models::limit_response_bytes(config.aggregator.max_response_bytes);
+    engine_models::limit_read_timeout(config.aggregator.read_timeout);
+
+    let client: Client = http_client::upstream_client(config).map_err(std::io::Error::other)?;
+
+    log::info!(
+        "Probing {} upstream search engine(s)",
+        config.upstream_search_engines.len()
+    );
+    let outcomes: Vec<ProbeOutcome> = join_all(
+        config
+            .upstream_search_engines
+            .iter()
+            .map(|engine| probe_engine(engine, config, &client)),
+    )
+    .await;
+
+    for outcome in &outcomes {
+        match &outcome.response {
+            Ok(results) => log::info!(
+                "Engine `{}` is reachable ({results} results in {}ms)",
+                outcome.engine,
+                outcome.elapsed.as_millis()
+            ),
+            Err(_) if outcome.is_reachable() => log::warn!(
+                "Engine `{}` is reachable but provided no results for the probe query",
+                outcome.engine
+            ),
+            Err(error) => log::error!(
+                "Engine `{}` is unreachable ({:?}): {}",
+                outcome.engine,
+                EngineErrorKind::of(error),
+                error_message(error)
+            ),
+        }
+    }
+
+    check_outcomes(&outcomes, config.aggregator.probe_engines_strict)
+}
+
+/// A helper function which probes the provided upstream search engine with the test query.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the upstream search engine to be probed.
+/// * `config` - It takes the parsed config struct.
+/// * `client` - It takes the http client used for the probe.
+async fn probe_engine(engine: &EngineHandler, config: &Config, client: &Client) -> ProbeOutcome {
+    let (name, search_engine) = engine
+        .to_owned()
+        .with_base_url(config.aggregator.engine_base_url(engine.name()))
+        .into_name_engine();
+    let headers: UpstreamHeaders =
+        UpstreamHeaders::new(select_user_agent(&config.aggregator), None, None);
+
+    let started: Instant = Instant::now();
+    let response: Result<usize, Report<EngineError>> = search_engine
+        .results(
+            &search_engine.transform_query(PROBE_QUERY),
+            1,
+            &headers,
+            client,
+            config.request_timeout,
+            search_engine.map_safe_search(config.safe_search),
+            usize::MAX,
+        )
+        .await
+        .map(|results| results.len());
+
+    ProbeOutcome {
+        engine: name,
+        response,
+        elapsed: started.elapsed(),
+    }
+}
+
+/// A helper function which decides whether the server can be started after probing the upstream
+/// search engines.
+///
+/// # Arguments
+///
+/// * `outcomes` - It takes the outcomes of the probes of the upstream search engines.
+/// * `strict` - It takes the option to whether refuse to start if any engine is unreachable.
+///
+/// # Error
+///
+/// Returns an error naming the unreachable engines if `strict` is enabled and any engine is
+/// unreachable.
+fn check_outcomes(outcomes: &[ProbeOutcome], strict: bool) -> std::io::Result<()> {
+    let unreachable: Vec<&str> = outcomes
+        .iter()
+        .filter(|outcome| !outcome.is_reachable())
+        .map(|outcome| outcome.engine)
+        .collect();
+
+    match strict && !unreachable.is_empty() {
+        true => Err(std::io::Error::other(format!(
+            "The upstream search engine(s) {} are unreachable",
+            unreachable.join(", ")
+        ))),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(engine: &'static str, response: Result<usize, Report<EngineError>>) -> ProbeOutcome {
+        ProbeOutcome {
+            engine,
+            response,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_only_unreachable_engines_fail_the_strict_probe() {
+        let outcomes: Vec<ProbeOutcome> = vec![
+            outcome("duckduckgo", Ok(10)),
+            outcome("searx", Err(Report::new(EngineError::EmptyResultSet))),
+        ];
+        assert!(check_outcomes(&outcomes, true).is_ok());
+
+        let mut outcomes: Vec<ProbeOutcome> = outcomes;
+        outcomes.push(outcome(
+            "startpage",
+            Err(Report::new(EngineError::RequestError)),
+        ));
+        assert!(check_outcomes(&outcomes, false).is_ok());
+        let error: std::io::Error = check_outcomes(&outcomes, true).unwrap_err();
+        assert!(error.to_string().contains("startpage"));
+        assert!(!error.to_string().contains("searx"));
+    }
+}
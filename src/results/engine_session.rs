@@ -0,0 +1,147 @@
+//! This module provides the functionality to store the cookies of the sessions established with the
+//! upstream search engines which require them (like a consent cookie) to provide the results, so
+//! that a session is only established once in a while instead of before every search.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use reqwest::header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE};
+
+/// The time (secs) for which the cookies of a session are reused before a new session is
+/// established with the upstream search engine.
+const SESSION_TTL: u64 = 30 * 60;
+
+/// A static variable which stores the cookies of the sessions established with the upstream search
+/// engines along with the time when they were established, keyed by the url used to establish the
+/// session.
+static SESSIONS: OnceLock<Mutex<HashMap<String, (String, Instant)>>> = OnceLock::new();
+
+/// A helper function which returns the store of the established sessions.
+fn sessions() -> &'static Mutex<HashMap<String, (String, Instant)>> {
+    SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A function which returns the cookies of the session established through the provided url if
+/// it has not expired yet.
+///
+/// # Arguments
+///
+/// * `session_url` - It takes the url used to establish the session.
+pub fn cached_session(session_url: &str) -> Option<String> {
+    let sessions = sessions().lock().ok()?;
+    sessions
+        .get(session_url)
+        .filter(|(_, established)| established.elapsed() < Duration::from_secs(SESSION_TTL))
+        .map(|(cookies, _)| cookies.clone())
+}
+
+/// A function which stores the cookies of the session established through the provided url so
+/// that they are reused by the following requests to the upstream search engine.
+///
+/// # Arguments
+///
+/// * `session_url` - It takes the url used to establish the session.
+/// * `cookies` - It takes the cookies of the session in the form of a `Cookie` header value.
+pub fn store_session(session_url: &str, cookies: &str) {
+    if let Ok(mut sessions) = sessions().lock() {
+        sessions.insert(session_url.to_owned(), (cookies.to_owned(), Instant::now()));
+    }
+}
+
+/// A function which forgets the session established through the provided url, so that a new
+/// session is established by the next request to the upstream search engine. It is used when the
+/// upstream search engine rejects a request made with the cookies of the session.
+///
+/// # Arguments
+///
+/// * `session_url` - It takes the url used to establish the session.
+pub fn invalidate_session(session_url: &str) {
+    if let Ok(mut sessions) = sessions().lock() {
+        sessions.remove(session_url);
+    }
+}
+
+/// A function which builds the value of a `Cookie` header from the `Set-Cookie` headers of a
+/// response, only the name and the value of the cookies are kept while their attributes (like the
+/// expiry or the path) are ignored.
+///
+/// # Arguments
+///
+/// * `headers` - It takes the headers of the response which established the session.
+///
+/// # Returns
+///
+/// The cookies joined by `; ` or `None` if the response did not set any cookie.
+pub fn cookies_from_headers(headers: &HeaderMap) -> Option<String> {
+    let cookies: Vec<&str> = headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next())
+        .map(str::trim)
+        .filter(|cookie| cookie.contains('='))
+        .collect();
+
+    match cookies.is_empty() {
+        true => None,
+        false => Some(cookies.join("; ")),
+    }
+}
+
+/// A function which adds the cookies of a session to the `Cookie` header of a request, keeping the
+/// cookies which were already set on the request.
+///
+/// # Arguments
+///
+/// * `header_map` - It takes the headers of the request to be sent to the upstream search engine.
+/// * `cookies` - It takes the cookies of the session in the form of a `Cookie` header value.
+pub fn add_session_cookies(header_map: &mut HeaderMap, cookies: &str) {
+    let cookies: String = match header_map.get(COOKIE).and_then(|value| value.to_str().ok()) {
+        Some(existing) if !existing.is_empty() => format!("{existing}; {cookies}"),
+        _ => cookies.to_owned(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&cookies) {
+        header_map.insert(COOKIE, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookies_from_headers_keeps_only_the_name_and_value() {
+        let mut headers: HeaderMap = HeaderMap::new();
+        assert_eq!(cookies_from_headers(&headers), None);
+
+        headers.append(
+            SET_COOKIE,
+            HeaderValue::from_static("preferences=abc; Path=/; Secure"),
+        );
+        headers.append(SET_COOKIE, HeaderValue::from_static("consent=yes"));
+        assert_eq!(
+            cookies_from_headers(&headers).as_deref(),
+            Some("preferences=abc; consent=yes")
+        );
+    }
+
+    #[test]
+    fn test_sessions_are_stored_and_invalidated() {
+        let session_url: &str = "https://session.test/";
+        assert_eq!(cached_session(session_url), None);
+
+        store_session(session_url, "consent=yes");
+        assert_eq!(cached_session(session_url).as_deref(), Some("consent=yes"));
+
+        invalidate_session(session_url);
+        assert_eq!(cached_session(session_url), None);
+
+        let mut header_map: HeaderMap = HeaderMap::new();
+        header_map.insert(COOKIE, HeaderValue::from_static("theme=dark"));
+        add_session_cookies(&mut header_map, "consent=yes");
+        assert_eq!(header_map[COOKIE], "theme=dark; consent=yes");
+    }
+}
@@ -0,0 +1,402 @@
+//! This module provides the functionality to load and compile the regex and glob based rules
+//! present in the blocklist and allowlist files once, so that they can be shared between all the requests and
+//! reloaded without restarting the server.
+
+use crate::handler::paths::{file_path, FileType};
+use crate::models::aggregation_models::SearchResult;
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    sync::{PoisonError, RwLock},
+};
+
+/// A named struct which stores the compiled regex based rules of the blocklist and allowlist files.
+pub struct FilterLists {
+    /// It stores the compiled rules of the blocklist file.
+    blocklist: Vec<Regex>,
+    /// It stores the compiled rules of the allowlist file.
+    allowlist: Vec<Regex>,
+}
+
+impl FilterLists {
+    /// A function which reads and compiles the rules of the blocklist and allowlist files.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if either of the files cannot be found or read or if any of the rules
+    /// is not a valid regex pattern.
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        FilterLists::from_files(
+            file_path(FileType::BlockList)?,
+            file_path(FileType::AllowList)?,
+        )
+    }
+
+    /// A function which reads and compiles the rules of the provided blocklist and allowlist
+    /// files.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocklist_path` - It takes the file path of the blocklist as an argument.
+    /// * `allowlist_path` - It takes the file path of the allowlist as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if either of the files cannot be read or if any of the rules is not a
+    /// valid regex pattern.
+    pub fn from_files(
+        blocklist_path: &str,
+        allowlist_path: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(FilterLists {
+            blocklist: compile_filter_list(blocklist_path)?,
+            allowlist: compile_filter_list(allowlist_path)?,
+        })
+    }
+
+    /// A function which checks whether the provided search query should be disallowed, which is
+    /// the case when it matches any of the rules of the blocklist and none of the rules of the
+    /// allowlist.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query to be checked against the lists as an argument.
+    pub fn is_disallowed(&self, query: &str) -> bool {
+        self.blocklist.iter().any(|re| re.is_match(query))
+            && !self.allowlist.iter().any(|re| re.is_match(query))
+    }
+
+    /// A function which removes the search results matching any of the rules of the blocklist
+    /// from the provided results, unless they also match any of the rules of the allowlist. The
+    /// rules are matched against the lowercased url, title and description of every result.
+    ///
+    /// # Arguments
+    ///
+    /// * `result_map` - It takes the search results mapped by their urls.
+    pub fn filter_results(&self, result_map: &mut HashMap<String, SearchResult>) {
+        result_map.retain(|url, search_result| {
+            let texts: [String; 3] = [
+                url.to_lowercase(),
+                search_result.title.to_lowercase(),
+                search_result.description.to_lowercase(),
+            ];
+            !matches_any(&self.blocklist, &texts) || matches_any(&self.allowlist, &texts)
+        });
+    }
+}
+
+/// A helper function which checks whether any of the provided rules matches any of the provided
+/// texts.
+///
+/// # Arguments
+///
+/// * `rules` - It takes the compiled rules of a list.
+/// * `texts` - It takes the texts to be checked against the rules.
+fn matches_any(rules: &[Regex], texts: &[String]) -> bool {
+    rules
+        .iter()
+        .any(|re| texts.iter().any(|text| re.is_match(text)))
+}
+
+/// A structure to efficiently share the compiled filter lists between threads while allowing them
+/// to be reloaded - as it is protected by a RwLock.
+pub struct SharedFilterLists {
+    /// The compiled filter lists protected from concurrent access by a RwLock.
+    filter_lists: RwLock<FilterLists>,
+}
+
+impl SharedFilterLists {
+    /// A function that creates a new `SharedFilterLists` from the compiled filter lists.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter_lists` - It takes the compiled filter lists as an argument.
+    pub fn new(filter_lists: FilterLists) -> Self {
+        Self {
+            filter_lists: RwLock::new(filter_lists),
+        }
+    }
+
+    /// A function which checks whether the provided search query should be disallowed based on
+    /// the currently loaded filter lists.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query to be checked against the lists as an argument.
+    pub fn is_disallowed(&self, query: &str) -> bool {
+        self.filter_lists
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_disallowed(query)
+    }
+
+    /// A function which removes the search results disallowed by the currently loaded filter
+    /// lists from the provided results.
+    ///
+    /// # Arguments
+    ///
+    /// * `result_map` - It takes the search results mapped by their urls.
+    pub fn filter_results(&self, result_map: &mut HashMap<String, SearchResult>) {
+        self.filter_lists
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .filter_results(result_map)
+    }
+
+    /// A function which reads and compiles the blocklist and allowlist files again and replaces
+    /// the currently loaded filter lists with them, so that both the search queries and the
+    /// search results are checked against the reloaded lists from the next search onwards.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the files could not be loaded, in which case the currently loaded
+    /// filter lists are kept.
+    pub fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.replace(FilterLists::load()?);
+        Ok(())
+    }
+
+    /// A helper function which replaces the currently loaded filter lists with the provided ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter_lists` - It takes the compiled filter lists to be used from now on.
+    fn replace(&self, filter_lists: FilterLists) {
+        *self
+            .filter_lists
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = filter_lists;
+    }
+}
+
+/// The marker which a line of a list file can be prefixed with to make it a glob pattern.
+const GLOB_MARKER: &str = "glob:";
+
+/// A helper function which reads the regex and glob based rules from the provided list file and
+/// compiles them. Empty lines are ignored.
+///
+/// # Arguments
+///
+/// * `file_path` - It takes the file path of the list as the argument.
+///
+/// # Error
+///
+/// Returns an error if the file cannot be opened or read or a config error pointing to the
+/// offending line if any of the rules is not a valid pattern.
+pub fn compile_filter_list(file_path: &str) -> Result<Vec<Regex>, Box<dyn std::error::Error>> {
+    let mut rules: Vec<Regex> = Vec::new();
+    for (idx, line) in BufReader::new(File::open(file_path)?).lines().enumerate() {
+        let line: String = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        rules.push(compile_rule(line.trim()).map_err(|error| {
+            format!(
+                "Config Error: Invalid pattern `{}` on line {} of `{}`: {}",
+                line.trim(),
+                idx + 1,
+                file_path,
+                error
+            )
+        })?);
+    }
+    Ok(rules)
+}
+
+/// A helper function which compiles a single rule of a list file. A rule prefixed with `glob:` or
+/// starting with a `*` (which can never be a valid regex) is a glob pattern, while every other
+/// rule is a regex pattern.
+///
+/// # Arguments
+///
+/// * `rule` - It takes the trimmed rule as the argument.
+///
+/// # Error
+///
+/// Returns an error if the rule is not a valid regex pattern.
+fn compile_rule(rule: &str) -> Result<Regex, regex::Error> {
+    match rule.strip_prefix(GLOB_MARKER) {
+        Some(glob) => Regex::new(&glob_to_regex(glob.trim())),
+        None if rule.starts_with('*') => Regex::new(&glob_to_regex(rule)),
+        None => Regex::new(rule),
+    }
+}
+
+/// A helper function which converts a glob pattern into an equivalent case insensitive regex
+/// pattern matching the whole text, where `*` matches any number of characters, `?` matches a
+/// single character and every other character matches itself.
+///
+/// # Arguments
+///
+/// * `glob` - It takes the glob pattern as the argument.
+fn glob_to_regex(glob: &str) -> String {
+    let pattern: String = glob
+        .chars()
+        .map(|character| match character {
+            '*' => ".*".to_owned(),
+            '?' => ".".to_owned(),
+            _ => regex::escape(&character.to_string()),
+        })
+        .collect();
+    format!("(?i)^{pattern}$")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    /// A helper function which builds the search results the filter lists are tested against.
+    fn search_results() -> HashMap<String, SearchResult> {
+        [
+            SearchResult::new(
+                "Example Domain",
+                "https://www.example.com",
+                "This domain is for use in illustrative examples in documents.",
+                &["google", "bing"],
+            ),
+            SearchResult::new(
+                "Rust Programming Language",
+                "https://www.rust-lang.org/",
+                "A systems programming language that runs blazingly fast, prevents segfaults, and guarantees thread safety.",
+                &["google", "duckduckgo"],
+            ),
+        ]
+        .into_iter()
+        .map(|search_result| (search_result.url.clone(), search_result))
+        .collect()
+    }
+
+    /// A helper function which compiles the provided rules of the blocklist and allowlist.
+    fn filter_lists(blocklist_rules: &[&str], allowlist_rules: &[&str]) -> FilterLists {
+        let mut blocklist = NamedTempFile::new().unwrap();
+        let mut allowlist = NamedTempFile::new().unwrap();
+        for rule in blocklist_rules {
+            writeln!(blocklist, "{rule}").unwrap();
+        }
+        for rule in allowlist_rules {
+            writeln!(allowlist, "{rule}").unwrap();
+        }
+        blocklist.flush().unwrap();
+        allowlist.flush().unwrap();
+
+        FilterLists::from_files(
+            blocklist.path().to_str().unwrap(),
+            allowlist.path().to_str().unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_filter_results() {
+        let mut result_map: HashMap<String, SearchResult> = search_results();
+        filter_lists(&["example", "rust"], &[]).filter_results(&mut result_map);
+        assert!(result_map.is_empty());
+    }
+
+    #[test]
+    fn test_filter_results_wildcard() {
+        let mut result_map: HashMap<String, SearchResult> = search_results();
+        filter_lists(&["ex.*le"], &[]).filter_results(&mut result_map);
+        assert_eq!(result_map.len(), 1);
+        assert!(result_map.contains_key("https://www.rust-lang.org/"));
+    }
+
+    #[test]
+    fn test_filter_results_keeps_allowed_results() {
+        let mut result_map: HashMap<String, SearchResult> = search_results();
+        filter_lists(&["example", "rust"], &["illustrative"]).filter_results(&mut result_map);
+        assert_eq!(result_map.len(), 1);
+        assert!(result_map.contains_key("https://www.example.com"));
+    }
+
+    #[test]
+    fn test_replaced_lists_apply_to_both_queries_and_results() {
+        let shared_filter_lists: SharedFilterLists =
+            SharedFilterLists::new(filter_lists(&["casino"], &[]));
+        let mut result_map: HashMap<String, SearchResult> = search_results();
+        shared_filter_lists.filter_results(&mut result_map);
+        assert_eq!(result_map.len(), 2);
+        assert!(!shared_filter_lists.is_disallowed("example"));
+
+        shared_filter_lists.replace(filter_lists(&["example"], &[]));
+
+        shared_filter_lists.filter_results(&mut result_map);
+        assert_eq!(result_map.len(), 1);
+        assert!(shared_filter_lists.is_disallowed("example"));
+    }
+
+    #[test]
+    fn test_from_files_file_not_found() {
+        let allowlist = NamedTempFile::new().unwrap();
+        let result =
+            FilterLists::from_files("non-existent-file.txt", allowlist.path().to_str().unwrap());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_from_files_compiles_rules() -> Result<(), Box<dyn std::error::Error>> {
+        let mut blocklist = NamedTempFile::new()?;
+        writeln!(blocklist, "casino")?;
+        writeln!(blocklist)?;
+        writeln!(blocklist, "gambl.*")?;
+        blocklist.flush()?;
+
+        let mut allowlist = NamedTempFile::new()?;
+        writeln!(allowlist, "casino royale")?;
+        allowlist.flush()?;
+
+        let filter_lists = FilterLists::from_files(
+            blocklist.path().to_str().unwrap(),
+            allowlist.path().to_str().unwrap(),
+        )?;
+
+        assert_eq!(filter_lists.blocklist.len(), 2);
+        assert!(filter_lists.is_disallowed("online gambling"));
+        assert!(!filter_lists.is_disallowed("casino royale"));
+        assert!(!filter_lists.is_disallowed("rust programming"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_files_rejects_invalid_regex() {
+        let mut blocklist = NamedTempFile::new().unwrap();
+        writeln!(blocklist, "valid").unwrap();
+        writeln!(blocklist, "invalid(").unwrap();
+        blocklist.flush().unwrap();
+
+        let allowlist = NamedTempFile::new().unwrap();
+
+        let result = FilterLists::from_files(
+            blocklist.path().to_str().unwrap(),
+            allowlist.path().to_str().unwrap(),
+        );
+
+        assert!(result.is_err());
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("Invalid pattern `invalid(` on line 2"));
+    }
+
+    #[test]
+    fn test_compile_rule_supports_globs() {
+        let rule: Regex = compile_rule("*casino*").unwrap();
+        assert!(rule.is_match("online Casino games"));
+        assert!(!rule.is_match("online gambling"));
+
+        let rule: Regex = compile_rule("glob: *.example.?om").unwrap();
+        assert!(rule.is_match("https://www.example.com"));
+        assert!(!rule.is_match("https://wwwxexample.com"));
+        assert!(!rule.is_match("https://www.example.com/path"));
+        assert!(compile_rule("glob: [").unwrap().is_match("["));
+
+        // the other rules are still regex patterns.
+        assert!(compile_rule("gambl.*").unwrap().is_match("gambling"));
+    }
+}
@@ -0,0 +1,42 @@
+//! This module provides the functionality to build the http client shared between all the requests
+//! made to the upstream search engines, so that the connections to the upstream search engines are
+//! pooled and reused between the searches instead of being established for every request.
+
+use crate::config::parser::Config;
+use reqwest::Client;
+use std::time::Duration;
+
+/// The time (secs) for which an idle pooled connection is kept open before it is closed.
+const POOL_IDLE_TIMEOUT: u64 = 90;
+
+/// The interval (secs) at which the TCP keep-alive probes are sent on the pooled connections.
+const TCP_KEEPALIVE: u64 = 60;
+
+/// A function which builds the http client shared between all the requests made to the upstream
+/// search engines. The client keeps up to `connection_pool_size` idle connections open for every
+/// upstream host and gives up on establishing a connection after the `connect_timeout` (so that
+/// the unreachable engines fail fast while the whole request is still bounded by the
+/// `request_timeout`). The proxy
+/// set through the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables is used if any
+/// is set, while the user agent is sent with every request as it can be rotated between searches.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct.
+///
+/// # Error
+///
+/// Returns an error if the TLS backend of the client could not be initialized.
+pub fn upstream_client(config: &Config) -> Result<Client, reqwest::Error> {
+    Client::builder()
+        .pool_max_idle_per_host(config.aggregator.connection_pool_size)
+        .pool_idle_timeout(Duration::from_secs(POOL_IDLE_TIMEOUT))
+        .tcp_keepalive(Duration::from_secs(TCP_KEEPALIVE))
+        .connect_timeout(Duration::from_secs(
+            config
+                .aggregator
+                .connect_timeout
+                .min(config.request_timeout) as u64,
+        ))
+        .build()
+}
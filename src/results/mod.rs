@@ -3,4 +3,14 @@
 //! provides various models to aggregate search results into a standardized form.
 
 pub mod aggregator;
+pub mod bangs;
+pub mod circuit_breaker;
+pub mod direct_navigation;
+pub mod engine_probe;
+pub mod engine_session;
+pub mod favicon;
+pub mod filter_lists;
+pub mod http_client;
+pub mod outbound_guard;
+pub mod sanitizer;
 pub mod user_agent;
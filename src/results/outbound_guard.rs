@@ -0,0 +1,199 @@
+//! This module provides the functionality to guard the outbound requests made to the upstream
+//! search engines, so that the server can not be used to reach private, loopback or link-local
+//! addresses (server side request forgery) unless it is explicitly allowed in the config.
+
+use crate::models::engine_models::EngineError;
+use error_stack::{Report, Result, ResultExt};
+use reqwest::Url;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock,
+};
+
+/// A static variable which stores whether the outbound requests are allowed to reach non public
+/// addresses. It is set once from the config at startup and defaults to `false`.
+static ALLOW_PRIVATE_ADDRESSES: OnceLock<bool> = OnceLock::new();
+
+/// A function which sets whether the outbound requests are allowed to reach non public addresses.
+/// Only the first call has an effect as the option is meant to be set once at startup.
+///
+/// # Arguments
+///
+/// * `allow` - It takes the parsed `allow_private_addresses` option from the config.
+pub fn allow_private_addresses(allow: bool) {
+    if ALLOW_PRIVATE_ADDRESSES.set(allow).is_err() {
+        log::debug!("The outbound request guard was already configured");
+    }
+}
+
+/// A function which checks whether the provided address is a public address.
+///
+/// # Arguments
+///
+/// * `address` - It takes the resolved ip address of a host.
+///
+/// # Returns
+///
+/// It returns `false` for unspecified (`0.0.0.0/8`, `::`), loopback (`127.0.0.0/8`, `::1`),
+/// private (`10.0.0.0/8`, `172.16.0.0/12`, `192.168.0.0/16`, `fc00::/7`), link-local
+/// (`169.254.0.0/16`, `fe80::/10`), shared (`100.64.0.0/10`), documentation, broadcast and
+/// multicast addresses and `true` for all the other addresses. Ipv4 mapped ipv6 addresses are
+/// checked as the ipv4 address they map to.
+pub fn is_public_address(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            let [first, second, ..] = address.octets();
+            !(address.is_unspecified()
+                || address.is_loopback()
+                || address.is_private()
+                || address.is_link_local()
+                || address.is_broadcast()
+                || address.is_documentation()
+                || address.is_multicast()
+                || first == 0
+                || (first == 100 && (64..128).contains(&second)))
+        }
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(mapped) => is_public_address(IpAddr::V4(mapped)),
+            None => {
+                let first_segment: u16 = address.segments()[0];
+                !(address.is_unspecified()
+                    || address.is_loopback()
+                    || address.is_multicast()
+                    || (first_segment & 0xfe00) == 0xfc00
+                    || (first_segment & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// A helper function which checks whether all the provided addresses can be requested.
+///
+/// # Arguments
+///
+/// * `addresses` - It takes the addresses the host of a request resolved to.
+/// * `allow_private` - It takes whether requests to non public addresses are allowed.
+fn addresses_allowed(addresses: &[SocketAddr], allow_private: bool) -> bool {
+    !addresses.is_empty()
+        && (allow_private
+            || addresses
+                .iter()
+                .all(|address| is_public_address(address.ip())))
+}
+
+/// A function which resolves the host of the provided url and rejects the request if any of the
+/// addresses it resolves to is not a public address, unless requests to non public addresses are
+/// allowed in the config.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url which is about to be requested.
+///
+/// # Error
+///
+/// Returns an `UnexpectedError` if the url is invalid or if the request is rejected and a
+/// `RequestError` if the host of the url can not be resolved.
+pub async fn guard_outbound_request(url: &str) -> Result<(), EngineError> {
+    let url: Url = Url::parse(url)
+        .change_context(EngineError::UnexpectedError)
+        .attach_printable_lazy(|| format!("invalid upstream url: {url}"))?;
+
+    let host: &str = url
+        .host_str()
+        .ok_or(Report::new(EngineError::UnexpectedError))
+        .attach_printable_lazy(|| format!("upstream url without a host: {url}"))?;
+    // the brackets around ipv6 addresses are not accepted by the resolver.
+    let host: &str = host.trim_start_matches('[').trim_end_matches(']');
+    let port: u16 = url.port_or_known_default().unwrap_or(443);
+
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .change_context(EngineError::RequestError)
+        .attach_printable_lazy(|| format!("unable to resolve the upstream host: {host}"))?
+        .collect();
+
+    match addresses_allowed(&addresses, *ALLOW_PRIVATE_ADDRESSES.get().unwrap_or(&false)) {
+        true => Ok(()),
+        false => Err(
+            Report::new(EngineError::UnexpectedError).attach_printable(format!(
+                "refusing to request {host} as it resolves to a non public address"
+            )),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_public_ranges_are_rejected() {
+        for address in [
+            "127.0.0.1",
+            "127.255.255.254",
+            "10.0.0.1",
+            "10.255.255.255",
+            "172.16.0.1",
+            "172.31.255.255",
+            "192.168.1.1",
+            "169.254.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "100.127.255.255",
+            "0.0.0.0",
+            "0.1.2.3",
+            "255.255.255.255",
+            "224.0.0.1",
+            "::",
+            "::1",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "ff02::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(
+                !is_public_address(address.parse().unwrap()),
+                "{address} should not be public"
+            );
+        }
+    }
+
+    #[test]
+    fn test_public_addresses_are_accepted() {
+        for address in [
+            "1.1.1.1",
+            "93.184.216.34",
+            "172.32.0.1",
+            "100.128.0.1",
+            "169.255.0.1",
+            "2606:2800:220:1::",
+            "::ffff:93.184.216.34",
+        ] {
+            assert!(
+                is_public_address(address.parse().unwrap()),
+                "{address} should be public"
+            );
+        }
+    }
+
+    #[test]
+    fn test_addresses_allowed() {
+        let public: SocketAddr = "93.184.216.34:443".parse().unwrap();
+        let loopback: SocketAddr = "127.0.0.1:443".parse().unwrap();
+
+        assert!(addresses_allowed(&[public], false));
+        assert!(!addresses_allowed(&[public, loopback], false));
+        assert!(addresses_allowed(&[public, loopback], true));
+        assert!(!addresses_allowed(&[], true));
+    }
+
+    #[tokio::test]
+    async fn test_guard_rejects_loopback_urls() {
+        assert!(guard_outbound_request("http://127.0.0.1:8080/search")
+            .await
+            .is_err());
+        assert!(guard_outbound_request("http://[::1]/").await.is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! This module provides the functionality to sanitize the results scraped from the upstream search
+//! engines, so that the markup provided by an upstream search engine (or injected into it) can
+//! never end up being executed in the browser of the user.
+
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node};
+
+/// The elements whose content is never displayed and so is dropped entirely while converting the
+/// markup into text.
+const IGNORED_ELEMENTS: [&str; 5] = ["script", "style", "noscript", "template", "iframe"];
+
+/// The names of the query parameters which are only used to track the user (the parameters whose
+/// name starts with one of the `TRACKING_PARAMETER_PREFIXES` are also treated as such), they are
+/// removed from the urls of the results when the `clean_urls` option is enabled.
+const TRACKING_PARAMETERS: [&str; 20] = [
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "ttclid",
+    "igshid",
+    "li_fat_id",
+    "mc_cid",
+    "mc_eid",
+    "mkt_tok",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "vero_id",
+];
+
+/// The inline elements which are kept in the notice banner configured by the operator, all the
+/// other elements are stripped while keeping their text.
+const NOTICE_ELEMENTS: [&str; 9] = ["a", "b", "strong", "i", "em", "u", "code", "small", "br"];
+
+/// The prefixes of the names of the query parameters which are only used to track the user.
+const TRACKING_PARAMETER_PREFIXES: [&str; 2] = ["utm_", "pk_"];
+
+/// A function which converts the markup scraped from an upstream search engine into plain text.
+/// All the elements (along with their attributes like event handlers) are stripped, the content of
+/// the elements like `<script>` and `<style>` is dropped, the html entities are decoded and the
+/// whitespace is collapsed.
+///
+/// # Arguments
+///
+/// * `html` - It takes the markup scraped from the upstream search engine.
+pub fn html_to_text(html: &str) -> String {
+    let fragment: Html = Html::parse_fragment(html);
+
+    let text: String = fragment
+        .root_element()
+        .descendants()
+        .filter(|node| {
+            !node.ancestors().any(|ancestor| {
+                ancestor
+                    .value()
+                    .as_element()
+                    .is_some_and(|element| IGNORED_ELEMENTS.contains(&element.name()))
+            })
+        })
+        .filter_map(|node| node.value().as_text().map(|text| &**text))
+        .collect();
+
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// A function which checks whether the provided url of a result can be safely linked to, which is
+/// the case only for the `http` and `https` urls so that urls like `javascript:` urls are rejected.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the result.
+pub fn is_safe_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// A function which removes the tracking query parameters (like `utm_source` or `fbclid`) from the
+/// provided url of a result. The remaining query parameters are kept as they are (along with their
+/// encoding and order) and the url is returned unchanged if it is not a valid url or if it would
+/// no longer be a valid url after removing the tracking parameters.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the result.
+pub fn strip_tracking_parameters(url: &str) -> String {
+    let mut parsed_url: Url = match Url::parse(url) {
+        Ok(parsed_url) => parsed_url,
+        Err(_) => return url.to_owned(),
+    };
+
+    let query: String = match parsed_url.query() {
+        Some(query) => query.to_owned(),
+        None => return url.to_owned(),
+    };
+
+    let kept_parameters: Vec<&str> = query
+        .split('&')
+        .filter(|parameter| !parameter.is_empty() && !is_tracking_parameter(parameter))
+        .collect();
+
+    match kept_parameters.is_empty() {
+        true => parsed_url.set_query(None),
+        false => parsed_url.set_query(Some(&kept_parameters.join("&"))),
+    }
+
+    let cleaned_url: String = parsed_url.to_string();
+    match Url::parse(&cleaned_url).is_ok() {
+        true => cleaned_url,
+        false => url.to_owned(),
+    }
+}
+
+/// A helper function which checks whether the provided query parameter (like `utm_source=feed`)
+/// is a tracking parameter.
+///
+/// # Arguments
+///
+/// * `parameter` - It takes the raw query parameter along with its value.
+fn is_tracking_parameter(parameter: &str) -> bool {
+    let name: String = parameter
+        .split('=')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    TRACKING_PARAMETERS.contains(&name.as_str())
+        || TRACKING_PARAMETER_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// A function which sanitizes the markup of the notice banner provided by the operator in the
+/// config file. Only a few inline elements (like `<a>`, `<b>` or `<em>`) are kept without any
+/// attribute except the `href` of the links, which has to be an `http`, `https` or a relative
+/// url. The other elements are stripped while keeping their text (except the content of the
+/// elements like `<script>` which is dropped) and the markup is rebuilt from the parsed tree, so
+/// that the unclosed or block elements can never break the layout of the pages.
+///
+/// # Arguments
+///
+/// * `html` - It takes the markup of the notice banner from the config file.
+pub fn sanitize_notice_html(html: &str) -> String {
+    let mut sanitized: String = String::new();
+    write_notice_children(Html::parse_fragment(html).root_element(), &mut sanitized);
+    sanitized.trim().to_owned()
+}
+
+/// A helper function which writes the sanitized markup of the children of the provided element.
+///
+/// # Arguments
+///
+/// * `element` - It takes the element whose children need to be written.
+/// * `sanitized` - It takes the sanitized markup written so far.
+fn write_notice_children(element: ElementRef<'_>, sanitized: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => sanitized.push_str(&escape_html(text)),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_notice_element(child, sanitized);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// A helper function which writes the sanitized markup of the provided element.
+///
+/// # Arguments
+///
+/// * `element` - It takes the element which needs to be written.
+/// * `sanitized` - It takes the sanitized markup written so far.
+fn write_notice_element(element: ElementRef<'_>, sanitized: &mut String) {
+    let name: &str = element.value().name();
+    if IGNORED_ELEMENTS.contains(&name) {
+        return;
+    }
+    if !NOTICE_ELEMENTS.contains(&name) {
+        return write_notice_children(element, sanitized);
+    }
+
+    match name {
+        "br" => return sanitized.push_str("<br>"),
+        "a" => match element.value().attr("href").map(str::trim) {
+            Some(href)
+                if is_safe_url(href) || (href.starts_with('/') && !href.starts_with("//")) =>
+            {
+                sanitized.push_str(&format!(
+                    "<a href=\"{}\" rel=\"noopener noreferrer\">",
+                    escape_html(href)
+                ))
+            }
+            _ => sanitized.push_str("<a>"),
+        },
+        _ => sanitized.push_str(&format!("<{name}>")),
+    }
+    write_notice_children(element, sanitized);
+    sanitized.push_str(&format!("</{name}>"));
+}
+
+/// A helper function which escapes the provided text so that it can be placed inside an html
+/// element or attribute.
+///
+/// # Arguments
+///
+/// * `text` - It takes the text which needs to be escaped.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text_strips_markup_and_scripts() {
+        assert_eq!(
+            html_to_text("Learn <b>Rust</b> &amp; <i onclick=\"alert(1)\">more</i>"),
+            "Learn Rust & more"
+        );
+        assert_eq!(
+            html_to_text(
+                "<script>alert(document.cookie)</script>safe <img src=x onerror=alert(1)>text"
+            ),
+            "safe text"
+        );
+        assert_eq!(html_to_text("&lt;script&gt;"), "<script>");
+        assert_eq!(html_to_text("  plain\n text  "), "plain text");
+    }
+
+    #[test]
+    fn test_is_safe_url() {
+        assert!(is_safe_url("https://www.rust-lang.org/"));
+        assert!(is_safe_url("http://example.com/page?q=1"));
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(!is_safe_url("data:text/html,<script>alert(1)</script>"));
+        assert!(!is_safe_url("/relative/path"));
+    }
+
+    #[test]
+    fn test_strip_tracking_parameters() {
+        assert_eq!(
+            strip_tracking_parameters(
+                "https://example.com/page?utm_source=feed&id=42&fbclid=abc&q=a%20b#section"
+            ),
+            "https://example.com/page?id=42&q=a%20b#section"
+        );
+        assert_eq!(
+            strip_tracking_parameters("https://example.com/?UTM_MEDIUM=email&gclid=1"),
+            "https://example.com/"
+        );
+        assert_eq!(
+            strip_tracking_parameters("https://example.com/?utmost=1&page=2"),
+            "https://example.com/?utmost=1&page=2"
+        );
+        assert_eq!(strip_tracking_parameters("not a url"), "not a url");
+    }
+
+    #[test]
+    fn test_sanitize_notice_html_keeps_only_inline_markup() {
+        assert_eq!(
+            sanitize_notice_html(
+                "<b>Maintenance</b> tonight, <a href=\"https://status.example.com/?a=1&b=2\" onclick=\"alert(1)\">details</a>"
+            ),
+            "<b>Maintenance</b> tonight, <a href=\"https://status.example.com/?a=1&amp;b=2\" rel=\"noopener noreferrer\">details</a>"
+        );
+        assert_eq!(
+            sanitize_notice_html("<div><p>Please <em>donate</em><br/><a href=\"/about\">here"),
+            "Please <em>donate</em><br><a href=\"/about\" rel=\"noopener noreferrer\">here</a>"
+        );
+        assert_eq!(
+            sanitize_notice_html(
+                "<script>alert(1)</script><a href=\"javascript:alert(1)\">x</a><a href=\"//evil.example\">y</a> 1 &lt; 2"
+            ),
+            "<a>x</a><a>y</a> 1 &lt; 2"
+        );
+        assert_eq!(sanitize_notice_html(""), "");
+    }
+}
@@ -3,6 +3,19 @@
 use std::sync::OnceLock;
 
 use fake_useragent::{Browsers, UserAgents, UserAgentsBuilder};
+use rand::seq::SliceRandom;
+
+use crate::models::parser_models::AggregatorConfig;
+
+/// A small built-in pool of common user agents from which a user agent is picked for every search
+/// when rotating the user agents is enabled.
+const USER_AGENT_POOL: [&str; 5] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/118.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:118.0) Gecko/20100101 Firefox/118.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Safari/605.1.15",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/118.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:118.0) Gecko/20100101 Firefox/118.0",
+];
 
 /// A static variable which stores the initially build `UserAgents` struct. So as it can be resused
 /// again and again without the need of reinitializing the `UserAgents` struct.
@@ -32,3 +45,35 @@ pub fn random_user_agent() -> &'static str {
         })
         .random()
 }
+
+/// A function to pick a user agent from the built-in pool of common user agents.
+///
+/// # Returns
+///
+/// A user agent string picked at random from the pool.
+pub fn rotating_user_agent() -> &'static str {
+    USER_AGENT_POOL
+        .choose(&mut rand::thread_rng())
+        .copied()
+        .unwrap_or(USER_AGENT_POOL[0])
+}
+
+/// A function to select the user agent sent to the upstream search engines for a search based on
+/// the aggregator config. A user agent is picked from the built-in pool when rotating the user
+/// agents is enabled, otherwise the configured user agent is used and if no user agent is
+/// configured a randomly generated user agent is used.
+///
+/// # Arguments
+///
+/// * `config` - It takes the aggregator config which provides the user agent options.
+///
+/// # Returns
+///
+/// The user agent string to be sent to the upstream search engines.
+pub fn select_user_agent(config: &AggregatorConfig) -> &str {
+    match (config.rotate_user_agent, &config.user_agent) {
+        (true, _) => rotating_user_agent(),
+        (false, Some(user_agent)) => user_agent,
+        (false, None) => random_user_agent(),
+    }
+}
@@ -0,0 +1,238 @@
+//! This module provides the middleware which restricts the access to the whole instance to the
+//! requests providing the `access_token` from the config file, so that a private instance can only
+//! be used by the ones who know the shared secret.
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header,
+    web, Error, HttpRequest, HttpResponse,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use std::{collections::HashMap, sync::Arc};
+
+/// The path of the route which is always accessible so that the health of the instance can be
+/// checked without the access token.
+const HEALTH_CHECK_PATH: &str = "/healthz";
+
+/// The name of the query parameter in which the access token can be provided instead of the
+/// `Authorization` header.
+const TOKEN_QUERY_PARAMETER: &str = "token";
+
+/// A middleware which rejects the requests which do not provide the access token with an
+/// unauthorized response. Every request is allowed if no access token is provided.
+pub struct AccessToken {
+    /// It stores the access token required by the requests.
+    token: Option<Arc<str>>,
+}
+
+impl AccessToken {
+    /// Constructs a new `AccessToken` middleware with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - It takes the `access_token` option from the config file.
+    pub fn new(token: Option<&str>) -> Self {
+        AccessToken {
+            token: token.map(Arc::from),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AccessToken
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = AccessTokenMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AccessTokenMiddleware {
+            service,
+            token: self.token.clone(),
+        }))
+    }
+}
+
+/// The service created by the `AccessToken` middleware which wraps the services of the app.
+pub struct AccessTokenMiddleware<S> {
+    /// It stores the wrapped service.
+    service: S,
+    /// It stores the access token required by the requests.
+    token: Option<Arc<str>>,
+}
+
+impl<S, B> Service<ServiceRequest> for AccessTokenMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match &self.token {
+            Some(token) if !is_authorized(req.request(), token) => {
+                let response: HttpResponse = HttpResponse::Unauthorized()
+                    .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                    .finish();
+                Box::pin(ready(Ok(req.into_response(response).map_into_right_body())))
+            }
+            _ => {
+                let response = self.service.call(req);
+                Box::pin(async move { Ok(response.await?.map_into_left_body()) })
+            }
+        }
+    }
+}
+
+/// A helper function which checks whether the request is allowed to access the instance, which is
+/// the case when it provides the access token as a bearer token in the `Authorization` header or
+/// in the `token` query parameter, or when it is a request to the health check route.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be checked.
+/// * `token` - It takes the access token required by the requests.
+fn is_authorized(req: &HttpRequest, token: &str) -> bool {
+    if req.path() == HEALTH_CHECK_PATH {
+        return true;
+    }
+
+    let header_token: Option<&str> = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    let query_token: Option<String> =
+        web::Query::<HashMap<String, String>>::from_query(req.query_string())
+            .ok()
+            .and_then(|mut params| params.remove(TOKEN_QUERY_PARAMETER));
+
+    // both of the tokens are always compared so that the time taken does not tell which of them
+    // was provided.
+    let header_matches: bool =
+        header_token.is_some_and(|header_token| tokens_match(header_token, token));
+    let query_matches: bool =
+        query_token.is_some_and(|query_token| tokens_match(&query_token, token));
+    header_matches | query_matches
+}
+
+/// A function which builds the request line of the request logged by the logger (like
+/// `GET /search?q=rust HTTP/1.1`) with the value of the `token` query parameter redacted, so that
+/// the access token provided in the url is never written to the logs.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be logged.
+pub fn redacted_request_line(req: &ServiceRequest) -> String {
+    match req.query_string() {
+        "" => format!("{} {} {:?}", req.method(), req.path(), req.version()),
+        query => format!(
+            "{} {}?{} {:?}",
+            req.method(),
+            req.path(),
+            redact_token(query),
+            req.version()
+        ),
+    }
+}
+
+/// A helper function which replaces the value of the `token` query parameter in the provided
+/// query string.
+///
+/// # Arguments
+///
+/// * `query` - It takes the query string of the request.
+fn redact_token(query: &str) -> String {
+    query
+        .split('&')
+        .map(|param| match param.split_once('=') {
+            Some((TOKEN_QUERY_PARAMETER, _)) => format!("{TOKEN_QUERY_PARAMETER}=[redacted]"),
+            _ => param.to_owned(),
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// A function which compares the provided token with the expected token in constant time, so that
+/// the time taken by the comparison does not leak how much of the provided token is correct. Only
+/// the length of the expected token can be learned from the time taken.
+///
+/// # Arguments
+///
+/// * `provided` - It takes the token provided by the request.
+/// * `expected` - It takes the token required by the instance.
+pub fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (provided, expected)| {
+                difference | (provided ^ expected)
+            })
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_is_authorized_accepts_the_header_and_the_query_parameter() {
+        let req: HttpRequest = TestRequest::with_uri("/search?q=rust")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_http_request();
+        assert!(is_authorized(&req, "secret"));
+
+        let req: HttpRequest =
+            TestRequest::with_uri("/search?q=rust&token=secret").to_http_request();
+        assert!(is_authorized(&req, "secret"));
+    }
+
+    #[test]
+    fn test_redacted_request_line_hides_the_token() {
+        let req: ServiceRequest =
+            TestRequest::with_uri("/search?q=rust&token=secret&page=2").to_srv_request();
+        assert_eq!(
+            redacted_request_line(&req),
+            "GET /search?q=rust&token=[redacted]&page=2 HTTP/1.1"
+        );
+
+        let req: ServiceRequest = TestRequest::with_uri("/search").to_srv_request();
+        assert_eq!(redacted_request_line(&req), "GET /search HTTP/1.1");
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    #[test]
+    fn test_is_authorized_rejects_missing_and_wrong_tokens() {
+        let req: HttpRequest = TestRequest::with_uri("/search?q=rust").to_http_request();
+        assert!(!is_authorized(&req, "secret"));
+
+        let req: HttpRequest = TestRequest::with_uri("/?token=wrong")
+            .insert_header((header::AUTHORIZATION, "Bearer wrong"))
+            .to_http_request();
+        assert!(!is_authorized(&req, "secret"));
+
+        let req: HttpRequest = TestRequest::with_uri("/healthz").to_http_request();
+        assert!(is_authorized(&req, "secret"));
+    }
+}
@@ -0,0 +1,260 @@
+//! This module provides the functionality to find the ip address of the client behind the trusted
+//! reverse proxies (like nginx) from the `X-Forwarded-For` and `X-Real-IP` headers, so that the
+//! rate limiter limits every client on its own instead of all the clients behind the proxy at
+//! once. The headers are only trusted when the connecting peer is one of the trusted proxies, as
+//! they can be set to anything by the clients themselves.
+
+use std::{net::IpAddr, sync::Arc};
+
+use actix_governor::{KeyExtractor, SimpleKeyExtractionError};
+use actix_web::{dev::ServiceRequest, http::header::HeaderMap};
+
+/// The header in which the reverse proxies append the address of the peer they received the
+/// request from.
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The header in which the reverse proxies set the address of the client.
+const REAL_IP_HEADER: &str = "x-real-ip";
+
+/// A named struct which stores a trusted proxy, which is either a single ip address or a network
+/// of addresses in the CIDR notation (like `10.0.0.0/8`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrustedProxy {
+    /// It stores the first address of the network.
+    network: IpAddr,
+    /// It stores the length of the prefix of the network in bits.
+    prefix: u8,
+}
+
+impl TrustedProxy {
+    /// A function which parses a trusted proxy from an entry of the `trusted_proxies` option.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - It takes an ip address (like `127.0.0.1`) or a network in the CIDR notation
+    /// (like `10.0.0.0/8`).
+    ///
+    /// # Returns
+    ///
+    /// It returns `None` if the entry is not a valid ip address or network.
+    pub fn parse(entry: &str) -> Option<Self> {
+        let (address, prefix) = match entry.trim().split_once('/') {
+            Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, prefix.parse().ok()?),
+            None => {
+                let address: IpAddr = entry.trim().parse().ok()?;
+                (address, max_prefix(address))
+            }
+        };
+
+        match prefix <= max_prefix(address) {
+            true => Some(TrustedProxy {
+                network: address,
+                prefix,
+            }),
+            false => None,
+        }
+    }
+
+    /// A function which checks whether the provided address belongs to the trusted proxy.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - It takes the ip address to be checked.
+    pub fn contains(&self, address: IpAddr) -> bool {
+        match (self.network, canonical(address)) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                prefix_matches(u32::from(network), u32::from(address), self.prefix, 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                prefix_matches(u128::from(network), u128::from(address), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A helper function which returns the length (in bits) of the provided kind of ip address.
+///
+/// # Arguments
+///
+/// * `address` - It takes an ip address.
+fn max_prefix(address: IpAddr) -> u8 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// A helper function which converts the ipv4 mapped ipv6 addresses to the ipv4 address they map
+/// to, so that an ipv4 proxy is matched on a dual stack listener.
+///
+/// # Arguments
+///
+/// * `address` - It takes an ip address.
+fn canonical(address: IpAddr) -> IpAddr {
+    match address {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(address, IpAddr::V4),
+        IpAddr::V4(_) => address,
+    }
+}
+
+/// A helper function which checks whether the first `prefix` bits of the two addresses match.
+///
+/// # Arguments
+///
+/// * `network` - It takes the network address as an integer.
+/// * `address` - It takes the checked address as an integer.
+/// * `prefix` - It takes the length of the prefix in bits.
+/// * `bits` - It takes the length of the addresses in bits.
+fn prefix_matches<T>(network: T, address: T, prefix: u8, bits: u8) -> bool
+where
+    T: std::ops::Shr<u32, Output = T> + PartialEq,
+{
+    match prefix {
+        0 => true,
+        _ => {
+            let shift: u32 = (bits - prefix) as u32;
+            network >> shift == address >> shift
+        }
+    }
+}
+
+/// A function which finds the ip address of the client which made the request. When the
+/// connecting peer is a trusted proxy the last address of the `X-Forwarded-For` header which is
+/// not a trusted proxy is the client (the addresses before it could have been set by the client
+/// itself), otherwise the `X-Real-IP` header is used if it is provided. The peer is the client in
+/// all the other cases.
+///
+/// # Arguments
+///
+/// * `peer` - It takes the address of the connecting peer.
+/// * `headers` - It takes the headers of the request.
+/// * `trusted_proxies` - It takes the trusted proxies from the config.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[TrustedProxy]) -> IpAddr {
+    let is_trusted = |address: IpAddr| trusted_proxies.iter().any(|proxy| proxy.contains(address));
+    if !is_trusted(peer) {
+        return peer;
+    }
+
+    // the proxies append to the header, so it is walked from the last (closest) address.
+    let forwarded_for: String = headers
+        .get_all(FORWARDED_FOR_HEADER)
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<&str>>()
+        .join(",");
+    let client: Option<IpAddr> = forwarded_for
+        .rsplit(',')
+        // an address which can not be parsed ends the chain as it can not be told who added it.
+        .map_while(|address| address.trim().parse::<IpAddr>().ok())
+        .find(|address| !is_trusted(*address));
+
+    client
+        .or_else(|| {
+            headers
+                .get(REAL_IP_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<IpAddr>().ok())
+        })
+        .unwrap_or(peer)
+}
+
+/// A key extractor for the rate limiter which limits the requests by the ip address of the
+/// client found with the `client_ip` function instead of the address of the connecting peer.
+#[derive(Clone)]
+pub struct ClientIpKeyExtractor {
+    /// It stores the trusted proxies from the config.
+    trusted_proxies: Arc<[TrustedProxy]>,
+}
+
+impl ClientIpKeyExtractor {
+    /// Constructs a new `ClientIpKeyExtractor` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `trusted_proxies` - It takes the trusted proxies from the config.
+    pub fn new(trusted_proxies: &[TrustedProxy]) -> Self {
+        ClientIpKeyExtractor {
+            trusted_proxies: Arc::from(trusted_proxies),
+        }
+    }
+}
+
+impl KeyExtractor for ClientIpKeyExtractor {
+    type Key = IpAddr;
+    type KeyExtractionError = SimpleKeyExtractionError<&'static str>;
+
+    fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+        req.peer_addr()
+            .map(|peer| client_ip(peer.ip(), req.headers(), &self.trusted_proxies))
+            .ok_or_else(|| {
+                SimpleKeyExtractionError::new(
+                    "Could not extract the peer IP address from the request",
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    fn headers(entries: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers: HeaderMap = HeaderMap::new();
+        for (name, value) in entries {
+            headers.append(
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn test_trusted_proxy_parsing_and_matching() {
+        let network: TrustedProxy = TrustedProxy::parse("10.0.0.0/8").unwrap();
+        assert!(network.contains("10.1.2.3".parse().unwrap()));
+        assert!(network.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!network.contains("11.0.0.1".parse().unwrap()));
+
+        let single: TrustedProxy = TrustedProxy::parse("::1").unwrap();
+        assert!(single.contains("::1".parse().unwrap()));
+        assert!(!single.contains("::2".parse().unwrap()));
+
+        for entry in ["", "localhost", "10.0.0.0/33", "10.0.0.0/x", "::/129"] {
+            assert_eq!(TrustedProxy::parse(entry), None, "{entry}");
+        }
+    }
+
+    #[test]
+    fn test_client_ip_is_only_taken_from_trusted_proxies() {
+        let trusted: Vec<TrustedProxy> = vec![TrustedProxy::parse("10.0.0.0/8").unwrap()];
+        let forwarded: HeaderMap = headers(&[
+            ("x-forwarded-for", "1.1.1.1, 2.2.2.2"),
+            ("x-forwarded-for", "10.0.0.2"),
+        ]);
+
+        // the headers of an untrusted peer are ignored.
+        assert_eq!(
+            client_ip("3.3.3.3".parse().unwrap(), &forwarded, &trusted),
+            "3.3.3.3".parse::<IpAddr>().unwrap()
+        );
+        // the address set by the client itself (`1.1.1.1`) is never used.
+        assert_eq!(
+            client_ip("10.0.0.1".parse().unwrap(), &forwarded, &trusted),
+            "2.2.2.2".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            client_ip(
+                "10.0.0.1".parse().unwrap(),
+                &headers(&[("x-real-ip", "4.4.4.4")]),
+                &trusted
+            ),
+            "4.4.4.4".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            client_ip("10.0.0.1".parse().unwrap(), &HeaderMap::new(), &trusted),
+            "10.0.0.1".parse::<IpAddr>().unwrap()
+        );
+    }
+}
@@ -3,5 +3,10 @@
 //! the search route. Also, caches the next, current and previous search results in the search
 //! routes with the help of the redis server.
 
+pub mod access_token;
+pub mod client_ip;
+pub mod query_log;
+pub mod request_id;
 pub mod router;
 pub mod routes;
+pub mod search_queue;
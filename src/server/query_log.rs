@@ -0,0 +1,374 @@
+//! This module provides the optional query log from which the aggregate statistics of the
+//! searches made on the instance (like the number of searches made every day) can be gathered
+//! without storing anything identifying the users. The raw search queries are never logged, in the
+//! `anonymized` mode only the salted hashes of the queries are counted.
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::web;
+use async_trait::async_trait;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::parser::Config,
+    models::parser_models::{QueryLogMode, QueryLogSinkKind},
+};
+
+/// The length of the random salt generated when the `query_log_salt` option is not provided.
+const GENERATED_SALT_LENGTH: usize = 32;
+
+/// A trait through which the aggregate query statistics are stored, so that they can be stored
+/// anywhere (like a file or a redis server).
+#[async_trait]
+pub trait QueryLogSink: Send + Sync {
+    /// A function which counts a search made on the provided day.
+    ///
+    /// # Arguments
+    ///
+    /// * `day` - It takes the day (like `2023-10-16`) on which the search was made.
+    /// * `query_hash` - It takes the salted hash of the search query, which is only provided in
+    /// the `anonymized` mode.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the search could not be counted.
+    async fn record(&self, day: &str, query_hash: Option<&str>) -> std::io::Result<()>;
+}
+
+/// A named struct which stores the statistics of the searches made on a day.
+#[derive(Default, Serialize, Deserialize)]
+struct DayCounts {
+    /// It stores the number of searches made on the day.
+    total: u64,
+    /// It stores the number of searches made for every hash of the search queries.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    queries: BTreeMap<String, u64>,
+}
+
+/// A query log sink which stores the statistics as json in a file, mapped by the days.
+pub struct FileSink {
+    /// It stores the path of the file.
+    path: PathBuf,
+    /// It stores the statistics mapped by the days, which are written to the file as a whole on
+    /// every search.
+    counts: Arc<Mutex<BTreeMap<String, DayCounts>>>,
+}
+
+impl FileSink {
+    /// Constructs a new `FileSink` which continues counting from the statistics already stored in
+    /// the file if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - It takes the path of the file.
+    pub fn new(path: &str) -> Self {
+        let counts: BTreeMap<String, DayCounts> = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        FileSink {
+            path: PathBuf::from(path),
+            counts: Arc::new(Mutex::new(counts)),
+        }
+    }
+}
+
+#[async_trait]
+impl QueryLogSink for FileSink {
+    async fn record(&self, day: &str, query_hash: Option<&str>) -> std::io::Result<()> {
+        let path: PathBuf = self.path.clone();
+        let counts = self.counts.clone();
+        let day: String = day.to_owned();
+        let query_hash: Option<String> = query_hash.map(str::to_owned);
+
+        // the file is written while holding the lock so that an older snapshot of the statistics
+        // can never overwrite a newer one.
+        web::block(move || {
+            let mut counts = counts.lock().unwrap_or_else(PoisonError::into_inner);
+            let day_counts: &mut DayCounts = counts.entry(day).or_default();
+            day_counts.total += 1;
+            if let Some(query_hash) = query_hash {
+                *day_counts.queries.entry(query_hash).or_default() += 1;
+            }
+            std::fs::write(&path, serde_json::to_string_pretty(&*counts)?)
+        })
+        .await
+        .map_err(std::io::Error::other)?
+    }
+}
+
+/// A query log sink which stores the statistics in a redis server, the number of searches made on
+/// a day is stored in the `<prefix>query_log:<day>` key and the number of searches made for every
+/// hash of the search queries in the `<prefix>query_log:<day>:queries` hash.
+#[cfg(feature = "redis-cache")]
+pub struct RedisSink {
+    /// It stores the redis client.
+    client: redis::Client,
+    /// It stores the connection to the redis server, which is established on the first search.
+    connection: async_once_cell::OnceCell<redis::aio::ConnectionManager>,
+    /// It stores the prefix which namespaces all the keys stored in the redis server.
+    key_prefix: String,
+}
+
+#[cfg(feature = "redis-cache")]
+impl RedisSink {
+    /// Constructs a new `RedisSink` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `redis_url` - It takes the redis connection url.
+    /// * `key_prefix` - It takes the prefix which namespaces all the keys stored in the redis
+    /// server.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the redis connection url is invalid.
+    pub fn new(redis_url: &str, key_prefix: &str) -> redis::RedisResult<Self> {
+        Ok(RedisSink {
+            client: redis::Client::open(redis_url)?,
+            connection: async_once_cell::OnceCell::new(),
+            key_prefix: key_prefix.to_owned(),
+        })
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+#[async_trait]
+impl QueryLogSink for RedisSink {
+    async fn record(&self, day: &str, query_hash: Option<&str>) -> std::io::Result<()> {
+        let to_io_error =
+            |error: redis::RedisError| std::io::Error::new(std::io::ErrorKind::Other, error);
+
+        let mut connection: redis::aio::ConnectionManager = self
+            .connection
+            .get_or_try_init(self.client.get_tokio_connection_manager())
+            .await
+            .map_err(to_io_error)?
+            .clone();
+
+        let key: String = format!("{}query_log:{day}", self.key_prefix);
+        let mut pipeline: redis::Pipeline = redis::pipe();
+        pipeline.incr(&key, 1).ignore();
+        if let Some(query_hash) = query_hash {
+            pipeline
+                .hincr(format!("{key}:queries"), query_hash, 1)
+                .ignore();
+        }
+        pipeline
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(to_io_error)
+    }
+}
+
+/// A struct which logs the searches made on the instance to the query log sink selected in the
+/// config, it is shared between all the searches.
+pub struct QueryLog {
+    /// It stores what is logged about the searches.
+    mode: QueryLogMode,
+    /// It stores the secret salt with which the search queries are hashed.
+    salt: String,
+    /// It stores the sink to which the searches are logged, which is only provided when the query
+    /// log is enabled.
+    sink: Option<Box<dyn QueryLogSink>>,
+}
+
+impl QueryLog {
+    /// Constructs a new `QueryLog` which logs the searches in the provided mode to the provided
+    /// sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - It takes what is logged about the searches.
+    /// * `salt` - It takes the secret salt with which the search queries are hashed.
+    /// * `sink` - It takes the sink to which the searches are logged.
+    pub fn new(mode: QueryLogMode, salt: &str, sink: Box<dyn QueryLogSink>) -> Self {
+        QueryLog {
+            mode,
+            salt: salt.to_owned(),
+            sink: match mode {
+                QueryLogMode::Off => None,
+                _ => Some(sink),
+            },
+        }
+    }
+
+    /// Constructs a new `QueryLog` with the mode, the salt and the sink from the config.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the sink could not be set up (like when the redis connection url is
+    /// invalid).
+    pub fn from_config(config: &Config) -> std::io::Result<Self> {
+        let server = &config.server;
+        if server.query_log == QueryLogMode::Off {
+            return Ok(QueryLog {
+                mode: QueryLogMode::Off,
+                salt: String::new(),
+                sink: None,
+            });
+        }
+
+        let sink: Box<dyn QueryLogSink> = match server.query_log_sink {
+            QueryLogSinkKind::File => Box::new(FileSink::new(&server.query_log_file)),
+            #[cfg(feature = "redis-cache")]
+            QueryLogSinkKind::Redis => Box::new(
+                RedisSink::new(&config.redis_url, &config.redis_prefix).map_err(|error| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, error)
+                })?,
+            ),
+            #[cfg(not(feature = "redis-cache"))]
+            QueryLogSinkKind::Redis => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "The `redis` sink of the `query_log_sink` option requires the `redis-cache` feature",
+                ))
+            }
+        };
+
+        // the hashes can only be compared between the restarts of the server if the salt is
+        // provided.
+        let salt: String = server.query_log_salt.clone().unwrap_or_else(|| {
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(GENERATED_SALT_LENGTH)
+                .map(char::from)
+                .collect()
+        });
+
+        Ok(QueryLog::new(server.query_log, &salt, sink))
+    }
+
+    /// A function which checks whether the searches are logged.
+    pub fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// A function which logs a search for the provided query, the query itself is never logged
+    /// and any failure to log it is only logged as an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query.
+    pub async fn record(&self, query: &str) {
+        let Some(sink) = &self.sink else {
+            return;
+        };
+
+        let now: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let query_hash: Option<String> = match self.mode {
+            QueryLogMode::Anonymized => Some(hash_query(query, &self.salt)),
+            _ => None,
+        };
+
+        if let Err(error) = sink.record(&day_of(now), query_hash.as_deref()).await {
+            log::error!("Unable to log the search to the query log: {error}");
+        }
+    }
+}
+
+/// A helper function which hashes the search query with the salt. The query is normalized first
+/// so that the same query typed differently (like `Rust  Lang` and `rust lang`) is counted once.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query.
+/// * `salt` - It takes the secret salt.
+fn hash_query(query: &str, salt: &str) -> String {
+    let normalized: String = query
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase();
+    format!("{:x}", md5::compute(format!("{salt}{normalized}")))
+}
+
+/// A helper function which converts the provided unix timestamp to the day (in UTC) it falls on,
+/// in the `YYYY-MM-DD` format.
+///
+/// # Arguments
+///
+/// * `timestamp` - It takes the unix timestamp (in seconds).
+fn day_of(timestamp: u64) -> String {
+    // the civil date is derived from the days since the epoch in eras of 400 years, as described
+    // in http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days: u64 = timestamp / 86400 + 719468;
+    let era: u64 = days / 146097;
+    let day_of_era: u64 = days % 146097;
+    let year_of_era: u64 =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year: u64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index: u64 = (5 * day_of_year + 2) / 153;
+    let day: u64 = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month: u64 = match month_index < 10 {
+        true => month_index + 3,
+        false => month_index - 9,
+    };
+    let year: u64 = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_of_timestamps() {
+        assert_eq!(day_of(0), "1970-01-01");
+        assert_eq!(day_of(951_782_400), "2000-02-29");
+        assert_eq!(day_of(1_697_414_399), "2023-10-15");
+        assert_eq!(day_of(1_697_414_400), "2023-10-16");
+    }
+
+    #[test]
+    fn test_queries_are_hashed_with_the_salt() {
+        let hash: String = hash_query(" Rust   Lang ", "salt");
+        assert_eq!(hash, hash_query("rust lang", "salt"));
+        assert_ne!(hash, hash_query("rust lang", "pepper"));
+        assert!(!hash.contains("rust"));
+    }
+
+    #[actix_web::test]
+    async fn test_file_sink_counts_the_searches() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path: &str = file.path().to_str().unwrap();
+
+        let query_log: QueryLog = QueryLog::new(
+            QueryLogMode::Anonymized,
+            "salt",
+            Box::new(FileSink::new(path)),
+        );
+        query_log.record("rust").await;
+        query_log.record("Rust").await;
+        query_log.record("sweden").await;
+
+        let json: String = std::fs::read_to_string(path).unwrap();
+        assert!(!json.contains("rust") && !json.contains("sweden"));
+        let counts: BTreeMap<String, DayCounts> = serde_json::from_str(&json).unwrap();
+        let day_counts: &DayCounts = counts.values().next().unwrap();
+        assert_eq!(day_counts.total, 3);
+        assert_eq!(day_counts.queries[&hash_query("rust", "salt")], 2);
+
+        // the counting continues from the statistics stored in the file.
+        let sink: FileSink = FileSink::new(path);
+        sink.record("2023-10-16", None).await.unwrap();
+        let counts: BTreeMap<String, DayCounts> =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(counts.len(), 2);
+    }
+}
@@ -0,0 +1,123 @@
+//! This module provides the middleware which assigns an id to every request, so that the log lines
+//! of all the upstream requests made while handling a search can be correlated with each other and
+//! with the response sent to the user.
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue},
+    Error, HttpMessage, HttpRequest,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use rand::Rng;
+
+/// The name of the response header in which the id of the request is sent back to the user.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// A named struct which stores the id assigned to a request, it is stored in the extensions of the
+/// request so that the handlers can access it.
+#[derive(Clone, Debug)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Constructs a new `RequestId` with a random id made up of 16 hexadecimal characters.
+    pub fn new() -> Self {
+        RequestId(format!("{:016x}", rand::thread_rng().gen::<u64>()))
+    }
+
+    /// A function which returns the id of the request.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A function which returns the id assigned to the provided request by the `RequestIdentifier`
+/// middleware, a placeholder is returned if the request was not handled by the middleware.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request whose id is needed.
+pub fn request_id(req: &HttpRequest) -> String {
+    req.extensions()
+        .get::<RequestId>()
+        .map(|request_id| request_id.as_str().to_owned())
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+/// A middleware which assigns a new id to every request and sends it back to the user in the
+/// `X-Request-Id` response header.
+pub struct RequestIdentifier;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdentifier
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestIdentifierMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdentifierMiddleware { service }))
+    }
+}
+
+/// The service created by the `RequestIdentifier` middleware which wraps the services of the app.
+pub struct RequestIdentifierMiddleware<S> {
+    /// It stores the wrapped service.
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdentifierMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id: RequestId = RequestId::new();
+        req.extensions_mut().insert(request_id.clone());
+
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let mut response: ServiceResponse<B> = response.await?;
+            if let Ok(value) = HeaderValue::from_str(request_id.as_str()) {
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_request_id_is_read_from_the_extensions() {
+        let req: HttpRequest = TestRequest::default().to_http_request();
+        assert_eq!(request_id(&req), "-");
+
+        let id: RequestId = RequestId::new();
+        assert_eq!(id.as_str().len(), 16);
+        req.extensions_mut().insert(id.clone());
+        assert_eq!(request_id(&req), id.as_str());
+    }
+}
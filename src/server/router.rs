@@ -3,29 +3,55 @@
 //! when requested.
 
 use crate::{
-    config::parser::Config,
-    handler::paths::{file_path, FileType},
+    cache::cacher::SharedCache,
+    config::parser::{Config, SharedConfig},
+    engines::registry::registered_engines,
+    handler::{
+        paths::{file_path, FileType},
+        styles::AvailableStyles,
+    },
+    models::{
+        engine_models::EngineHandler,
+        parser_models::RobotsTxt,
+        server_models::{Cookie, SettingsForm, SettingsPage},
+    },
+    results::filter_lists::SharedFilterLists,
+    server::access_token::tokens_match,
+};
+use actix_web::{
+    cookie::{time::Duration, Cookie as HttpCookie, SameSite},
+    get,
+    http::header,
+    post, web, HttpRequest, HttpResponse,
 };
-use actix_web::{get, web, HttpRequest, HttpResponse};
 use handlebars::Handlebars;
-use std::fs::read_to_string;
+use std::{fs::read_to_string, sync::Arc};
+
+/// The name of the header in which the `admin_token` is provided to the admin only routes, which
+/// is kept apart from the `Authorization` header carrying the `access_token` of a private instance
+/// so that both of them can be provided at once.
+const ADMIN_TOKEN_HEADER: &str = "X-Admin-Token";
 
 /// Handles the route of index page or main page of the `websurfx` meta search engine website.
 #[get("/")]
 pub async fn index(
     hbs: web::Data<Handlebars<'_>>,
-    config: web::Data<Config>,
+    config: web::Data<SharedConfig>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    let page_content: String = hbs.render("index", &config.style).unwrap();
-    Ok(HttpResponse::Ok().body(page_content))
+    let config: Arc<Config> = config.load();
+    let page_content: String = hbs.render("index", &config.style)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page_content))
 }
 
 /// Handles the route of any other accessed route/page which is not provided by the
 /// website essentially the 404 error page.
 pub async fn not_found(
     hbs: web::Data<Handlebars<'_>>,
-    config: web::Data<Config>,
+    config: web::Data<SharedConfig>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
     let page_content: String = hbs.render("404", &config.style)?;
 
     Ok(HttpResponse::Ok()
@@ -33,32 +59,421 @@ pub async fn not_found(
         .body(page_content))
 }
 
-/// Handles the route of robots.txt page of the `websurfx` meta search engine website.
+/// Handles the route of robots.txt page of the `websurfx` meta search engine website, which serves
+/// the content from the `robots_txt` or the `robots_txt_file` option if either is provided and
+/// the `robots.txt` file of the theme otherwise.
 #[get("/robots.txt")]
-pub async fn robots_data(_req: HttpRequest) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    let page_content: String =
-        read_to_string(format!("{}/robots.txt", file_path(FileType::Theme)?))?;
+pub async fn robots_data(
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    let page_content: String = match &config.server.robots_txt {
+        Some(RobotsTxt::Inline(content)) => content.clone(),
+        Some(RobotsTxt::File(path)) => read_to_string(path)?,
+        None => read_to_string(format!("{}/robots.txt", file_path(FileType::Theme)?))?,
+    };
     Ok(HttpResponse::Ok()
         .content_type("text/plain; charset=ascii")
         .body(page_content))
 }
 
+/// Handles the health check route of the `websurfx` meta search engine website, which is always
+/// accessible (even when an `access_token` is required) so that the health of the instance can be
+/// monitored.
+#[get("/healthz")]
+pub async fn health_check() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body("ok")
+}
+
+/// Handles the version route of the `websurfx` meta search engine website, which reports the
+/// version of `websurfx`, the git commit it was built from (if it was known at build time) and
+/// the upstream search engines registered in it as json.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/version"
+/// ```
+#[get("/version")]
+pub async fn version() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "commit": option_env!("WEBSURFX_GIT_COMMIT"),
+        "engines": registered_engines(),
+    }))
+}
+
 /// Handles the route of about page of the `websurfx` meta search engine website.
 #[get("/about")]
 pub async fn about(
     hbs: web::Data<Handlebars<'_>>,
-    config: web::Data<Config>,
+    config: web::Data<SharedConfig>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
     let page_content: String = hbs.render("about", &config.style)?;
-    Ok(HttpResponse::Ok().body(page_content))
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page_content))
 }
 
 /// Handles the route of settings page of the `websurfx` meta search engine website.
 #[get("/settings")]
 pub async fn settings(
     hbs: web::Data<Handlebars<'_>>,
-    config: web::Data<Config>,
+    config: web::Data<SharedConfig>,
+    styles: web::Data<AvailableStyles>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    let page_content: String = hbs.render(
+        "settings",
+        &SettingsPage {
+            style: &config.style,
+            themes: &styles.themes,
+            colorschemes: &styles.colorschemes,
+            engines: &registered_engines(),
+        },
+    )?;
+    // the settings page shows the personal preferences of the user and so it is never cached.
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(page_content))
+}
+
+/// Handles the submission of the settings page of the `websurfx` meta search engine website. It
+/// validates the submitted preferences and saves them in the `appCookie` before redirecting back
+/// to the settings page. The selected engines are provided as a comma separated list of names.
+/// The preferences are rejected with `403 Forbidden` when the cookies are disabled.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -d "theme=simple&colorscheme=nord&engines=duckduckgo,searx&safe_search_level=1" "http://127.0.0.1:8080/settings"
+/// ```
+#[post("/settings")]
+pub async fn save_settings(
+    form: web::Form<SettingsForm>,
+    config: web::Data<SharedConfig>,
+    styles: web::Data<AvailableStyles>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    let preferences: Cookie<'_> = Cookie {
+        theme: &form.theme,
+        colorscheme: &form.colorscheme,
+        engines: form
+            .engines
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect(),
+        safe_search_level: form.safe_search_level.unwrap_or(config.safe_search).min(2),
+    };
+
+    if let Err(response) = validate_settings(&preferences, &styles) {
+        return Ok(response);
+    }
+
+    // the preferences can only be saved in the browser storage when the cookies are disabled.
+    if config.server.disable_cookies {
+        return Ok(cookies_disabled());
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .cookie(settings_cookie(&preferences)?)
+        .insert_header(("location", "/settings"))
+        .finish())
+}
+
+/// Handles the route which exports the preferences saved in the `appCookie` as a downloadable
+/// json file, which can be imported back through the settings import route. The preferences from
+/// the config file are exported if no (valid) preferences are saved. The export is rejected with
+/// `403 Forbidden` when the cookies are disabled.
+///
+/// # Example
+///
+/// ```bash
+/// curl -b 'appCookie={"theme":"simple","colorscheme":"nord","engines":["searx"],"safe_search_level":1}' "http://127.0.0.1:8080/settings/export"
+/// ```
+#[get("/settings/export")]
+pub async fn export_settings(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    if config.server.disable_cookies {
+        return Ok(cookies_disabled());
+    }
+
+    let cookie: Option<HttpCookie<'static>> = req.cookie("appCookie");
+    let preferences: Cookie<'_> = match cookie
+        .as_ref()
+        .and_then(|cookie| serde_json::from_str(cookie.value()).ok())
+    {
+        Some(preferences) => preferences,
+        None => Cookie {
+            theme: &config.style.theme,
+            colorscheme: &config.style.colorscheme,
+            engines: config
+                .upstream_search_engines
+                .iter()
+                .map(EngineHandler::name)
+                .collect(),
+            safe_search_level: config.safe_search.min(2),
+        },
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"websurfx-settings.json\"",
+        ))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(serde_json::to_string_pretty(&preferences)?))
+}
+
+/// Handles the route which imports the preferences from the json file produced by the settings
+/// export route and saves them in the `appCookie`. The imported engines, theme and colorscheme are
+/// validated in the same way as the ones submitted from the settings page, and the import is
+/// rejected with `403 Forbidden` when the cookies are disabled.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -H "Content-Type: application/json" --data-binary @websurfx-settings.json "http://127.0.0.1:8080/settings/import"
+/// ```
+#[post("/settings/import")]
+pub async fn import_settings(
+    body: web::Bytes,
+    config: web::Data<SharedConfig>,
+    styles: web::Data<AvailableStyles>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    let page_content: String = hbs.render("settings", &config.style)?;
-    Ok(HttpResponse::Ok().body(page_content))
+    let config: Arc<Config> = config.load();
+    if config.server.disable_cookies {
+        return Ok(cookies_disabled());
+    }
+
+    let preferences: Cookie<'_> = match serde_json::from_slice(&body) {
+        Ok(preferences) => preferences,
+        Err(error) => {
+            return Ok(HttpResponse::BadRequest()
+                .content_type("text/plain; charset=utf-8")
+                .body(format!("The imported settings are not valid: {error}")))
+        }
+    };
+
+    if let Err(response) = validate_settings(&preferences, &styles) {
+        return Ok(response);
+    }
+
+    Ok(HttpResponse::NoContent()
+        .cookie(settings_cookie(&preferences)?)
+        .finish())
+}
+
+/// A helper function which validates the preferences submitted from the settings page or
+/// imported from a settings file, which must only select the known engines, an available theme
+/// and colorscheme and a safe search level from `0` to `2`.
+///
+/// # Arguments
+///
+/// * `preferences` - It takes the preferences to be saved in the `appCookie`.
+/// * `styles` - It takes the themes and colorschemes available in the public folder.
+///
+/// # Error
+///
+/// Returns the `400 Bad Request` response to be sent back if the preferences are not valid.
+fn validate_settings(
+    preferences: &Cookie<'_>,
+    styles: &AvailableStyles,
+) -> Result<(), HttpResponse> {
+    let unknown_engines: Vec<&str> = preferences
+        .engines
+        .iter()
+        .copied()
+        .filter(|name| EngineHandler::new(name).is_none())
+        .collect();
+
+    if !unknown_engines.is_empty() {
+        return Err(HttpResponse::BadRequest()
+            .content_type("text/plain; charset=utf-8")
+            .body(format!(
+                "Unknown engine(s) selected: {}. Please select the engines from the ones listed on the settings page.",
+                unknown_engines.join(", ")
+            )));
+    }
+
+    if !styles.is_valid(preferences.theme, preferences.colorscheme) {
+        return Err(HttpResponse::BadRequest()
+            .content_type("text/plain; charset=utf-8")
+            .body("Unknown theme or colorscheme selected."));
+    }
+
+    if preferences.safe_search_level > 2 {
+        return Err(HttpResponse::BadRequest()
+            .content_type("text/plain; charset=utf-8")
+            .body("The safe search level should be from 0 to 2."));
+    }
+
+    Ok(())
+}
+
+/// A helper function which builds the `appCookie` storing the provided preferences of the user.
+///
+/// # Arguments
+///
+/// * `preferences` - It takes the validated preferences of the user.
+///
+/// # Error
+///
+/// Returns an error if the preferences could not be serialized.
+fn settings_cookie(preferences: &Cookie<'_>) -> Result<HttpCookie<'static>, serde_json::Error> {
+    // The cookie is not marked as `HttpOnly` because the client side scripts need to read it to
+    // apply the selected theme and to display it in the cookies tab of the settings page.
+    Ok(
+        HttpCookie::build("appCookie", serde_json::to_string(preferences)?)
+            .path("/")
+            .same_site(SameSite::Strict)
+            .http_only(false)
+            .max_age(Duration::days(365))
+            .finish(),
+    )
+}
+
+/// A helper function which builds the response sent back when the preferences are saved,
+/// exported or imported while the cookies are disabled in the config.
+fn cookies_disabled() -> HttpResponse {
+    HttpResponse::Forbidden()
+        .content_type("text/plain; charset=utf-8")
+        .body("Saving the settings in a cookie is disabled on this instance, please save them from the settings page with javascript enabled.")
+}
+
+/// Handles the admin only route which reloads the blocklist and allowlist files without restarting
+/// the server. The route requires the `admin_token` provided in the config file to be sent in the
+/// `X-Admin-Token` header and is disabled if no `admin_token` is provided.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -H "X-Admin-Token: <admin_token>" "http://127.0.0.1:8080/reload-filters"
+/// ```
+#[post("/reload-filters")]
+pub async fn reload_filters(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    filter_lists: web::Data<SharedFilterLists>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    if let Err(response) = authorize_admin(&req, &config) {
+        return Ok(response);
+    }
+
+    match filter_lists.reload() {
+        Ok(()) => Ok(HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .body("The filter lists have been reloaded successfully.")),
+        Err(error) => {
+            log::error!("Unable to reload the filter lists: {error}");
+            Ok(HttpResponse::InternalServerError()
+                .content_type("text/plain; charset=utf-8")
+                .body(format!("Unable to reload the filter lists: {error}")))
+        }
+    }
+}
+
+/// Handles the admin only route which removes all the cached search results and favicons, so that
+/// the changes made to the config (like the upstream search engines) take effect immediately
+/// instead of once the cached results expire. Only the keys stored by websurfx are removed from the
+/// redis server. The route requires the `admin_token` provided in the config file to be sent in the
+/// `X-Admin-Token` header and is disabled if no `admin_token` is provided.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -H "X-Admin-Token: <admin_token>" "http://127.0.0.1:8080/cache/flush"
+/// ```
+#[post("/cache/flush")]
+pub async fn flush_cache(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    if let Err(response) = authorize_admin(&req, &config) {
+        return Ok(response);
+    }
+
+    match cache.flush().await {
+        Ok(removed) => Ok(HttpResponse::Ok().json(serde_json::json!({ "removed": removed }))),
+        Err(error) => {
+            log::error!("Unable to flush the cache: {error:?}");
+            Ok(HttpResponse::InternalServerError().json(
+                serde_json::json!({ "error": format!("Unable to flush the cache: {error}") }),
+            ))
+        }
+    }
+}
+
+/// A helper function which checks whether the request to an admin only route is authorized, which
+/// is the case when it provides the `admin_token` from the config file in the `X-Admin-Token`
+/// header.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to the admin only route.
+/// * `config` - It takes the parsed config.
+///
+/// # Error
+///
+/// Returns the response to be sent back if the request is not authorized, which is a not found
+/// response if no `admin_token` is provided and an unauthorized response otherwise.
+fn authorize_admin(req: &HttpRequest, config: &Config) -> Result<(), HttpResponse> {
+    let admin_token: &str = match &config.admin_token {
+        Some(admin_token) => admin_token,
+        None => return Err(HttpResponse::NotFound().finish()),
+    };
+
+    let is_authorized: bool = req
+        .headers()
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|token| tokens_match(token, admin_token));
+
+    match is_authorized {
+        true => Ok(()),
+        false => Err(HttpResponse::Unauthorized().finish()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_admin_token_is_provided_apart_from_the_access_token() {
+        let mut config: Config = Config::validate_only("./websurfx/config.lua").unwrap();
+        config.server.access_token = Some("access".to_owned());
+        config.admin_token = Some("admin".to_owned());
+
+        // the access token of a private instance and the admin token are provided together.
+        let req: HttpRequest = TestRequest::post()
+            .insert_header((header::AUTHORIZATION, "Bearer access"))
+            .insert_header((ADMIN_TOKEN_HEADER, "admin"))
+            .to_http_request();
+        assert!(authorize_admin(&req, &config).is_ok());
+
+        // the admin token is not accepted as a bearer token.
+        let req: HttpRequest = TestRequest::post()
+            .insert_header((header::AUTHORIZATION, "Bearer admin"))
+            .to_http_request();
+        assert!(authorize_admin(&req, &config).is_err());
+
+        let req: HttpRequest = TestRequest::post()
+            .insert_header((ADMIN_TOKEN_HEADER, "access"))
+            .to_http_request();
+        assert!(authorize_admin(&req, &config).is_err());
+    }
 }
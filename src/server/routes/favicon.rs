@@ -0,0 +1,151 @@
+//! This module handles the favicon and the thumbnail proxy routes of the search engine website.
+
+use crate::{
+    cache::cacher::SharedCache,
+    config::parser::{Config, SharedConfig},
+    models::server_models::{FaviconParams, ThumbnailParams},
+    results::{
+        favicon::{fetch_favicon, fetch_thumbnail, validate_domain, Favicon, FaviconError},
+        user_agent::select_user_agent,
+    },
+};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use std::sync::Arc;
+
+/// The time (in seconds) for which the browsers are allowed to cache the proxied favicons.
+const FAVICON_MAX_AGE: u64 = 7 * 24 * 60 * 60;
+
+/// The time (in seconds) for which the browsers are allowed to cache the proxied thumbnails.
+const THUMBNAIL_MAX_AGE: u64 = 24 * 60 * 60;
+
+/// Handles the favicon proxy route of the `websurfx` meta search engine website. It takes the
+/// `domain` url parameter and responds with the favicon of the domain which is fetched (and
+/// cached) server-side, so that the ip address of the user is never leaked to the domains of the
+/// search results.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/favicon-proxy?domain=example.com"
+/// ```
+#[get("/favicon-proxy")]
+pub async fn favicon_proxy(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    let params = web::Query::<FaviconParams>::from_query(req.query_string())?;
+    let domain: String = match params.domain.as_deref().and_then(validate_domain) {
+        Some(domain) => domain,
+        None => {
+            return Ok(HttpResponse::BadRequest()
+                .body("The `domain` parameter is missing or is not a valid public domain"))
+        }
+    };
+
+    if let Ok(favicon) = cache.cached_favicon(&domain).await {
+        return Ok(image_response(favicon, FAVICON_MAX_AGE));
+    }
+
+    match fetch_favicon(
+        &domain,
+        select_user_agent(&config.aggregator),
+        config.request_timeout,
+    )
+    .await
+    {
+        Ok(favicon) => {
+            if let Err(error) = cache.cache_favicon(&favicon, &domain).await {
+                log::error!("Cache Error: Unable to cache the favicon of {domain}: {error:?}");
+            }
+            Ok(image_response(favicon, FAVICON_MAX_AGE))
+        }
+        Err(error) => {
+            log::debug!("Favicon Error: {error:?}");
+            Ok(error_response(&error))
+        }
+    }
+}
+
+/// Handles the thumbnail proxy route of the `websurfx` meta search engine website. It takes the
+/// `url` url parameter and responds with the thumbnail at the url which is fetched (and cached)
+/// server-side with the same restrictions as the favicons: only public `http` and `https` urls
+/// are requested and only raster images within the size limit are served. The route is only
+/// available when the `proxy_thumbnails` option is enabled.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/thumbnail-proxy?url=https%3A%2F%2Fi.ytimg.com%2Fvi%2Fabc%2Fhqdefault.jpg"
+/// ```
+#[get("/thumbnail-proxy")]
+pub async fn thumbnail_proxy(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    if !config.aggregator.proxy_thumbnails {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let params = web::Query::<ThumbnailParams>::from_query(req.query_string())?;
+    let url: &str = match params.url.as_deref() {
+        Some(url) if !url.trim().is_empty() => url.trim(),
+        _ => return Ok(HttpResponse::BadRequest().body("The `url` parameter is missing")),
+    };
+
+    if let Ok(thumbnail) = cache.cached_thumbnail(url).await {
+        return Ok(image_response(thumbnail, THUMBNAIL_MAX_AGE));
+    }
+
+    match fetch_thumbnail(
+        url,
+        select_user_agent(&config.aggregator),
+        config.request_timeout,
+    )
+    .await
+    {
+        Ok(thumbnail) => {
+            if let Err(error) = cache.cache_thumbnail(&thumbnail, url).await {
+                log::error!("Cache Error: Unable to cache the thumbnail {url}: {error:?}");
+            }
+            Ok(image_response(thumbnail, THUMBNAIL_MAX_AGE))
+        }
+        Err(error) => {
+            log::debug!("Thumbnail Error: {error:?}");
+            Ok(error_response(&error))
+        }
+    }
+}
+
+/// A helper function which maps an error which occurred while fetching an image to the response
+/// of the proxy routes.
+///
+/// # Arguments
+///
+/// * `error` - It takes the error which occurred while fetching the image.
+fn error_response(error: &error_stack::Report<FaviconError>) -> HttpResponse {
+    match error.current_context() {
+        FaviconError::InvalidDomain | FaviconError::ForbiddenAddress => {
+            HttpResponse::Forbidden().finish()
+        }
+        FaviconError::RequestError => HttpResponse::BadGateway().finish(),
+    }
+}
+
+/// A helper function which builds the response serving the provided image. The response can be
+/// cached by the browser and is never sniffed into another content type.
+///
+/// # Arguments
+///
+/// * `image` - It takes the image which needs to be served.
+/// * `max_age` - It takes the time (in seconds) for which the browsers can cache the image.
+fn image_response(image: Favicon, max_age: u64) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(image.content_type)
+        .insert_header(("cache-control", format!("public, max-age={max_age}")))
+        .insert_header(("x-content-type-options", "nosniff"))
+        .body(image.body)
+}
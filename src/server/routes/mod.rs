@@ -1,3 +1,5 @@
 //! This module provides modules to handle various routes in the search engine website.
 
+pub mod favicon;
+pub mod redirect;
 pub mod search;
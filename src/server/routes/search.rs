@@ -2,30 +2,60 @@
 
 use crate::{
     cache::cacher::SharedCache,
-    config::parser::Config,
-    handler::paths::{file_path, FileType},
+    config::parser::{Config, SharedConfig},
+    handler::styles::AvailableStyles,
     models::{
-        aggregation_models::SearchResults,
-        engine_models::EngineHandler,
-        server_models::{Cookie, SearchParams},
+        aggregation_models::{
+            EngineErrorInfo, Pagination, ResultStats, SearchResult, SearchResults, StreamEvent,
+        },
+        engine_models::{normalize_regions, EngineHandler, UpstreamHeaders},
+        parser_models::{Bang, SafeSearchBlockMode, Style},
+        server_models::{Cookie, OutputFormat, SearchParams, SearchType, SortOrder},
+    },
+    results::{
+        aggregator::{aggregate, sort_results, stream_results},
+        bangs::{parse_bang, redirect_url},
+        circuit_breaker::CircuitBreaker,
+        direct_navigation::direct_navigation_url,
+        filter_lists::SharedFilterLists,
+        user_agent::select_user_agent,
+    },
+    server::{
+        query_log::QueryLog,
+        request_id::request_id,
+        search_queue::{SearchQueue, SearchQueueFull, SearchQueueSlot},
     },
-    results::aggregator::aggregate,
 };
-use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_web::{
+    cookie::Cookie as HttpCookie,
+    get,
+    http::header::{self, Header, ACCEPT_LANGUAGE},
+    web, HttpRequest, HttpResponse,
+};
+use futures::stream;
 use handlebars::Handlebars;
-use regex::Regex;
+use reqwest::Client;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Read},
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
+    sync::Arc,
+    time::Instant,
+};
+use tokio::{
+    join,
+    sync::{mpsc, Semaphore},
 };
-use tokio::join;
+
+/// Aliases for long type annotations
+type EngineSelection = (Vec<EngineHandler>, Vec<EngineErrorInfo>, u8);
 
 /// Handles the route of any other accessed route/page which is not provided by the
 /// website essentially the 404 error page.
 pub async fn not_found(
     hbs: web::Data<Handlebars<'_>>,
-    config: web::Data<Config>,
+    config: web::Data<SharedConfig>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
     let page_content: String = hbs.render("404", &config.style)?;
 
     Ok(HttpResponse::Ok()
@@ -34,7 +64,14 @@ pub async fn not_found(
 }
 
 /// Handles the route of search page of the `websurfx` meta search engine website and it takes
-/// two search url parameters `q` and `page` where `page` parameter is optional.
+/// two search url parameters `q` and `page` where `page` parameter is optional. The optional
+/// `type` parameter can be set to `videos` or `news` to request video or news results instead of
+/// general results. The search results are returned as json (like the search api does) instead of
+/// the search page when the `Accept` header of the request prefers `application/json`, and the
+/// general search results are returned as an RSS 2.0 feed when the `format` parameter is set to
+/// `rss`. The optional `sort` parameter sorts the general search results by their `relevance`
+/// (the default), their publishing `date` (the results without one follow in the order of their
+/// relevance) or `alphabetical`ly by their titles.
 ///
 /// # Example
 ///
@@ -47,13 +84,34 @@ pub async fn not_found(
 /// ```bash
 /// curl "http://127.0.0.1:8080/search?q=sweden"
 /// ```
+///
+/// Or
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/search?q=sweden&type=videos"
+/// ```
+///
+/// Or
+///
+/// ```bash
+/// curl -H "Accept: application/json" "http://127.0.0.1:8080/search?q=sweden"
+/// ```
+///
+/// Or
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/search?q=sweden&format=rss"
+/// ```
 #[get("/search")]
 pub async fn search(
     hbs: web::Data<Handlebars<'_>>,
     req: HttpRequest,
-    config: web::Data<Config>,
+    config: web::Data<SharedConfig>,
     cache: web::Data<SharedCache>,
+    query_log: web::Data<QueryLog>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let started: Instant = Instant::now();
+    let config: Arc<Config> = config.load();
     let params = web::Query::<SearchParams>::from_query(req.query_string())?;
     match &params.q {
         Some(query) => {
@@ -62,58 +120,72 @@ pub async fn search(
                     .insert_header(("location", "/"))
                     .finish());
             }
-            let page = match &params.page {
-                Some(page) => *page,
-                None => 1,
+            if let Some(rejection) = reject_long_query(query, config.aggregator.max_query_length) {
+                return Ok(rejection);
+            }
+            // only the first page is logged so that paging through the results of a search is not
+            // counted as more searches, the search is logged in the background.
+            if query_log.is_enabled() && params.page.unwrap_or(1) <= 1 {
+                let query_log: web::Data<QueryLog> = query_log.clone();
+                let query: String = query.to_owned();
+                tokio::spawn(async move { query_log.record(&query).await });
+            }
+            if let Some(redirect) = bang_redirect(query, &config) {
+                return Ok(redirect);
+            }
+            if let Some(redirect) = direct_navigation_redirect(query, &config) {
+                return Ok(redirect);
+            }
+            let template: &str = match params.search_type {
+                SearchType::General => "search",
+                SearchType::Videos => "videos",
+                SearchType::News => "news",
             };
 
-            let (_, results, _) = join!(
-                results(
-                    format!(
-                        "http://{}:{}/search?q={}&page={}&safesearch=",
-                        config.binding_ip,
-                        config.port,
-                        query,
-                        page - 1,
-                    ),
-                    &config,
-                    &cache,
-                    query,
-                    page - 1,
-                    req.clone(),
-                    &params.safesearch
-                ),
-                results(
-                    format!(
-                        "http://{}:{}/search?q={}&page={}&safesearch=",
-                        config.binding_ip, config.port, query, page
-                    ),
-                    &config,
-                    &cache,
-                    query,
-                    page,
-                    req.clone(),
-                    &params.safesearch
-                ),
-                results(
-                    format!(
-                        "http://{}:{}/search?q={}&page={}&safesearch=",
-                        config.binding_ip,
-                        config.port,
-                        query,
-                        page + 1,
-                    ),
-                    &config,
-                    &cache,
-                    query,
-                    page + 1,
-                    req.clone(),
-                    &params.safesearch
-                )
-            );
+            // a single slot of the search queue is held while the results of the search are
+            // fetched, a search which can not enter the queue is turned away.
+            let slot: SearchQueueSlot = match search_queue(&req)?.try_enter() {
+                Ok(slot) => slot,
+                Err(SearchQueueFull) => return Ok(queue_full_response(&config)),
+            };
+            let mut results: SearchResults =
+                search_results(&config, &cache, query, &req, &params).await?;
+            drop(slot);
+            if config.style.show_result_stats {
+                results.set_stats(ResultStats::new(results.len(), started.elapsed()));
+            }
 
-            let page_content: String = hbs.render("search", &results?)?;
-            Ok(HttpResponse::Ok().body(page_content))
+            if params.format == OutputFormat::Rss {
+                return Ok(rss_response(&req, &results, &config));
+            }
+
+            // the scripts asking for json are served the same json as the search api.
+            if prefers_json(&req) {
+                let mut response: HttpResponse = json_response(&results);
+                response
+                    .headers_mut()
+                    .insert(header::VARY, header::HeaderValue::from_static("Accept"));
+                return Ok(response);
+            }
+
+            let page_content: String = hbs.render(template, &results)?;
+            // the results of a page are stable for as long as they are cached and so the browser
+            // is allowed to reuse the page for that long. The page depends on the preferences
+            // saved in the cookie and so it is only reused for the same cookie.
+            Ok(HttpResponse::Ok()
+                .content_type("text/html; charset=utf-8")
+                .insert_header((
+                    header::CACHE_CONTROL,
+                    format!("private, max-age={}", config.cache_expiry),
+                ))
+                .insert_header((
+                    header::VARY,
+                    match config.server.disable_cookies {
+                        true => "Accept",
+                        false => "Cookie, Accept",
+                    },
+                ))
+                .body(page_content))
         }
         None => Ok(HttpResponse::Found()
             .insert_header(("location", "/"))
@@ -121,164 +193,1608 @@ pub async fn search(
     }
 }
 
+/// Handles the route of the search api of the `websurfx` meta search engine which takes the same
+/// search url parameters as the search page and returns the search results as json. The status
+/// code of the response reflects the outcome of the search:
+///
+/// * `200` - The search results were fetched or no results were found in which case the flags in
+/// the json (like `filtered`, `disallowed` and `noEnginesSelected`) tell the reason.
+/// * `400` - The search query was missing, empty or longer than the `max_query_length` option.
+/// * `502` - All the upstream search engines failed to provide the search results.
+/// * `503` - Too many searches were fetching their results at once (see the
+/// `max_pending_searches` option), the search should be retried after the time given in the
+/// `Retry-After` header.
+///
+/// The `pagination` object of the json provides the `page` number along with whether the next page
+/// has any results (`hasNext`) and its url (`nextUrl`), so that the empty pages are not requested.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/api/search?q=sweden&page=1"
+/// ```
+#[get("/api/search")]
+pub async fn api_search(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    let params = web::Query::<SearchParams>::from_query(req.query_string())?;
+    let query: &str = match &params.q {
+        Some(query) if !query.trim().is_empty() => query,
+        _ => {
+            return Ok(HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "The search query `q` is missing or empty" })))
+        }
+    };
+
+    if let Some(rejection) = reject_long_query(query, config.aggregator.max_query_length) {
+        return Ok(rejection);
+    }
+
+    if let Some(redirect) = bang_redirect(query, &config) {
+        return Ok(redirect);
+    }
+
+    // a single slot of the search queue is held while the results of the search are fetched.
+    let slot: SearchQueueSlot = match search_queue(&req)?.try_enter() {
+        Ok(slot) => slot,
+        Err(SearchQueueFull) => return Ok(queue_full_response(&config)),
+    };
+    let results: SearchResults = search_results(&config, &cache, query, &req, &params).await?;
+    drop(slot);
+
+    Ok(json_response(&results))
+}
+
+/// A helper function which builds the json response of the search results, which is a `502 Bad
+/// Gateway` response if all the upstream search engines failed to provide the search results.
+///
+/// # Arguments
+///
+/// * `results` - It takes the search results to be sent.
+fn json_response(results: &SearchResults) -> HttpResponse {
+    match results.upstream_failed() {
+        true => HttpResponse::BadGateway().json(results),
+        false => HttpResponse::Ok().json(results),
+    }
+}
+
+/// A helper function which builds the RSS 2.0 feed response of the general search results. The
+/// feed links to the search page the feed is built from, that is the same search url without the
+/// `format` parameter.
+///
+/// # Arguments
+///
+/// * `req` - It takes the search request.
+/// * `results` - It takes the search results which need to be formatted as a feed.
+/// * `config` - It takes the parsed config which provides the cache expiry.
+fn rss_response(req: &HttpRequest, results: &SearchResults, config: &Config) -> HttpResponse {
+    let query_string: String = req
+        .query_string()
+        .split('&')
+        .filter(|parameter| !parameter.starts_with("format="))
+        .collect::<Vec<&str>>()
+        .join("&");
+    let connection_info = req.connection_info();
+    let link: String = format!(
+        "{}://{}{}?{query_string}",
+        connection_info.scheme(),
+        connection_info.host(),
+        req.path()
+    );
+
+    HttpResponse::Ok()
+        .content_type("application/rss+xml; charset=utf-8")
+        .insert_header((
+            header::CACHE_CONTROL,
+            format!("private, max-age={}", config.cache_expiry),
+        ))
+        .body(results.to_rss(&link))
+}
+
+/// A helper function which checks whether the client prefers a json response over the html search
+/// page according to the `Accept` header of the request, which is the case only when
+/// `application/json` is ranked higher than `text/html` and the wildcards. The html search page is
+/// preferred when the header is missing or invalid.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+fn prefers_json(req: &HttpRequest) -> bool {
+    let accept: header::Accept = match header::Accept::parse(req) {
+        Ok(accept) => accept,
+        Err(_) => return false,
+    };
+
+    accept
+        .ranked()
+        .into_iter()
+        .find(|mime| {
+            mime.essence_str() == "application/json"
+                || mime.essence_str() == "text/html"
+                || mime.subtype() == "*"
+        })
+        .is_some_and(|mime| mime.essence_str() == "application/json")
+}
+
+/// Handles the route of the streaming search api of the `websurfx` meta search engine which takes
+/// the same search url parameters as the search page and streams the general search results as
+/// server-sent events while the upstream search engines provide them, so that the results can be
+/// rendered progressively. A `results` event carrying the name of an engine along with its new
+/// results is sent as each engine completes, followed by a final `done` event carrying the
+/// search results json with the errors returned by the engines. The streamed results are not
+/// cached and the redirect bangs are not followed.
+///
+/// # Example
+///
+/// ```bash
+/// curl -N "http://127.0.0.1:8080/search/stream?q=sweden&page=1"
+/// ```
+#[get("/search/stream")]
+pub async fn search_stream(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config: Arc<Config> = config.load();
+    let params = web::Query::<SearchParams>::from_query(req.query_string())?;
+    let query: String = match &params.q {
+        Some(query) if !query.trim().is_empty() => query.to_owned(),
+        _ => {
+            return Ok(HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "The search query `q` is missing or empty" })))
+        }
+    };
+
+    if let Some(rejection) = reject_long_query(&query, config.aggregator.max_query_length) {
+        return Ok(rejection);
+    }
+
+    let page: u32 = normalize_page(params.page, config.aggregator.max_page);
+    let semaphore: Arc<Semaphore> = req
+        .app_data::<web::Data<Semaphore>>()
+        .ok_or("The upstream request semaphore has not been registered")?
+        .clone()
+        .into_inner();
+    let client: Client = upstream_client(&req)?;
+    let breaker: Arc<CircuitBreaker> = circuit_breaker(&req)?;
+
+    // restrict the search to a single upstream search engine if the query starts with an engine
+    // bang (like `!w`) in which case the bang is stripped from the query sent upstream.
+    let (bang_engine, upstream_query): (Option<EngineHandler>, String) =
+        match parse_bang(&query, &config.aggregator.bangs) {
+            Some((Bang::Engine(engine), bang_query)) if !bang_query.is_empty() => {
+                (Some(engine.clone()), bang_query)
+            }
+            _ => (None, query.clone()),
+        };
+
+    let (engines, _, safe_search_level) =
+        selected_engines(&req, &config, params.safesearch, bang_engine.as_ref())?;
+    // the results are only streamed for the first of the regions.
+    let headers: UpstreamHeaders = upstream_headers(
+        &req,
+        &config,
+        search_regions(&req, &config).first().map(String::as_str),
+    );
+
+    let (sender, receiver) = mpsc::channel::<StreamEvent>(engines.len() + 1);
+
+    let filter_lists: web::Data<SharedFilterLists> = req
+        .app_data::<web::Data<SharedFilterLists>>()
+        .ok_or("The filter lists have not been registered")?
+        .clone();
+    let is_disallowed: bool = safe_search_level == 4 && filter_lists.is_disallowed(&upstream_query);
+
+    // the streamed results are never cached and so nothing is streamed in the maintenance mode.
+    match is_disallowed || config.server.maintenance_mode {
+        true => {
+            let mut results: SearchResults = match is_disallowed {
+                true => blocked_results(config.safe_search_block_mode),
+                false => {
+                    let mut results: SearchResults = SearchResults::default();
+                    results.set_maintenance();
+                    results
+                }
+            };
+            results.set_page_query(&query);
+            let _ = sender.send(StreamEvent::Done(Box::new(results))).await;
+        }
+        false => {
+            let slot: SearchQueueSlot = match search_queue(&req)?.try_enter() {
+                Ok(slot) => slot,
+                Err(SearchQueueFull) => return Ok(queue_full_response(&config)),
+            };
+            actix_web::rt::spawn(async move {
+                // the slot is held until all the results have been streamed.
+                let _slot: SearchQueueSlot = slot;
+                stream_results(
+                    &upstream_query,
+                    page,
+                    &config,
+                    &engines,
+                    safe_search_level,
+                    &headers,
+                    &client,
+                    &semaphore,
+                    &breaker,
+                    &filter_lists,
+                    sender,
+                )
+                .await
+            });
+        }
+    }
+
+    // the events are sent to the client as they are received from the streaming task.
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let event: StreamEvent = receiver.recv().await?;
+        Some((event.to_sse().map(web::Bytes::from), receiver))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events))
+}
+
+/// A helper function which builds the response rejecting a search query which is longer than the
+/// allowed length, so that such a query is never forwarded to the upstream search engines.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user.
+/// * `max_query_length` - It takes the maximum length (in characters) of the search query.
+///
+/// # Returns
+///
+/// It returns the `400 Bad Request` response or `None` if the query is within the allowed length.
+fn reject_long_query(query: &str, max_query_length: usize) -> Option<HttpResponse> {
+    match query.chars().count() > max_query_length {
+        true => Some(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("The search query is longer than {max_query_length} characters")
+        }))),
+        false => None,
+    }
+}
+
+/// A helper function which builds the response turning away a search when the search queue is
+/// full, which tells the client to retry the search after the request timeout by when the searches
+/// in the queue are done fetching their results.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct which provides the request timeout.
+///
+/// # Returns
+///
+/// It returns the `503 Service Unavailable` response.
+fn queue_full_response(config: &Config) -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .insert_header((
+            header::RETRY_AFTER,
+            config.request_timeout.max(1).to_string(),
+        ))
+        .json(serde_json::json!({
+            "error": "Too many searches are in progress, please retry the search later"
+        }))
+}
+
+/// A helper function which builds the redirect response for a search query starting with a bang
+/// (like `!gh query`) which is configured to redirect to an external website.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user.
+/// * `config` - It takes the parsed config struct which provides the configured bangs.
+///
+/// # Returns
+///
+/// It returns the redirect response or `None` if the query does not start with a redirect bang.
+fn bang_redirect(query: &str, config: &Config) -> Option<HttpResponse> {
+    match parse_bang(query, &config.aggregator.bangs) {
+        Some((Bang::Redirect(url), query)) => Some(
+            HttpResponse::Found()
+                .insert_header(("location", redirect_url(url, &query)))
+                .finish(),
+        ),
+        _ => None,
+    }
+}
+
+/// A helper function which builds the redirect response for a search query which is exactly a
+/// domain or an url (like `github.com`) when the `direct_navigation` option is enabled.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user.
+/// * `config` - It takes the parsed config struct.
+///
+/// # Returns
+///
+/// It returns the redirect response or `None` if the query should be searched for.
+fn direct_navigation_redirect(query: &str, config: &Config) -> Option<HttpResponse> {
+    match config.aggregator.direct_navigation {
+        true => direct_navigation_url(query).map(|url| {
+            HttpResponse::Found()
+                .insert_header(("location", url))
+                .finish()
+        }),
+        false => None,
+    }
+}
+
+/// Fetches the search results of the requested page for the search query along with the results
+/// of the previous and the next page (so that they are cached for the pagination) and warms the
+/// cache for the pages ahead in the background if it is enabled. The general search results are
+/// paginated server-side instead if the `results_per_page` option is enabled.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct.
+/// * `cache` - It takes the shared cache in which the search results are cached.
+/// * `query` - It takes the search query as a string slice.
+/// * `req` - It takes the `HttpRequest` struct of the search.
+/// * `params` - It takes the parsed search url parameters.
+///
+/// # Error
+///
+/// It returns an appropriate error if the search results of the requested page could not be
+/// fetched.
+async fn search_results(
+    config: &Arc<Config>,
+    cache: &web::Data<SharedCache>,
+    query: &str,
+    req: &HttpRequest,
+    params: &SearchParams,
+) -> Result<SearchResults, Box<dyn std::error::Error>> {
+    let page: u32 = normalize_page(params.page, config.aggregator.max_page);
+
+    if config.aggregator.results_per_page > 0 && params.search_type == SearchType::General {
+        return paginated_results(config, cache, query, page, req, &params.safesearch).await;
+    }
+
+    let (_, results, next_results) = join!(
+        results(
+            config,
+            cache,
+            query,
+            page - 1,
+            req.clone(),
+            &params.safesearch,
+            params.search_type
+        ),
+        results(
+            config,
+            cache,
+            query,
+            page,
+            req.clone(),
+            &params.safesearch,
+            params.search_type
+        ),
+        results(
+            config,
+            cache,
+            query,
+            page + 1,
+            req.clone(),
+            &params.safesearch,
+            params.search_type
+        )
+    );
+
+    warm_next_pages(
+        config,
+        cache,
+        query,
+        page,
+        req,
+        params.safesearch,
+        params.search_type,
+    );
+
+    // the next page is fetched along with the requested page anyway and so whether it has any
+    // results is known without any extra request.
+    let has_next: bool = page < config.aggregator.max_page
+        && next_results.is_ok_and(|next_results| !next_results.is_empty());
+    let mut results: SearchResults = results?;
+    results.set_pagination(Pagination::new(
+        page,
+        has_next.then(|| next_page_url(req, page)),
+    ));
+    Ok(results)
+}
+
+/// A helper function which builds the absolute url of the page after the provided page for the
+/// same search, that is the same search url with the `page` parameter incremented.
+///
+/// # Arguments
+///
+/// * `req` - It takes the search request.
+/// * `page` - It takes the page number of the search results.
+fn next_page_url(req: &HttpRequest, page: u32) -> String {
+    let mut parameters: Vec<&str> = req
+        .query_string()
+        .split('&')
+        .filter(|parameter| !parameter.is_empty() && !parameter.starts_with("page="))
+        .collect();
+    let page_parameter: String = format!("page={}", page + 1);
+    parameters.push(&page_parameter);
+
+    let connection_info = req.connection_info();
+    format!(
+        "{}://{}{}?{}",
+        connection_info.scheme(),
+        connection_info.host(),
+        req.path(),
+        parameters.join("&")
+    )
+}
+
 /// Fetches the results for a query and page. It First checks the redis cache, if that
 /// fails it gets proper results by requesting from the upstream search engines.
 ///
 /// # Arguments
 ///
-/// * `url` - It takes the url of the current page that requested the search results for a
-/// particular search query.
 /// * `config` - It takes a parsed config struct.
-/// * `query` - It takes the page number as u32 value.
+/// * `cache` - It takes the shared cache in which the search results are cached.
+/// * `query` - It takes the search query as a string slice.
+/// * `page` - It takes the page number as u32 value.
 /// * `req` - It takes the `HttpRequest` struct as a value.
+/// * `safe_search` - It takes the safe search level provided in the search url.
+/// * `search_type` - It takes the type of results requested in the search url.
 ///
 /// # Error
 ///
 /// It returns the `SearchResults` struct if the search results could be successfully fetched from
 /// the cache or from the upstream search engines otherwise it returns an appropriate error.
 async fn results(
-    url: String,
     config: &Config,
     cache: &web::Data<SharedCache>,
     query: &str,
     page: u32,
     req: HttpRequest,
     safe_search: &Option<u8>,
+    search_type: SearchType,
 ) -> Result<SearchResults, Box<dyn std::error::Error>> {
+    // the regions the results are localized to, which are a part of the key as the results
+    // differ between the regions.
+    let regions: Vec<String> = search_regions(&req, config);
+
+    // the order the results are sorted in.
+    let sort: SortOrder = sort_order(&req);
+
+    // restrict the search to a single upstream search engine if the query starts with an engine
+    // bang (like `!w`) in which case the bang is stripped from the query sent upstream.
+    let bang_search: Option<(EngineHandler, String)> =
+        match parse_bang(query, &config.aggregator.bangs) {
+            Some((Bang::Engine(engine), bang_query)) if !bang_query.is_empty() => {
+                Some((engine.clone(), bang_query))
+            }
+            _ => None,
+        };
+    let upstream_query: &str = bang_search
+        .as_ref()
+        .map_or(query, |(_, bang_query)| bang_query.as_str());
+
+    // the engines and the safe search level (including the ones saved in the cookie) are resolved
+    // before the cache lookup as the level is a part of the key.
+    let (engines, selection_notes, safe_search_level) = selected_engines(
+        &req,
+        config,
+        *safe_search,
+        bang_search.as_ref().map(|(engine, _)| engine),
+    )?;
+
+    // the key under which the results are both looked up and cached.
+    let url: String = cache_key(config, &req, query, page, search_type, safe_search_level);
+
+    // the style selected by the user which is applied to the cached results as well since they
+    // are shared between all the users.
+    let style: Style = user_style(&req, config);
+
     // fetch the cached results json.
     let cached_results = cache.cached_json(&url).await;
     // check if fetched cache results was indeed fetched or it was an error and if so
     // handle the data accordingly.
     match cached_results {
-        Ok(results) => Ok(results),
+        Ok(mut results) => {
+            results.add_style(&style);
+            if config.server.maintenance_mode {
+                results.set_maintenance();
+            }
+            results.set_safe_search_level(safe_search_level);
+            Ok(results)
+        }
+        // only the cached results are served in the maintenance mode so that the upstream search
+        // engines are never requested, a cache miss results in an empty page flagged as such.
+        Err(_) if config.server.maintenance_mode => {
+            let mut results: SearchResults = SearchResults::default();
+            results.set_maintenance();
+            results.set_page_query(query);
+            results.add_style(&style);
+            results.set_safe_search_level(safe_search_level);
+            Ok(results)
+        }
         Err(_) => {
-            let mut safe_search_level: u8 = match config.safe_search {
-                3..=4 => config.safe_search,
-                _ => match safe_search {
-                    Some(safesearch) => match safesearch {
-                        0..=2 => *safesearch,
-                        _ => config.safe_search,
-                    },
-                    None => config.safe_search,
-                },
-            };
+            // the filter lists compiled at startup which are shared between all the searches.
+            let filter_lists = req
+                .app_data::<web::Data<SharedFilterLists>>()
+                .ok_or("The filter lists have not been registered")?;
 
-            if safe_search_level == 4 {
-                let mut results: SearchResults = SearchResults::default();
-                let mut _flag: bool =
-                    is_match_from_filter_list(file_path(FileType::BlockList)?, query)?;
-                _flag = !is_match_from_filter_list(file_path(FileType::AllowList)?, query)?;
-
-                if _flag {
-                    results.set_disallowed();
-                    results.add_style(&config.style);
-                    results.set_page_query(query);
-                    cache.cache_results(&results, &url).await?;
-                    results.set_safe_search_level(safe_search_level);
-                    return Ok(results);
-                }
+            if safe_search_level == 4 && filter_lists.is_disallowed(upstream_query) {
+                let mut results: SearchResults = blocked_results(config.safe_search_block_mode);
+                results.add_style(&style);
+                results.set_page_query(query);
+                cache.cache_results(&results, &url).await?;
+                results.set_safe_search_level(safe_search_level);
+                return Ok(results);
             }
 
-            // check if the cookie value is empty or not if it is empty then use the
-            // default selected upstream search engines from the config file otherwise
-            // parse the non-empty cookie and grab the user selected engines from the
-            // UI and use that.
-            let mut results: SearchResults = match req.cookie("appCookie") {
-                Some(cookie_value) => {
-                    let cookie_value: Cookie<'_> =
-                        serde_json::from_str(cookie_value.name_value().1)?;
-
-                    let engines: Vec<EngineHandler> = cookie_value
-                        .engines
-                        .iter()
-                        .filter_map(|name| EngineHandler::new(name))
-                        .collect();
-
-                    safe_search_level = match config.safe_search {
-                        3..=4 => config.safe_search,
-                        _ => match safe_search {
-                            Some(safesearch) => match safesearch {
-                                0..=2 => *safesearch,
-                                _ => config.safe_search,
-                            },
-                            None => cookie_value.safe_search_level,
-                        },
-                    };
-
-                    match engines.is_empty() {
-                        false => {
-                            aggregate(
-                                query,
-                                page,
-                                config.aggregator.random_delay,
-                                config.debug,
-                                &engines,
-                                config.request_timeout,
-                                safe_search_level,
-                            )
-                            .await?
-                        }
-                        true => {
-                            let mut search_results = SearchResults::default();
-                            search_results.set_no_engines_selected();
-                            search_results.set_page_query(query);
-                            search_results
-                        }
-                    }
-                }
-                None => {
-                    aggregate(
-                        query,
+            // the semaphore shared between all the searches which bounds the number of concurrent
+            // requests made to the upstream search engines.
+            let semaphore: Arc<Semaphore> = req
+                .app_data::<web::Data<Semaphore>>()
+                .ok_or("The upstream request semaphore has not been registered")?
+                .clone()
+                .into_inner();
+            let client: Client = upstream_client(&req)?;
+            let breaker: Arc<CircuitBreaker> = circuit_breaker(&req)?;
+
+            let mut results: SearchResults = match engines.is_empty() {
+                false => {
+                    // the headers are only built when the upstream search engines are requested
+                    // as generating a random user agent can require a network request.
+                    let headers: UpstreamHeaders =
+                        upstream_headers(&req, config, regions.first().map(String::as_str));
+                    let mut search_results = aggregate(
+                        upstream_query,
                         page,
-                        config.aggregator.random_delay,
-                        config.debug,
-                        &config.upstream_search_engines,
-                        config.request_timeout,
+                        config,
+                        &engines,
                         safe_search_level,
+                        search_type,
+                        &headers,
+                        &regions,
+                        &client,
+                        &semaphore,
+                        &breaker,
+                        cache,
+                        filter_lists,
                     )
-                    .await?
+                    .await?;
+                    if search_type == SearchType::General && sort != SortOrder::Relevance {
+                        let mut sorted: Vec<SearchResult> = search_results.results();
+                        sort_results(&mut sorted, sort);
+                        search_results.set_results(sorted);
+                    }
+                    for note in selection_notes {
+                        search_results.add_engine_error_info(note);
+                    }
+                    search_results
+                }
+                true => {
+                    let mut search_results = SearchResults::default();
+                    search_results.set_no_engines_selected();
+                    search_results.set_page_query(query);
+                    search_results
                 }
             };
             if results.engine_errors_info().is_empty()
-                && results.results().is_empty()
+                && results.is_empty()
                 && !results.no_engines_selected()
             {
                 results.set_filtered();
             }
-            results.add_style(&config.style);
-            cache
-                .cache_results(&results, &(format!("{url}{safe_search_level}")))
-                .await?;
+            // keep the bang in the query shown in the search bar.
+            results.set_page_query(query);
+            results.add_style(&style);
+            // the degraded results (like when most of the engines failed transiently or fewer
+            // engines than required returned results) are not cached so that the search is retried
+            // on the next request.
+            if should_cache(&results, config.min_results_to_cache) {
+                cache.cache_results(&results, &url).await?;
+            }
             results.set_safe_search_level(safe_search_level);
             Ok(results)
         }
     }
 }
 
-/// A helper function which checks whether the search query contains any keywords which should be
-/// disallowed/allowed based on the regex based rules present in the blocklist and allowlist files.
+/// A helper function which builds the key under which the results of a page of a search are
+/// cached, which is the url of the page along with everything the results depend on (like the
+/// regions, the engines selected in the search url, the sort order and the safe search level).
+/// The same key is used to look up and to cache the results so that the pages cached in the
+/// background are found by the searches.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct.
+/// * `req` - It takes the `HttpRequest` struct of the search.
+/// * `query` - It takes the search query as a string slice.
+/// * `page` - It takes the page number of the results.
+/// * `search_type` - It takes the type of results requested in the search url.
+/// * `safe_search_level` - It takes the resolved safe search level of the search.
+fn cache_key(
+    config: &Config,
+    req: &HttpRequest,
+    query: &str,
+    page: u32,
+    search_type: SearchType,
+    safe_search_level: u8,
+) -> String {
+    format!(
+        "http://{}/search?q={}&page={}&type={}&region={}&engines={}&sort={}&safesearch={}",
+        server_address(&config.binding_ip, config.port),
+        query,
+        page,
+        search_type.as_str(),
+        search_regions(req, config).join(","),
+        url_engines(req).unwrap_or_default().join(","),
+        sort_order(req).as_str(),
+        safe_search_level,
+    )
+}
+
+/// A helper function which builds the search results answering a search for a query disallowed by
+/// the blocklist, which either show the block page or silently show no results as if they had
+/// been filtered depending on the configured mode.
+///
+/// # Arguments
+///
+/// * `mode` - It takes the `safe_search_block_mode` option from the config.
+fn blocked_results(mode: SafeSearchBlockMode) -> SearchResults {
+    let mut results: SearchResults = SearchResults::default();
+    match mode {
+        SafeSearchBlockMode::BlockPage => results.set_disallowed(),
+        SafeSearchBlockMode::Silent => results.set_filtered(),
+    }
+    results
+}
+
+/// A helper function which checks whether the search results have enough results to be cached
+/// and were returned by enough upstream search engines.
+///
+/// # Arguments
+///
+/// * `results` - It takes the search results aggregated from the upstream search engines.
+/// * `min_results_to_cache` - It takes the minimum number of results required to cache them.
+fn should_cache(results: &SearchResults, min_results_to_cache: usize) -> bool {
+    !results.degraded && results.len() >= min_results_to_cache
+}
+
+/// A helper function which builds the `host:port` address of the server used in the urls built
+/// by the server, the IPv6 addresses are enclosed in brackets (like `[::1]:8080`).
+///
+/// # Arguments
+///
+/// * `binding_ip` - It takes the `binding_ip` option from the config.
+/// * `port` - It takes the `port` option from the config.
+fn server_address(binding_ip: &str, port: u16) -> String {
+    match binding_ip.parse::<IpAddr>() {
+        Ok(ip) => SocketAddr::new(ip, port).to_string(),
+        Err(_) => format!("{binding_ip}:{port}"),
+    }
+}
+
+/// A helper function which returns the http client shared between all the requests made to the
+/// upstream search engines.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+///
+/// # Error
+///
+/// Returns an error if the http client has not been registered with the app.
+fn upstream_client(req: &HttpRequest) -> Result<Client, Box<dyn std::error::Error>> {
+    Ok(req
+        .app_data::<web::Data<Client>>()
+        .ok_or("The upstream http client has not been registered")?
+        .get_ref()
+        .clone())
+}
+
+/// A helper function which returns the search queue shared between all the searches, which bounds
+/// the number of searches fetching their results from the upstream search engines at once.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+///
+/// # Error
+///
+/// Returns an error if the search queue has not been registered with the app.
+fn search_queue(req: &HttpRequest) -> Result<&web::Data<SearchQueue>, Box<dyn std::error::Error>> {
+    Ok(req
+        .app_data::<web::Data<SearchQueue>>()
+        .ok_or("The search queue has not been registered")?)
+}
+
+/// A helper function which returns the circuit breaker shared between all the searches, which
+/// skips the upstream search engines which keep failing.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+///
+/// # Error
+///
+/// Returns an error if the circuit breaker has not been registered with the app.
+fn circuit_breaker(req: &HttpRequest) -> Result<Arc<CircuitBreaker>, Box<dyn std::error::Error>> {
+    Ok(req
+        .app_data::<web::Data<CircuitBreaker>>()
+        .ok_or("The circuit breaker has not been registered")?
+        .clone()
+        .into_inner())
+}
+
+/// A helper function which builds the headers sent to the upstream search engines, the
+/// `Accept-Language` header of the user is forwarded only if it is enabled in the config.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+/// * `config` - It takes the parsed config struct.
+/// * `region` - It takes the region the results are localized to.
+fn upstream_headers(req: &HttpRequest, config: &Config, region: Option<&str>) -> UpstreamHeaders {
+    let accept_language: Option<&str> = match config.aggregator.forward_accept_language {
+        true => req
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|accept_language| accept_language.to_str().ok()),
+        false => None,
+    };
+
+    let mut headers: UpstreamHeaders = UpstreamHeaders::new(
+        select_user_agent(&config.aggregator),
+        accept_language,
+        region,
+    );
+    headers.set_request_id(&request_id(req));
+    headers
+}
+
+/// A helper function which decides the upstream search engines and the safe search level used
+/// for a search. The engines and the level saved in the `appCookie` are used if it is present,
+/// otherwise the ones from the config file are used. The engines selected in the `engines`
+/// parameter of the search url take precedence over both of them and the search is restricted
+/// to the engine of the bang if the query starts with an engine bang.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+/// * `config` - It takes the parsed config struct.
+/// * `safe_search` - It takes the safe search level provided in the search url.
+/// * `bang_engine` - It takes the engine of the bang the query starts with.
+///
+/// # Error
+///
+/// It returns an error if the `appCookie` could not be parsed.
+///
+/// # Returns
+///
+/// It returns the selected engines, the notes on the selection (like the selection from the search
+/// url or the cookie being invalid, in which case the engines from the config file are used, or
+/// exceeding the `max_engines_per_request` option) and the safe search level.
+fn selected_engines(
+    req: &HttpRequest,
+    config: &Config,
+    safe_search: Option<u8>,
+    bang_engine: Option<&EngineHandler>,
+) -> Result<EngineSelection, Box<dyn std::error::Error>> {
+    let url_engines: Option<Vec<String>> = url_engines(req);
+
+    let (engines, invalid_selection, cookie_safe_search): (Vec<EngineHandler>, bool, Option<u8>) =
+        match app_cookie(req, config) {
+            Some(cookie_value) => {
+                let cookie_value: Cookie<'_> = serde_json::from_str(cookie_value.name_value().1)?;
+
+                let (engines, invalid_selection) = match (bang_engine, &url_engines) {
+                    (Some(engine), _) => (vec![engine.clone()], false),
+                    (None, Some(url_engines)) => {
+                        engines_from_selection(url_engines, &config.upstream_search_engines)
+                    }
+                    (None, None) => engines_from_selection(
+                        &cookie_value.engines,
+                        &config.upstream_search_engines,
+                    ),
+                };
+
+                (
+                    engines,
+                    invalid_selection,
+                    Some(cookie_value.safe_search_level),
+                )
+            }
+            None => {
+                let (engines, invalid_selection) = match (bang_engine, &url_engines) {
+                    (Some(engine), _) => (vec![engine.clone()], false),
+                    (None, Some(url_engines)) => {
+                        engines_from_selection(url_engines, &config.upstream_search_engines)
+                    }
+                    (None, None) => (config.upstream_search_engines.clone(), false),
+                };
+
+                (engines, invalid_selection, None)
+            }
+        };
+
+    let mut selection_notes: Vec<EngineErrorInfo> = Vec::new();
+    if invalid_selection {
+        selection_notes.push(EngineErrorInfo::invalid_engine_selection());
+    }
+
+    // the fan-out of a search is bounded regardless of how many engines were selected.
+    let max_engines: usize = config.aggregator.max_engines_per_request;
+    let (engines, truncated) = limit_engines(engines, max_engines);
+    if truncated {
+        log::warn!("More than {max_engines} engines were selected, only the first {max_engines} engines are used");
+        selection_notes.push(EngineErrorInfo::engines_limit_exceeded());
+    }
+
+    Ok((
+        engines,
+        selection_notes,
+        resolve_safe_search_level(
+            config.safe_search,
+            config.safe_search_locked,
+            safe_search,
+            cookie_safe_search,
+        ),
+    ))
+}
+
+/// A helper function which bounds the number of the upstream search engines requested by a search
+/// to the provided maximum. The engines selected more than once are only kept once and the
+/// engines beyond the maximum are dropped, preserving the order in which they were selected.
+///
+/// # Arguments
+///
+/// * `engines` - It takes the selected upstream search engines.
+/// * `max_engines` - It takes the `max_engines_per_request` option, where zero disables the limit.
+///
+/// # Returns
+///
+/// It returns the engines to be requested along with whether any engine was dropped for exceeding
+/// the maximum.
+fn limit_engines(engines: Vec<EngineHandler>, max_engines: usize) -> (Vec<EngineHandler>, bool) {
+    let mut limited: Vec<EngineHandler> = Vec::with_capacity(engines.len());
+    for engine in engines {
+        if !limited.iter().any(|added| added.name() == engine.name()) {
+            limited.push(engine);
+        }
+    }
+
+    match max_engines > 0 && limited.len() > max_engines {
+        true => {
+            limited.truncate(max_engines);
+            (limited, true)
+        }
+        false => (limited, false),
+    }
+}
+
+/// A helper function which parses the `engines` parameter of the search url (like
+/// `?engines=duckduckgo,searx`) into the lowercased names of the selected engines, which are
+/// sorted and deduplicated so that the same selection always results in the same cache key.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+///
+/// # Returns
+///
+/// It returns the names of the selected engines or `None` if no engine is selected in the url.
+fn url_engines(req: &HttpRequest) -> Option<Vec<String>> {
+    let params = web::Query::<SearchParams>::from_query(req.query_string()).ok()?;
+    let mut engines: Vec<String> = params
+        .engines
+        .as_deref()?
+        .split(',')
+        .map(|engine| engine.trim().to_lowercase())
+        .filter(|engine| !engine.is_empty())
+        .collect();
+    engines.sort();
+    engines.dedup();
+
+    match engines.is_empty() {
+        true => None,
+        false => Some(engines),
+    }
+}
+
+/// A helper function which decides the safe search level used for a search. The level provided
+/// in the search url takes precedence over the one saved in the cookie, which in turn takes
+/// precedence over the one from the config file, except when the config file enforces a level:
+///
+/// * When `safe_search_locked` is enabled, the level from the config file is always used.
+/// * When the level from the config file is 3 or 4, it is always used.
+/// * Only the levels 0 to 2 can be requested in the search url, any other level falls back to the
+/// level from the config file.
+///
+/// # Arguments
+///
+/// * `config_level` - It takes the safe search level from the config file.
+/// * `locked` - It takes the `safe_search_locked` option from the config file.
+/// * `requested` - It takes the safe search level provided in the search url.
+/// * `cookie` - It takes the safe search level saved in the cookie.
+fn resolve_safe_search_level(
+    config_level: u8,
+    locked: bool,
+    requested: Option<u8>,
+    cookie: Option<u8>,
+) -> u8 {
+    if locked {
+        return config_level;
+    }
+
+    match config_level {
+        3..=4 => config_level,
+        _ => match requested {
+            Some(level @ 0..=2) => level,
+            Some(_) => config_level,
+            None => cookie.unwrap_or(config_level),
+        },
+    }
+}
+
+/// A helper function which returns the `appCookie` storing the preferences of the user, the
+/// cookie is ignored when the cookies are disabled in the config.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+/// * `config` - It takes the parsed config struct which provides the `disable_cookies` option.
+fn app_cookie(req: &HttpRequest, config: &Config) -> Option<HttpCookie<'static>> {
+    match config.server.disable_cookies {
+        true => None,
+        false => req.cookie("appCookie"),
+    }
+}
+
+/// A helper function which builds the style of the website selected by the user in the
+/// `appCookie`. The theme or colorscheme which is not available anymore (or was never available)
+/// is replaced with the one from the config file so that the page never renders broken.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+/// * `config` - It takes the parsed config struct which provides the default style.
+fn user_style(req: &HttpRequest, config: &Config) -> Style {
+    let cookie = app_cookie(req, config);
+    let selected: Option<Cookie<'_>> = cookie
+        .as_ref()
+        .and_then(|cookie| serde_json::from_str(cookie.value()).ok());
+
+    match (selected, req.app_data::<web::Data<AvailableStyles>>()) {
+        (Some(selected), Some(styles)) => {
+            styles.resolve(selected.theme, selected.colorscheme, &config.style)
+        }
+        _ => config.style.clone(),
+    }
+}
+
+/// A helper function which determines the regions the search results should be localized to. The
+/// `region` parameter of the search url (which can be a comma separated list of regions) takes
+/// precedence over the region from the config file, the invalid regions are ignored and only the
+/// first `MAX_REGIONS` regions are kept.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` of the search.
+/// * `config` - It takes the parsed config struct which provides the default region.
+fn search_regions(req: &HttpRequest, config: &Config) -> Vec<String> {
+    let regions: Vec<String> = web::Query::<SearchParams>::from_query(req.query_string())
+        .ok()
+        .and_then(|params| params.region.as_deref().map(normalize_regions))
+        .unwrap_or_default();
+
+    match regions.is_empty() {
+        true => config.region.clone().into_iter().collect(),
+        false => regions,
+    }
+}
+
+/// A helper function which returns the order the general search results are sorted in, which is
+/// provided in the `sort` parameter of the search url. The results are sorted by their relevance if
+/// it is missing.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct of the search.
+fn sort_order(req: &HttpRequest) -> SortOrder {
+    web::Query::<SearchParams>::from_query(req.query_string())
+        .map(|params| params.sort)
+        .unwrap_or_default()
+}
+
+/// A helper function which builds the requested page of the merged list of the general search
+/// results of all the upstream search engines. The pages of the upstream search engines are fetched
+/// (or taken from the cache) one after the other and merged without the duplicate results until
+/// the merged list is long enough to fill the requested page, the engines run out of results or
+/// the configured maximum page number is reached.
 ///
 /// # Arguments
 ///
-/// * `file_path` - It takes the file path of the list as the argument.
-/// * `query` - It takes the search query to be checked against the list as an argument.
+/// * `config` - It takes the parsed config struct.
+/// * `cache` - It takes the shared cache in which the search results are cached.
+/// * `query` - It takes the search query as a string slice.
+/// * `page` - It takes the normalized page number of the merged list.
+/// * `req` - It takes the `HttpRequest` struct of the search.
+/// * `safe_search` - It takes the safe search level provided in the search url.
 ///
 /// # Error
 ///
-/// Returns a bool indicating whether the results were found in the list or not on success
-/// otherwise returns a standard error type on a failure.
-fn is_match_from_filter_list(
-    file_path: &str,
+/// It returns an appropriate error if the search results of any of the pages of the upstream
+/// search engines could not be fetched.
+async fn paginated_results(
+    config: &Config,
+    cache: &web::Data<SharedCache>,
     query: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let mut flag = false;
-    let mut reader = BufReader::new(File::open(file_path)?);
-    for line in reader.by_ref().lines() {
-        let re = Regex::new(&line?)?;
-        if re.is_match(query) {
-            flag = true;
+    page: u32,
+    req: &HttpRequest,
+    safe_search: &Option<u8>,
+) -> Result<SearchResults, Box<dyn std::error::Error>> {
+    let results_per_page: usize = config.aggregator.results_per_page;
+    let max_total_results: usize = match config.aggregator.max_total_results {
+        0 => usize::MAX,
+        max_total_results => max_total_results,
+    };
+    // one more result than the requested page shows is needed to know whether there is a next
+    // page.
+    let needed: usize = results_per_page
+        .saturating_mul(page as usize)
+        .saturating_add(1)
+        .min(max_total_results);
+
+    let mut merged: Vec<SearchResult> = Vec::new();
+    let mut first_page: Option<SearchResults> = None;
+
+    for engine_page in 1..=config.aggregator.max_page {
+        let mut page_results: SearchResults = results(
+            config,
+            cache,
+            query,
+            engine_page,
+            req.clone(),
+            safe_search,
+            SearchType::General,
+        )
+        .await?;
+
+        let fetched: Vec<SearchResult> = page_results.results();
+        let exhausted: bool = fetched.is_empty();
+        merge_page(&mut merged, fetched);
+        merged.truncate(max_total_results);
+
+        // the first page provides the flags, the engine errors and the related searches.
+        first_page.get_or_insert(page_results);
+
+        if exhausted || merged.len() >= needed {
             break;
         }
     }
-    Ok(flag)
+
+    // the pages are sorted on their own and so the merged list is sorted as a whole again.
+    sort_results(&mut merged, sort_order(req));
+
+    let has_next: bool = page < config.aggregator.max_page
+        && merged.len() > results_per_page.saturating_mul(page as usize);
+
+    let mut results: SearchResults = first_page.unwrap_or_default();
+    results.set_results(page_of(merged, page, results_per_page));
+    results.set_pagination(Pagination::new(
+        page,
+        has_next.then(|| next_page_url(req, page)),
+    ));
+    Ok(results)
+}
+
+/// A helper function which appends the results of a page of the upstream search engines to the
+/// merged list of results, skipping the results which are already present in it.
+///
+/// # Arguments
+///
+/// * `merged` - It takes the merged list of results.
+/// * `page_results` - It takes the results of the page of the upstream search engines.
+fn merge_page(merged: &mut Vec<SearchResult>, page_results: Vec<SearchResult>) {
+    for result in page_results {
+        if !merged.iter().any(|existing| existing.url == result.url) {
+            merged.push(result);
+        }
+    }
+}
+
+/// A helper function which takes the results of the requested page out of the merged list of
+/// results.
+///
+/// # Arguments
+///
+/// * `merged` - It takes the merged list of results.
+/// * `page` - It takes the page number (starting from `1`).
+/// * `results_per_page` - It takes the number of results shown on every page.
+fn page_of(merged: Vec<SearchResult>, page: u32, results_per_page: usize) -> Vec<SearchResult> {
+    merged
+        .into_iter()
+        .skip(results_per_page.saturating_mul(page.saturating_sub(1) as usize))
+        .take(results_per_page)
+        .collect()
+}
+
+/// A helper function which spawns detached background tasks that fetch and cache the results of
+/// the pages after the next page (as many as the `prefetch_ahead` option allows) so that navigating
+/// to them is instant. The tasks do not block the response and the requests made by them share the
+/// concurrency semaphore with all the other searches. Every task holds a slot of the search queue
+/// and the warming is skipped once the queue is full, so that it never turns away the searches of
+/// the users.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct.
+/// * `cache` - It takes the shared cache in which the search results are cached.
+/// * `query` - It takes the search query as a string slice.
+/// * `page` - It takes the page number of the served page.
+/// * `req` - It takes the `HttpRequest` of the served page.
+/// * `safe_search` - It takes the safe search level provided in the search url.
+/// * `search_type` - It takes the type of results requested in the search url.
+fn warm_next_pages(
+    config: &Arc<Config>,
+    cache: &web::Data<SharedCache>,
+    query: &str,
+    page: u32,
+    req: &HttpRequest,
+    safe_search: Option<u8>,
+    search_type: SearchType,
+) {
+    for prefetch_page in prefetch_pages(
+        page,
+        config.aggregator.prefetch_ahead,
+        config.aggregator.max_page,
+    ) {
+        let slot: SearchQueueSlot = match search_queue(req)
+            .ok()
+            .and_then(|search_queue| search_queue.try_enter().ok())
+        {
+            Some(slot) => slot,
+            None => return,
+        };
+        let config: Arc<Config> = config.clone();
+        let cache: web::Data<SharedCache> = cache.clone();
+        let query: String = query.to_owned();
+        let req: HttpRequest = req.clone();
+
+        actix_web::rt::spawn(async move {
+            // the slot is held until the page has been fetched and cached.
+            let _slot: SearchQueueSlot = slot;
+            if let Err(error) = results(
+                &config,
+                &cache,
+                &query,
+                prefetch_page,
+                req,
+                &safe_search,
+                search_type,
+            )
+            .await
+            {
+                log::error!("Failed to prefetch the page {prefetch_page}: {error}");
+            }
+        });
+    }
+}
+
+/// A helper function which computes the pages to be prefetched in the background after serving a
+/// page. The next page is skipped as it is always fetched along with the served page and the
+/// pages beyond the configured maximum page number are never prefetched.
+///
+/// # Arguments
+///
+/// * `page` - It takes the page number of the served page.
+/// * `prefetch_ahead` - It takes the number of pages after the next page to be prefetched.
+/// * `max_page` - It takes the configured maximum page number.
+fn prefetch_pages(page: u32, prefetch_ahead: u32, max_page: u32) -> RangeInclusive<u32> {
+    page.saturating_add(2)
+        ..=page
+            .saturating_add(prefetch_ahead.saturating_add(1))
+            .min(max_page)
+}
+
+/// A helper function which parses the engine names selected through the settings cookie or the
+/// search url into engine handlers. If engines were selected but none of them are known engines
+/// then the default engines from the config file are used instead.
+///
+/// # Arguments
+///
+/// * `selected_engines` - It takes the engine names selected through the settings cookie or the
+/// search url.
+/// * `default_engines` - It takes the upstream search engines enabled in the config file.
+///
+/// # Returns
+///
+/// It returns the engine handlers to be used along with a boolean indicating whether the
+/// selection was invalid and the default engines were used instead.
+fn engines_from_selection(
+    selected_engines: &[impl AsRef<str>],
+    default_engines: &[EngineHandler],
+) -> (Vec<EngineHandler>, bool) {
+    let engines: Vec<EngineHandler> = selected_engines
+        .iter()
+        .filter_map(|name| EngineHandler::new(name.as_ref()))
+        .collect();
+
+    match engines.is_empty() && !selected_engines.is_empty() {
+        true => {
+            log::warn!(
+                "None of the selected engines are valid, falling back to the default engines"
+            );
+            (default_engines.to_vec(), true)
+        }
+        false => (engines, false),
+    }
+}
+
+/// A helper function which normalizes the page number provided in the search url so that it
+/// is at least `1` (which prevents the previous page number from underflowing) and at most the
+/// configured maximum page number (which prevents the upstream search engines from being
+/// hammered with deep paging requests).
+///
+/// # Arguments
+///
+/// * `page` - It takes the page number provided in the search url.
+/// * `max_page` - It takes the configured maximum page number.
+fn normalize_page(page: Option<u32>, max_page: u32) -> u32 {
+    page.unwrap_or(1).clamp(1, max_page.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_results_depend_on_the_block_mode() {
+        let results: SearchResults = blocked_results(SafeSearchBlockMode::BlockPage);
+        assert!(results.disallowed);
+        assert!(!results.filtered);
+        assert!(results.is_empty());
+
+        let results: SearchResults = blocked_results(SafeSearchBlockMode::Silent);
+        assert!(!results.disallowed);
+        assert!(results.filtered);
+        assert!(results.is_empty());
+        // the disallowed state never leaks to the user in the silent mode.
+        assert!(serde_json::to_string(&results)
+            .unwrap()
+            .contains(r#""disallowed":false"#));
+    }
+
+    #[test]
+    fn test_ipv6_server_addresses_are_bracketed() {
+        assert_eq!(server_address("127.0.0.1", 8080), "127.0.0.1:8080");
+        assert_eq!(server_address("::1", 8080), "[::1]:8080");
+        assert_eq!(
+            server_address("2001:db8::8a2e:370:7334", 443),
+            "[2001:db8::8a2e:370:7334]:443"
+        );
+        assert_eq!(
+            format!("http://{}/search?q=rust", server_address("::1", 8080)),
+            "http://[::1]:8080/search?q=rust"
+        );
+    }
+
+    #[cfg(all(feature = "memory-cache", not(feature = "redis-cache")))]
+    #[actix_web::test]
+    async fn test_warmed_pages_are_served_from_the_cache() {
+        use crate::cache::cacher::Cache;
+        use crate::results::filter_lists::FilterLists;
+        use actix_web::test::TestRequest;
+
+        let mut config: Config = Config::validate_only("./websurfx/config.lua").unwrap();
+        // the searches without any engine are answered without requesting the upstream search
+        // engines and are cached like any other search.
+        config.upstream_search_engines = Vec::new();
+        let cache: web::Data<SharedCache> =
+            web::Data::new(SharedCache::new(Cache::new_in_memory(60)));
+        let req: HttpRequest = TestRequest::with_uri("/search?q=rust&page=1")
+            .app_data(web::Data::new(Semaphore::new(1)))
+            .app_data(web::Data::new(Client::new()))
+            .app_data(web::Data::new(CircuitBreaker::new()))
+            .app_data(web::Data::new(SharedFilterLists::new(
+                FilterLists::load().unwrap(),
+            )))
+            .to_http_request();
+
+        // the page is warmed the same way as the pages prefetched in the background.
+        let warmed: SearchResults = results(
+            &config,
+            &cache,
+            "rust",
+            3,
+            req.clone(),
+            &None,
+            SearchType::General,
+        )
+        .await
+        .unwrap();
+        assert!(warmed.no_engines_selected());
+
+        // in the maintenance mode only the cached results are served, so the page is only found
+        // if it is looked up under the key it was cached with.
+        config.server.maintenance_mode = true;
+        let served: SearchResults =
+            results(&config, &cache, "rust", 3, req, &None, SearchType::General)
+                .await
+                .unwrap();
+        assert!(served.no_engines_selected());
+    }
+
+    #[test]
+    fn test_results_below_the_threshold_are_not_cached() {
+        let results: SearchResults = SearchResults::new(
+            vec![SearchResult::new(
+                "Rust",
+                "https://www.rust-lang.org/",
+                "",
+                &["duckduckgo"],
+            )],
+            "rust",
+            &[],
+        );
+
+        assert!(!should_cache(&results, 5));
+        assert!(!should_cache(&SearchResults::default(), 1));
+        assert!(should_cache(&results, 1));
+        assert!(should_cache(&SearchResults::default(), 0));
+
+        let mut degraded: SearchResults = results.clone();
+        degraded.set_degraded();
+        assert!(!should_cache(&degraded, 1));
+    }
+
+    #[test]
+    fn test_next_page_url_increments_the_page_of_the_same_search() {
+        use actix_web::test::TestRequest;
+
+        let next_url = |uri: &str, page: u32| -> String {
+            next_page_url(&TestRequest::with_uri(uri).to_http_request(), page)
+        };
+
+        assert_eq!(
+            next_url("/api/search?q=rust&page=2&type=news", 2),
+            "http://localhost:8080/api/search?q=rust&type=news&page=3"
+        );
+        assert_eq!(
+            next_url("/search?q=rust", 1),
+            "http://localhost:8080/search?q=rust&page=2"
+        );
+
+        let pagination: Pagination = Pagination::new(3, None);
+        assert!(!pagination.has_next);
+        assert!(!serde_json::to_string(&pagination)
+            .unwrap()
+            .contains("nextUrl"));
+    }
+
+    #[test]
+    fn test_prefers_json_only_when_json_is_ranked_first() {
+        use actix_web::test::TestRequest;
+
+        let accepting = |accept: &str| -> bool {
+            prefers_json(
+                &TestRequest::default()
+                    .insert_header((header::ACCEPT, accept))
+                    .to_http_request(),
+            )
+        };
+
+        assert!(accepting("application/json"));
+        assert!(accepting("application/json, */*;q=0.8"));
+        assert!(accepting("text/html;q=0.5, application/json"));
+        assert!(!accepting("*/*"));
+        assert!(!accepting(
+            "text/html,application/xhtml+xml,application/json;q=0.9,*/*;q=0.8"
+        ));
+        assert!(!prefers_json(&TestRequest::default().to_http_request()));
+    }
+
+    #[test]
+    fn test_normalize_page_zero_behaves_like_first_page() {
+        assert_eq!(normalize_page(Some(0), 10), normalize_page(Some(1), 10));
+        assert_eq!(normalize_page(Some(0), 10), 1);
+        assert_eq!(normalize_page(None, 10), 1);
+    }
+
+    #[test]
+    fn test_engines_from_garbage_cookie_falls_back_to_default_engines() {
+        let cookie: Cookie<'_> = serde_json::from_str(
+            r#"{"theme":"simple","colorscheme":"nord","engines":["foo","b@r"],"safe_search_level":0}"#,
+        )
+        .unwrap();
+        let default_engines: Vec<EngineHandler> = vec![EngineHandler::new("duckduckgo").unwrap()];
+
+        let (engines, invalid_selection) =
+            engines_from_selection(&cookie.engines, &default_engines);
+
+        assert!(invalid_selection);
+        assert_eq!(engines.len(), 1);
+        assert_eq!(engines[0].to_owned().into_name_engine().0, "duckduckgo");
+    }
+
+    #[test]
+    fn test_over_long_engine_selection_is_truncated_in_order() {
+        let selected: Vec<&str> = [
+            "yandex",
+            "searx",
+            "duckduckgo",
+            "searx",
+            "mojeek",
+            "wikipedia",
+            "startpage",
+        ]
+        .into_iter()
+        .cycle()
+        .take(70)
+        .collect();
+        let (engines, _) = engines_from_selection(&selected, &[]);
+        assert_eq!(engines.len(), 70);
+
+        let (engines, truncated) = limit_engines(engines, 3);
+        assert!(truncated);
+        let names: Vec<&str> = engines.iter().map(EngineHandler::name).collect();
+        assert_eq!(names, ["yandex", "searx", "duckduckgo"]);
+
+        let (engines, _) = engines_from_selection(&selected, &[]);
+        let (engines, truncated) = limit_engines(engines, 0);
+        assert!(!truncated);
+        assert_eq!(engines.len(), 6);
+
+        let (engines, _) = engines_from_selection(&["searx", "searx"], &[]);
+        assert!(!limit_engines(engines, 1).1);
+    }
+
+    #[test]
+    fn test_engines_from_selection_keeps_valid_and_empty_selections() {
+        let default_engines: Vec<EngineHandler> = vec![EngineHandler::new("duckduckgo").unwrap()];
+
+        let (engines, invalid_selection) =
+            engines_from_selection(&["searx", "unknown"], &default_engines);
+        assert!(!invalid_selection);
+        assert_eq!(engines.len(), 1);
+        assert_eq!(engines[0].to_owned().into_name_engine().0, "searx");
+
+        let (engines, invalid_selection) = engines_from_selection(&[] as &[&str], &default_engines);
+        assert!(!invalid_selection);
+        assert!(engines.is_empty());
+    }
+
+    #[test]
+    fn test_url_engines_are_normalized() {
+        let req: HttpRequest = actix_web::test::TestRequest::with_uri(
+            "/search?q=rust&engines=Searx,%20duckduckgo,searx,",
+        )
+        .to_http_request();
+        assert_eq!(
+            url_engines(&req),
+            Some(vec!["duckduckgo".to_owned(), "searx".to_owned()])
+        );
+
+        for uri in [
+            "/search?q=rust",
+            "/search?q=rust&engines=",
+            "/search?q=rust&engines=,",
+        ] {
+            let req: HttpRequest = actix_web::test::TestRequest::with_uri(uri).to_http_request();
+            assert_eq!(url_engines(&req), None, "{uri}");
+        }
+    }
+
+    #[test]
+    fn test_overlong_query_is_rejected() {
+        let rejection: Option<HttpResponse> = reject_long_query(&"a".repeat(600), 512);
+        assert_eq!(
+            rejection.map(|response| response.status()),
+            Some(actix_web::http::StatusCode::BAD_REQUEST)
+        );
+
+        assert!(reject_long_query(&"a".repeat(512), 512).is_none());
+        // the length is counted in characters rather than bytes.
+        assert!(reject_long_query(&"é".repeat(512), 512).is_none());
+    }
+
+    #[test]
+    fn test_normalize_page_clamps_to_max_page() {
+        assert_eq!(normalize_page(Some(5), 10), 5);
+        assert_eq!(normalize_page(Some(u32::MAX), 10), 10);
+    }
+
+    #[test]
+    fn test_result_links_open_in_new_tab_only_when_enabled() {
+        let mut handlebars: Handlebars<'_> = Handlebars::new();
+        handlebars
+            .register_templates_directory(".html", "public/templates")
+            .unwrap();
+
+        let mut results: SearchResults = SearchResults::new(
+            vec![SearchResult::new(
+                "Rust",
+                "https://www.rust-lang.org/",
+                "A language empowering everyone.",
+                &["searx"],
+            )],
+            "rust",
+            &[],
+        );
+        let mut style: Style = Style::new("simple".to_owned(), "nord".to_owned());
+
+        results.add_style(&style);
+        let page: String = handlebars.render("search", &results).unwrap();
+        assert!(page.contains(r#"<a href="https://www.rust-lang.org/" rel="noopener noreferrer">"#));
+
+        style.set_open_in_new_tab(true);
+        results.add_style(&style);
+        let page: String = handlebars.render("search", &results).unwrap();
+        assert!(page.contains(
+            r#"<a href="https://www.rust-lang.org/" rel="noopener noreferrer" target="_blank">"#
+        ));
+    }
+
+    #[test]
+    fn test_merged_pages_are_paginated_without_duplicates() {
+        let page_results = |urls: &[&str]| -> Vec<SearchResult> {
+            urls.iter()
+                .map(|url| SearchResult::new("title", url, "description", &["searx"]))
+                .collect()
+        };
+
+        let mut merged: Vec<SearchResult> = Vec::new();
+        merge_page(
+            &mut merged,
+            page_results(&["https://a.com", "https://b.com"]),
+        );
+        merge_page(
+            &mut merged,
+            page_results(&["https://b.com", "https://c.com"]),
+        );
+
+        let urls = |results: Vec<SearchResult>| -> Vec<String> {
+            results.into_iter().map(|result| result.url).collect()
+        };
+        assert_eq!(
+            urls(page_of(merged.clone(), 1, 2)),
+            vec!["https://a.com", "https://b.com"]
+        );
+        assert_eq!(urls(page_of(merged.clone(), 2, 2)), vec!["https://c.com"]);
+        assert!(page_of(merged, 3, 2).is_empty());
+    }
+
+    #[test]
+    fn test_prefetch_pages_skips_next_page_and_respects_max_page() {
+        assert!(prefetch_pages(1, 0, 10).is_empty());
+        assert_eq!(prefetch_pages(1, 1, 10).collect::<Vec<u32>>(), vec![3]);
+        assert_eq!(prefetch_pages(3, 2, 10).collect::<Vec<u32>>(), vec![5, 6]);
+        assert_eq!(prefetch_pages(8, 3, 10).collect::<Vec<u32>>(), vec![10]);
+        assert!(prefetch_pages(10, 3, 10).is_empty());
+    }
+
+    #[test]
+    fn test_safe_search_level_precedence() {
+        // the level requested in the url wins over the cookie which wins over the config.
+        assert_eq!(resolve_safe_search_level(1, false, Some(0), Some(2)), 0);
+        assert_eq!(resolve_safe_search_level(1, false, None, Some(2)), 2);
+        assert_eq!(resolve_safe_search_level(1, false, None, None), 1);
+        // only the levels 0 to 2 can be requested in the url.
+        assert_eq!(resolve_safe_search_level(1, false, Some(4), Some(2)), 1);
+        // the levels 3 and 4 of the config can not be overridden.
+        assert_eq!(resolve_safe_search_level(3, false, Some(0), Some(0)), 3);
+        assert_eq!(resolve_safe_search_level(4, false, None, Some(1)), 4);
+    }
+
+    #[test]
+    fn test_locked_safe_search_level_ignores_url_and_cookie() {
+        assert_eq!(resolve_safe_search_level(2, true, Some(0), Some(0)), 2);
+        assert_eq!(resolve_safe_search_level(2, true, None, Some(1)), 2);
+        assert_eq!(resolve_safe_search_level(1, true, Some(2), None), 1);
+    }
 }
@@ -0,0 +1,128 @@
+//! This module provides the bounded queue of the searches which are fetching their results from the
+//! upstream search engines, so that a spike of traffic can not pile up an unbounded number of
+//! aggregations exhausting the resources of the instance. The searches beyond the capacity of the
+//! queue are turned away (with a `503 Service Unavailable` response) instead of waiting.
+
+use std::{fmt, sync::Arc};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// A named struct which stores the slots of the searches fetching their results from the upstream
+/// search engines, which includes the searches waiting for their turn to make the upstream
+/// requests (bounded by the `max_concurrent_requests` option).
+pub struct SearchQueue {
+    /// It stores the free slots of the queue, the queue is unbounded if it is `None`.
+    slots: Option<Arc<Semaphore>>,
+}
+
+/// A named struct which holds a slot of the search queue, the slot is freed when it is dropped.
+pub struct SearchQueueSlot {
+    /// It stores the permit of the slot, which is `None` if the queue is unbounded.
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// An error type returned when a search can not enter the search queue as all of its slots are
+/// taken.
+#[derive(Debug)]
+pub struct SearchQueueFull;
+
+impl fmt::Display for SearchQueueFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The search queue is full")
+    }
+}
+
+impl std::error::Error for SearchQueueFull {}
+
+impl SearchQueue {
+    /// Constructs a new `SearchQueue` with the given capacity.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_pending_searches` - It takes the maximum number of searches which can fetch their
+    /// results at once, a value of `0` leaves the queue unbounded.
+    pub fn new(max_pending_searches: usize) -> Self {
+        SearchQueue {
+            slots: (max_pending_searches > 0)
+                .then(|| Arc::new(Semaphore::new(max_pending_searches))),
+        }
+    }
+
+    /// A function which takes a slot of the queue for a search without waiting for one to be
+    /// freed.
+    ///
+    /// # Error
+    ///
+    /// Returns the `SearchQueueFull` error if all the slots of the queue are taken.
+    pub fn try_enter(&self) -> Result<SearchQueueSlot, SearchQueueFull> {
+        match &self.slots {
+            Some(slots) => Ok(SearchQueueSlot {
+                _permit: Some(
+                    slots
+                        .clone()
+                        .try_acquire_owned()
+                        .map_err(|_| SearchQueueFull)?,
+                ),
+            }),
+            None => Ok(SearchQueueSlot { _permit: None }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn test_search_queue_bounds_the_concurrent_searches_under_load() {
+        let queue: Arc<SearchQueue> = Arc::new(SearchQueue::new(8));
+        let running: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let peak: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        // a spike of searches arriving at once, every admitted search holds its slot while its
+        // results are being fetched.
+        let tasks: Vec<_> = (0..200)
+            .map(|_| {
+                let (queue, running, peak) = (queue.clone(), running.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let _slot: SearchQueueSlot = match queue.try_enter() {
+                        Ok(slot) => slot,
+                        Err(SearchQueueFull) => return false,
+                    };
+                    let now_running: usize = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now_running, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    true
+                })
+            })
+            .collect();
+
+        let mut admitted: usize = 0;
+        for task in tasks {
+            admitted += usize::from(task.await.unwrap());
+        }
+
+        assert!(peak.load(Ordering::SeqCst) <= 8);
+        assert!(admitted >= 8);
+        assert!(admitted < 200);
+
+        // the slots are freed once the searches are done.
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+        let slots: Vec<SearchQueueSlot> = (0..8).map(|_| queue.try_enter().unwrap()).collect();
+        assert!(queue.try_enter().is_err());
+        drop(slots);
+        assert!(queue.try_enter().is_ok());
+    }
+
+    #[test]
+    fn test_search_queue_of_zero_capacity_is_unbounded() {
+        let queue: SearchQueue = SearchQueue::new(0);
+        let slots: Vec<SearchQueueSlot> = (0..1000).map(|_| queue.try_enter().unwrap()).collect();
+        assert_eq!(slots.len(), 1000);
+    }
+}
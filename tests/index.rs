@@ -9,11 +9,13 @@ fn spawn_app() -> String {
     let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
     let port = listener.local_addr().unwrap().port();
     let config = Config::parse(false).unwrap();
+    #[cfg(all(feature = "memory-cache", not(feature = "redis-cache")))]
+    let cache_expiry: u64 = config.cache_expiry;
     let server = run(
         listener,
         config,
         #[cfg(all(feature = "memory-cache", not(feature = "redis-cache")))]
-        websurfx::cache::cacher::Cache::new_in_memory(),
+        websurfx::cache::cacher::Cache::new_in_memory(cache_expiry),
     )
     .expect("Failed to bind address");
 
@@ -1,19 +1,67 @@
 -- ### General ###
 logging = true -- an option to enable or disable logs.
 debug = false -- an option to enable or disable debug mode.
-threads = 10 -- the amount of threads that the app will use to run (the value should be greater than 0).
+threads = 10 -- the amount of threads that the app will use to run (0 uses one thread for every available cpu).
 
 -- ### Server ###
 port = "8080" -- port on which server should be launched
-binding_ip = "127.0.0.1" --ip address on the which server should be launched.
+binding_ip = "127.0.0.1" --ip address on the which server should be launched (an IPv6 address like "::1" is accepted as well).
+-- The `ip:port` addresses on which the server should listen instead of the `binding_ip` and `port`
+-- options above (optional), for example to be reachable on both an internal and an external interface.
+-- listen = { "127.0.0.1:8080", "192.168.1.10:8080" }
+enable_compression = true -- whether to compress (gzip/brotli) the responses based on the `Accept-Encoding` header of the request (defaults to true).
 production_use = false -- whether to use production mode or not (in other words this option should be used if it is to be used to host it on the server to provide a service to a large number of users (more than one))
 -- if production_use is set to true
 -- There will be a random delay before sending the request to the search engines, this is to prevent DDoSing the upstream search engines from a large number of simultaneous requests.
 request_timeout = 30 -- timeout for the search requests sent to the upstream search engines to be fetched (value in seconds).
+connect_timeout = 5 -- the time within which the connection to an upstream search engine has to be established (value in seconds, defaults to the `request_timeout`).
+read_timeout = 0 -- the time to wait for every read of the body of a response of an upstream search engine (value in seconds, 0 only bounds the reads by the `request_timeout`).
+shutdown_timeout = 30 -- the time to wait for the in-flight requests to complete when the server is shutting down on SIGINT or SIGTERM (value in seconds, defaults to 30).
+max_connections = 25000 -- the maximum number of concurrent connections accepted by each worker thread of the server (defaults to 25000).
+max_pending_searches = 0 -- the maximum number of searches fetching their results from the upstream search engines at once, the searches beyond it are answered with a `503` response (0 disables it).
+client_timeout = 5 -- the time within which a client has to send the headers of its request before the connection is closed (value in seconds, defaults to 5).
+max_concurrent_requests = 16 -- the maximum number of requests that can be sent to the upstream search engines concurrently (shared between all the searches).
+max_engines_per_request = 0 -- the maximum number of upstream search engines requested by a single search, the engines selected beyond it are dropped (0 disables it).
+connection_pool_size = 16 -- the maximum number of idle connections kept open to every upstream search engine (0 disables keeping them open).
+engine_retries = 1 -- the number of times a request to an upstream search engine is retried when it fails with a network or server error (defaults to 1).
+-- user_agent = "Mozilla/5.0 (X11; Linux x86_64; rv:118.0) Gecko/20100101 Firefox/118.0" -- the user agent sent to the upstream search engines (a randomly generated user agent is used if it is not provided).
+rotate_user_agent = false -- whether to pick the user agent sent to the upstream search engines from a built-in pool of common user agents for every search (defaults to false).
+forward_accept_language = false -- whether to forward the `Accept-Language` header of the user to the upstream search engines (defaults to false).
+maintenance_mode = false -- whether only the cached search results are served without requesting the upstream search engines.
+disable_cookies = false -- whether the server never reads nor sets any cookie, the preferences of the users are then only saved in their browsers (changing it requires a restart).
+-- access_token = "change-me" -- the token required by all the requests (except `/healthz`) to access the instance, as a bearer token or the `token` query parameter (the instance is open to everyone if it is not provided).
+-- admin_token = "change-me" -- the token required in the `X-Admin-Token` header to access the admin only routes like `/reload-filters` (the admin only routes are disabled if it is not provided).
 rate_limiter = {
 	number_of_requests = 20, -- The number of request that are allowed within a provided time limit.
 	time_limit = 3, -- The time limit in which the quantity of requests that should be accepted.
+	-- The routes (and their sub paths) which are rate limited with their own budget instead of
+	-- the one above.
+	routes = {
+		-- ["/search"] = { number_of_requests = 10, time_limit = 3 },
+		-- ["/static"] = { number_of_requests = 200, time_limit = 3 },
+	},
 }
+-- The reverse proxies (ip addresses or networks) whose `X-Forwarded-For` and `X-Real-IP` headers
+-- are trusted to provide the ip address of the client which is rate limited.
+-- trusted_proxies = { "127.0.0.1", "::1", "10.0.0.0/8" }
+-- What is logged about the searches for the aggregate statistics of the instance, the raw search
+-- queries are never logged. The modes provided are:
+-- {{
+-- off - Nothing is logged
+-- counts - Only the number of searches made every day is counted
+-- anonymized - The number of searches made for every salted hash of the queries is counted as well
+-- }}
+query_log = "off"
+query_log_sink = "file" -- where the statistics are stored, either `file` or `redis` (requires the `redis-cache` feature).
+query_log_file = "query_log.json" -- the file in which the statistics are stored by the `file` sink.
+-- query_log_salt = "change-me" -- the secret salt with which the queries are hashed (a random salt is generated on every start if it is not provided).
+-- The `robots.txt` served by the instance, either inline or from a file, the `robots.txt` of the
+-- theme is served if neither is provided.
+-- robots_txt = "User-agent: *\nDisallow: /search"
+-- robots_txt_file = "/etc/websurfx/robots.txt"
+-- The overrides of the security headers added to all the responses, an empty value disables the
+-- header (the other headers keep their defaults).
+-- security_headers = { referrer_policy = "same-origin", x_frame_options = "" }
 
 -- ### Search ###
 -- Filter results based on different levels. The levels provided are:
@@ -25,6 +73,55 @@ rate_limiter = {
 -- 4 - Aggressive
 -- }}
 safe_search = 2
+safe_search_locked = false -- whether the safe search level above is enforced by ignoring the level provided in the search url or saved in the settings (defaults to false).
+safe_search_block_mode = "block_page" -- how the queries disallowed by the blocklist are answered at the safe search level 4, either "block_page" or "silent" (no results).
+-- region = "us-en" -- the default region (country code and language code) the search results are localized to (optional).
+max_page = 10 -- the maximum page number that can be requested (requests for pages beyond it are clamped to it).
+max_query_length = 512 -- the maximum length (in characters) of the search query, longer queries are rejected (defaults to 512).
+-- The bang shortcuts which can be provided at the start of the search query (like `!w rust`).
+-- A bang either restricts the search to a single upstream search engine or redirects the search
+-- to an url in which `{query}` is replaced with the search query.
+bangs = {
+	w = "wikipedia",
+	ddg = "duckduckgo",
+	gh = "https://github.com/search?q={query}",
+}
+prefetch_ahead = 0 -- the number of pages after the next page whose results are fetched and cached in the background after a search page is served (0 disables it).
+results_per_engine = 0 -- the maximum number of results taken from every upstream search engine (0 disables the limit).
+-- The per engine overrides of the `results_per_engine` option.
+results_per_engine_overrides = {
+	-- searx = 20,
+}
+results_per_page = 0 -- the number of results shown on every search page after merging the results of all the upstream search engines (0 disables it).
+max_total_results = 0 -- the maximum number of results kept after merging and ranking the results of all the upstream search engines (0 disables it).
+-- The weights of the agreement of the upstream search engines and of the positions of the results
+-- in the results of the engines with which the results are ranked.
+ranking_weights = { agreement = 1, position = 1 }
+min_engines_responded = 0 -- the minimum number of upstream search engines which should return results, the results of fewer engines are flagged as degraded and are not cached (0 disables it).
+hide_sponsored = false -- whether the sponsored results (ads) marked by the upstream search engines are removed instead of being labelled.
+clean_urls = false -- whether the tracking query parameters (like `utm_source` or `fbclid`) are removed from the urls of the search results.
+direct_navigation = false -- whether the search queries which are exactly a domain or an url (like `github.com`) are redirected straight to the website.
+proxy_thumbnails = false -- whether the thumbnails of the video results are loaded through the server instead of directly from the upstream domains.
+redirect_clicks = false -- whether the clicks on the search results are routed through the `/r` route of the server which redirects to the result.
+log_clicks = false -- whether the clicks routed through the `/r` route are counted and logged along with the domain of the result only.
+strategy = "wait_all" -- how long the search waits for the upstream search engines, either "wait_all" or a table like `{ first_n = { count = 2, deadline = 1500 } }` to return once `count` engines have responded or `deadline` (millis) has passed.
+-- The circuit breaker which skips an upstream search engine for `cooldown` (secs) after `failures`
+-- consecutive failures within `window` (secs), a `failures` of 0 disables it.
+circuit_breaker = {
+	failures = 0,
+	window = 60,
+	cooldown = 300,
+}
+-- The uniformly distributed random delay (millis) applied before every request to the upstream
+-- search engines, which adds to the latency of the searches. A `max` of 0 disables it.
+delay = {
+	min = 0,
+	max = 0,
+}
+allow_private_addresses = false -- whether the requests to the upstream search engines are allowed to reach private, loopback and link-local addresses.
+max_response_bytes = 5 * 1024 * 1024 -- the maximum size (in bytes) of the body of a response of an upstream search engine which is read (0 disables the limit).
+probe_engines_on_start = false -- whether the upstream search engines are probed with a test query when the server starts and their reachability is logged.
+probe_engines_strict = false -- whether the server refuses to start if any upstream search engine is unreachable when they are probed.
 
 -- ### Website ###
 -- The different colorschemes provided are:
@@ -44,12 +141,33 @@ safe_search = 2
 -- }}
 colorscheme = "catppuccin-mocha" -- the colorscheme name which should be used for the website theme
 theme = "simple" -- the theme name which should be used for the website
+-- templates_path = "/etc/websurfx/templates" -- the folder containing the handlebars templates (the `templates` folder of the public folder is used if it is not provided).
+open_in_new_tab = false -- whether the links of the search results are opened in a new tab.
+show_result_stats = false -- whether the number of the search results and the time taken to fetch them are shown above the results.
+snippet_max_length = 0 -- the maximum length (in characters) of the descriptions of the search results, which are truncated at a word boundary (0 disables it).
+notice_html = "" -- the markup of a notice banner shown at the top of all the pages (only a few inline elements like `<a>` and `<b>` are kept, empty shows no banner).
 
 -- ### Caching ###
 redis_url = "redis://127.0.0.1:8082" -- redis connection url address on which the client should connect on.
+redis_prefix = "websurfx:" -- the prefix of all the keys stored in the redis server (defaults to "websurfx:").
+cache_compression = "none" -- the compression format of the search results stored in the redis server, either `none`, `gzip` or `zstd` (defaults to "none").
+cache_expiry = 60 -- the time for which the search results are cached (value in seconds, defaults to 60).
+min_results_to_cache = 0 -- the minimum number of results the search results should have to be cached (0 caches all of them).
+cache_per_engine = false -- whether the results of every upstream search engine are cached on their own to be reused by the searches made with another selection of engines.
 
 -- ### Search Engines ###
 upstream_search_engines = {
 	DuckDuckGo = true,
 	Searx = false,
-} -- select the upstream search engines from which the results should be fetched.
+	Invidious = false,
+	Wikipedia = false,
+	Startpage = false,
+	Mojeek = false,
+	Yandex = false,
+	Google = false,
+	SearxNG = false, -- requires the `base_url` of the searxng instance, like `SearxNG = { base_url = "https://searxng.example.org" }`.
+} -- select the upstream search engines from which the results should be fetched (a positive number instead of `true` enables the engine with the number as its ranking weight).
+-- An engine can also be enabled with a table to set its weight and a fallback engine used in its
+-- place when it fails, for example `DuckDuckGo = { weight = 2, fallback = "Mojeek" }`. The table
+-- can also override the base url which the requests of the engine are sent to, for example
+-- `Searx = { base_url = "https://searx.example.org" }`.